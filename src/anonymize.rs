@@ -0,0 +1,251 @@
+//! Scrubbing personal data from zested JSON so that it can be shared, for
+//! example alongside a bug report.
+
+use crate::{load_json, write_snapshot, Error};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Fields of user objects that are removed outright (set to `null`)
+const SCRUBBED_USER_FIELDS: &[&str] = &[
+    "avatar_url", "city", "country_code", "description", "visuals", "website", "website_title"
+];
+
+/// Replaces the personal data in zested JSON with pseudonyms while keeping the
+/// structure of the data intact.
+///
+/// User ids are swapped for small sequential ids, and usernames, names, and
+/// permalinks for names like "user-1" derived from them. Email addresses are
+/// replaced, avatar URLs (along with a few other user profile fields) are set
+/// to `null`, and the user parts of SoundCloud URLs and URNs are rewritten to
+/// match. Track and playlist data is left as it is.
+///
+/// The same user gets the same pseudonym everywhere, including across
+/// separate files anonymized with the same `Anonymizer`, so relationships
+/// between the files (such as which tracks were uploaded by the same user)
+/// are preserved.
+///
+/// ```ignore
+/// let mut anonymizer = Anonymizer::new();
+/// for name in &["me.json", "likes.json", "playlists.json"] {
+///     anonymizer.anonymize_file(archive.join(name), out.join(name))?;
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct Anonymizer {
+    /// Original user id -> pseudonymous id
+    ids: HashMap<u64, u64>,
+    /// Original username or permalink -> pseudonym
+    names: HashMap<String, String>
+}
+
+impl Anonymizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Anonymize the JSON saved at `src` (with `write_snapshot` or similar),
+    /// writing the result to `dst`.
+    ///
+    /// `src` and `dst` may be the same path.
+    pub fn anonymize_file<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, src: P, dst: Q) -> Result<(), Error> {
+        let mut value: Value = load_json(src)?;
+        self.anonymize(&mut value);
+        write_snapshot(&value, dst, true)
+    }
+
+    /// Anonymize the given JSON in place.
+    ///
+    /// This works on any of the data zested by this crate, whether it's `Me`,
+    /// `Likes`, `Playlists`, or something else that contains users.
+    pub fn anonymize(&mut self, value: &mut Value) {
+        // users are found first so that URLs mentioning them can be rewritten
+        // no matter where in the data they appear
+        self.collect_users(value, false);
+        self.rewrite(value, false);
+    }
+
+    fn pseudonym_id(&mut self, id: u64) -> u64 {
+        let next = self.ids.len() as u64 + 1;
+        *self.ids.entry(id).or_insert(next)
+    }
+
+    fn collect_users(&mut self, value: &Value, is_user: bool) {
+        match value {
+            Value::Object(map) => {
+                if is_user || is_user_object(map) {
+                    if let Some(id) = map.get("id").and_then(Value::as_u64) {
+                        let name = format!("user-{}", self.pseudonym_id(id));
+                        for key in &["username", "permalink"] {
+                            if let Some(original) = map.get(*key).and_then(Value::as_str) {
+                                self.names.insert(original.to_string(), name.clone());
+                            }
+                        }
+                    }
+                }
+
+                for (key, v) in map {
+                    self.collect_users(v, key == "user");
+                }
+            },
+            Value::Array(values) => {
+                for v in values {
+                    self.collect_users(v, false);
+                }
+            },
+            _ => {}
+        }
+    }
+
+    fn rewrite(&mut self, value: &mut Value, is_user: bool) {
+        match value {
+            Value::Object(map) => {
+                let is_user = is_user || is_user_object(map);
+
+                for (key, v) in map.iter_mut() {
+                    match (key.as_str(), &*v) {
+                        ("id", Value::Number(n)) if is_user => {
+                            if let Some(id) = n.as_u64() {
+                                *v = self.pseudonym_id(id).into();
+                            }
+                        },
+                        ("user_id", Value::Number(n)) => {
+                            if let Some(id) = n.as_u64() {
+                                *v = self.pseudonym_id(id).into();
+                            }
+                        },
+                        (k, Value::String(_)) if k.contains("email") => {
+                            *v = "user@example.com".into();
+                        },
+                        (k, Value::String(_)) if is_user && is_name_field(k) => {},
+                        (k, _) if is_user && SCRUBBED_USER_FIELDS.contains(&k) => {
+                            *v = Value::Null;
+                        },
+                        (_, Value::String(s)) => {
+                            let rewritten = self.rewrite_string(s);
+                            *v = rewritten.into();
+                        },
+                        _ => self.rewrite(v, key == "user")
+                    }
+                }
+
+                // names are derived from the (already anonymized) id, so
+                // they're filled in once the rest of the object is done
+                if is_user {
+                    let id = map.get("id").and_then(Value::as_u64).unwrap_or_default();
+                    for (key, v) in map.iter_mut() {
+                        if is_name_field(key) && v.is_string() {
+                            *v = name_for(key, id).into();
+                        }
+                    }
+                }
+            },
+            Value::Array(values) => {
+                for v in values {
+                    self.rewrite(v, false);
+                }
+            },
+            _ => {}
+        }
+    }
+
+    // Rewrite the user parts of URLs and URNs
+    fn rewrite_string(&mut self, s: &str) -> String {
+        if let Some(id) = s.strip_prefix("soundcloud:users:").and_then(|id| id.parse().ok()) {
+            return format!("soundcloud:users:{}", self.pseudonym_id(id));
+        }
+
+        if let Some((prefix, rest)) = s.split_once("/users/") {
+            let (id, suffix) = rest.split_at(rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len()));
+            if let Ok(id) = id.parse() {
+                return format!("{}/users/{}{}", prefix, self.pseudonym_id(id), suffix);
+            }
+        }
+
+        for host in &["https://soundcloud.com/", "http://soundcloud.com/", "https://m.soundcloud.com/"] {
+            if let Some(rest) = s.strip_prefix(host) {
+                let (permalink, suffix) = rest.split_at(rest.find(['/', '?', '#']).unwrap_or(rest.len()));
+                if let Some(name) = self.names.get(permalink) {
+                    return format!("{}{}{}", host, name, suffix);
+                }
+            }
+        }
+
+        s.to_string()
+    }
+}
+
+fn is_user_object(map: &Map<String, Value>) -> bool {
+    map.get("kind").and_then(Value::as_str) == Some("user")
+}
+
+fn is_name_field(key: &str) -> bool {
+    matches!(key, "username" | "permalink" | "full_name" | "first_name" | "last_name")
+}
+
+fn name_for(key: &str, id: u64) -> String {
+    match key {
+        "full_name" => format!("User {}", id),
+        "first_name" => "User".into(),
+        "last_name" => id.to_string(),
+        _ => format!("user-{}", id)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn anonymization() {
+        let user = json!({
+            "id": 5551234,
+            "kind": "user",
+            "username": "Real Name",
+            "permalink": "real-name",
+            "permalink_url": "https://soundcloud.com/real-name",
+            "uri": "https://api.soundcloud.com/users/5551234",
+            "urn": "soundcloud:users:5551234",
+            "avatar_url": "https://i1.sndcdn.com/avatars-abc-large.jpg",
+            "full_name": "Real Person",
+            "city": "Somewhere"
+        });
+        let mut likes = json!({
+            "collections": [{
+                "track": {
+                    "id": 42,
+                    "title": "A Track",
+                    "permalink_url": "https://soundcloud.com/real-name/a-track?in=x",
+                    "user_id": 5551234,
+                    "user": user
+                }
+            }]
+        });
+        let mut me = json!({ "kind": "user", "id": 5551234, "primary_email": "me@real.com", "username": "Real Name" });
+
+        let mut anonymizer = Anonymizer::new();
+        anonymizer.anonymize(&mut me);
+        anonymizer.anonymize(&mut likes);
+
+        assert_eq!(me, json!({ "kind": "user", "id": 1, "primary_email": "user@example.com", "username": "user-1" }));
+
+        let track = &likes["collections"][0]["track"];
+        assert_eq!(track["id"], 42);
+        assert_eq!(track["title"], "A Track");
+        assert_eq!(track["permalink_url"], "https://soundcloud.com/user-1/a-track?in=x");
+        assert_eq!(track["user_id"], 1);
+        assert_eq!(track["user"], json!({
+            "id": 1,
+            "kind": "user",
+            "username": "user-1",
+            "permalink": "user-1",
+            "permalink_url": "https://soundcloud.com/user-1",
+            "uri": "https://api.soundcloud.com/users/1",
+            "urn": "soundcloud:users:1",
+            "avatar_url": null,
+            "full_name": "User 1",
+            "city": null
+        }));
+    }
+}
@@ -0,0 +1,78 @@
+// SoundCloud serves artwork and avatars in a fixed set of sizes, selected by a
+// suffix on the image's file name (`...-large.jpg`)
+
+/// One of the sizes SoundCloud serves artwork and avatars in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ArtworkSize {
+    /// The image as it was uploaded
+    Original,
+    /// 500x500
+    T500x500,
+    /// 400x400
+    Crop,
+    /// 300x300
+    T300x300,
+    /// 100x100 (what the API hands out by default)
+    Large,
+    /// 67x67
+    T67x67,
+    /// 47x47
+    Badge,
+    /// 32x32
+    Small,
+    /// 20x20 (18x18 for avatars)
+    Tiny,
+    /// 16x16
+    Mini,
+}
+
+impl ArtworkSize {
+    pub const ALL: [ArtworkSize; 10] = [
+        ArtworkSize::Original,
+        ArtworkSize::T500x500,
+        ArtworkSize::Crop,
+        ArtworkSize::T300x300,
+        ArtworkSize::Large,
+        ArtworkSize::T67x67,
+        ArtworkSize::Badge,
+        ArtworkSize::Small,
+        ArtworkSize::Tiny,
+        ArtworkSize::Mini,
+    ];
+
+    /// The suffix used in image URLs for this size
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ArtworkSize::Original => "original",
+            ArtworkSize::T500x500 => "t500x500",
+            ArtworkSize::Crop => "crop",
+            ArtworkSize::T300x300 => "t300x300",
+            ArtworkSize::Large => "large",
+            ArtworkSize::T67x67 => "t67x67",
+            ArtworkSize::Badge => "badge",
+            ArtworkSize::Small => "small",
+            ArtworkSize::Tiny => "tiny",
+            ArtworkSize::Mini => "mini",
+        }
+    }
+}
+
+/// Rewrite an artwork or avatar URL to point at the given size of the image.
+///
+/// Returns `None` if the URL doesn't end in one of the known size suffixes,
+/// since there's no telling what rewriting it would do.
+pub fn sized_url(url: &str, size: ArtworkSize) -> Option<String> {
+    let (path, query) = match url.find('?') {
+        Some(i) => url.split_at(i),
+        None => (url, "")
+    };
+
+    let dot = path.rfind('.')?;
+    let dash = path[..dot].rfind('-')?;
+    let current = &path[dash + 1..dot];
+    if !ArtworkSize::ALL.iter().any(|s| s.as_str() == current) {
+        return None;
+    }
+
+    Some(format!("{}-{}{}{}", &path[..dash], size.as_str(), &path[dot..], query))
+}
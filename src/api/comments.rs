@@ -0,0 +1,28 @@
+// Mirrors the shape of the objects returned by `tracks/{id}/comments`, with all
+// properties optional like the rest of the API types
+
+use serde_derive::{Serialize, Deserialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use super::common::*;
+use super::ids::*;
+use chrono::{DateTime, Utc};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Comment {
+    pub id: Option<i64>,
+    pub kind: Option<String>,
+    pub body: Option<String>,
+    #[serde(default, with = "datetime")]
+    pub created_at: Option<DateTime<Utc>>,
+    /// The position in the track the comment was left at, in milliseconds
+    pub timestamp: Option<i64>,
+    pub track_id: Option<TrackId>,
+    pub user_id: Option<UserId>,
+    /// The user who left the comment
+    pub user: Option<User>,
+    pub uri: Option<String>,
+    /// Any fields returned by the API that aren't covered above
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
@@ -2,21 +2,23 @@
 //
 // Turn on derive debug impl and make all properties optional
 
-// TODO: a lot of stuff that could use enums to strongly type certain properties
-// can't right now because serde doesn't support deserializing to an enum with
-// a non-unit variant fallback to capture the value as a string if it doesn't
-// match one of the existing unit variants
-//
-// see https://github.com/serde-rs/serde/pull/1382#issuecomment-424706998
+// Enums that mirror stringly-typed API values end with an untagged
+// `Other(String)` variant so that new values SoundCloud introduces don't break
+// deserialization; the raw string is kept around instead.
 
 use serde_derive::{Serialize, Deserialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use chrono::{DateTime, Utc};
+use super::ids::{TrackId, UserId};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Track {
     pub comment_count: Option<i64>,
     pub full_duration: Option<i64>,
     pub downloadable: Option<bool>,
-    pub created_at: Option<String>,
+    #[serde(default, with = "datetime")]
+    pub created_at: Option<DateTime<Utc>>,
     pub description: Option<String>,
     pub media: Option<Media>,
     pub title: Option<String>,
@@ -29,42 +31,60 @@ pub struct Track {
     pub tag_list: Option<String>,
     pub download_url: Option<String>,
     pub genre: Option<String>,
-    pub id: Option<i64>,
+    pub id: Option<TrackId>,
     pub reposts_count: Option<i64>,
-    pub state: Option<String>,
+    pub state: Option<TrackState>,
     pub label_name: Option<String>,
-    pub last_modified: Option<String>,
+    #[serde(default, with = "datetime")]
+    pub last_modified: Option<DateTime<Utc>>,
     pub commentable: Option<bool>,
-    pub policy: Option<String>,
+    pub policy: Option<Policy>,
     pub visuals: Option<Visuals>,
     pub kind: Option<String>,
     pub purchase_url: Option<String>,
-    pub sharing: Option<String>,
+    pub sharing: Option<Sharing>,
+    /// Only present for private tracks, in the representation shown to their
+    /// owner (see `Zester::my_tracks`)
+    pub secret_token: Option<String>,
     pub uri: Option<String>,
     pub download_count: Option<i64>,
+    // The v1 API calls likes "favoritings"
+    #[serde(alias = "favoritings_count")]
     pub likes_count: Option<i64>,
     pub urn: Option<String>,
-    pub license: Option<String>,
+    pub license: Option<License>,
     pub purchase_title: Option<String>,
-    pub display_date: Option<String>,
-    pub embeddable_by: Option<String>,
-    pub release_date: Option<String>,
-    pub user_id: Option<i64>,
-    pub monetization_model: Option<String>,
+    #[serde(default, with = "datetime")]
+    pub display_date: Option<DateTime<Utc>>,
+    pub embeddable_by: Option<EmbeddableBy>,
+    #[serde(default, with = "datetime")]
+    pub release_date: Option<DateTime<Utc>>,
+    pub user_id: Option<UserId>,
+    pub monetization_model: Option<MonetizationModel>,
     pub waveform_url: Option<String>,
     pub permalink: Option<String>,
     pub permalink_url: Option<String>,
     pub user: Option<User>,
     pub playback_count: Option<i64>,
+    /// The JSON this track was deserialized from, if
+    /// `ZesterConfig::keep_raw_json` was enabled when it was fetched
+    #[serde(default, rename = "_raw", skip_serializing_if = "Option::is_none")]
+    pub raw: Option<Value>,
+    /// Any fields returned by the API that aren't covered above
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Media {
     pub transcodings: Option<Vec<Transcoding>>,
+    /// Any fields returned by the API that aren't covered above
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
 }
 
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct PublisherMetadata {
     pub urn: Option<String>,
     pub contains_music: Option<bool>,
@@ -82,30 +102,47 @@ pub struct PublisherMetadata {
     pub c_line: Option<String>,
     pub c_line_for_display: Option<String>,
     pub iswc: Option<String>,
+    /// Any fields returned by the API that aren't covered above
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Visuals {
     pub urn: Option<String>,
     pub enabled: Option<bool>,
     pub visuals: Option<Vec<Visual>>,
+    /// Any fields returned by the API that aren't covered above
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Visual {
     pub urn: Option<String>,
     pub entry_time: Option<i64>,
     pub visual_url: Option<String>,
+    /// Any fields returned by the API that aren't covered above
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// A single page of a paginated API response.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Page<T> {
+    pub collection: Option<Vec<T>>,
+    pub next_href: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct User {
     pub avatar_url: Option<String>,
     pub first_name: Option<String>,
     pub full_name: Option<String>,
-    pub id: Option<i64>,
+    pub id: Option<UserId>,
     pub kind: Option<String>,
-    pub last_modified: Option<String>,
+    #[serde(default, with = "datetime")]
+    pub last_modified: Option<DateTime<Utc>>,
     pub last_name: Option<String>,
     pub permalink: Option<String>,
     pub permalink_url: Option<String>,
@@ -115,10 +152,13 @@ pub struct User {
     pub verified: Option<bool>,
     pub city: Option<String>,
     pub country_code: Option<String>,
+    /// Any fields returned by the API that aren't covered above
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
 }
 
 // As far as I can tell none of these fields need to be optional
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Transcoding {
     pub url: String,
     pub preset: String,
@@ -126,26 +166,211 @@ pub struct Transcoding {
     pub snipped: bool,
     pub format: Format,
     pub quality: Quality,
+    /// Any fields returned by the API that aren't covered above
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Format {
     pub protocol: Protocol,
     pub mime_type: String,
+    /// Any fields returned by the API that aren't covered above
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
 }
 
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Protocol {
     #[serde(rename = "hls")]
     Hls,
     #[serde(rename = "progressive")]
     Progressive,
+    /// HLS with DRM (used for some Go+ tracks), which can't be downloaded
+    #[serde(rename = "ctr-encrypted-hls")]
+    CtrEncryptedHls,
+    /// HLS with DRM (used for some Go+ tracks), which can't be downloaded
+    #[serde(rename = "cbc-encrypted-hls")]
+    CbcEncryptedHls,
+    #[serde(untagged)]
+    Other(String),
 }
 
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Quality {
     #[serde(rename = "hq")]
     Hq,
     #[serde(rename = "sq")]
     Sq,
+    #[serde(untagged)]
+    Other(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TrackState {
+    #[serde(rename = "finished")]
+    Finished,
+    #[serde(rename = "processing")]
+    Processing,
+    #[serde(rename = "failed")]
+    Failed,
+    #[serde(untagged)]
+    Other(String),
+}
+
+/// Determines whether or not a track can be streamed in full
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Policy {
+    #[serde(rename = "ALLOW")]
+    Allow,
+    #[serde(rename = "MONETIZE")]
+    Monetize,
+    /// Only a preview snippet of the track is available
+    #[serde(rename = "SNIP")]
+    Snip,
+    #[serde(rename = "BLOCK")]
+    Block,
+    #[serde(untagged)]
+    Other(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum MonetizationModel {
+    #[serde(rename = "NOT_APPLICABLE")]
+    NotApplicable,
+    #[serde(rename = "AD_SUPPORTED")]
+    AdSupported,
+    #[serde(rename = "SUB_HIGH_TIER")]
+    SubHighTier,
+    #[serde(rename = "BLACKBOX")]
+    Blackbox,
+    #[serde(untagged)]
+    Other(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Sharing {
+    #[serde(rename = "private")]
+    Private,
+    #[serde(rename = "public")]
+    Public,
+    #[serde(untagged)]
+    Other(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum License {
+    #[serde(rename = "all-rights-reserved")]
+    AllRightsReserved,
+    #[serde(rename = "no-rights-reserved")]
+    NoRightsReserved,
+    #[serde(rename = "cc-by")]
+    CcBy,
+    #[serde(rename = "cc-by-nc")]
+    CcByNc,
+    #[serde(rename = "cc-by-nd")]
+    CcByNd,
+    #[serde(rename = "cc-by-sa")]
+    CcBySa,
+    #[serde(rename = "cc-by-nc-nd")]
+    CcByNcNd,
+    #[serde(rename = "cc-by-nc-sa")]
+    CcByNcSa,
+    #[serde(untagged)]
+    Other(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum EmbeddableBy {
+    #[serde(rename = "all")]
+    All,
+    #[serde(rename = "me")]
+    Me,
+    #[serde(rename = "none")]
+    None,
+    #[serde(untagged)]
+    Other(String),
+}
+
+/// (De)serialization of the optional timestamps found throughout the API.
+///
+/// The v2 API hands out RFC 3339 timestamps, but some fields (`release_date`
+/// in particular) are occasionally empty strings or come back in the older v1
+/// format, so this is a bit more forgiving than chrono's own serde support.
+pub(crate) mod datetime {
+    use chrono::{DateTime, NaiveDate, SecondsFormat, TimeZone, Utc};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    /// The timestamp format used by the v1 API, e.g. `2019/11/20 05:41:07 +0000`
+    const V1_FORMAT: &str = "%Y/%m/%d %H:%M:%S %z";
+
+    pub fn parse(s: &str) -> Option<DateTime<Utc>> {
+        if let Ok(dt) = DateTime::parse_from_rfc3339(s) {
+            return Some(dt.with_timezone(&Utc));
+        }
+
+        if let Ok(dt) = DateTime::parse_from_str(s, V1_FORMAT) {
+            return Some(dt.with_timezone(&Utc));
+        }
+
+        NaiveDate::parse_from_str(s, "%Y-%m-%d")
+            .ok()
+            .and_then(|d| d.and_hms_opt(0, 0, 0))
+            .map(|dt| Utc.from_utc_datetime(&dt))
+    }
+
+    pub fn serialize<S: Serializer>(dt: &Option<DateTime<Utc>>, s: S) -> Result<S::Ok, S::Error> {
+        match dt {
+            Some(dt) => s.serialize_some(&dt.to_rfc3339_opts(SecondsFormat::Secs, true)),
+            None => s.serialize_none()
+        }
+    }
+
+    // A timestamp in a format we don't recognize is treated as missing rather
+    // than failing to deserialize the whole page it's on
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<DateTime<Utc>>, D::Error> {
+        Ok(Option::<String>::deserialize(d)?.and_then(|s| parse(&s)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn unknown_enum_values_fall_back() -> Result<(), serde_json::Error> {
+        let protocol: Protocol = serde_json::from_str(r#""progressive""#)?;
+        assert_eq!(protocol, Protocol::Progressive);
+
+        let protocol: Protocol = serde_json::from_str(r#""dash""#)?;
+        assert_eq!(protocol, Protocol::Other("dash".into()));
+        assert_eq!(serde_json::to_string(&protocol)?, r#""dash""#);
+
+        let quality: Quality = serde_json::from_str(r#""hq""#)?;
+        assert_eq!(quality, Quality::Hq);
+
+        let quality: Quality = serde_json::from_str(r#""lossless""#)?;
+        assert_eq!(quality, Quality::Other("lossless".into()));
+        assert_eq!(quality.rank(), Quality::Sq.rank());
+
+        let product: crate::api::me::ProductId = serde_json::from_str(r#""consumer-high-tier""#)?;
+        assert_eq!(product, crate::api::me::ProductId::ConsumerHighTier);
+
+        Ok(())
+    }
+
+    #[test]
+    fn lenient_timestamps() -> Result<(), serde_json::Error> {
+        let last_modified = |value: &str| -> Result<Option<String>, serde_json::Error> {
+            let user: User = serde_json::from_value(serde_json::json!({ "id": 1, "last_modified": value }))?;
+            Ok(user.last_modified.map(|dt| dt.to_rfc3339()))
+        };
+
+        assert_eq!(last_modified("2019-11-20T05:41:07Z")?.as_deref(), Some("2019-11-20T05:41:07+00:00"));
+        assert_eq!(last_modified("2019/11/20 05:41:07 +0000")?.as_deref(), Some("2019-11-20T05:41:07+00:00"));
+        assert_eq!(last_modified("")?, None);
+        assert_eq!(last_modified("sometime last week")?, None);
+
+        Ok(())
+    }
 }
@@ -2,25 +2,25 @@
 //
 // Turn on derive debug impl and make all properties optional
 
-// TODO: a lot of stuff that could use enums to strongly type certain properties
-// can't right now because serde doesn't support deserializing to an enum with
-// a non-unit variant fallback to capture the value as a string if it doesn't
-// match one of the existing unit variants
-//
-// see https://github.com/serde-rs/serde/pull/1382#issuecomment-424706998
+// Enums that mirror stringly-typed API values end with an untagged
+// `Other(String)` variant so that new values SoundCloud introduces don't break
+// deserialization; the raw string is kept around instead.
 
 use serde_derive::{Serialize, Deserialize};
+use serde_json::Value;
+use std::collections::HashMap;
 use super::common::*;
+use chrono::{DateTime, Utc};
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct LikesRaw {
-    pub collection: Option<Vec<LikesCollection>>,
-    pub next_href: Option<String>,
-}
+pub type LikesRaw = Page<LikesCollection>;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct LikesCollection {
-    pub created_at: Option<String>,
+    #[serde(default, with = "datetime")]
+    pub created_at: Option<DateTime<Utc>>,
     // Made this non-optional since it will always be present here
     pub track: Track,
+    /// Any fields returned by the API that aren't covered above
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
 }
@@ -3,20 +3,26 @@
 // Turn on derive debug impl and make all properties optional
 
 use serde_derive::{Serialize, Deserialize};
+use serde_json::Value;
+use std::collections::HashMap;
 use super::common::*;
+use super::ids::*;
+use chrono::{DateTime, Utc};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Me {
     pub avatar_url: Option<String>,
     pub blocked_tracks_count: Option<i64>,
     pub city: Option<String>,
+    pub country_code: Option<String>,
     pub comments_count: Option<i64>,
     pub consumer_subscriptions: Option<Vec<RSubscription>>,
     pub consumer_subscription: Option<RSubscription>,
-    pub created_at: Option<String>,
+    #[serde(default, with = "datetime")]
+    pub created_at: Option<DateTime<Utc>>,
     pub creator_subscriptions: Option<Vec<RSubscription>>,
     pub creator_subscription: Option<RSubscription>,
-    pub default_license: Option<String>,
+    pub default_license: Option<License>,
     pub default_tracks_feedable: Option<bool>,
     pub description: Option<String>,
     pub downloads_disabled: Option<bool>,
@@ -28,12 +34,14 @@ pub struct Me {
     pub gender: Option<String>,
     pub groups_count: Option<i64>,
     pub hidden_tracks_count: Option<i64>,
-    pub id: Option<i64>,
+    pub id: Option<UserId>,
     pub kind: Option<String>,
-    pub last_modified: Option<String>,
+    #[serde(default, with = "datetime")]
+    pub last_modified: Option<DateTime<Utc>>,
     pub last_name: Option<String>,
     pub likes_count: Option<i64>,
     pub playlist_likes_count: Option<i64>,
+    pub public_favorites_count: Option<i64>,
     pub locale: Option<String>,
     pub permalink: Option<String>,
     pub permalink_url: Option<String>,
@@ -50,20 +58,31 @@ pub struct Me {
     pub username: Option<String>,
     pub verified: Option<bool>,
     pub visuals: Option<Visuals>,
+    pub website: Option<String>,
+    pub website_title: Option<String>,
     pub confirmed: Option<bool>,
+    /// Any fields returned by the API that aren't covered above
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RSubscription {
     pub product: Option<Product>,
+    /// Any fields returned by the API that aren't covered above
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Product {
-    pub id: Option<String>,
+    pub id: Option<ProductId>,
+    /// Any fields returned by the API that aren't covered above
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Quota {
     pub unlimited_upload_quota: Option<bool>,
     pub upload_seconds_used: Option<i64>,
@@ -71,4 +90,26 @@ pub struct Quota {
     pub upload_tracks_used: Option<i64>,
     pub unlimited_upload_duration_quota: Option<bool>,
     pub unlimited_upload_track_quota: Option<bool>,
+    /// Any fields returned by the API that aren't covered above
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+/// The SoundCloud subscription products a user can have
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ProductId {
+    #[serde(rename = "free")]
+    Free,
+    /// SoundCloud Go
+    #[serde(rename = "consumer-mid-tier")]
+    ConsumerMidTier,
+    /// SoundCloud Go+
+    #[serde(rename = "consumer-high-tier")]
+    ConsumerHighTier,
+    #[serde(rename = "creator-pro")]
+    CreatorPro,
+    #[serde(rename = "creator-pro-unlimited")]
+    CreatorProUnlimited,
+    #[serde(untagged)]
+    Other(String),
 }
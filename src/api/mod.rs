@@ -1,18 +1,34 @@
+pub mod comments;
+pub mod artwork;
 pub mod common;
+pub mod ids;
 pub mod likes;
 pub mod me;
 pub mod playlists;
+pub(crate) mod raw;
+pub mod spotlight;
+pub mod stats;
+pub mod system_playlists;
 
 use serde_derive::{Serialize, Deserialize};
-use common::{Track, Quality, Protocol};
-use playlists::Playlist;
+use artwork::ArtworkSize;
+use common::{Format, Track, Transcoding, Quality, Protocol, User};
+use playlists::{Playlist, PlaylistKind, SetType};
 use likes::LikesCollection;
-use me::Me;
-use crate::{Error, Zester};
+use me::{Me, ProductId};
+use ids::TrackId;
+use crate::filename::track_artist;
+use crate::{catch_worker_panic, Error, Zester};
+use chrono::{DateTime, Utc};
+use crate::hls::HlsDownload;
+use crate::reader::{StallReader, ThrottledReader};
+use crate::retry::Retrier;
+use crate::events::TracksInfoCompletionEvent;
 use std::io::prelude::*;
-use std::collections::HashMap;
+use std::io::Cursor;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::sync::mpsc;
 use std::thread;
-use std::time::Duration;
 
 // TODO: fix naming discrepancies between fields of structs
 #[derive(Debug, Serialize, Deserialize)]
@@ -25,56 +41,530 @@ pub struct Playlists {
     pub playlists: Vec<Playlist>,
 }
 
+impl Likes {
+    /// The likes that were made during the given period.
+    ///
+    /// `since` is inclusive and `until` is exclusive; `None` leaves that end of
+    /// the period unbounded. Likes whose date is unknown are never included.
+    pub fn liked_between(
+        &self,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>
+    ) -> impl Iterator<Item = &LikesCollection> {
+        self.collections.iter().filter(move |c| match c.created_at {
+            Some(liked_at) => {
+                !matches!(since, Some(since) if liked_at < since) &&
+                    !matches!(until, Some(until) if liked_at >= until)
+            },
+            None => false
+        })
+    }
+
+    /// The liked tracks whose genre matches the given genre (ignoring case).
+    pub fn filter_by_genre(&self, genre: &str) -> Vec<&Track> {
+        self.collections.iter().map(|c| &c.track).filter(|t| t.has_genre(genre)).collect()
+    }
+
+    /// The liked tracks that have the given tag (ignoring case).
+    pub fn filter_by_tag(&self, tag: &str) -> Vec<&Track> {
+        self.collections.iter().map(|c| &c.track).filter(|t| t.has_tag(tag)).collect()
+    }
+
+    /// The liked tracks whose title contains the given text (ignoring case).
+    pub fn find_by_title(&self, substr: &str) -> Vec<&Track> {
+        self.tracks().filter(|t| title_contains(t, substr)).collect()
+    }
+
+    /// The liked tracks by the given artist (ignoring case).
+    ///
+    /// The artist is the one from the track's publisher metadata if present,
+    /// otherwise the uploader's username.
+    pub fn by_artist(&self, name: &str) -> Vec<&Track> {
+        self.tracks().filter(|t| is_by_artist(t, name)).collect()
+    }
+
+    /// The liked tracks sorted by title (ignoring case).
+    pub fn sorted_by_title(&self) -> Vec<&Track> {
+        sorted_by_title(self.tracks())
+    }
+
+    /// The liked tracks sorted from most to least played.
+    pub fn sorted_by_plays(&self) -> Vec<&Track> {
+        sorted_by_plays(self.tracks())
+    }
+
+    /// The liked tracks grouped by artist.
+    ///
+    /// Tracks without a known artist are left out.
+    pub fn grouped_by_artist(&self) -> BTreeMap<&str, Vec<&Track>> {
+        group_by(self.tracks(), track_artist)
+    }
+
+    /// The liked tracks grouped by genre.
+    ///
+    /// Tracks without a genre are left out.
+    pub fn grouped_by_genre(&self) -> BTreeMap<&str, Vec<&Track>> {
+        group_by(self.tracks(), |t| t.genre.as_deref().filter(|g| !g.is_empty()))
+    }
+
+    fn tracks(&self) -> impl Iterator<Item = &Track> {
+        self.collections.iter().map(|c| &c.track)
+    }
+}
+
+impl Playlists {
+    /// The tracks across all playlists whose genre matches the given genre
+    /// (ignoring case).
+    ///
+    /// Tracks that appear in multiple playlists are only included once.
+    pub fn filter_by_genre(&self, genre: &str) -> Vec<&Track> {
+        self.unique_tracks().filter(|t| t.has_genre(genre)).collect()
+    }
+
+    /// The tracks across all playlists that have the given tag (ignoring case).
+    ///
+    /// Tracks that appear in multiple playlists are only included once.
+    pub fn filter_by_tag(&self, tag: &str) -> Vec<&Track> {
+        self.unique_tracks().filter(|t| t.has_tag(tag)).collect()
+    }
+
+    /// The playlists that contain the track with the given id.
+    pub fn containing_track(&self, id: TrackId) -> Vec<&Playlist> {
+        self.playlists
+            .iter()
+            .filter(|p| p.tracks.iter().flatten().any(|t| t.id == Some(id)))
+            .collect()
+    }
+
+    /// The playlists whose title contains the given text (ignoring case).
+    pub fn find_by_title(&self, substr: &str) -> Vec<&Playlist> {
+        let substr = substr.to_lowercase();
+        self.playlists
+            .iter()
+            .filter(|p| p.title.as_ref().is_some_and(|t| t.to_lowercase().contains(&substr)))
+            .collect()
+    }
+
+    /// The tracks across all playlists by the given artist (ignoring case).
+    ///
+    /// Tracks that appear in multiple playlists are only included once.
+    pub fn by_artist(&self, name: &str) -> Vec<&Track> {
+        self.unique_tracks().filter(|t| is_by_artist(t, name)).collect()
+    }
+
+    /// The tracks across all playlists sorted by title (ignoring case).
+    ///
+    /// Tracks that appear in multiple playlists are only included once.
+    pub fn sorted_by_title(&self) -> Vec<&Track> {
+        sorted_by_title(self.unique_tracks())
+    }
+
+    /// The tracks across all playlists grouped by artist.
+    ///
+    /// Tracks that appear in multiple playlists are only included once, and
+    /// tracks without a known artist are left out.
+    pub fn grouped_by_artist(&self) -> BTreeMap<&str, Vec<&Track>> {
+        group_by(self.unique_tracks(), track_artist)
+    }
+
+    /// The playlists that are albums, EPs, singles, or compilations (see
+    /// `Playlist::is_album`).
+    pub fn albums(&self) -> Vec<&Playlist> {
+        self.playlists.iter().filter(|p| p.is_album()).collect()
+    }
+
+    /// The first album containing the track with the given id, along with the
+    /// track's (1-based) number on it.
+    pub fn album_for_track(&self, id: TrackId) -> Option<(&Playlist, usize)> {
+        self.playlists
+            .iter()
+            .filter(|p| p.is_album())
+            .find_map(|p| Some((p, p.track_number(id)?)))
+    }
+
+    /// All of the tracks across all playlists, without duplicates.
+    pub fn unique_tracks(&self) -> impl Iterator<Item = &Track> {
+        let mut seen_ids = HashSet::new();
+
+        self.playlists
+            .iter()
+            .flat_map(|p| p.tracks.iter().flatten())
+            .filter(move |t| match t.id {
+                Some(id) => seen_ids.insert(id),
+                None => true
+            })
+    }
+}
+
+/// Parse a `tag_list` into individual tags.
+///
+/// Tags are separated by whitespace, and multi-word tags are wrapped in double
+/// quotes (`house "deep house" chill`). An unterminated quote runs to the end
+/// of the list. Duplicates (ignoring case) are dropped, keeping the first.
+pub fn parse_tag_list(tag_list: &str) -> Vec<String> {
+    let mut tags: Vec<String> = vec![];
+    let mut push = |tag: &str| {
+        let tag = tag.split_whitespace().collect::<Vec<_>>().join(" ");
+        if !tag.is_empty() && !tags.iter().any(|t| t.to_lowercase() == tag.to_lowercase()) {
+            tags.push(tag);
+        }
+    };
+
+    let mut current = String::new();
+    let mut in_quotes = false;
+    for c in tag_list.chars() {
+        match c {
+            '"' => {
+                push(&current);
+                current.clear();
+                in_quotes = !in_quotes;
+            },
+            c if c.is_whitespace() && !in_quotes => {
+                push(&current);
+                current.clear();
+            },
+            c => current.push(c)
+        }
+    }
+    push(&current);
+
+    tags
+}
+
+fn title_contains(track: &Track, substr: &str) -> bool {
+    let substr = substr.to_lowercase();
+    track.title.as_ref().is_some_and(|t| t.to_lowercase().contains(&substr))
+}
+
+fn is_by_artist(track: &Track, name: &str) -> bool {
+    track_artist(track).is_some_and(|a| a.to_lowercase() == name.to_lowercase())
+}
+
+fn sorted_by_title<'a, I: Iterator<Item = &'a Track>>(tracks: I) -> Vec<&'a Track> {
+    let mut tracks: Vec<&Track> = tracks.collect();
+    tracks.sort_by_cached_key(|t| t.title.as_ref().map(|t| t.to_lowercase()));
+    tracks
+}
+
+fn sorted_by_plays<'a, I: Iterator<Item = &'a Track>>(tracks: I) -> Vec<&'a Track> {
+    let mut tracks: Vec<&Track> = tracks.collect();
+    tracks.sort_by_key(|t| std::cmp::Reverse(t.playback_count.unwrap_or(0)));
+    tracks
+}
+
+fn group_by<'a, I, K>(tracks: I, key: K) -> BTreeMap<&'a str, Vec<&'a Track>> where
+    I: Iterator<Item = &'a Track>,
+    K: Fn(&'a Track) -> Option<&'a str>
+{
+    let mut groups: BTreeMap<&str, Vec<&Track>> = BTreeMap::new();
+    for track in tracks {
+        if let Some(k) = key(track) {
+            groups.entry(k).or_default().push(track);
+        }
+    }
+    groups
+}
+
 impl Track {
+    /// The track's artwork URL, rewritten to point at the given size.
+    ///
+    /// Returns `None` if the track has no artwork or its URL isn't in the
+    /// expected format.
+    pub fn artwork_url_sized(&self, size: ArtworkSize) -> Option<String> {
+        artwork::sized_url(self.artwork_url.as_ref()?, size)
+    }
+
+    /// The track's tags, parsed from its `tag_list`.
+    ///
+    /// Tags are separated by whitespace, and tags containing spaces are
+    /// wrapped in double quotes. Duplicate tags are only included once.
+    pub fn tags(&self) -> Vec<String> {
+        self.tag_list.as_deref().map(parse_tag_list).unwrap_or_default()
+    }
+
+    /// Whether or not the track has the given tag (ignoring case).
+    pub fn has_tag(&self, tag: &str) -> bool {
+        let tag = tag.to_lowercase();
+        self.tags().iter().any(|t| t.to_lowercase() == tag)
+    }
+
+    /// Whether or not the track's genre matches the given genre (ignoring case).
+    pub fn has_genre(&self, genre: &str) -> bool {
+        self.genre.as_ref().is_some_and(|g| g.trim().to_lowercase() == genre.trim().to_lowercase())
+    }
+
+    /// The transcoding that will be used when downloading the track's audio.
+    pub fn download_transcoding(&self) -> Result<&Transcoding, Error> {
+        let transcodings = self.media
+            .as_ref()
+            .ok_or_else(|| Error::DataNotPresent("media information".into()))?
+            .transcodings
+            .as_ref()
+            .ok_or_else(|| Error::DataNotPresent("transcodings information".into()))?;
+
+        if self.is_drm_protected() {
+            return Err(Error::DrmProtected);
+        }
+
+        // prefer a high-quality progressive stream, falling back to the best
+        // standard-quality one (qualities we don't know about are treated as
+        // standard quality)
+        //
+        // some tracks only have HLS streams available, which are downloaded a
+        // segment at a time instead
+        //
+        // preview snippets are only used when there's nothing else
+        let best_with_protocol = |protocol: Protocol, snipped: bool| transcodings
+            .iter()
+            .filter(|t| t.format.protocol == protocol && t.snipped == snipped)
+            .fold(None, |best: Option<&Transcoding>, t| match best {
+                Some(b) if b.quality.rank() >= t.quality.rank() => Some(b),
+                _ => Some(t)
+            });
+
+        best_with_protocol(Protocol::Progressive, false)
+            .or_else(|| best_with_protocol(Protocol::Hls, false))
+            .or_else(|| best_with_protocol(Protocol::Progressive, true))
+            .or_else(|| best_with_protocol(Protocol::Hls, true))
+            .ok_or_else(|| Error::DataNotPresent("desired transcoding".into()))
+    }
+
+    /// Whether only a preview snippet of the track's audio is available (see
+    /// `ZesterConfig::download_snippets`).
+    pub fn is_snippet_only(&self) -> bool {
+        self.download_transcoding().is_ok_and(|t| t.snipped)
+    }
+
+    /// Whether the track's audio will be downloaded over HLS (a segment at a
+    /// time) rather than as a single file.
+    pub(crate) fn downloads_over_hls(&self) -> bool {
+        self.download_transcoding().is_ok_and(|t| t.format.protocol == Protocol::Hls)
+    }
+
+    /// Whether the track's audio is only available with DRM, which means it
+    /// can't be downloaded.
+    pub fn is_drm_protected(&self) -> bool {
+        let transcodings = match self.media.as_ref().and_then(|m| m.transcodings.as_ref()) {
+            Some(transcodings) if !transcodings.is_empty() => transcodings,
+            _ => return false
+        };
+
+        transcodings.iter().all(|t| t.format.protocol.is_encrypted())
+    }
+
+    /// Resolve the URL of the track's audio file.
+    ///
+    /// For tracks that are only available over HLS, this is the URL of the
+    /// playlist listing the audio's segments.
+    pub fn media_url(&self, zester: &Zester) -> Result<String, Error> {
+        let info_url = &self.download_transcoding()?.url;
+
+        // private tracks can only be streamed with their secret token
+        let query: Vec<_> = self.secret_token.iter().map(|token| ("secret_token", token.as_str())).collect();
+
+        // the transcoding URL points to some JSON containing the actual URL to
+        // the media file
+        let info_json: serde_json::Value = serde_json::from_str(&zester.api_req_full(info_url, &query, false)?)?;
+        if let Some(url) = info_json.get("url").and_then(|u| u.as_str()) {
+            Ok(url.into())
+        } else {
+            Err(Error::DataNotPresent("media file url in info json".into()))
+        }
+    }
+
+    /// Estimate the size in bytes of the track's audio file from its duration
+    /// and bitrate, without making any requests.
+    ///
+    /// Returns `None` if the track can't be downloaded.
+    pub fn estimated_size(&self) -> Option<u64> {
+        let transcoding = self.download_transcoding().ok()?;
+        Some(transcoding.duration.max(0) as u64 * transcoding.estimated_bitrate() / 8 / 1000)
+    }
+
     /// Download the track's associated audio file and return a `Read` instance
     /// providing the data.
     pub fn download(&self, zester: &Zester) -> Result<impl Read, Error> {
-        // first we need to determine what we're downloading
-        let info_url;
-        if let Some(media) = &self.media {
-            if let Some(transcodings) = &media.transcodings {
-                    // TODO: make selection more robust
-                    // right now we just look for the first progressive stream that's
-                    // also high-quality and bail out if we don't find one
-
-                    // TODO: also going to have to support HLS
-                    // some tracks only have HLS streams available for download
-                    if let Some(transcoding) = transcodings
-                        .iter()
-                        .find(|t|
-                            t.quality == Quality::Hq &&
-                            t.format.protocol == Protocol::Progressive
-                        ) {
-                        info_url = &transcoding.url;
-                    } else {
-                        return Err(Error::DataNotPresent("desired transcoding".into()))
-                    }
-            } else {
-                return Err(Error::DataNotPresent("transcodings information".into()))
-            }
-        } else {
-            return Err(Error::DataNotPresent("media information".into()))
+        Ok(self.download_with_len(zester)?.0)
+    }
+
+    /// Same as `download`, but also returns the size of the audio file if the
+    /// server reported it.
+    pub(crate) fn download_with_len(&self, zester: &Zester) -> Result<(impl Read, Option<u64>), Error> {
+        let media_url = self.media_url(zester)?;
+
+        if self.downloads_over_hls() {
+            let mut download = HlsDownload::start(zester, &media_url)?;
+            download.download_remaining(zester, |_| true)?;
+            let data = download.into_audio(&self.download_transcoding()?.format.mime_type);
+            let len = data.len() as u64;
+
+            return Ok((Box::new(Cursor::new(data)) as Box<dyn Read>, Some(len)));
+        }
+
+        let (reader, len) = download_media(zester, &media_url)?;
+        Ok((Box::new(reader), len))
+    }
+}
+
+/// Download the audio file at the given media URL (see `Track::media_url`),
+/// returning its size as well if the server reported it.
+pub(crate) fn download_media(zester: &Zester, media_url: &str) -> Result<(impl Read, Option<u64>), Error> {
+    let (reader, len) = zester.call_with_len(&mut ureq::get(media_url))?;
+    let reader = StallReader::new(reader, zester.config.stall_timeout);
+    let reader = ThrottledReader::new(reader, zester.throttle.clone(), zester.config.max_download_rate);
+
+    Ok((reader, len))
+}
+
+impl User {
+    /// The user's avatar URL, rewritten to point at the given size.
+    ///
+    /// Returns `None` if the user has no avatar or its URL isn't in the
+    /// expected format.
+    pub fn avatar_url_sized(&self, size: ArtworkSize) -> Option<String> {
+        artwork::sized_url(self.avatar_url.as_ref()?, size)
+    }
+}
+
+/// The file extension conventionally used for audio of the given mime type
+/// (such as `audio/mp4; codecs="mp4a.40.2"`), if it's one we know about.
+pub fn extension_for_mime_type(mime_type: &str) -> Option<&'static str> {
+    let mut parts = mime_type.split(';');
+    let essence = parts.next().unwrap_or("").trim().to_lowercase();
+    let codecs = parts
+        .map(str::trim)
+        .find_map(|p| p.strip_prefix("codecs="))
+        .map(|c| c.trim_matches('"').to_lowercase())
+        .unwrap_or_default();
+
+    match essence.as_str() {
+        "audio/mpeg" | "audio/mp3" => Some("mp3"),
+        "audio/mp4" | "audio/x-m4a" | "audio/m4a" => Some("m4a"),
+        "audio/aac" => Some("aac"),
+        "audio/ogg" | "audio/opus" if codecs.contains("opus") || essence == "audio/opus" => Some("opus"),
+        "audio/ogg" => Some("ogg"),
+        "audio/webm" => Some("webm"),
+        "audio/flac" | "audio/x-flac" => Some("flac"),
+        "audio/wav" | "audio/x-wav" | "audio/wave" => Some("wav"),
+        _ => None
+    }
+}
+
+impl Format {
+    /// The file extension for audio in this format, if its mime type is one we
+    /// know about.
+    pub fn file_extension(&self) -> Option<&'static str> {
+        extension_for_mime_type(&self.mime_type)
+    }
+}
+
+impl PlaylistKind {
+    /// Whether this is an album, EP, single, or compilation.
+    pub fn is_album(&self) -> bool {
+        matches!(self, PlaylistKind::Album | PlaylistKind::Ep | PlaylistKind::Single | PlaylistKind::Compilation)
+    }
+}
+
+impl Protocol {
+    /// Whether the protocol uses DRM.
+    pub fn is_encrypted(&self) -> bool {
+        matches!(self, Protocol::CtrEncryptedHls | Protocol::CbcEncryptedHls)
+    }
+}
+
+impl Quality {
+    /// How desirable this quality is when choosing a transcoding to download;
+    /// higher is better.
+    ///
+    /// Qualities this crate doesn't know about rank the same as `Sq`.
+    pub fn rank(&self) -> u8 {
+        match self {
+            Quality::Hq => 1,
+            Quality::Sq | Quality::Other(_) => 0
         }
+    }
+}
 
-        // now we use the URL we got to get the actual URL to the media file
-        let info_json: serde_json::Value = serde_json::from_str(&zester.api_req_full(info_url, &[], false)?)?;
-        if let Some(url) = info_json.get("url") {
-            Ok(ureq::get(url.as_str().unwrap()).call().into_reader())
+impl Transcoding {
+    /// A best guess at the bitrate of this transcoding in bits per second.
+    ///
+    /// Presets that include their bitrate (such as `aac_256k`) are trusted;
+    /// otherwise this falls back to typical values for the codec.
+    pub fn estimated_bitrate(&self) -> u64 {
+        let from_preset = self.preset
+            .split('_')
+            .find_map(|part| part.strip_suffix('k').and_then(|kbps| kbps.parse::<u64>().ok()));
+
+        if let Some(kbps) = from_preset {
+            kbps * 1000
+        } else if self.preset.starts_with("opus") {
+            64_000
         } else {
-            Err(Error::DataNotPresent("media file url in info json".into()))
+            128_000
         }
     }
 }
 
 impl Me {
+    /// The total number of playlists the user has created or liked.
+    ///
+    /// Counts that are missing from the API response are treated as zero.
     pub fn total_playlist_count(&self) -> i64 {
-        self.playlist_count.unwrap() +
-            self.playlist_likes_count.unwrap() +
-            self.private_playlists_count.unwrap()
+        self.playlist_count.unwrap_or(0) +
+            self.playlist_likes_count.unwrap_or(0) +
+            self.private_playlists_count.unwrap_or(0)
+    }
+
+    /// The user's consumer subscription product (such as SoundCloud Go+), if
+    /// the API reported one.
+    pub fn consumer_product(&self) -> Option<&ProductId> {
+        self.consumer_subscription
+            .iter()
+            .chain(self.consumer_subscriptions.iter().flatten())
+            .find_map(|s| s.product.as_ref()?.id.as_ref())
+    }
+
+    /// Whether the user is subscribed to SoundCloud Go+, which gives access to
+    /// full-length versions of tracks that are otherwise only available as
+    /// previews.
+    pub fn has_go_plus(&self) -> bool {
+        self.consumer_product() == Some(&ProductId::ConsumerHighTier)
     }
 }
 
 impl Playlist {
+    /// The playlist's artwork URL, rewritten to point at the given size.
+    ///
+    /// Returns `None` if the playlist has no artwork or its URL isn't in the
+    /// expected format.
+    pub fn artwork_url_sized(&self, size: ArtworkSize) -> Option<String> {
+        artwork::sized_url(self.artwork_url.as_ref()?, size)
+    }
+
+    /// The playlist's tags, parsed from its `tag_list` (see `parse_tag_list`).
+    pub fn tags(&self) -> Vec<String> {
+        self.tag_list.as_deref().map(parse_tag_list).unwrap_or_default()
+    }
+
+    /// Whether the playlist is an album, EP, single, or compilation rather
+    /// than an ordinary playlist.
+    ///
+    /// The order of an album's tracks is meaningful, so they're given track
+    /// numbers when archived.
+    pub fn is_album(&self) -> bool {
+        self.is_album == Some(true)
+            || self.kind.as_ref().is_some_and(PlaylistKind::is_album)
+            || matches!(
+                self.set_type,
+                Some(SetType::Album) | Some(SetType::Ep) | Some(SetType::Single) | Some(SetType::Compilation)
+            )
+    }
+
+    /// The (1-based) position of the track with the given id in the playlist.
+    pub fn track_number(&self, id: TrackId) -> Option<usize> {
+        self.tracks.iter().flatten().position(|t| t.id == Some(id)).map(|i| i + 1)
+    }
+
     /// Make sure all info is present for all tracks in this playlist.
     /// 
     /// I noticed during the implementation of downloading the audio for all of
@@ -82,12 +572,53 @@ impl Playlist {
     /// API is not complete (and is notably lacking the media URLs which we of
     /// course need).
     /// 
-    /// This method fixes that by making some batch requests for track info
-    // TODO: add event hooks
-    pub fn complete_tracks_info(&mut self, zester: &Zester) -> Result<(), Error> {
+    /// This method fixes that by making some batch requests for track info.
+    /// Large playlists don't include their whole track list either; the ids of
+    /// any tracks that are left out (according to `track_count`) are looked up
+    /// first so that they can be completed too.
+    ///
+    /// The tracks are kept in the order the playlist lists them in.
+    ///
+    /// The callback you provide will be called when various events occur,
+    /// allowing you to follow the progress of the batches.
+    pub fn complete_tracks_info<F: Fn(TracksInfoCompletionEvent)>(&mut self, zester: &Zester, cb: F) -> Result<(), Error> {
+        self.complete_tracks_info_with(zester, &mut Retrier::new(zester), cb)
+    }
+
+    // Implementation of `complete_tracks_info`, counting retries with the given
+    // retrier
+    pub(crate) fn complete_tracks_info_with<F: Fn(TracksInfoCompletionEvent)>(
+        &mut self,
+        zester: &Zester,
+        retrier: &mut Retrier,
+        cb: F
+    ) -> Result<(), Error> {
+        use TracksInfoCompletionEvent::*;
+
         let mut track_ids_to_complete = vec![];
         let mut info_map = HashMap::new();
-        let pause_secs = 2;
+
+        // Large playlists only include part of their track list, so the ids
+        // of the rest of the tracks have to be looked up separately
+        let num_embedded = self.tracks.as_ref().map_or(0, Vec::len);
+        if self.id.is_some() && self.track_count.is_some_and(|n| n > num_embedded as i64) {
+            let ids = loop {
+                match self.track_ids(zester) {
+                    Ok(ids) => break ids,
+                    Err(e) => {
+                        if e.is_retryable() && retrier.pause(|retry| cb(PausedAfterServerError { retry }))? {
+                            continue;
+                        }
+                        return Err(e);
+                    }
+                }
+            };
+            retrier.reset();
+
+            let tracks = merge_track_ids(self.tracks.take().unwrap_or_default(), &ids);
+            cb(FetchedRemainingTrackIds { num: tracks.len().saturating_sub(num_embedded) as u64 });
+            self.tracks = Some(tracks);
+        }
 
         let tracks = if let Some(tracks) = &self.tracks {
             tracks
@@ -95,39 +626,302 @@ impl Playlist {
             return Ok(());
         };
 
+        // Tracks without an id can't be looked up, so they're left as they are
         for track in tracks.iter() {
-            if track.media.is_none() {
-                track_ids_to_complete.push(track.id.unwrap() as u64);
+            if let (None, Some(id)) = (&track.media, track.id) {
+                match zester.cached_track(id) {
+                    Some(cached) => {
+                        info_map.insert(id, cached);
+                    },
+                    None => track_ids_to_complete.push(id)
+                }
             }
         }
+        // the same track can appear in a playlist more than once
+        track_ids_to_complete.sort_unstable();
+        track_ids_to_complete.dedup();
 
-        let mut chunks_iter = track_ids_to_complete.chunks(10);
-        let mut maybe_chunk = chunks_iter.next();
-        while let Some(ids) = maybe_chunk {
-            for track in match zester.tracks_info(ids) {
-                Ok(t) => t,
-                Err(Error::HttpError(code)) if code >= 500 && code < 600 => {
-                    // the server responded with an error. waiting a couple of seconds
-                    // and then trying again seems to resolve this, so that's
-                    // what we'll do
-                    thread::sleep(Duration::from_secs(pause_secs));
-                    continue;
-                },
-                Err(e) => return Err(e)
-            } {
-                info_map.insert(track.id.unwrap(), track);
-            }
+        let batches: Vec<_> = track_ids_to_complete.chunks(zester.config.track_info_batch_size.max(1)).collect();
+        cb(NumBatchesToDownload { num: batches.len() as u64, tracks_num: track_ids_to_complete.len() as u64 });
 
-            maybe_chunk = chunks_iter.next();
-        }
+        // Batches are fetched on worker threads, while events and retries are
+        // dealt with here so that the callback doesn't need to be thread-safe
+        let workers = zester.config.track_info_concurrency.max(1);
+        let mut pending: VecDeque<usize> = (0..batches.len()).collect();
+        thread::scope(|s| -> Result<(), Error> {
+            let (tx, rx) = mpsc::channel();
+            let mut in_flight = 0;
+
+            loop {
+                while in_flight < workers {
+                    let index = match pending.pop_front() {
+                        Some(index) => index,
+                        None => break
+                    };
+
+                    cb(StartBatchDownload { index, num_tracks: batches[index].len() });
+                    let tx = tx.clone();
+                    let ids = batches[index];
+                    s.spawn(move || {
+                        let result = catch_worker_panic("track info", || zester.tracks_info(ids));
+                        let _ = tx.send((index, result));
+                    });
+                    in_flight += 1;
+                }
+
+                if in_flight == 0 {
+                    return Ok(());
+                }
+
+                let (index, result) = rx.recv().expect("track info worker exited without sending");
+                in_flight -= 1;
+                match result {
+                    Ok(tracks) => {
+                        cb(FinishBatchDownload { index, count: tracks.len() });
+                        for track in tracks {
+                            if let Some(id) = track.id {
+                                zester.cache_track(&track);
+                                info_map.insert(id, track);
+                            }
+                        }
+                        retrier.reset();
+                    },
+                    Err(e) => {
+                        // the server responded with an error. waiting a bit and
+                        // then trying again seems to resolve this, so that's
+                        // what we'll do
+                        if e.is_retryable() && retrier.pause(|retry| cb(PausedAfterServerError { retry }))? {
+                            pending.push_front(index);
+                            continue;
+                        }
+                        return Err(e);
+                    }
+                }
+            }
+        })?;
 
         // Replace info in this playlist with the info we obtained
-        for track in self.tracks.as_mut().unwrap().iter_mut() {
-            if let Some(updated_track) = info_map.remove(track.id.as_ref().unwrap()) {
-                *track = updated_track;
+        let mut unavailable = vec![];
+        for track in self.tracks.iter_mut().flatten() {
+            if let Some(updated_track) = track.id.and_then(|id| info_map.get(&id)) {
+                *track = updated_track.clone();
+            } else if let (None, Some(id)) = (&track.media, track.id) {
+                unavailable.push(id);
             }
         }
+        if !unavailable.is_empty() {
+            cb(TracksUnavailable { ids: unavailable });
+        }
 
         Ok(())
     }
+
+    // The ids of all of the tracks in this playlist, in order
+    fn track_ids(&self, zester: &Zester) -> Result<Vec<TrackId>, Error> {
+        #[derive(Deserialize)]
+        struct TrackIds {
+            tracks: Vec<TrackStub>
+        }
+
+        #[derive(Deserialize)]
+        struct TrackStub {
+            id: TrackId
+        }
+
+        let id = self.id.ok_or_else(|| Error::DataNotPresent("playlist id".into()))?;
+        let json_string = zester.api_req(&format!("playlists/{}", id), &[("representation", "id")])?;
+        let ids: TrackIds = serde_json::from_str(&json_string)?;
+
+        Ok(ids.tracks.into_iter().map(|t| t.id).collect())
+    }
+}
+
+// Rebuild a playlist's track list from the complete list of its track ids,
+// reusing the track info that was already present and leaving just an id for
+// the rest (which `complete_tracks_info` then fills in)
+pub(crate) fn merge_track_ids(tracks: Vec<Track>, ids: &[TrackId]) -> Vec<Track> {
+    let mut known: HashMap<TrackId, Vec<Track>> = HashMap::new();
+    let mut without_ids = vec![];
+    for track in tracks.into_iter().rev() {
+        match track.id {
+            Some(id) => known.entry(id).or_default().push(track),
+            None => without_ids.push(track)
+        }
+    }
+
+    let mut merged: Vec<Track> = ids
+        .iter()
+        .map(|&id| known.get_mut(&id).and_then(Vec::pop).unwrap_or_else(|| stub_track(id)))
+        .collect();
+    merged.extend(without_ids.into_iter().rev());
+
+    merged
+}
+
+// A track with nothing but an id
+fn stub_track(id: TrackId) -> Track {
+    serde_json::from_value(serde_json::json!({ "id": id }))
+        .expect("a track can be made from just an id")
+}
+
+#[cfg(test)]
+mod test {
+    use super::{extension_for_mime_type, merge_track_ids, parse_tag_list};
+    use super::common::Track;
+    use super::playlists::{Playlist, PlaylistKind};
+    use crate::Error;
+    use super::ids::TrackId;
+    use super::artwork::{sized_url, ArtworkSize};
+
+    #[test]
+    fn tag_list_parsing() {
+        assert_eq!(
+            parse_tag_list(r#"house "deep house"  chill	"lo fi" House"#),
+            vec!["house", "deep house", "chill", "lo fi"]
+        );
+        assert_eq!(parse_tag_list(r#"a"b c"d"#), vec!["a", "b c", "d"]);
+        assert_eq!(parse_tag_list(r#""unterminated quote"#), vec!["unterminated quote"]);
+        assert!(parse_tag_list(r#"  "" "#).is_empty());
+    }
+
+    #[test]
+    fn artwork_url_rewriting() {
+        assert_eq!(
+            sized_url("https://i1.sndcdn.com/artworks-000123-abc-large.jpg", ArtworkSize::T500x500).as_deref(),
+            Some("https://i1.sndcdn.com/artworks-000123-abc-t500x500.jpg")
+        );
+        assert_eq!(
+            sized_url("https://i1.sndcdn.com/avatars-000123-abc-large.png?1", ArtworkSize::Original).as_deref(),
+            Some("https://i1.sndcdn.com/avatars-000123-abc-original.png?1")
+        );
+        assert_eq!(sized_url("https://i1.sndcdn.com/artworks-000123-abc.jpg", ArtworkSize::Crop), None);
+    }
+
+    #[test]
+    fn mime_type_extensions() {
+        let cases = [
+            ("audio/mpeg", Some("mp3")),
+            ("audio/mp3", Some("mp3")),
+            ("audio/mp4; codecs=\"mp4a.40.2\"", Some("m4a")),
+            ("audio/x-m4a", Some("m4a")),
+            ("audio/m4a", Some("m4a")),
+            ("audio/aac", Some("aac")),
+            ("audio/ogg; codecs=\"opus\"", Some("opus")),
+            ("audio/opus", Some("opus")),
+            ("audio/ogg", Some("ogg")),
+            ("audio/ogg; codecs=vorbis", Some("ogg")),
+            ("audio/webm", Some("webm")),
+            ("audio/flac", Some("flac")),
+            ("audio/x-flac", Some("flac")),
+            ("audio/wav", Some("wav")),
+            ("audio/x-wav", Some("wav")),
+            ("audio/wave", Some("wav")),
+            (" Audio/MPEG ", Some("mp3")),
+            // types we don't know fall back to no extension at all, rather
+            // than a guess
+            ("audio/x-unknown", None),
+            ("video/mp4", None),
+            ("", None),
+        ];
+
+        for (mime_type, extension) in cases {
+            assert_eq!(extension_for_mime_type(mime_type), extension, "{:?}", mime_type);
+        }
+    }
+
+    #[test]
+    fn track_id_merging() {
+        let track = |id: u64, title: &str| -> Track {
+            serde_json::from_value(serde_json::json!({ "id": id, "title": title })).unwrap()
+        };
+
+        let embedded = vec![track(1, "one"), track(2, "two")];
+        let ids: Vec<TrackId> = serde_json::from_str("[1, 3, 2, 4]").unwrap();
+        let merged = merge_track_ids(embedded, &ids);
+
+        assert_eq!(merged.iter().map(|t| t.id).collect::<Vec<_>>(), ids.into_iter().map(Some).collect::<Vec<_>>());
+        assert_eq!(merged[0].title.as_deref(), Some("one"));
+        assert_eq!(merged[1].title, None);
+        assert_eq!(merged[2].title.as_deref(), Some("two"));
+    }
+
+    #[test]
+    fn snippet_transcodings() {
+        let transcoding = |protocol: &str, snipped: bool| serde_json::json!({
+            "url": format!("https://api-v2.soundcloud.com/media/{}", protocol),
+            "preset": "mp3_0_0",
+            "duration": 30000,
+            "snipped": snipped,
+            "format": { "protocol": protocol, "mime_type": "audio/mpeg" },
+            "quality": "sq"
+        });
+        let track = |transcodings: Vec<serde_json::Value>| -> Track {
+            serde_json::from_value(serde_json::json!({ "id": 1, "media": { "transcodings": transcodings } })).unwrap()
+        };
+
+        // a full stream is preferred over a snippet, even over HLS
+        let full = track(vec![transcoding("progressive", true), transcoding("hls", false)]);
+        assert!(!full.is_snippet_only());
+        assert!(full.download_transcoding().unwrap().url.ends_with("hls"));
+
+        let snippet = track(vec![transcoding("hls", true), transcoding("progressive", true)]);
+        assert!(snippet.is_snippet_only());
+        assert!(snippet.download_transcoding().unwrap().url.ends_with("progressive"));
+    }
+
+    #[test]
+    fn playlist_kinds() {
+        let playlist = |kind: &str| -> Playlist {
+            serde_json::from_value(serde_json::json!({ "id": 1, "kind": kind })).unwrap()
+        };
+
+        assert_eq!(playlist("playlist").kind, Some(PlaylistKind::Playlist));
+        assert_eq!(playlist("playlist-like").kind, Some(PlaylistKind::PlaylistLike));
+        assert_eq!(playlist("ep").kind, Some(PlaylistKind::Ep));
+        assert_eq!(playlist("mixtape").kind, Some(PlaylistKind::Other("mixtape".into())));
+
+        for kind in ["album", "ep", "single", "compilation"] {
+            assert!(playlist(kind).is_album(), "{}", kind);
+        }
+        for kind in ["playlist", "playlist-like", "mixtape"] {
+            assert!(!playlist(kind).is_album(), "{}", kind);
+        }
+
+        // unknown kinds are written back out as they were read
+        assert_eq!(serde_json::to_value(&playlist("mixtape").kind).unwrap(), "mixtape");
+    }
+
+    #[test]
+    fn drm_protection() {
+        let transcoding = |protocol: &str| serde_json::json!({
+            "url": format!("https://api-v2.soundcloud.com/media/{}", protocol),
+            "preset": "aac_160k",
+            "duration": 180000,
+            "snipped": false,
+            "format": { "protocol": protocol, "mime_type": "audio/mp4; codecs=\"mp4a.40.2\"" },
+            "quality": "hq"
+        });
+        let track = |media: serde_json::Value| -> Track {
+            serde_json::from_value(serde_json::json!({ "id": 1, "media": media })).unwrap()
+        };
+
+        let protected = track(serde_json::json!({
+            "transcodings": [transcoding("ctr-encrypted-hls"), transcoding("cbc-encrypted-hls")]
+        }));
+        assert!(protected.is_drm_protected());
+        assert!(matches!(protected.download_transcoding(), Err(Error::DrmProtected)));
+
+        // any unencrypted stream can still be downloaded
+        let mixed = track(serde_json::json!({ "transcodings": [transcoding("cbc-encrypted-hls"), transcoding("hls")] }));
+        assert!(!mixed.is_drm_protected());
+        assert!(mixed.download_transcoding().unwrap().url.ends_with("/hls"));
+
+        assert!(!track(serde_json::json!({ "transcodings": [transcoding("progressive")] })).is_drm_protected());
+        assert!(!track(serde_json::json!({ "transcodings": [transcoding("some-new-protocol")] })).is_drm_protected());
+
+        // missing information isn't treated as DRM
+        assert!(!track(serde_json::json!({ "transcodings": [] })).is_drm_protected());
+        assert!(!track(serde_json::json!({})).is_drm_protected());
+        assert!(!track(serde_json::Value::Null).is_drm_protected());
+    }
 }
@@ -2,15 +2,16 @@
 //
 // Turn on derive debug impl and make all properties optional
 
-// TODO: a lot of stuff that could use enums to strongly type certain properties
-// can't right now because serde doesn't support deserializing to an enum with
-// a non-unit variant fallback to capture the value as a string if it doesn't
-// match one of the existing unit variants
-//
-// see https://github.com/serde-rs/serde/pull/1382#issuecomment-424706998
+// Enums that mirror stringly-typed API values end with an untagged
+// `Other(String)` variant so that new values SoundCloud introduces don't break
+// deserialization; the raw string is kept around instead.
 
 use serde_derive::{Serialize, Deserialize};
+use serde_json::Value;
+use std::collections::HashMap;
 use super::common::*;
+use super::ids::*;
+use chrono::{DateTime, Utc};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Playlist {
@@ -24,85 +25,88 @@ pub struct Playlist {
     pub uri: Option<String>,
     pub label_name: Option<String>,
     pub tag_list: Option<String>,
-    pub set_type: Option<String>,
+    pub set_type: Option<SetType>,
     pub public: Option<bool>,
     pub track_count: Option<i64>,
-    pub user_id: Option<i64>,
-    pub last_modified: Option<String>,
-    pub license: Option<String>,
+    pub user_id: Option<UserId>,
+    #[serde(default, with = "datetime")]
+    pub last_modified: Option<DateTime<Utc>>,
+    pub license: Option<License>,
     pub tracks: Option<Vec<Track>>,
-    pub id: Option<i64>,
-    pub release_date: Option<String>,
-    pub display_date: Option<String>,
-    pub sharing: Option<String>,
+    pub id: Option<PlaylistId>,
+    #[serde(default, with = "datetime")]
+    pub release_date: Option<DateTime<Utc>>,
+    #[serde(default, with = "datetime")]
+    pub display_date: Option<DateTime<Utc>>,
+    pub sharing: Option<Sharing>,
     pub secret_token: Option<String>,
-    pub created_at: Option<String>,
+    #[serde(default, with = "datetime")]
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(alias = "favoritings_count")]
     pub likes_count: Option<i64>,
-    pub kind: Option<String>,
+    pub kind: Option<PlaylistKind>,
     pub title: Option<String>,
     pub purchase_title: Option<String>,
     pub managed_by_feeds: Option<bool>,
     pub artwork_url: Option<String>,
     pub is_album: Option<bool>,
     pub user: Option<User>,
-    pub published_at: Option<String>,
-    pub embeddable_by: Option<String>,
+    #[serde(default, with = "datetime")]
+    pub published_at: Option<DateTime<Utc>>,
+    pub embeddable_by: Option<EmbeddableBy>,
+    /// The JSON this playlist was deserialized from, if
+    /// `ZesterConfig::keep_raw_json` was enabled when it was fetched
+    #[serde(default, rename = "_raw", skip_serializing_if = "Option::is_none")]
+    pub raw: Option<Value>,
+    /// Any fields returned by the API that aren't covered above
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
 }
 
 // This stuff and below is for the json that specifies playlists to grab info
 // for
 //
 // Their API stuffs a bunch of extra data in here for some reason
-#[derive(Debug, Serialize, Deserialize)]
-pub struct PlaylistsRaw {
-    pub collection: Option<Vec<PlaylistCollection>>,
-    pub next_href: Option<String>,
-}
+pub type PlaylistsRaw = Page<PlaylistCollection>;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PlaylistCollection {
     pub playlist: Option<PlaylistMeta>,
-    pub created_at: Option<String>,
+    #[serde(default, with = "datetime")]
+    pub created_at: Option<DateTime<Utc>>,
     pub user: Option<User>,
     pub uuid: Option<String>,
+    /// Any fields returned by the API that aren't covered above
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct PlaylistMeta {
-    pub managed_by_feeds: Option<bool>,
-    pub kind: Option<Type>,
-    pub set_type: Option<SetType>,
-    pub created_at: Option<String>,
-    pub sharing: Option<Sharing>,
-    pub title: Option<String>,
-    pub track_count: Option<i64>,
-    pub uri: Option<String>,
-    pub secret_token: Option<String>,
-    pub duration: Option<i64>,
-    pub likes_count: Option<i64>,
-    pub artwork_url: Option<String>,
-    pub public: Option<bool>,
-    pub display_date: Option<String>,
-    pub user_id: Option<i64>,
-    pub is_album: Option<bool>,
-    pub id: Option<i64>,
-    pub reposts_count: Option<i64>,
-    pub permalink: Option<String>,
-    pub permalink_url: Option<String>,
-    pub published_at: Option<String>,
-    pub last_modified: Option<String>,
-    pub user: Option<User>,
-}
+/// The "meta"-data about a playlist found in lists of playlists.
+///
+/// This is the same as a `Playlist`, just without the tracks (and a few other
+/// fields) filled in.
+pub type PlaylistMeta = Playlist;
 
-#[derive(Debug, Serialize, Deserialize)]
-pub enum Type {
+/// The kind of set a playlist is.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub enum PlaylistKind {
     #[serde(rename = "playlist")]
     Playlist,
     #[serde(rename = "playlist-like")]
     PlaylistLike,
+    #[serde(rename = "album")]
+    Album,
+    #[serde(rename = "ep")]
+    Ep,
+    #[serde(rename = "single")]
+    Single,
+    #[serde(rename = "compilation")]
+    Compilation,
+    #[serde(untagged)]
+    Other(String),
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub enum SetType {
     #[serde(rename = "album")]
     Album,
@@ -112,12 +116,8 @@ pub enum SetType {
     Ep,
     #[serde(rename = "single")]
     Single,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub enum Sharing {
-    #[serde(rename = "private")]
-    Private,
-    #[serde(rename = "public")]
-    Public,
+    #[serde(rename = "compilation")]
+    Compilation,
+    #[serde(untagged)]
+    Other(String),
 }
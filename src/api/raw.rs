@@ -0,0 +1,62 @@
+//! Keeping the JSON that tracks and playlists were deserialized from (see
+//! `ZesterConfig::keep_raw_json`).
+
+use super::common::{Page, Track};
+use super::likes::LikesCollection;
+use super::playlists::{Playlist, PlaylistCollection};
+use serde_json::Value;
+
+/// Something that holds tracks or playlists whose raw JSON can be kept.
+pub(crate) trait KeepRaw {
+    /// Store the parts of `raw` (the JSON `self` was deserialized from) in the
+    /// tracks and playlists within `self`.
+    fn keep_raw(&mut self, raw: Value);
+}
+
+impl KeepRaw for Track {
+    fn keep_raw(&mut self, raw: Value) {
+        self.raw = Some(raw);
+    }
+}
+
+// The playlist's tracks are left alone, since their JSON is already part of
+// the playlist's
+impl KeepRaw for Playlist {
+    fn keep_raw(&mut self, raw: Value) {
+        self.raw = Some(raw);
+    }
+}
+
+impl KeepRaw for LikesCollection {
+    fn keep_raw(&mut self, mut raw: Value) {
+        if let Some(track) = raw.get_mut("track") {
+            self.track.keep_raw(track.take());
+        }
+    }
+}
+
+impl KeepRaw for PlaylistCollection {
+    fn keep_raw(&mut self, mut raw: Value) {
+        if let (Some(playlist), Some(raw)) = (self.playlist.as_mut(), raw.get_mut("playlist")) {
+            playlist.keep_raw(raw.take());
+        }
+    }
+}
+
+impl<T: KeepRaw> KeepRaw for Vec<T> {
+    fn keep_raw(&mut self, raw: Value) {
+        if let Value::Array(items) = raw {
+            for (item, raw) in self.iter_mut().zip(items) {
+                item.keep_raw(raw);
+            }
+        }
+    }
+}
+
+impl<T: KeepRaw> KeepRaw for Page<T> {
+    fn keep_raw(&mut self, mut raw: Value) {
+        if let (Some(collection), Some(raw)) = (self.collection.as_mut(), raw.get_mut("collection")) {
+            collection.keep_raw(raw.take());
+        }
+    }
+}
@@ -0,0 +1,52 @@
+// Mirrors the shape of the items returned by the `users/{id}/spotlight`
+// endpoint, which lists the tracks and playlists pinned to a user's profile
+
+use serde::{Deserialize, Deserializer};
+use serde_derive::Serialize;
+use serde_json::Value;
+use super::common::Track;
+use super::playlists::Playlist;
+
+/// An item pinned to the "spotlight" section at the top of a user's profile.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum SpotlightItem {
+    Track(Box<Track>),
+    Playlist(Box<Playlist>),
+    /// A kind of item this crate doesn't know about, kept as raw JSON
+    Other(Value),
+}
+
+// Items are told apart by their "kind", which an untagged enum can't do since
+// every field of `Track` and `Playlist` is optional
+impl<'de> Deserialize<'de> for SpotlightItem {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+
+        let value = Value::deserialize(deserializer)?;
+        match value.get("kind").and_then(Value::as_str) {
+            Some("track") => serde_json::from_value(value).map(SpotlightItem::Track).map_err(D::Error::custom),
+            Some("playlist") => serde_json::from_value(value).map(SpotlightItem::Playlist).map_err(D::Error::custom),
+            _ => Ok(SpotlightItem::Other(value))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn spotlight_items_by_kind() {
+        let items: Vec<SpotlightItem> = serde_json::from_value(json!([
+            { "kind": "track", "id": 1, "title": "Pinned" },
+            { "kind": "playlist", "id": 2, "title": "Pinned Set" },
+            { "kind": "something-new", "id": 3 }
+        ])).unwrap();
+
+        assert!(matches!(&items[0], SpotlightItem::Track(t) if t.title.as_deref() == Some("Pinned")));
+        assert!(matches!(&items[1], SpotlightItem::Playlist(p) if p.title.as_deref() == Some("Pinned Set")));
+        assert!(matches!(&items[2], SpotlightItem::Other(_)));
+    }
+}
@@ -0,0 +1,72 @@
+// Mirrors the shape of the objects returned by the (undocumented) track stats
+// endpoint, which is only available to the owner of the track
+
+use serde_derive::{Serialize, Deserialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use super::common::*;
+use chrono::{DateTime, Utc};
+
+/// The period and granularity to request stats for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StatsRange {
+    /// The start of the period (inclusive)
+    pub since: DateTime<Utc>,
+    /// The end of the period (exclusive)
+    pub until: DateTime<Utc>,
+    /// The length of time each data point covers
+    pub interval: StatsInterval,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatsInterval {
+    Day,
+    Week,
+    Month,
+}
+
+impl StatsInterval {
+    /// The value the API expects for this interval
+    pub fn as_str(self) -> &'static str {
+        match self {
+            StatsInterval::Day => "day",
+            StatsInterval::Week => "week",
+            StatsInterval::Month => "month",
+        }
+    }
+}
+
+/// Time series of the activity on a track.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TrackStats {
+    pub plays: Option<Vec<DataPoint>>,
+    pub likes: Option<Vec<DataPoint>>,
+    pub downloads: Option<Vec<DataPoint>>,
+    pub comments: Option<Vec<DataPoint>>,
+    pub reposts: Option<Vec<DataPoint>>,
+    /// The users who played the track the most during the period
+    pub top_listeners: Option<Vec<TopListener>>,
+    /// Any fields returned by the API that aren't covered above
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+/// The value of a statistic for the interval starting at `timestamp`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DataPoint {
+    #[serde(default, with = "datetime")]
+    pub timestamp: Option<DateTime<Utc>>,
+    pub value: Option<i64>,
+    /// Any fields returned by the API that aren't covered above
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TopListener {
+    pub user: Option<User>,
+    pub plays: Option<i64>,
+    /// Any fields returned by the API that aren't covered above
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
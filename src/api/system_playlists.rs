@@ -0,0 +1,75 @@
+// Mirrors the shape of the objects returned by the `mixed-selections` and
+// `system-playlists` endpoints, with all properties optional like the rest of
+// the API types
+
+use serde_derive::{Serialize, Deserialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use super::common::*;
+use chrono::{DateTime, Utc};
+
+/// A personalized playlist that SoundCloud generates for the user, such as
+/// their weekly mix or a "Daily Drops" selection.
+///
+/// System playlists are identified by URNs (like
+/// "soundcloud:system-playlists:weekly:1234") rather than numeric ids, and
+/// their contents rotate regularly.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SystemPlaylist {
+    pub id: Option<String>,
+    pub urn: Option<String>,
+    pub kind: Option<String>,
+    pub title: Option<String>,
+    pub short_title: Option<String>,
+    pub description: Option<String>,
+    pub short_description: Option<String>,
+    pub playlist_type: Option<String>,
+    pub tracking_feature_name: Option<String>,
+    pub permalink: Option<String>,
+    pub permalink_url: Option<String>,
+    pub artwork_url: Option<String>,
+    pub calculated_artwork_url: Option<String>,
+    pub is_public: Option<bool>,
+    #[serde(default, with = "datetime")]
+    pub last_updated: Option<DateTime<Utc>>,
+    /// The user the playlist was generated for
+    pub made_for: Option<User>,
+    pub user: Option<User>,
+    pub tracks: Option<Vec<Track>>,
+    /// Any fields returned by the API that aren't covered above
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+/// A group of playlists shown together on the discover page (such as "Made
+/// for you" or "Trending by genre").
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Selection {
+    pub id: Option<String>,
+    pub urn: Option<String>,
+    pub kind: Option<String>,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub tracking_feature_name: Option<String>,
+    /// The playlists in the selection, which can be both system playlists and
+    /// ordinary playlists (see `Selection::system_playlists`)
+    pub items: Option<Page<Value>>,
+    /// Any fields returned by the API that aren't covered above
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+impl Selection {
+    /// The system playlists in the selection.
+    ///
+    /// Their track information usually isn't complete; use
+    /// `Zester::system_playlist` to get all of it.
+    pub fn system_playlists(&self) -> Vec<SystemPlaylist> {
+        self.items
+            .iter()
+            .flat_map(|page| page.collection.iter().flatten())
+            .filter(|item| item.get("kind").and_then(Value::as_str) == Some("system-playlist"))
+            .filter_map(|item| serde_json::from_value(item.clone()).ok())
+            .collect()
+    }
+}
@@ -0,0 +1,1773 @@
+use crate::{snapshot, write_json_to, Error, Zester};
+use crate::api::{Likes, Playlists};
+use crate::api::common::Track;
+use crate::api::playlists::Playlist;
+use crate::events::*;
+use crate::manifest::{ArchiveManifest, HashingReader, ManifestEntry, MANIFEST_PATH};
+use crate::api::ids::TrackId;
+use crate::filename::{long_path, sanitize, track_artist, track_stem, track_title};
+use chrono::SecondsFormat;
+use crate::reader::DownloadInterrupted;
+use crate::report::{SkipReason, ZestReport};
+#[cfg(feature = "ffmpeg")]
+use crate::transcode::Transcoder;
+use serde::Serialize;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Component, Path, PathBuf};
+#[cfg(feature = "ffmpeg")]
+use std::time::Duration;
+
+/// The file extension used for downloaded audio when its format is unknown
+const DEFAULT_AUDIO_EXTENSION: &str = "mp3";
+
+/// The number of tracks saved between each save of the archive's manifest, so
+/// that not much is lost if the archive is interrupted
+const MANIFEST_SAVE_INTERVAL: usize = 20;
+
+/// The directory within an archive that track audio is saved under
+const TRACKS_DIR: &str = "tracks";
+
+/// The directory within an archive that track artwork is saved under
+const ARTWORK_DIR: &str = "artwork";
+
+/// The directory within an archive that M3U playlists are saved under
+const PLAYLISTS_DIR: &str = "playlists";
+
+/// The name that liked tracks are grouped under by layouts that save tracks
+/// once per playlist
+pub(crate) const LIKES_DIR_NAME: &str = "Likes";
+
+/// Options that control what `Zester::archive_to` archives.
+#[derive(Debug, Clone)]
+pub struct ArchiveOptions {
+    /// The number of recent likes to archive; `None` skips likes entirely.
+    pub likes: Option<u64>,
+    /// The number of recent playlists to archive; `None` skips playlists
+    /// entirely.
+    pub playlists: Option<u64>,
+    /// Whether or not to download the audio for archived tracks.
+    pub download_audio: bool,
+    /// Whether or not to download the artwork for archived tracks.
+    pub download_artwork: bool,
+    /// Whether or not to pretty-print the metadata JSON files.
+    pub pretty_print: bool,
+    /// The format of the sidecar file to write next to each track's audio,
+    /// if any.
+    ///
+    /// Sidecars hold the track's description, tags, upload date, permalink,
+    /// license, and play counts, so that this information stays with the
+    /// audio. Tracks that are part of an archived album also get the album's
+    /// title and their track number.
+    pub sidecars: Option<SidecarFormat>,
+    /// Whether or not to archive the tracks and playlists pinned to the
+    /// spotlight section of the user's profile.
+    pub spotlight: bool,
+    /// How to transcode downloaded audio before saving it, if at all.
+    ///
+    /// Transcoded audio is saved with the extension of the format it was
+    /// transcoded into.
+    #[cfg(feature = "ffmpeg")]
+    pub transcode: Option<Transcoder>,
+    /// Where in the archive's `tracks` directory each track's audio is saved.
+    pub layout: ArchiveLayout,
+    /// How to save tracks that belong in more than one place in the archive
+    /// (see `ArchiveLayout::PerPlaylist`); `None` saves a full copy of the
+    /// audio in each place.
+    ///
+    /// If a link can't be made (because the sink doesn't support links, or
+    /// the filesystem doesn't) a copy is saved instead.
+    pub link_shared_tracks: Option<LinkKind>,
+    /// Whether or not to hash the audio and artwork already in the archive to
+    /// check it against the archive's manifest, rather than only checking its
+    /// size.
+    ///
+    /// Hashing catches files that were damaged after they were written, but
+    /// means reading every file in the archive each time it's resumed.
+    pub verify_hashes: bool,
+}
+
+/// The formats that per-track sidecar files can be written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SidecarFormat {
+    /// A JSON file with the same name as the audio file, but a `.json`
+    /// extension
+    Json,
+    /// An XML `.nfo` file like the ones media centers (such as Kodi) read
+    Nfo,
+}
+
+impl SidecarFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            SidecarFormat::Json => "json",
+            SidecarFormat::Nfo => "nfo",
+        }
+    }
+}
+
+/// The ways that track audio can be laid out within an archive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArchiveLayout {
+    /// Every track in the `tracks` directory, named "Artist - Title"
+    Flat,
+    /// A directory for each playlist, named after it, holding the playlist's
+    /// tracks; liked tracks go in a "Likes" directory
+    ///
+    /// Tracks that are part of more than one playlist are saved once for
+    /// each (see `ArchiveOptions::link_shared_tracks`).
+    PerPlaylist,
+    /// A directory for each artist, holding their tracks named by title
+    PerArtist,
+    /// A path template, relative to the `tracks` directory and without an
+    /// extension, like `"{artist}/{album}/{track_number} {title}"`
+    ///
+    /// The placeholders are:
+    ///
+    /// * `{artist}` and `{title}`
+    /// * `{album}`: the title of the album the track is on, or "Unknown Album"
+    /// * `{track_number}`: the track's number on its album as two digits, or
+    ///   "00" if it isn't on one
+    /// * `{playlist}`: the name of the playlist (or "Likes"), which saves the
+    ///   track once for each playlist it's part of like `PerPlaylist` does
+    /// * `{id}`: the track's id
+    ///
+    /// `/` separates directories; it's replaced, along with anything else that
+    /// isn't allowed in filenames, wherever it appears in a placeholder's
+    /// value.
+    Template(String),
+}
+
+impl ArchiveLayout {
+    /// The path within the archive that a track's audio is saved to.
+    ///
+    /// `playlist` is the name of the playlist that this copy of the track is
+    /// saved for (only layouts that save tracks once per playlist use it),
+    /// and `album` is the album the track is on along with its number on it.
+    /// The file is given the extension `extension`, or else the one matching
+    /// the format that will be downloaded.
+    pub fn track_path(
+        &self,
+        track: &Track,
+        playlist: &str,
+        album: Option<(&Playlist, usize)>,
+        extension: Option<&str>
+    ) -> PathBuf {
+        let tracks_dir = Path::new(TRACKS_DIR);
+        let extension = audio_extension(track, extension);
+        let file_name = |stem: String| format!("{}.{}", stem, extension);
+
+        match self {
+            ArchiveLayout::Flat => tracks_dir.join(file_name(track_stem(track))),
+            ArchiveLayout::PerPlaylist => tracks_dir.join(sanitize(playlist)).join(file_name(track_stem(track))),
+            ArchiveLayout::PerArtist => tracks_dir
+                .join(sanitize(track_artist(track).unwrap_or("Unknown Artist")))
+                .join(file_name(sanitize(&track_title(track)))),
+            ArchiveLayout::Template(template) => {
+                let mut components: Vec<String> = template
+                    .split('/')
+                    .filter(|c| !c.is_empty())
+                    .map(|c| sanitize(&expand_placeholders(c, track, playlist, album)))
+                    .collect();
+
+                match components.pop() {
+                    Some(stem) => tracks_dir.join(components.iter().collect::<PathBuf>()).join(file_name(stem)),
+                    None => tracks_dir.join(file_name(track_stem(track)))
+                }
+            }
+        }
+    }
+
+    /// Whether or not tracks are saved once for each playlist they're part
+    /// of.
+    pub fn is_per_playlist(&self) -> bool {
+        match self {
+            ArchiveLayout::PerPlaylist => true,
+            ArchiveLayout::Template(template) => template.contains("{playlist}"),
+            ArchiveLayout::Flat | ArchiveLayout::PerArtist => false
+        }
+    }
+}
+
+// Fill in the placeholders in a component of an `ArchiveLayout::Template`
+fn expand_placeholders(component: &str, track: &Track, playlist: &str, album: Option<(&Playlist, usize)>) -> String {
+    let album_title = album
+        .and_then(|(a, _)| a.title.as_deref())
+        .or_else(|| track.publisher_metadata.as_ref().and_then(|m| m.album_title.as_deref()))
+        .unwrap_or("Unknown Album");
+
+    let values = [
+        ("{artist}", track_artist(track).unwrap_or("Unknown Artist").to_string()),
+        ("{title}", track_title(track)),
+        ("{album}", album_title.to_string()),
+        ("{track_number}", format!("{:02}", album.map_or(0, |(_, number)| number))),
+        ("{playlist}", playlist.to_string()),
+        ("{id}", track.id.map(|id| id.to_string()).unwrap_or_default()),
+    ];
+
+    values.iter().fold(component.to_string(), |expanded, (placeholder, value)| {
+        expanded.replace(placeholder, &sanitize(value))
+    })
+}
+
+/// The kinds of links that can be made to a file already in an archive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkKind {
+    /// A symbolic link, with a path relative to the link
+    Symlink,
+    /// A hard link, which looks like an ordinary file but takes up no extra
+    /// space
+    Hardlink,
+}
+
+impl Default for ArchiveOptions {
+    fn default() -> Self {
+        Self {
+            likes: Some(u64::MAX),
+            playlists: Some(u64::MAX),
+            download_audio: true,
+            download_artwork: true,
+            pretty_print: false,
+            sidecars: None,
+            spotlight: true,
+            #[cfg(feature = "ffmpeg")]
+            transcode: None,
+            layout: ArchiveLayout::Flat,
+            link_shared_tracks: None,
+            verify_hashes: false,
+        }
+    }
+}
+
+/// A summary of what happened during a call to `Zester::archive_to`.
+#[derive(Debug, Default, serde_derive::Serialize)]
+pub struct ArchiveReport {
+    pub likes_count: usize,
+    pub playlists_count: usize,
+    /// The outcome of archiving track audio
+    ///
+    /// Tracks whose audio was already present in the archive are recorded as
+    /// skipped.
+    pub audio: ZestReport,
+    pub artwork_downloaded: usize,
+}
+
+/// A destination that the files making up an archive are written to.
+///
+/// All paths handed to a sink are relative to the root of the archive.
+pub trait ArchiveSink {
+    /// Whether or not a complete file is already present at the given path.
+    fn contains(&self, path: &Path) -> bool;
+
+    /// Write all of the data from `data` to a file at the given path.
+    fn write(&mut self, path: &Path, data: &mut dyn Read) -> Result<(), Error>;
+
+    /// Make the given path a link to the file at `target`, which is already
+    /// in the sink, replacing anything already at the path (such as a file
+    /// left incomplete by an earlier archive).
+    ///
+    /// Sinks that can't hold links don't need to implement this; by default
+    /// it fails, and the file is written out in full instead.
+    fn link(&mut self, _target: &Path, _path: &Path, _kind: LinkKind) -> Result<(), Error> {
+        Err(Error::IoError(io::Error::new(io::ErrorKind::Unsupported, "links aren't supported by this sink")))
+    }
+
+    /// The size of the file at the given path, if it's present and the sink
+    /// can tell.
+    ///
+    /// This, along with `open`, is used to check files already in the sink
+    /// against the archive's manifest. Sinks that don't implement them have
+    /// their files assumed to be complete.
+    fn size(&self, _path: &Path) -> Option<u64> {
+        None
+    }
+
+    /// Read back the file at the given path, if it's present and the sink can.
+    fn open(&self, _path: &Path) -> Option<Box<dyn Read + '_>> {
+        None
+    }
+
+    /// The paths of all of the files in the given directory of the sink,
+    /// including those in directories within it.
+    ///
+    /// This, along with `rename`, is used to move files that were named by
+    /// an older version of this crate to where they're expected now. Sinks
+    /// that don't implement them have their files left where they are (and
+    /// the files are downloaded again under their new names).
+    fn files(&self, _dir: &Path) -> Vec<PathBuf> {
+        vec![]
+    }
+
+    /// Move the file at `from` to `to`.
+    fn rename(&mut self, _from: &Path, _to: &Path) -> Result<(), Error> {
+        Err(Error::IoError(io::Error::new(io::ErrorKind::Unsupported, "renaming isn't supported by this sink")))
+    }
+
+    /// Whether writing to a path that's already in the sink replaces the file
+    /// there.
+    ///
+    /// Files that are updated as an archive goes along (like the manifest) are
+    /// only written once, at the end, to sinks that can't overwrite files.
+    fn supports_overwrite(&self) -> bool {
+        true
+    }
+}
+
+/// An `ArchiveSink` that writes files into a directory.
+pub struct DirSink {
+    root: PathBuf,
+}
+
+impl DirSink {
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        Self { root: root.into() }
+    }
+
+    // The full path of a file in the archive, which for deeply nested layouts
+    // can be longer than Windows normally allows
+    fn full_path(&self, path: &Path) -> PathBuf {
+        long_path(&self.root.join(path)).into_owned()
+    }
+}
+
+impl ArchiveSink for DirSink {
+    fn contains(&self, path: &Path) -> bool {
+        self.full_path(path).exists()
+    }
+
+    /// The data is written to a temporary file that is moved into place once
+    /// it is complete, so an interrupted download never leaves a partial file
+    /// behind.
+    fn write(&mut self, path: &Path, data: &mut dyn Read) -> Result<(), Error> {
+        let path = self.full_path(path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut part_path = path.as_os_str().to_owned();
+        part_path.push(".part");
+        let part_path = PathBuf::from(part_path);
+
+        let mut file = File::create(&part_path)?;
+        io::copy(data, &mut file)?;
+        file.flush()?;
+        drop(file);
+
+        fs::rename(&part_path, &path)?;
+        Ok(())
+    }
+
+    fn link(&mut self, target: &Path, path: &Path, kind: LinkKind) -> Result<(), Error> {
+        let link_path = self.full_path(path);
+        if let Some(parent) = link_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // links can't be made over an existing file
+        if fs::symlink_metadata(&link_path).is_ok() {
+            fs::remove_file(&link_path)?;
+        }
+
+        match kind {
+            LinkKind::Hardlink => fs::hard_link(self.full_path(target), &link_path)?,
+            // relative, so that the archive can be moved around
+            LinkKind::Symlink => {
+                let relative = relative_path(path.parent().unwrap_or_else(|| Path::new("")), target);
+                symlink(&relative, &link_path)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn size(&self, path: &Path) -> Option<u64> {
+        fs::metadata(self.full_path(path)).ok().map(|m| m.len())
+    }
+
+    fn open(&self, path: &Path) -> Option<Box<dyn Read + '_>> {
+        let file = File::open(self.full_path(path)).ok()?;
+        Some(Box::new(io::BufReader::new(file)))
+    }
+
+    fn files(&self, dir: &Path) -> Vec<PathBuf> {
+        let mut files = vec![];
+        let mut dirs = vec![dir.to_path_buf()];
+        while let Some(dir) = dirs.pop() {
+            let entries = match fs::read_dir(self.full_path(&dir)) {
+                Ok(entries) => entries,
+                Err(_) => continue
+            };
+
+            for entry in entries.flatten() {
+                let path = dir.join(entry.file_name());
+                match entry.file_type() {
+                    Ok(file_type) if file_type.is_dir() => dirs.push(path),
+                    Ok(_) => files.push(path),
+                    Err(_) => {}
+                }
+            }
+        }
+
+        files
+    }
+
+    /// The directory the file was in is removed if it's left empty.
+    fn rename(&mut self, from: &Path, to: &Path) -> Result<(), Error> {
+        let from = self.full_path(from);
+        let to = self.full_path(to);
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::rename(&from, &to)?;
+
+        // fails if there's anything else in it
+        if let Some(parent) = from.parent() {
+            let _ = fs::remove_dir(parent);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+fn symlink(target: &Path, path: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, path)
+}
+
+#[cfg(windows)]
+fn symlink(target: &Path, path: &Path) -> io::Result<()> {
+    std::os::windows::fs::symlink_file(target, path)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn symlink(_target: &Path, _path: &Path) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "symlinks aren't supported on this platform"))
+}
+
+/// The path to `target` relative to the directory `from`, where both are
+/// relative to the same root.
+fn relative_path(from: &Path, target: &Path) -> PathBuf {
+    let from: Vec<Component> = from.components().filter(|c| *c != Component::CurDir).collect();
+    let target: Vec<Component> = target.components().filter(|c| *c != Component::CurDir).collect();
+    let common = from.iter().zip(target.iter()).take_while(|(a, b)| a == b).count();
+
+    let mut relative: PathBuf = from[common..].iter().map(|_| OsString::from("..")).collect();
+    relative.extend(&target[common..]);
+    relative
+}
+
+impl Zester {
+    /// Archive the user's data to the directory at the given path.
+    ///
+    /// This zests the user's likes and playlists, writes their metadata to JSON
+    /// files, and downloads the audio and artwork for all of the tracks
+    /// involved, resulting in the following layout:
+    ///
+    /// ```text
+    /// <path>/
+    ///     me.json
+    ///     likes.json
+    ///     playlists.json
+    ///     spotlight.json
+    ///     tracks/Artist - Title.mp3
+    ///     artwork/Artist - Title.jpg
+    ///     playlists/Playlist Title.m3u
+    /// ```
+    ///
+    /// The layout of the `tracks` directory can be changed with
+    /// `ArchiveOptions::layout`. Playlists with the same title get their id
+    /// added to their name (like "Playlist Title (123).m3u"), as do tracks
+    /// with the same artist and title.
+    ///
+    /// Audio and artwork that is already present is not downloaded again, so
+    /// an interrupted archive can be resumed by archiving to the same path.
+    /// The size and hash of each of these files is recorded in `manifest.json`
+    /// as it's written; files that don't match it when the archive is resumed
+    /// are downloaded again (see `ArchiveOptions::verify_hashes`). Files that
+    /// were named by an older version of this crate, whose filenames were
+    /// sanitized differently, are moved to where they belong now.
+    ///
+    /// The callback you provide will be called when various events occur,
+    /// allowing you to handle them as you please.
+    pub fn archive_to<P: AsRef<Path>, F: Fn(ArchiveEvent)>(
+        &self,
+        path: P,
+        options: ArchiveOptions,
+        cb: F
+    ) -> Result<ArchiveReport, Error> {
+        self.archive_into(&mut DirSink::new(path.as_ref()), options, cb)
+    }
+
+    /// Archive the user's data into the given sink.
+    ///
+    /// This does the same thing as `archive_to`, but lets you decide where the
+    /// files end up. Paths in events are relative to the root of the archive.
+    pub fn archive_into<S: ArchiveSink, F: Fn(ArchiveEvent)>(
+        &self,
+        sink: &mut S,
+        options: ArchiveOptions,
+        cb: F
+    ) -> Result<ArchiveReport, Error> {
+        use ArchiveEvent::*;
+
+        let playlists_dir = Path::new(PLAYLISTS_DIR);
+
+        let mut report = ArchiveReport::default();
+        let extension = transcoded_extension(&options);
+        let mut manifest = ArchiveManifest::load(sink);
+
+        if let Some(me) = &self.me {
+            let me_path = Path::new("me.json");
+            write_json_to_sink(sink, &snapshot::wrap(me), me_path, options.pretty_print)?;
+            cb(MetadataWritten { path: me_path });
+        }
+
+        let likes = match options.likes {
+            Some(num) => {
+                let likes = self.likes(num, |e| cb(Likes(e)))?;
+                let likes_path = Path::new("likes.json");
+                write_json_to_sink(sink, &snapshot::wrap(&likes), likes_path, options.pretty_print)?;
+                cb(MetadataWritten { path: likes_path });
+
+                report.likes_count = likes.collections.len();
+                Some(likes)
+            },
+            None => None
+        };
+
+        let playlists = match options.playlists {
+            Some(num) => {
+                let playlists = self.playlists(num, |e| cb(Playlists(e)))?;
+                let playlists_path = Path::new("playlists.json");
+                write_json_to_sink(sink, &snapshot::wrap(&playlists), playlists_path, options.pretty_print)?;
+                cb(MetadataWritten { path: playlists_path });
+
+                report.playlists_count = playlists.playlists.len();
+                Some(playlists)
+            },
+            None => None
+        };
+
+        if options.spotlight {
+            if let Some(id) = self.me.as_ref().and_then(|me| me.id).or(self.user_id) {
+                match self.spotlight(id) {
+                    Ok(spotlight) => {
+                        let spotlight_path = Path::new("spotlight.json");
+                        write_json_to_sink(sink, &snapshot::wrap(&spotlight), spotlight_path, options.pretty_print)?;
+                        cb(MetadataWritten { path: spotlight_path });
+                    },
+                    Err(err) => cb(SpotlightError { err })
+                }
+            }
+        }
+
+        // tracks that are part of an album get its title and their number on
+        // it in their sidecars
+        let album_for = |track: &Track| {
+            playlists.as_ref().and_then(|p| p.album_for_track(track.id?))
+        };
+
+        let tracks = archived_tracks(likes.as_ref(), playlists.as_ref());
+        let track_paths = TrackPaths::new(
+            &options.layout,
+            extension,
+            likes.as_ref(),
+            playlists.as_ref(),
+            &tracks,
+            |track, path, renamed| cb(FilenameCollision { track_info: track, path, renamed })
+        );
+
+        // the paths that files belonging to this archive are expected at,
+        // which files named by older versions are moved to
+        let mut expected_paths = HashSet::new();
+        for track in tracks.iter() {
+            for audio_path in track_paths.all(track) {
+                for format in [SidecarFormat::Json, SidecarFormat::Nfo] {
+                    expected_paths.insert(audio_path.with_extension(format.extension()));
+                }
+                expected_paths.insert(audio_path);
+            }
+            expected_paths.extend(track_paths.artwork(track));
+        }
+        // the name each playlist's files are saved under
+        let named_playlists: Vec<(&Playlist, String)> = playlists
+            .iter()
+            .flat_map(|p| p.playlists.iter().zip(playlist_stems(p)))
+            .collect();
+        for (_, stem) in named_playlists.iter() {
+            expected_paths.insert(playlists_dir.join(format!("{}.m3u", stem)));
+        }
+        for (path, renamed) in renamed_files(sink, &expected_paths) {
+            if sink.rename(&path, &renamed).is_ok() {
+                manifest.rename(&path, &renamed);
+                cb(FileRenamed { path: &path, renamed: &renamed });
+            }
+        }
+
+        for (playlist, stem) in named_playlists.iter() {
+            let m3u_path = playlists_dir.join(format!("{}.m3u", stem));
+            let m3u = m3u(playlist, playlists_dir, |t| {
+                track_paths
+                    .path_for(t, stem)
+                    .unwrap_or_else(|| options.layout.track_path(t, stem, album_for(t), extension))
+            });
+            sink.write(&m3u_path, &mut m3u.as_bytes())?;
+            cb(MetadataWritten { path: &m3u_path });
+        }
+
+        if options.download_artwork {
+            for track in tracks.iter() {
+                let (url, artwork_path) = match (&track.artwork_url, track_paths.artwork(track)) {
+                    (Some(url), Some(artwork_path)) => (url, artwork_path),
+                    _ => continue
+                };
+
+                if sink.contains(&artwork_path) {
+                    match manifest.check(sink, &artwork_path, options.verify_hashes) {
+                        Some(mismatch) => cb(IncompleteFileFound { track_info: track, path: &artwork_path, mismatch }),
+                        None => {
+                            cb(ArtworkAlreadyArchived { track_info: track, path: &artwork_path });
+                            continue;
+                        }
+                    }
+                }
+
+                let result = self.call(&mut ureq::get(url)).and_then(|mut r| {
+                    let mut artwork = vec![];
+                    r.read_to_end(&mut artwork)?;
+                    sink.write(&artwork_path, &mut artwork.as_slice())?;
+                    Ok(artwork)
+                });
+                match result {
+                    Ok(artwork) => {
+                        manifest.insert(&artwork_path, ManifestEntry::new(&artwork));
+                        report.artwork_downloaded += 1;
+                        cb(ArtworkSaved { track_info: track, path: &artwork_path });
+                    },
+                    Err(err) => cb(ArtworkSaveError { track_info: track, err })
+                }
+            }
+        }
+
+        if options.download_audio {
+            let mut tracks_to_download = vec![];
+            // files left incomplete by an earlier archive, to be replaced
+            let mut incomplete_paths = HashSet::new();
+            for track in tracks {
+                let audio_paths = track_paths.all(track);
+                for path in audio_paths.iter().filter(|p| sink.contains(p)) {
+                    if let Some(mismatch) = manifest.check(sink, path, options.verify_hashes) {
+                        cb(IncompleteFileFound { track_info: track, path, mismatch });
+                        incomplete_paths.insert(path.clone());
+                    }
+                }
+                let is_present = |p: &PathBuf| sink.contains(p) && !incomplete_paths.contains(p);
+
+                if audio_paths.iter().all(is_present) {
+                    report.audio.record_skipped(track, SkipReason::AlreadyArchived);
+                    cb(TrackAlreadyArchived { track_info: track, path: &audio_paths[0] });
+
+                    // sidecars may have been turned on since the audio was
+                    // archived
+                    if let Some(format) = options.sidecars {
+                        for audio_path in audio_paths.iter() {
+                            let sidecar_path = audio_path.with_extension(format.extension());
+                            if !sink.contains(&sidecar_path) {
+                                match write_sidecar(sink, track, album_for(track), &sidecar_path, format, options.pretty_print) {
+                                    Ok(()) => cb(MetadataWritten { path: &sidecar_path }),
+                                    Err(err) => cb(SidecarSaveError { track_info: track, path: &sidecar_path, err })
+                                }
+                            }
+                        }
+                    }
+                } else {
+                    tracks_to_download.push(track);
+                }
+            }
+
+            let sink = RefCell::new(&mut *sink);
+            let manifest = RefCell::new(&mut manifest);
+            // the number of tracks saved since the manifest was last saved
+            let unsaved_tracks = Cell::new(0usize);
+            let save_errors = RefCell::new(vec![]);
+            let mut audio_report = self.tracks_audio(tracks_to_download.into_iter(), |e| match e {
+                TracksAudioZestingEvent::FinishTrackDownload { track_info, mut track_data, .. } => {
+                    let mut sink = sink.borrow_mut();
+                    let audio_paths = track_paths.all(track_info);
+                    // the copy of the track that other copies are linked to
+                    let is_present = |p: &PathBuf| sink.contains(p) && !incomplete_paths.contains(p);
+                    let mut saved_path = audio_paths.iter().find(|p| is_present(p));
+                    let missing_paths: Vec<&PathBuf> = audio_paths.iter().filter(|p| !is_present(p)).collect();
+
+                    // the audio is streamed straight into the sink, unless it
+                    // has to be transcoded or saved more than once
+                    let audio = match transcode_audio(&options, track_info, album_for(track_info), &mut track_data, &cb) {
+                        Ok(Some(transcoded)) => Ok(Some(transcoded)),
+                        Ok(None) if missing_paths.len() > 1 => {
+                            let mut audio = vec![];
+                            track_data.read_to_end(&mut audio).map(|_| Some(audio)).map_err(Error::from)
+                        },
+                        Ok(None) => Ok(None),
+                        // the download's failure is reported by `tracks_audio`
+                        Err(err) if DownloadInterrupted::caused(&err) => return,
+                        Err(err) => {
+                            save_errors.borrow_mut().push((track_info.id.unwrap_or_default(), err.clone()));
+                            cb(TrackTranscodeError { track_info, err });
+                            return;
+                        }
+                    };
+                    let mut entry = match &audio {
+                        Ok(Some(audio)) => Some(ManifestEntry::new(audio)),
+                        _ => None
+                    };
+
+                    for audio_path in missing_paths {
+                        let linked = match (saved_path, options.link_shared_tracks) {
+                            (Some(target), Some(kind)) => sink.link(target, audio_path, kind).is_ok(),
+                            _ => false
+                        };
+
+                        let result = match (&audio, linked) {
+                            (_, true) => Ok(()),
+                            (Ok(Some(audio)), false) => sink.write(audio_path, &mut audio.as_slice()),
+                            (Ok(None), false) => {
+                                let mut reader = HashingReader::new(&mut track_data);
+                                let result = sink.write(audio_path, &mut reader);
+                                entry = Some(reader.entry());
+                                result
+                            },
+                            (Err(err), false) => Err(err.clone())
+                        };
+
+                        match result {
+                            Ok(()) => {
+                                match saved_path {
+                                    Some(target) if linked => cb(TrackLinked { track_info, path: audio_path, target }),
+                                    _ => cb(TrackSaved { track_info, path: audio_path })
+                                }
+                                saved_path = saved_path.or(Some(audio_path));
+                                if let Some(entry) = &entry {
+                                    manifest.borrow_mut().insert(audio_path, entry.clone());
+                                }
+
+                                if let Some(format) = options.sidecars {
+                                    let sidecar_path = audio_path.with_extension(format.extension());
+                                    let result = write_sidecar(
+                                        *sink,
+                                        track_info,
+                                        album_for(track_info),
+                                        &sidecar_path,
+                                        format,
+                                        options.pretty_print
+                                    );
+                                    match result {
+                                        Ok(()) => cb(MetadataWritten { path: &sidecar_path }),
+                                        Err(err) => cb(SidecarSaveError { track_info, path: &sidecar_path, err })
+                                    }
+                                }
+                            },
+                            // the download's failure is reported by `tracks_audio`
+                            Err(err) if DownloadInterrupted::caused(&err) => break,
+                            Err(err) => {
+                                save_errors.borrow_mut().push((track_info.id.unwrap_or_default(), err.clone()));
+                                cb(TrackSaveError { track_info, err });
+                                break;
+                            }
+                        }
+                    }
+
+                    // failing to save the manifest here isn't a problem, since
+                    // it's saved again at the end
+                    unsaved_tracks.set(unsaved_tracks.get() + 1);
+                    if unsaved_tracks.get() == MANIFEST_SAVE_INTERVAL && sink.supports_overwrite() {
+                        unsaved_tracks.set(0);
+                        let _ = write_json_to_sink(*sink, &**manifest.borrow(), Path::new(MANIFEST_PATH), false);
+                    }
+                },
+                e => cb(TrackAudio(e))
+            })?;
+
+            for (id, err) in save_errors.into_inner() {
+                audio_report.downgrade_to_failed(id, err);
+            }
+            report.audio.merge(audio_report);
+        }
+
+        if options.download_audio || options.download_artwork {
+            let manifest_path = Path::new(MANIFEST_PATH);
+            write_json_to_sink(sink, &manifest, manifest_path, options.pretty_print)?;
+            cb(MetadataWritten { path: manifest_path });
+        }
+
+        Ok(report)
+    }
+}
+
+/// Identifies a track: by its id, or if it doesn't have one, by the object.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum TrackKey {
+    Id(TrackId),
+    Object(*const Track),
+}
+
+impl TrackKey {
+    fn new(track: &Track) -> Self {
+        match track.id {
+            Some(id) => TrackKey::Id(id),
+            None => TrackKey::Object(track)
+        }
+    }
+}
+
+/// The tracks in an archive's likes and playlists, each included once.
+pub(crate) fn archived_tracks<'t>(likes: Option<&'t Likes>, playlists: Option<&'t Playlists>) -> Vec<&'t Track> {
+    let mut seen_ids = HashSet::new();
+    likes
+        .iter()
+        .flat_map(|l| l.collections.iter().map(|c| &c.track))
+        .chain(
+            playlists
+                .iter()
+                .flat_map(|p| p.playlists.iter())
+                .flat_map(|p| p.tracks.iter().flatten())
+        )
+        .filter(|t| match t.id {
+            Some(id) => seen_ids.insert(id),
+            None => true
+        })
+        .collect()
+}
+
+/// The paths in an archive that tracks' audio is saved to, keeping the paths
+/// of different tracks from colliding.
+#[derive(Default)]
+pub(crate) struct TrackPaths {
+    /// The paths for each track, along with the playlist each is for
+    paths: HashMap<TrackKey, Vec<(String, PathBuf)>>,
+    /// The path of each track's artwork
+    artwork: HashMap<TrackKey, PathBuf>,
+    /// The track that each path belongs to
+    ///
+    /// Paths are lowercased, since two paths that only differ in case are the
+    /// same path on some filesystems.
+    owners: HashMap<String, TrackKey>,
+}
+
+impl TrackPaths {
+    /// Work out where the layout saves the audio (and artwork) of the given
+    /// tracks (the tracks in `likes` and `playlists`), calling `on_collision`
+    /// with the original and new path of each path that has to be renamed so
+    /// that it doesn't collide with another track's.
+    ///
+    /// Paths are claimed in order of track id (with tracks that don't have one
+    /// last), so which of two colliding tracks keeps the filename doesn't
+    /// depend on the order the tracks are in, and doesn't change when an
+    /// archive is resumed.
+    pub(crate) fn new(
+        layout: &ArchiveLayout,
+        extension: Option<&str>,
+        likes: Option<&Likes>,
+        playlists: Option<&Playlists>,
+        tracks: &[&Track],
+        mut on_collision: impl FnMut(&Track, &Path, &Path)
+    ) -> Self {
+        let album_for = |track: &Track| playlists.and_then(|p| p.album_for_track(track.id?));
+
+        // which tracks are liked and which playlists each track is part of,
+        // worked out once rather than searched for every track
+        let liked: HashSet<TrackKey> = likes
+            .iter()
+            .flat_map(|l| l.collections.iter())
+            .map(|c| TrackKey::new(&c.track))
+            .collect();
+        let mut containing_playlists: HashMap<TrackKey, Vec<String>> = HashMap::new();
+        let named_playlists = playlists.iter().flat_map(|p| p.playlists.iter().zip(playlist_stems(p)));
+        for (playlist, name) in named_playlists {
+            for track in playlist.tracks.iter().flatten() {
+                let names = containing_playlists.entry(TrackKey::new(track)).or_default();
+                // the same track can appear in a playlist more than once
+                if names.last() != Some(&name) {
+                    names.push(name.clone());
+                }
+            }
+        }
+
+        // The paths that the layout puts a track's audio at, along with the
+        // playlist each is for; layouts that save tracks once per playlist
+        // give one for each playlist the track is in
+        let layout_paths = |track: &Track| -> Vec<(String, PathBuf)> {
+            let path_for = |name: String| {
+                let path = layout.track_path(track, &name, album_for(track), extension);
+                (name, path)
+            };
+            if !layout.is_per_playlist() {
+                return vec![path_for(LIKES_DIR_NAME.to_string())];
+            }
+
+            let key = TrackKey::new(track);
+            let is_liked = liked.contains(&key);
+            let playlist_names = containing_playlists.get(&key).into_iter().flatten().cloned();
+
+            let mut paths: Vec<(String, PathBuf)> = vec![];
+            let names = is_liked.then(|| LIKES_DIR_NAME.to_string()).into_iter().chain(playlist_names);
+            for (name, path) in names.map(path_for) {
+                if !paths.iter().any(|(_, p)| *p == path) {
+                    paths.push((name, path));
+                }
+            }
+
+            if paths.is_empty() {
+                paths.push(path_for(LIKES_DIR_NAME.to_string()));
+            }
+            paths
+        };
+
+        // different tracks can end up with the same filename (there are a lot
+        // of tracks called "Untitled"), and they mustn't overwrite each other
+        let mut by_id = tracks.to_vec();
+        by_id.sort_by_key(|t| (t.id.is_none(), t.id));
+
+        let mut track_paths = Self::default();
+        for track in by_id {
+            for (playlist, path) in layout_paths(track) {
+                let claimed = track_paths.claim(track, playlist, &path);
+                if claimed != path {
+                    on_collision(track, &path, &claimed);
+                }
+            }
+
+            // claimed the same way, so a track whose audio is renamed has its
+            // artwork renamed to match
+            if let Some(path) = artwork_path(track) {
+                let claimed = track_paths.claim_path(track, &path);
+                if claimed != path {
+                    on_collision(track, &path, &claimed);
+                }
+                track_paths.artwork.insert(TrackKey::new(track), claimed);
+            }
+        }
+        track_paths
+    }
+
+    /// Claim a path for the track's audio (for the given playlist), returning
+    /// the path to actually save it to.
+    fn claim(&mut self, track: &Track, playlist: String, path: &Path) -> PathBuf {
+        let path = self.claim_path(track, path);
+        self.paths.entry(TrackKey::new(track)).or_default().push((playlist, path.clone()));
+        path
+    }
+
+    /// Claim a path for one of the track's files, returning the path to
+    /// actually save it to.
+    ///
+    /// If the path already belongs to a different track, the track's id (or
+    /// failing that, a number) is added to the end of the filename.
+    fn claim_path(&mut self, track: &Track, path: &Path) -> PathBuf {
+        let key = TrackKey::new(track);
+        let is_taken = |path: &Path| self.owners.get(&path_key(path)).is_some_and(|&owner| owner != key);
+
+        let path = if is_taken(path) {
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+            let suffixes = track.id.map(|id| id.to_string()).into_iter().chain((2..).map(|n: u64| n.to_string()));
+            suffixes
+                .map(|suffix| {
+                    let mut file_name = OsString::from(format!("{} ({})", stem, suffix));
+                    if let Some(extension) = path.extension() {
+                        file_name.push(".");
+                        file_name.push(extension);
+                    }
+                    path.with_file_name(file_name)
+                })
+                .find(|p| !is_taken(p))
+                .unwrap_or_else(|| path.to_owned())
+        } else {
+            path.to_owned()
+        };
+
+        self.owners.insert(path_key(&path), key);
+        path
+    }
+
+    /// All of the paths the track's audio is saved to, the first of which is
+    /// where it's saved first.
+    pub(crate) fn all(&self, track: &Track) -> Vec<PathBuf> {
+        self.paths
+            .get(&TrackKey::new(track))
+            .map(|paths| paths.iter().map(|(_, path)| path.clone()).collect())
+            .unwrap_or_default()
+    }
+
+    /// The path the track's artwork is saved to, if it has any.
+    pub(crate) fn artwork(&self, track: &Track) -> Option<PathBuf> {
+        self.artwork.get(&TrackKey::new(track)).cloned()
+    }
+
+    /// The path of the track's audio for the given playlist, or its only path
+    /// if it isn't saved once per playlist.
+    pub(crate) fn path_for(&self, track: &Track, playlist: &str) -> Option<PathBuf> {
+        let paths = self.paths.get(&TrackKey::new(track))?;
+        paths
+            .iter()
+            .find(|(p, _)| p == playlist)
+            .or_else(|| paths.first())
+            .map(|(_, path)| path.clone())
+    }
+}
+
+fn path_key(path: &Path) -> String {
+    path.to_string_lossy().to_lowercase()
+}
+
+/// Serialize an object to JSON and write it to the given path in the sink.
+fn write_json_to_sink<S: ArchiveSink, O: Serialize>(
+    sink: &mut S,
+    object: &O,
+    path: &Path,
+    pretty_print: bool
+) -> Result<(), Error> {
+    let mut bytes = vec![];
+    write_json_to(object, &mut bytes, pretty_print)?;
+
+    sink.write(path, &mut bytes.as_slice())
+}
+
+/// The information about a track that's written to its sidecar file.
+#[derive(serde_derive::Serialize)]
+struct Sidecar<'a> {
+    id: Option<TrackId>,
+    title: Option<&'a str>,
+    artist: Option<&'a str>,
+    /// The title of the album the track was archived as part of, or otherwise
+    /// the one its publisher listed, if any
+    album: Option<&'a str>,
+    /// The track's position on the album it was archived as part of, so that
+    /// players sort the album in order rather than alphabetically
+    track_number: Option<usize>,
+    /// The number of tracks on the album
+    track_count: Option<usize>,
+    /// SoundCloud albums only ever have one disc, but some players won't sort
+    /// by track number without a disc number
+    disc_number: Option<usize>,
+    genre: Option<&'a str>,
+    tags: Vec<String>,
+    description: Option<&'a str>,
+    uploaded_at: Option<String>,
+    permalink_url: Option<&'a str>,
+    license: Option<String>,
+    playback_count: Option<i64>,
+    likes_count: Option<i64>,
+    reposts_count: Option<i64>,
+    comment_count: Option<i64>,
+}
+
+impl<'a> Sidecar<'a> {
+    fn new(track: &'a Track, album: Option<(&'a Playlist, usize)>) -> Self {
+        Self {
+            id: track.id,
+            title: track.title.as_deref(),
+            artist: track_artist(track),
+            album: album
+                .and_then(|(a, _)| a.title.as_deref())
+                .or_else(|| track.publisher_metadata.as_ref().and_then(|m| m.album_title.as_deref())),
+            track_number: album.map(|(_, number)| number),
+            track_count: album.and_then(|(a, _)| a.tracks.as_ref()).map(Vec::len),
+            disc_number: album.map(|_| 1),
+            genre: track.genre.as_deref().filter(|g| !g.is_empty()),
+            tags: track.tags(),
+            description: track.description.as_deref().filter(|d| !d.is_empty()),
+            uploaded_at: track.created_at.map(|d| d.to_rfc3339_opts(SecondsFormat::Secs, true)),
+            permalink_url: track.permalink_url.as_deref(),
+            // the license is stored the way the API spells it
+            license: track.license
+                .as_ref()
+                .and_then(|l| serde_json::to_value(l).ok())
+                .and_then(|l| l.as_str().map(String::from)),
+            playback_count: track.playback_count,
+            likes_count: track.likes_count,
+            reposts_count: track.reposts_count,
+            comment_count: track.comment_count,
+        }
+    }
+
+    /// The sidecar as an XML `.nfo` document.
+    fn to_nfo(&self) -> String {
+        let mut nfo = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n<track>\n");
+        let mut element = |name: &str, value: Option<&str>| {
+            if let Some(value) = value {
+                let value = value.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;");
+                nfo.push_str(&format!("    <{0}>{1}</{0}>\n", name, value));
+            }
+        };
+
+        element("id", self.id.map(|id| id.to_string()).as_deref());
+        element("title", self.title);
+        element("artist", self.artist);
+        element("album", self.album);
+        element("tracknumber", self.track_number.map(|n| n.to_string()).as_deref());
+        element("tracktotal", self.track_count.map(|n| n.to_string()).as_deref());
+        element("discnumber", self.disc_number.map(|n| n.to_string()).as_deref());
+        element("genre", self.genre);
+        for tag in self.tags.iter() {
+            element("tag", Some(tag));
+        }
+        element("plot", self.description);
+        element("premiered", self.uploaded_at.as_deref());
+        element("url", self.permalink_url);
+        element("license", self.license.as_deref());
+        element("playcount", self.playback_count.map(|c| c.to_string()).as_deref());
+        element("likes", self.likes_count.map(|c| c.to_string()).as_deref());
+        element("reposts", self.reposts_count.map(|c| c.to_string()).as_deref());
+        element("comments", self.comment_count.map(|c| c.to_string()).as_deref());
+
+        nfo.push_str("</track>\n");
+        nfo
+    }
+}
+
+/// Write a track's sidecar file to the given path in the sink.
+fn write_sidecar<S: ArchiveSink>(
+    sink: &mut S,
+    track: &Track,
+    album: Option<(&Playlist, usize)>,
+    path: &Path,
+    format: SidecarFormat,
+    pretty_print: bool
+) -> Result<(), Error> {
+    let sidecar = Sidecar::new(track, album);
+
+    let bytes = match format {
+        SidecarFormat::Json if pretty_print => serde_json::to_vec_pretty(&sidecar)?,
+        SidecarFormat::Json => serde_json::to_vec(&sidecar)?,
+        SidecarFormat::Nfo => sidecar.to_nfo().into_bytes()
+    };
+
+    sink.write(path, &mut bytes.as_slice())
+}
+
+/// The extension of the file a track's audio is saved to: the given extension,
+/// or else the one matching the format that will be downloaded.
+fn audio_extension<'a>(track: &Track, extension: Option<&'a str>) -> &'a str {
+    extension
+        .or_else(|| track.download_transcoding().ok().and_then(|t| t.format.file_extension()))
+        .unwrap_or(DEFAULT_AUDIO_EXTENSION)
+}
+
+/// The extension that audio is saved with after being transcoded, if it's
+/// going to be.
+#[cfg(feature = "ffmpeg")]
+pub(crate) fn transcoded_extension(options: &ArchiveOptions) -> Option<&'static str> {
+    options.transcode.as_ref().map(|t| t.format.extension())
+}
+
+#[cfg(not(feature = "ffmpeg"))]
+pub(crate) fn transcoded_extension(_options: &ArchiveOptions) -> Option<&'static str> {
+    None
+}
+
+/// Transcode a track's downloaded audio if the options call for it, reporting
+/// progress along the way.
+///
+/// The transcoded audio is tagged with the same information as the track's
+/// sidecar, including its number on the album it was archived as part of.
+#[cfg(feature = "ffmpeg")]
+fn transcode_audio<F: Fn(ArchiveEvent)>(
+    options: &ArchiveOptions,
+    track: &Track,
+    album: Option<(&Playlist, usize)>,
+    data: &mut dyn Read,
+    cb: &F
+) -> Result<Option<Vec<u8>>, Error> {
+    let transcoder = match &options.transcode {
+        Some(transcoder) => transcoder,
+        None => return Ok(None)
+    };
+
+    let sidecar = Sidecar::new(track, album);
+    let mut tags = vec![];
+    let mut tag = |key, value: Option<String>| tags.extend(value.map(|v| (key, v)));
+    tag("title", sidecar.title.map(String::from));
+    tag("artist", sidecar.artist.map(String::from));
+    tag("album", sidecar.album.map(String::from));
+    tag("genre", sidecar.genre.map(String::from));
+    tag("track", sidecar.track_number.map(|n| match sidecar.track_count {
+        Some(count) => format!("{}/{}", n, count),
+        None => n.to_string()
+    }));
+    tag("disc", sidecar.disc_number.map(|n| format!("{}/1", n)));
+
+    let duration = track.duration.map(|d| Duration::from_millis(d.max(0) as u64));
+    let transcoded = transcoder.transcode_tagged(data, &tags, |transcoded| {
+        cb(ArchiveEvent::TrackTranscodeProgress { track_info: track, transcoded, duration })
+    })?;
+
+    Ok(Some(transcoded))
+}
+
+#[cfg(not(feature = "ffmpeg"))]
+fn transcode_audio<F: Fn(ArchiveEvent)>(
+    _options: &ArchiveOptions,
+    _track: &Track,
+    _album: Option<(&Playlist, usize)>,
+    _data: &mut dyn Read,
+    _cb: &F
+) -> Result<Option<Vec<u8>>, Error> {
+    Ok(None)
+}
+
+/// A sanitized filename (without an extension) for the given playlist.
+pub(crate) fn playlist_stem(playlist: &Playlist) -> String {
+    match (&playlist.title, playlist.id) {
+        (Some(title), _) => sanitize(title),
+        (None, Some(id)) => format!("Untitled ({})", id),
+        (None, None) => "Untitled".into()
+    }
+}
+
+/// Sanitized filenames (without extensions) for each of the given playlists,
+/// in the same order.
+///
+/// Playlists with the same title get their id (or failing that, a number)
+/// added to the end of the name, like "Mix (123)", rather than sharing one.
+/// Names are compared ignoring case, since some filesystems do.
+pub(crate) fn playlist_stems(playlists: &Playlists) -> Vec<String> {
+    let mut used_names = HashSet::new();
+    playlists.playlists
+        .iter()
+        .map(|playlist| {
+            let stem = playlist_stem(playlist);
+            let mut name = stem.clone();
+            let mut candidates = playlist.id
+                .map(|id| format!("{} ({})", stem, id))
+                .into_iter()
+                .chain((2..).map(|n: u64| format!("{} ({})", stem, n)));
+            while !used_names.insert(name.to_lowercase()) {
+                name = candidates.next().unwrap_or_default();
+            }
+            name
+        })
+        .collect()
+}
+
+/// The path within the archive that a track's artwork is saved to, if it has
+/// any.
+fn artwork_path(track: &Track) -> Option<PathBuf> {
+    let url = track.artwork_url.as_ref()?;
+    Some(Path::new(ARTWORK_DIR).join(format!("{}.{}", track_stem(track), artwork_extension(url))))
+}
+
+/// The files in the sink that were named by older versions of `sanitize`,
+/// along with the path each belongs at now.
+///
+/// A file is only moved if it isn't at a path that's expected, and sanitizing
+/// each part of its path again (which gives the same name as sanitizing the
+/// original name would) puts it at one that is and that isn't already taken.
+fn renamed_files<S: ArchiveSink + ?Sized>(sink: &S, expected_paths: &HashSet<PathBuf>) -> Vec<(PathBuf, PathBuf)> {
+    [TRACKS_DIR, ARTWORK_DIR, PLAYLISTS_DIR]
+        .iter()
+        .flat_map(|dir| sink.files(Path::new(dir)))
+        .filter(|path| !expected_paths.contains(path))
+        .filter_map(|path| {
+            let renamed = resanitized_path(&path)?;
+            let is_free = expected_paths.contains(&renamed) && !sink.contains(&renamed);
+            is_free.then_some((path, renamed))
+        })
+        .collect()
+}
+
+// The given path with everything but its top-level directory and its
+// extension sanitized again
+fn resanitized_path(path: &Path) -> Option<PathBuf> {
+    let mut components: Vec<&str> = path.iter().map(|c| c.to_str()).collect::<Option<_>>()?;
+    let file_name = components.pop()?;
+    let (dir, subdirs) = components.split_first()?;
+
+    let mut resanitized = PathBuf::from(dir);
+    resanitized.extend(subdirs.iter().map(|c| sanitize(c)));
+    resanitized.push(match file_name.rsplit_once('.') {
+        Some((stem, extension)) => format!("{}.{}", sanitize(stem), extension),
+        None => sanitize(file_name)
+    });
+    Some(resanitized)
+}
+
+/// Guess the file extension of the image at the given artwork URL.
+fn artwork_extension(url: &str) -> &str {
+    url.rsplit('.')
+        .next()
+        .filter(|ext| ext.len() <= 4 && ext.chars().all(|c| c.is_ascii_alphanumeric()))
+        .unwrap_or("jpg")
+}
+
+/// An M3U playlist, to be saved in the directory `dir`, referencing the
+/// archived audio of the playlist's tracks at the paths given by `track_path`.
+fn m3u(playlist: &Playlist, dir: &Path, track_path: impl Fn(&Track) -> PathBuf) -> String {
+    let mut m3u = String::from("#EXTM3U\n");
+
+    for track in playlist.tracks.iter().flatten() {
+        let stem = track_stem(track);
+        let duration_secs = track.duration.map_or(-1, |d| d / 1000);
+        // M3U paths always use forward slashes
+        let path: Vec<_> = relative_path(dir, &track_path(track))
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .collect();
+
+        m3u.push_str(&format!("#EXTINF:{},{}\n", duration_secs, stem));
+        m3u.push_str(&format!("{}\n", path.join("/")));
+    }
+
+    m3u
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::TempDir;
+    use crate::manifest::FileMismatch;
+    use crate::transport::Transport;
+    use serde_json::{json, Value};
+    use std::io::Cursor;
+    use std::time::Duration;
+
+    const TRANSCODING_URL: &str = "https://api-v2.soundcloud.com/media/soundcloud:tracks:7/stream/progressive";
+    const MEDIA_URL: &str = "https://cf-media.sndcdn.com/test/7.128.mp3";
+    const ARTWORK_URL: &str = "https://i1.sndcdn.com/artworks-";
+
+    /// Answers an archive's requests with the given likes and playlists, and
+    /// the same audio for every track. Artwork is answered with its own URL.
+    struct ArchiveTransport {
+        likes: Value,
+        playlists: Vec<Value>,
+        audio: Vec<u8>
+    }
+
+    impl Transport for ArchiveTransport {
+        fn send(&self, request: &ureq::Request, _body: Option<&str>) -> Result<(Box<dyn Read + Send>, Option<u64>), Error> {
+            let url = request.get_url();
+            let playlist = self.playlists.iter().find(|p| {
+                p["uri"].as_str().is_some_and(|uri| uri.replace("api.", "api-v2.") == url)
+            });
+
+            let body = if url.contains("/track_likes") {
+                json!({ "collection": self.likes }).to_string().into_bytes()
+            } else if url.contains("/playlists/liked_and_owned") {
+                let collection: Vec<Value> = self.playlists.iter().map(|p| json!({ "playlist": p })).collect();
+                json!({ "collection": collection }).to_string().into_bytes()
+            } else if let Some(playlist) = playlist {
+                playlist.to_string().into_bytes()
+            } else if url.starts_with(TRANSCODING_URL) {
+                json!({ "url": MEDIA_URL }).to_string().into_bytes()
+            } else if url.starts_with(MEDIA_URL) {
+                self.audio.clone()
+            } else if url.starts_with(ARTWORK_URL) {
+                url.as_bytes().to_vec()
+            } else {
+                return Err(Error::NotFound { url: url.into(), body: "Not Found".into(), context: None });
+            };
+
+            let len = body.len() as u64;
+            Ok((Box::new(Cursor::new(body)), Some(len)))
+        }
+    }
+
+    // A `Zester` for the user with the given likes and playlists, whose
+    // requests are answered by an `ArchiveTransport`
+    fn archive_zester(likes: Vec<Value>, playlists: Vec<Value>, audio: &[u8]) -> Zester {
+        let me = json!({ "id": 1, "likes_count": likes.len(), "playlist_count": playlists.len() });
+        let mut zester = Zester::anonymous("test-client-id".into());
+        *zester.oauth_token.write().unwrap() = Some("test-token".into());
+        zester.me = Some(serde_json::from_value(me).unwrap());
+        zester.config.retry.pause = Duration::ZERO;
+        zester.config.retry.jitter = Duration::ZERO;
+        zester.set_transport(ArchiveTransport { likes: likes.into(), playlists, audio: audio.to_vec() });
+
+        zester
+    }
+
+    fn track(id: u64, title: &str) -> Value {
+        json!({
+            "id": id,
+            "title": title,
+            "duration": 181000,
+            "full_duration": 181000,
+            "user": { "username": "Artist" },
+            "media": {
+                "transcodings": [{
+                    "url": TRANSCODING_URL,
+                    "preset": "mp3_0_0",
+                    "duration": 181000,
+                    "snipped": false,
+                    "format": { "protocol": "progressive", "mime_type": "audio/mpeg" },
+                    "quality": "sq"
+                }]
+            }
+        })
+    }
+
+    fn liked_track(id: u64, title: &str) -> Value {
+        json!({ "created_at": "2021-09-15T08:30:00Z", "kind": "like", "track": track(id, title) })
+    }
+
+    fn playlist(id: u64, title: &str, tracks: Vec<Value>) -> Value {
+        json!({
+            "id": id,
+            "title": title,
+            "uri": format!("https://api.soundcloud.com/playlists/{}", id),
+            "track_count": tracks.len(),
+            "tracks": tracks
+        })
+    }
+
+    fn likes_only() -> ArchiveOptions {
+        ArchiveOptions {
+            playlists: None,
+            download_artwork: false,
+            spotlight: false,
+            ..ArchiveOptions::default()
+        }
+    }
+
+    #[test]
+    fn track_layouts() {
+        let track: Track = serde_json::from_value(json!({
+            "id": 7,
+            "title": "Side A/B",
+            "user": { "username": "Artist" },
+            "media": { "transcodings": [] }
+        })).unwrap();
+        let album: Playlist = serde_json::from_value(json!({ "title": "The Album", "tracks": [] })).unwrap();
+        let path = |layout: ArchiveLayout, album| layout.track_path(&track, "Road Trip", album, Some("mp3"));
+
+        assert_eq!(path(ArchiveLayout::Flat, None), Path::new("tracks/Artist - Side A_B.mp3"));
+        // audio in a format without a known extension is saved as an MP3
+        assert_eq!(ArchiveLayout::Flat.track_path(&track, "", None, None), Path::new("tracks/Artist - Side A_B.mp3"));
+        assert_eq!(path(ArchiveLayout::PerPlaylist, None), Path::new("tracks/Road Trip/Artist - Side A_B.mp3"));
+        assert_eq!(path(ArchiveLayout::PerArtist, None), Path::new("tracks/Artist/Side A_B.mp3"));
+        assert_eq!(
+            path(ArchiveLayout::Template("{artist}/{album}/{track_number} {title} [{id}]".into()), Some((&album, 3))),
+            Path::new("tracks/Artist/The Album/03 Side A_B [7].mp3")
+        );
+        assert_eq!(
+            path(ArchiveLayout::Template("../{album}/".into()), None),
+            Path::new("tracks/_/Unknown Album.mp3")
+        );
+
+        assert!(ArchiveLayout::Template("{playlist}/{title}".into()).is_per_playlist());
+        assert!(!ArchiveLayout::PerArtist.is_per_playlist());
+        assert_eq!(
+            relative_path(Path::new("playlists"), Path::new("tracks/Road Trip/a.mp3")),
+            Path::new("../tracks/Road Trip/a.mp3")
+        );
+        assert_eq!(relative_path(Path::new("tracks/Likes"), Path::new("tracks/Road Trip/a.mp3")), Path::new("../Road Trip/a.mp3"));
+    }
+
+    #[test]
+    fn colliding_track_paths() {
+        let tracks: Vec<Track> = serde_json::from_value(json!([
+            { "id": 1, "title": "Untitled" },
+            { "id": 2, "title": "untitled" },
+            { "title": "Untitled" },
+            { "title": "Untitled" }
+        ])).unwrap();
+        let path = Path::new("tracks/Untitled.mp3");
+
+        let mut paths = TrackPaths::default();
+        let claimed: Vec<PathBuf> = tracks.iter().map(|t| paths.claim(t, LIKES_DIR_NAME.into(), path)).collect();
+        assert_eq!(claimed, vec![
+            PathBuf::from("tracks/Untitled.mp3"),
+            PathBuf::from("tracks/Untitled (2).mp3"),
+            PathBuf::from("tracks/Untitled (3).mp3"),
+            PathBuf::from("tracks/Untitled (4).mp3"),
+        ]);
+
+        // the same track can claim its own path again
+        assert_eq!(paths.claim(&tracks[1], "Road Trip".into(), &claimed[1]), claimed[1]);
+        assert_eq!(paths.path_for(&tracks[1], "Road Trip"), Some(claimed[1].clone()));
+        assert_eq!(paths.all(&tracks[0]), vec![claimed[0].clone()]);
+
+        // which track keeps the filename doesn't depend on the order the
+        // tracks are found in
+        let claim_all = |tracks: &[&Track]| {
+            let mut collisions = vec![];
+            let paths = TrackPaths::new(&ArchiveLayout::Flat, None, None, None, tracks, |t, _, renamed| {
+                collisions.push((t.id, renamed.to_owned()));
+            });
+            (paths.all(tracks[0]), paths.all(tracks[1]), collisions)
+        };
+        let (first, second, collisions) = claim_all(&[&tracks[1], &tracks[0]]);
+        assert_eq!(first, vec![PathBuf::from("tracks/untitled (2).mp3")]);
+        assert_eq!(second, vec![PathBuf::from("tracks/Untitled.mp3")]);
+        assert_eq!(collisions, vec![(Some(TrackId(2)), first[0].clone())]);
+        assert_eq!(claim_all(&[&tracks[0], &tracks[1]]), (second, first, collisions));
+    }
+
+    #[test]
+    fn links_replace_incomplete_files() {
+        let root = TempDir::new("links");
+        let mut sink = DirSink::new(&*root);
+        let target = Path::new("tracks/Likes/a.mp3");
+        sink.write(target, &mut &b"complete audio"[..]).unwrap();
+
+        let mut kinds = vec![(LinkKind::Hardlink, "tracks/Road Trip/a.mp3")];
+        if cfg!(any(unix, windows)) {
+            kinds.push((LinkKind::Symlink, "tracks/Mix/a.mp3"));
+        }
+        for (kind, path) in kinds {
+            let path = Path::new(path);
+            sink.write(path, &mut &b"incomp"[..]).unwrap();
+            sink.link(target, path, kind).unwrap();
+            assert_eq!(fs::read(root.join(path)).unwrap(), b"complete audio");
+        }
+    }
+
+    #[test]
+    fn resumed_archives() {
+        let root = TempDir::new("resume");
+        let mut audio = b"ID3\x04\0\0\0\0\0\0".to_vec();
+        audio.resize(4096, 7);
+        let zester = archive_zester(vec![liked_track(7, "Song")], vec![], &audio);
+        let audio_path = root.join("tracks/Artist - Song.mp3");
+
+        zester.archive_to(&root, likes_only(), |_| {}).unwrap();
+        assert_eq!(fs::read(&audio_path).unwrap(), audio);
+
+        // as if the first archive had been interrupted part of the way through
+        // writing the track
+        fs::write(&audio_path, &audio[..1000]).unwrap();
+        let incomplete = RefCell::new(vec![]);
+        let report = zester.archive_to(&root, likes_only(), |e| if let ArchiveEvent::IncompleteFileFound { path, mismatch, .. } = e {
+            incomplete.borrow_mut().push((path.to_owned(), mismatch));
+        }).unwrap();
+        let expected = (PathBuf::from("tracks/Artist - Song.mp3"), FileMismatch::Size { expected: 4096, actual: 1000 });
+        assert_eq!(incomplete.into_inner(), [expected]);
+        assert_eq!(report.audio.downloaded, [TrackId(7)]);
+        assert_eq!(fs::read(&audio_path).unwrap(), audio);
+
+        // the complete track isn't downloaded again
+        let report = zester.archive_to(&root, likes_only(), |_| {}).unwrap();
+        assert_eq!(report.audio.skipped, [(TrackId(7), SkipReason::AlreadyArchived)]);
+    }
+
+    #[test]
+    fn files_named_by_older_versions() {
+        let root = TempDir::new("renamed");
+        let audio = b"ID3\x04\0\0\0\0\0\0".to_vec();
+        let zester = archive_zester(vec![liked_track(7, "Song .")], vec![], &audio);
+
+        // older versions only trimmed the dot off the end of this title
+        let old_path = Path::new("tracks/Artist - Song .mp3");
+        let new_path = Path::new("tracks/Artist - Song.mp3");
+        let mut sink = DirSink::new(&*root);
+        sink.write(old_path, &mut audio.as_slice()).unwrap();
+        let mut manifest = ArchiveManifest::default();
+        manifest.insert(old_path, ManifestEntry::new(&audio));
+        write_json_to_sink(&mut sink, &manifest, Path::new(MANIFEST_PATH), false).unwrap();
+
+        let renamed = RefCell::new(vec![]);
+        let report = zester.archive_to(&root, likes_only(), |e| if let ArchiveEvent::FileRenamed { path, renamed: to } = e {
+            renamed.borrow_mut().push((path.to_owned(), to.to_owned()));
+        }).unwrap();
+        assert_eq!(renamed.into_inner(), [(old_path.to_owned(), new_path.to_owned())]);
+        assert_eq!(report.audio.skipped, [(TrackId(7), SkipReason::AlreadyArchived)]);
+        assert!(!root.join(old_path).exists());
+        assert_eq!(fs::read(root.join(new_path)).unwrap(), audio);
+        assert!(ArchiveManifest::load(&sink).get(new_path).is_some());
+    }
+
+    #[test]
+    fn shared_tracks_are_linked() {
+        let root = TempDir::new("shared");
+        let audio = b"ID3\x04\0\0\0\0\0\0".to_vec();
+        let zester = archive_zester(
+            vec![liked_track(7, "Song")],
+            vec![playlist(20, "Road Trip", vec![track(7, "Song"), track(7, "Song")])],
+            &audio
+        );
+        let options = ArchiveOptions {
+            download_artwork: false,
+            spotlight: false,
+            layout: ArchiveLayout::PerPlaylist,
+            link_shared_tracks: Some(LinkKind::Hardlink),
+            ..ArchiveOptions::default()
+        };
+
+        let saved = RefCell::new(vec![]);
+        let report = zester.archive_to(&root, options, |e| match e {
+            ArchiveEvent::TrackSaved { path, .. } => saved.borrow_mut().push((path.to_owned(), None)),
+            ArchiveEvent::TrackLinked { path, target, .. } => {
+                saved.borrow_mut().push((path.to_owned(), Some(target.to_owned())))
+            },
+            _ => {}
+        }).unwrap();
+
+        // the track is downloaded once, and saved once for each place it
+        // belongs
+        let liked_path = PathBuf::from("tracks/Likes/Artist - Song.mp3");
+        let playlist_path = PathBuf::from("tracks/Road Trip/Artist - Song.mp3");
+        assert_eq!(report.audio.downloaded, [TrackId(7)]);
+        assert_eq!(saved.into_inner(), [(liked_path.clone(), None), (playlist_path.clone(), Some(liked_path))]);
+        assert_eq!(fs::read(root.join(playlist_path)).unwrap(), audio);
+    }
+
+    #[test]
+    fn playlists_with_the_same_title() {
+        let root = TempDir::new("same-title");
+        let zester = archive_zester(
+            vec![],
+            vec![
+                playlist(20, "Road Trip", vec![track(1, "One")]),
+                playlist(21, "road trip", vec![track(2, "Two")])
+            ],
+            b"audio"
+        );
+        let options = ArchiveOptions {
+            download_artwork: false,
+            spotlight: false,
+            layout: ArchiveLayout::PerPlaylist,
+            ..ArchiveOptions::default()
+        };
+        zester.archive_to(&root, options, |_| {}).unwrap();
+
+        // the second playlist gets its id added to its name rather than
+        // sharing the first one's files
+        let read = |path: &str| fs::read_to_string(root.join(path)).unwrap();
+        assert!(read("playlists/Road Trip.m3u").ends_with("../tracks/Road Trip/Artist - One.mp3\n"));
+        assert!(read("playlists/road trip (21).m3u").ends_with("../tracks/road trip (21)/Artist - Two.mp3\n"));
+        assert!(root.join("tracks/Road Trip/Artist - One.mp3").exists());
+        assert!(root.join("tracks/road trip (21)/Artist - Two.mp3").exists());
+        assert!(!root.join("tracks/Road Trip/Artist - Two.mp3").exists());
+    }
+
+    #[test]
+    fn colliding_tracks_artwork() {
+        let root = TempDir::new("artwork");
+        let liked_with_artwork = |id: u64| {
+            let mut like = liked_track(id, "Untitled");
+            like["track"]["artwork_url"] = format!("{}{}-large.jpg", ARTWORK_URL, id).into();
+            like
+        };
+        let zester = archive_zester(vec![liked_with_artwork(2), liked_with_artwork(1)], vec![], b"audio");
+        let options = ArchiveOptions { download_artwork: true, ..likes_only() };
+
+        let already_archived = Cell::new(0);
+        let report = zester.archive_to(&root, options, |e| {
+            if let ArchiveEvent::ArtworkAlreadyArchived { .. } = e {
+                already_archived.set(already_archived.get() + 1);
+            }
+        }).unwrap();
+
+        // each track's artwork is renamed along with its audio, rather than
+        // the two sharing one image
+        assert_eq!(report.artwork_downloaded, 2);
+        assert_eq!(already_archived.get(), 0);
+        assert!(root.join("tracks/Artist - Untitled (2).mp3").exists());
+        assert_eq!(fs::read_to_string(root.join("artwork/Artist - Untitled.jpg")).unwrap(), format!("{}1-large.jpg", ARTWORK_URL));
+        assert_eq!(
+            fs::read_to_string(root.join("artwork/Artist - Untitled (2).jpg")).unwrap(),
+            format!("{}2-large.jpg", ARTWORK_URL)
+        );
+    }
+
+    #[test]
+    fn sidecar_errors() {
+        let root = TempDir::new("sidecars");
+        let audio = b"ID3\x04\0\0\0\0\0\0".to_vec();
+        let zester = archive_zester(vec![liked_track(7, "Song"), liked_track(8, "Other")], vec![], &audio);
+        let options = ArchiveOptions { sidecars: Some(SidecarFormat::Json), ..likes_only() };
+
+        // a directory in the way of one of the sidecars
+        fs::create_dir_all(root.join("tracks/Artist - Other.json")).unwrap();
+
+        let written = RefCell::new(vec![]);
+        let errors = RefCell::new(vec![]);
+        let mut report = zester.archive_to(&root, options, |e| match e {
+            ArchiveEvent::MetadataWritten { path } => written.borrow_mut().push(path.to_owned()),
+            ArchiveEvent::SidecarSaveError { track_info, path, .. } => {
+                errors.borrow_mut().push((track_info.id, path.to_owned()))
+            },
+            ArchiveEvent::TrackSaveError { .. } => panic!("sidecar failure reported as a track failure"),
+            _ => {}
+        }).unwrap();
+
+        // the track whose sidecar couldn't be written was still saved
+        report.audio.downloaded.sort();
+        assert_eq!(report.audio.downloaded, [TrackId(7), TrackId(8)]);
+        assert!(report.audio.failed.is_empty());
+        assert!(written.into_inner().contains(&PathBuf::from("tracks/Artist - Song.json")));
+        assert_eq!(errors.into_inner(), [(Some(TrackId(8)), PathBuf::from("tracks/Artist - Other.json"))]);
+
+        let sidecar: Value = serde_json::from_slice(&fs::read(root.join("tracks/Artist - Song.json")).unwrap()).unwrap();
+        assert_eq!(sidecar["title"], "Song");
+    }
+
+    #[test]
+    fn album_sidecars() {
+        let root = TempDir::new("album-sidecars");
+        let audio = b"ID3\x04\0\0\0\0\0\0".to_vec();
+        let mut album = playlist(20, "The Album", vec![track(7, "Opener"), track(8, "Closer")]);
+        album["set_type"] = json!("album");
+        let zester = archive_zester(
+            vec![liked_track(9, "Single")],
+            vec![album, playlist(21, "Road Trip", vec![track(9, "Single")])],
+            &audio
+        );
+        let options = ArchiveOptions {
+            download_artwork: false,
+            spotlight: false,
+            sidecars: Some(SidecarFormat::Json),
+            ..ArchiveOptions::default()
+        };
+        zester.archive_to(&root, options, |_| {}).unwrap();
+
+        let sidecar = |name: &str| -> Value {
+            serde_json::from_slice(&fs::read(root.join("tracks").join(name)).unwrap()).unwrap()
+        };
+        let closer = sidecar("Artist - Closer.json");
+        assert_eq!(closer["album"], "The Album");
+        assert_eq!(closer["track_number"], 2);
+        assert_eq!(closer["track_count"], 2);
+        assert_eq!(closer["disc_number"], 1);
+        assert_eq!(sidecar("Artist - Opener.json")["track_number"], 1);
+
+        // playlists that aren't albums don't number their tracks
+        let single = sidecar("Artist - Single.json");
+        for field in ["album", "track_number", "track_count", "disc_number"] {
+            assert!(single[field].is_null(), "{} is set", field);
+        }
+
+        // .nfo sidecars hold the same fields
+        let closer: Track = serde_json::from_value(track(8, "Closer")).unwrap();
+        let album: Playlist = serde_json::from_value(json!({ "title": "The Album", "tracks": [track(7, "Opener"), closer] })).unwrap();
+        let nfo = Sidecar::new(&closer, Some((&album, 2))).to_nfo();
+        for element in [
+            "<album>The Album</album>",
+            "<tracknumber>2</tracknumber>",
+            "<tracktotal>2</tracktotal>",
+            "<discnumber>1</discnumber>"
+        ] {
+            assert!(nfo.contains(element), "no {} in {}", element, nfo);
+        }
+    }
+
+    #[cfg(feature = "zip")]
+    #[test]
+    fn zip_manifest_written_once() {
+        use crate::export::zip::ZipSink;
+
+        let likes = (1..=MANIFEST_SAVE_INTERVAL as u64 * 2 + 1).map(|id| liked_track(id, &format!("Song {}", id))).collect();
+        let zester = archive_zester(likes, vec![], b"audio");
+
+        let mut sink = ZipSink::new(Cursor::new(vec![]));
+        let report = zester.archive_into(&mut sink, likes_only(), |_| {}).unwrap();
+        assert_eq!(report.audio.downloaded.len(), MANIFEST_SAVE_INTERVAL * 2 + 1);
+
+        let mut zip = ::zip::ZipArchive::new(sink.finish().unwrap()).unwrap();
+        let manifests = (0..zip.len()).filter(|&i| zip.by_index(i).unwrap().name() == MANIFEST_PATH).count();
+        assert_eq!(manifests, 1);
+
+        // the one manifest covers every track
+        let manifest: Value = serde_json::from_reader(zip.by_name(MANIFEST_PATH).unwrap()).unwrap();
+        assert_eq!(manifest["files"].as_object().unwrap().len(), MANIFEST_SAVE_INTERVAL * 2 + 1);
+    }
+}
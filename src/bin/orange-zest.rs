@@ -0,0 +1,195 @@
+//! A command-line interface to orange-zest, for people who just want their
+//! data.
+//!
+//! Credentials are read from the `--oauth-token` and `--client-id` options, or
+//! from the `SOUNDCLOUD_OAUTH_TOKEN` and `SOUNDCLOUD_CLIENT_ID` environment
+//! variables. See the crate README for instructions on obtaining them.
+
+use orange_zest::archive::{ArchiveLayout, ArchiveOptions, LinkKind, SidecarFormat};
+use orange_zest::{write_snapshot, write_snapshot_to, Error, Zester};
+use serde::Serialize;
+use std::env;
+use std::io;
+use std::path::PathBuf;
+use std::process;
+
+const HELP: &str = "\
+orange-zest: zest SoundCloud for your data
+
+USAGE:
+    orange-zest [OPTIONS] <SUBCOMMAND>
+
+SUBCOMMANDS:
+    zest likes        Save your liked tracks as JSON
+    zest playlists    Save your playlists (and their tracks) as JSON
+    download <DIR>    Archive your likes and playlists, including audio and
+                      artwork, into DIR
+
+OPTIONS:
+    --oauth-token <TOKEN>    Your OAuth token [env: SOUNDCLOUD_OAUTH_TOKEN]
+    --client-id <ID>         Your client ID [env: SOUNDCLOUD_CLIENT_ID]
+    -n, --num <N>            Only zest the N most recent items
+    -o, --out <FILE>         Where to write zested JSON (default: stdout)
+    --pretty                 Pretty-print JSON
+    --no-audio               Don't download audio when archiving
+    --no-artwork             Don't download artwork when archiving
+    --sidecars <FORMAT>      Write a sidecar file (json or nfo) next to each
+                             track's audio when archiving
+    --layout <LAYOUT>        How to lay out archived tracks: flat (the
+                             default), playlist, artist, or a path template
+                             like \"{artist}/{album}/{title}\"
+    --link <KIND>            Link tracks that are in more than one playlist
+                             (symlink or hardlink) instead of copying them
+    -h, --help               Print this message
+";
+
+enum Command {
+    ZestLikes,
+    ZestPlaylists,
+    Download(PathBuf)
+}
+
+struct Args {
+    command: Command,
+    oauth_token: Option<String>,
+    client_id: Option<String>,
+    num: Option<u64>,
+    out: Option<PathBuf>,
+    pretty: bool,
+    no_audio: bool,
+    no_artwork: bool,
+    sidecars: Option<SidecarFormat>,
+    layout: Option<ArchiveLayout>,
+    link: Option<LinkKind>
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = pico_args::Arguments::from_env();
+
+    if args.contains(["-h", "--help"]) {
+        print!("{}", HELP);
+        process::exit(0);
+    }
+
+    let oauth_token = args.opt_value_from_str("--oauth-token").map_err(|e| e.to_string())?;
+    let client_id = args.opt_value_from_str("--client-id").map_err(|e| e.to_string())?;
+    let num = args.opt_value_from_str(["-n", "--num"]).map_err(|e| e.to_string())?;
+    let out = args.opt_value_from_str(["-o", "--out"]).map_err(|e| e.to_string())?;
+    let pretty = args.contains("--pretty");
+    let no_audio = args.contains("--no-audio");
+    let no_artwork = args.contains("--no-artwork");
+    let sidecars = args.opt_value_from_fn("--sidecars", |s| match s {
+        "json" => Ok(SidecarFormat::Json),
+        "nfo" => Ok(SidecarFormat::Nfo),
+        _ => Err("expected `json` or `nfo`")
+    }).map_err(|e| e.to_string())?;
+    let layout = args.opt_value_from_fn("--layout", |s| match s {
+        "flat" => Ok(ArchiveLayout::Flat),
+        "playlist" => Ok(ArchiveLayout::PerPlaylist),
+        "artist" => Ok(ArchiveLayout::PerArtist),
+        s if s.contains('{') => Ok(ArchiveLayout::Template(s.to_string())),
+        _ => Err("expected `flat`, `playlist`, `artist`, or a path template")
+    }).map_err(|e| e.to_string())?;
+    let link = args.opt_value_from_fn("--link", |s| match s {
+        "symlink" => Ok(LinkKind::Symlink),
+        "hardlink" => Ok(LinkKind::Hardlink),
+        _ => Err("expected `symlink` or `hardlink`")
+    }).map_err(|e| e.to_string())?;
+
+    let command = match args.subcommand().map_err(|e| e.to_string())?.as_deref() {
+        Some("zest") => match args.subcommand().map_err(|e| e.to_string())?.as_deref() {
+            Some("likes") => Command::ZestLikes,
+            Some("playlists") => Command::ZestPlaylists,
+            Some(other) => return Err(format!("unknown thing to zest: {}", other)),
+            None => return Err("expected `likes` or `playlists` after `zest`".into())
+        },
+        Some("download") => {
+            let dir = args.free_from_str().map_err(|_| "expected a directory after `download`")?;
+            Command::Download(dir)
+        },
+        Some(other) => return Err(format!("unknown subcommand: {}", other)),
+        None => return Err("no subcommand given (see --help)".into())
+    };
+
+    let rest = args.finish();
+    if !rest.is_empty() {
+        return Err(format!("unexpected arguments: {:?}", rest));
+    }
+
+    Ok(Args { command, oauth_token, client_id, num, out, pretty, no_audio, no_artwork, sidecars, layout, link })
+}
+
+fn zester(args: &Args) -> Result<Zester, String> {
+    let client_id = args.client_id.clone()
+        .or_else(|| env::var("SOUNDCLOUD_CLIENT_ID").ok())
+        .ok_or("no client ID given (use --client-id or SOUNDCLOUD_CLIENT_ID)")?;
+    let oauth_token = args.oauth_token.clone()
+        .or_else(|| env::var("SOUNDCLOUD_OAUTH_TOKEN").ok());
+
+    let zester = match oauth_token {
+        Some(oauth_token) => Zester::new(oauth_token, client_id),
+        #[cfg(feature = "browser-cookies")]
+        None => orange_zest::credentials::browser::credentials(client_id).and_then(Zester::from_credentials),
+        #[cfg(not(feature = "browser-cookies"))]
+        None => return Err("no OAuth token given (use --oauth-token or SOUNDCLOUD_OAUTH_TOKEN)".into())
+    };
+
+    zester.map_err(|e| e.to_string())
+}
+
+// Write zested data to the file given with `--out`, or stdout otherwise
+fn output<O: Serialize>(args: &Args, object: &O) -> Result<(), Error> {
+    match &args.out {
+        Some(path) => write_snapshot(object, path, args.pretty),
+        None => write_snapshot_to(object, io::stdout().lock(), args.pretty)
+    }
+}
+
+fn run(args: Args) -> Result<(), String> {
+    let zester = zester(&args)?;
+    let num = args.num.unwrap_or(u64::MAX);
+
+    match &args.command {
+        Command::ZestLikes => {
+            let likes = zester.likes(num, |e| eprintln!("{}", e)).map_err(|e| e.to_string())?;
+            output(&args, &likes).map_err(|e| e.to_string())
+        },
+        Command::ZestPlaylists => {
+            let playlists = zester.playlists(num, |e| eprintln!("{}", e)).map_err(|e| e.to_string())?;
+            output(&args, &playlists).map_err(|e| e.to_string())
+        },
+        Command::Download(dir) => {
+            let options = ArchiveOptions {
+                likes: Some(num),
+                playlists: Some(num),
+                download_audio: !args.no_audio,
+                download_artwork: !args.no_artwork,
+                pretty_print: args.pretty,
+                sidecars: args.sidecars,
+                layout: args.layout.clone().unwrap_or(ArchiveLayout::Flat),
+                link_shared_tracks: args.link,
+                ..ArchiveOptions::default()
+            };
+            let report = zester.archive_to(dir, options, |e| eprintln!("{}", e)).map_err(|e| e.to_string())?;
+
+            eprintln!(
+                "Archived {} likes and {} playlists; {} tracks downloaded, {} skipped, {} failed",
+                report.likes_count,
+                report.playlists_count,
+                report.audio.downloaded.len(),
+                report.audio.skipped.len(),
+                report.audio.failed.len()
+            );
+            Ok(())
+        }
+    }
+}
+
+fn main() {
+    let result = parse_args().and_then(run);
+
+    if let Err(e) = result {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,102 @@
+//! A trait covering the main zesting operations, for writing code that can
+//! be tested without network access.
+
+use crate::{Error, Zester};
+use crate::api::{Likes, Playlists};
+use crate::api::common::Track;
+use crate::api::ids::TrackId;
+use crate::api::me::Me;
+use crate::api::playlists::Playlist;
+use crate::events::*;
+use crate::report::ZestReport;
+
+/// The zesting operations of a `Zester`, as an object-safe trait.
+///
+/// Write application logic against `&dyn ZestClient` (or a generic
+/// `C: ZestClient`) instead of `Zester` directly, and it can be handed a mock
+/// in tests. The methods behave like the `Zester` methods of the same names;
+/// callbacks are taken as trait objects so that the trait can be used as one.
+///
+/// ```ignore
+/// fn count_likes(client: &dyn ZestClient) -> Result<usize, Error> {
+///     Ok(client.likes(u64::MAX, &|_| {})?.collections.len())
+/// }
+/// ```
+pub trait ZestClient {
+    /// See `Zester::me`.
+    fn me(&self) -> Result<Me, Error>;
+
+    /// See `Zester::likes`.
+    fn likes(&self, num_recent: u64, cb: &dyn Fn(LikesZestingEvent)) -> Result<Likes, Error>;
+
+    /// See `Zester::playlists`.
+    fn playlists(&self, num_recent: u64, cb: &dyn Fn(PlaylistsZestingEvent)) -> Result<Playlists, Error>;
+
+    /// See `Zester::tracks_info`.
+    fn tracks_info(&self, ids: &[TrackId]) -> Result<Vec<Track>, Error>;
+
+    /// See `Zester::likes_audio`.
+    fn likes_audio(
+        &self,
+        likes: &Likes,
+        num_recent: u64,
+        cb: &dyn Fn(TracksAudioZestingEvent)
+    ) -> Result<ZestReport, Error>;
+
+    /// See `Zester::playlists_audio`.
+    fn playlists_audio<'a>(
+        &self,
+        playlists: &mut dyn Iterator<Item = &'a Playlist>,
+        cb: &dyn Fn(PlaylistsAudioZestingEvent)
+    ) -> Result<ZestReport, Error>;
+
+    /// See `Zester::tracks_audio`.
+    fn tracks_audio<'a>(
+        &self,
+        tracks: &mut dyn Iterator<Item = &'a Track>,
+        cb: &dyn Fn(TracksAudioZestingEvent)
+    ) -> Result<ZestReport, Error>;
+}
+
+impl ZestClient for Zester {
+    fn me(&self) -> Result<Me, Error> {
+        Zester::me(self)
+    }
+
+    fn likes(&self, num_recent: u64, cb: &dyn Fn(LikesZestingEvent)) -> Result<Likes, Error> {
+        Zester::likes(self, num_recent, cb)
+    }
+
+    fn playlists(&self, num_recent: u64, cb: &dyn Fn(PlaylistsZestingEvent)) -> Result<Playlists, Error> {
+        Zester::playlists(self, num_recent, cb)
+    }
+
+    fn tracks_info(&self, ids: &[TrackId]) -> Result<Vec<Track>, Error> {
+        Zester::tracks_info(self, ids)
+    }
+
+    fn likes_audio(
+        &self,
+        likes: &Likes,
+        num_recent: u64,
+        cb: &dyn Fn(TracksAudioZestingEvent)
+    ) -> Result<ZestReport, Error> {
+        Zester::likes_audio(self, likes, num_recent, cb)
+    }
+
+    fn playlists_audio<'a>(
+        &self,
+        playlists: &mut dyn Iterator<Item = &'a Playlist>,
+        cb: &dyn Fn(PlaylistsAudioZestingEvent)
+    ) -> Result<ZestReport, Error> {
+        Zester::playlists_audio(self, playlists, cb)
+    }
+
+    fn tracks_audio<'a>(
+        &self,
+        tracks: &mut dyn Iterator<Item = &'a Track>,
+        cb: &dyn Fn(TracksAudioZestingEvent)
+    ) -> Result<ZestReport, Error> {
+        Zester::tracks_audio(self, tracks, cb)
+    }
+}
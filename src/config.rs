@@ -0,0 +1,211 @@
+use std::time::Duration;
+
+/// Configuration that controls how a `Zester` makes its requests.
+///
+/// The defaults are reasonable for most connections; tweak the fields of
+/// `Zester::config` after construction to change them.
+#[derive(Debug, Clone)]
+pub struct ZesterConfig {
+    /// The amount of time to wait for a connection to be established.
+    pub connect_timeout: Duration,
+    /// The amount of time to wait for more data to arrive while reading a
+    /// response.
+    ///
+    /// `None` means wait indefinitely.
+    pub read_timeout: Option<Duration>,
+    /// The maximum amount of time a single request (including reading its
+    /// entire response body) is allowed to take.
+    ///
+    /// This applies to both API requests and media downloads. `None` means
+    /// there is no limit.
+    pub request_timeout: Option<Duration>,
+    /// The maximum amount of time downloading a single track's audio is
+    /// allowed to take, including any retries, before the track is recorded as
+    /// failed (with `Error::TrackTimedOut`) and the zest moves on.
+    ///
+    /// This keeps one enormous track on a slow connection from holding up
+    /// the rest of the zest. `None` means there is no limit.
+    pub track_timeout: Option<Duration>,
+    /// The amount of time a download is allowed to go without receiving any
+    /// data before it's abandoned (and retried like a server error).
+    ///
+    /// Unlike `read_timeout`, this also catches connections that trickle in
+    /// data too slowly for the socket to notice. It should be shorter than
+    /// `read_timeout`. `None` means stalls aren't detected.
+    pub stall_timeout: Option<Duration>,
+    /// Whether or not to emit an estimate of the total number of bytes that
+    /// will be downloaded before downloading track audio.
+    ///
+    /// The estimate is based on track durations, so it doesn't cost any
+    /// additional requests.
+    pub estimate_download_size: bool,
+    /// The size of the chunks audio is read in, in bytes.
+    ///
+    /// Progress is reported after each chunk, so smaller chunks mean more
+    /// frequent progress events (which is nice on slow connections), while
+    /// larger ones mean less overhead on fast connections.
+    pub download_chunk_size: usize,
+    /// The maximum rate (in bytes per second) to download audio at.
+    ///
+    /// The limit applies to all of the `Zester`'s downloads combined, including
+    /// ones happening concurrently. `None` means there is no limit.
+    pub max_download_rate: Option<u64>,
+    /// Whether or not to check each downloaded track's audio before handing it
+    /// over.
+    ///
+    /// The amount of data received is compared to the length the server
+    /// reported, and the start of the data is checked against the format the
+    /// audio is supposed to be in.
+    pub verify_downloads: bool,
+    /// Whether or not to download the preview snippet of tracks that only
+    /// have a preview available to this `Zester` (such as Go+ tracks without
+    /// a Go+ subscription).
+    ///
+    /// Such tracks fail with `Error::SnippetOnly` when this is off. When it's
+    /// on, a `SnippetOnlyDownloaded` event marks the download and the track is
+    /// listed in `ZestReport::snippets`.
+    pub download_snippets: bool,
+    /// Whether or not to resolve the media URL of the next track while the
+    /// current one downloads.
+    ///
+    /// This overlaps the API request needed to find each track's audio file
+    /// with the transfer of the previous one, which speeds up zesting lots of
+    /// short tracks. A prefetched URL that has expired by the time it's used
+    /// is resolved again.
+    pub prefetch_media_urls: bool,
+    /// The number of times to re-download a track whose audio fails
+    /// verification before giving up on it.
+    pub max_verification_retries: u32,
+    /// How requests that fail because of a server error are retried.
+    pub retry: RetryPolicy,
+    /// The number of tracks to request information for at once when
+    /// completing the information about a playlist's tracks.
+    ///
+    /// The API accepts up to 50 ids per request; larger batches mean fewer
+    /// requests, but more to redo if one of them fails.
+    pub track_info_batch_size: usize,
+    /// The maximum number of batches of track information to request at the
+    /// same time when completing the information about a playlist's tracks.
+    ///
+    /// 1 means batches are requested one after another.
+    pub track_info_concurrency: usize,
+    /// The maximum number of playlists to download full information for at
+    /// the same time while zesting playlists.
+    ///
+    /// 1 means playlists are downloaded one after another.
+    pub playlist_info_concurrency: usize,
+    /// The number of items to request per page of paginated lists.
+    pub page_sizes: PageSizes,
+    /// Whether or not to keep the JSON that tracks and playlists were
+    /// deserialized from in their `raw` fields.
+    ///
+    /// The raw JSON is saved along with them, so archives stay lossless even
+    /// if SoundCloud changes its schema in ways the typed fields don't cover.
+    /// This roughly doubles the size of saved data.
+    pub keep_raw_json: bool,
+    /// Whether or not to remember the track information fetched while
+    /// completing playlists, so tracks that appear in several playlists are
+    /// only requested once.
+    ///
+    /// This is off by default: the cache lives as long as the `Zester` and
+    /// holds every track fetched while it's on, which adds up for a
+    /// long-running program (see `Zester::clear_track_cache`).
+    pub cache_track_info: bool,
+    /// The locale to ask for localized metadata in (such as genre names and
+    /// the titles of system playlists), like "en" or "de".
+    ///
+    /// It's sent with API requests as the `app_locale` query parameter and the
+    /// `Accept-Language` header. `None` leaves it up to the server, which
+    /// guesses based on where requests come from, so the same archive can come
+    /// out differently depending on where it's made.
+    pub locale: Option<String>,
+}
+
+/// The number of items requested per page of paginated lists.
+///
+/// Larger pages mean fewer requests, while smaller pages mean less to redo
+/// when a request fails on a flaky connection.
+#[derive(Debug, Clone)]
+pub struct PageSizes {
+    /// Liked tracks (`Zester::likes`)
+    pub likes: u64,
+    /// Liked and owned playlists (`Zester::playlists`)
+    pub playlists: u64,
+    /// Everything else, such as users and comments
+    pub other: u64,
+}
+
+impl Default for PageSizes {
+    fn default() -> Self {
+        Self {
+            likes: 500,
+            playlists: 50,
+            other: 200,
+        }
+    }
+}
+
+/// How requests that fail because of a server error (a 5xx response) are
+/// retried.
+///
+/// Waiting a couple of seconds and trying again usually resolves these.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// The amount of time to wait before retrying a request.
+    pub pause: Duration,
+    /// The maximum amount of time randomly added to each pause.
+    ///
+    /// When several requests fail at once (like during an outage), this keeps
+    /// them from all being retried at the same moment and failing together
+    /// again. Zero means every pause is exactly `pause`.
+    pub jitter: Duration,
+    /// The maximum number of times in a row a single request is retried before
+    /// giving up on it.
+    ///
+    /// `None` means requests are retried until they succeed.
+    pub max_consecutive_retries: Option<u32>,
+    /// The maximum number of retries a single zesting call (such as
+    /// `Zester::likes` or `Zester::tracks_audio`) makes across all of its
+    /// requests, after which it fails with `Error::RetryBudgetExhausted`.
+    ///
+    /// `None` means there is no overall limit.
+    pub max_total_retries: Option<u32>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            pause: Duration::from_secs(2),
+            jitter: Duration::from_secs(1),
+            max_consecutive_retries: None,
+            max_total_retries: None,
+        }
+    }
+}
+
+impl Default for ZesterConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_secs(10),
+            read_timeout: Some(Duration::from_secs(30)),
+            request_timeout: None,
+            track_timeout: None,
+            stall_timeout: Some(Duration::from_secs(20)),
+            estimate_download_size: true,
+            max_download_rate: None,
+            download_chunk_size: 64 * 1024,
+            verify_downloads: false,
+            download_snippets: false,
+            prefetch_media_urls: true,
+            max_verification_retries: 2,
+            retry: RetryPolicy::default(),
+            track_info_batch_size: 10,
+            track_info_concurrency: 1,
+            playlist_info_concurrency: 1,
+            page_sizes: PageSizes::default(),
+            keep_raw_json: false,
+            cache_track_info: false,
+            locale: None,
+        }
+    }
+}
@@ -0,0 +1,177 @@
+//! Reading the logged-in SoundCloud session from a local browser's cookies.
+//!
+//! This saves you from digging the OAuth token out of your browser's devtools.
+//! The client ID isn't stored in cookies, so you still need to provide it.
+//!
+//! Only Firefox is supported: Chrome (and other Chromium-based browsers)
+//! encrypt their cookie values with a key kept in the operating system's
+//! keychain, so they can't be read from the cookie database alone. If you use
+//! one of those, provide the OAuth token yourself.
+
+use super::Credentials;
+use crate::Error;
+use rusqlite::{Connection, OpenFlags};
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The name of the cookie that holds the OAuth token
+const TOKEN_COOKIE: &str = "oauth_token";
+
+/// The suffixes of the files SQLite keeps alongside a database in WAL mode,
+/// which hold changes that haven't made it into the database file yet
+const WAL_SUFFIXES: [&str; 2] = ["-wal", "-shm"];
+
+/// Used to give each read of a cookie database its own copy
+static NEXT_COPY: AtomicUsize = AtomicUsize::new(0);
+
+/// A browser whose cookies can be read.
+///
+/// Only Firefox is supported (see the module documentation for why).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Browser {
+    Firefox
+}
+
+/// Read the OAuth token from Firefox's cookies.
+///
+/// This is the same as `credentials_from(Browser::Firefox, client_id)`; other
+/// browsers aren't supported.
+pub fn credentials(client_id: String) -> Result<Credentials, Error> {
+    credentials_from(Browser::Firefox, client_id)
+}
+
+/// Read the OAuth token from the given browser's cookies.
+///
+/// All of the browser's profiles are searched. Returns a `DataNotPresent`
+/// error if none of them are logged in to SoundCloud.
+pub fn credentials_from(browser: Browser, client_id: String) -> Result<Credentials, Error> {
+    for cookie_store in cookie_stores(browser) {
+        if let Some(oauth_token) = read_token(browser, &cookie_store)? {
+            return Ok(Credentials { oauth_token, client_id });
+        }
+    }
+
+    // other browsers' cookies can't be read (see the module documentation),
+    // which is worth pointing out to anyone logged in with one of them
+    Err(Error::DataNotPresent("SoundCloud session in Firefox cookies (other browsers aren't supported)".into()))
+}
+
+// Read the OAuth token from the given cookie database, if it's there
+fn read_token(browser: Browser, cookie_store: &Path) -> Result<Option<String>, Error> {
+    let with_suffix = |path: &Path, suffix: &str| {
+        let mut p = OsString::from(path);
+        p.push(suffix);
+        PathBuf::from(p)
+    };
+
+    // Browsers keep their cookie database locked while they're running, so
+    // read from a copy instead. Recent changes (like logging in) are often
+    // only in the write-ahead log, so that's copied too.
+    let n = NEXT_COPY.fetch_add(1, Ordering::Relaxed);
+    let copy = env::temp_dir().join(format!("orange-zest-cookies-{}-{}", process::id(), n));
+
+    let result = fs::copy(cookie_store, &copy)
+        .and_then(|_| {
+            for suffix in WAL_SUFFIXES.iter() {
+                let wal_file = with_suffix(cookie_store, suffix);
+                if wal_file.is_file() {
+                    fs::copy(&wal_file, with_suffix(&copy, suffix))?;
+                }
+            }
+            Ok(())
+        })
+        .map_err(Error::from)
+        .and_then(|_| query_token(browser, &copy));
+
+    let _ = fs::remove_file(&copy);
+    for suffix in WAL_SUFFIXES.iter() {
+        let _ = fs::remove_file(with_suffix(&copy, suffix));
+    }
+    result
+}
+
+fn query_token(browser: Browser, db: &Path) -> Result<Option<String>, Error> {
+    let conn = Connection::open_with_flags(db, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+
+    let query = match browser {
+        Browser::Firefox => "SELECT value FROM moz_cookies WHERE host LIKE '%soundcloud.com' AND name = ?1"
+    };
+    let mut stmt = conn.prepare(query)?;
+    let mut rows = stmt.query(&[TOKEN_COOKIE])?;
+
+    while let Some(row) = rows.next()? {
+        let value: String = row.get(0)?;
+        if !value.is_empty() {
+            return Ok(Some(value));
+        }
+    }
+
+    Ok(None)
+}
+
+// All of the cookie databases for the given browser that exist on this machine
+fn cookie_stores(browser: Browser) -> Vec<PathBuf> {
+    profile_dirs(browser)
+        .into_iter()
+        .flat_map(|dir| {
+            let candidates = match browser {
+                Browser::Firefox => vec![dir.join("cookies.sqlite")]
+            };
+            candidates.into_iter().filter(|p| p.is_file())
+        })
+        .collect()
+}
+
+// The profile directories for the given browser
+fn profile_dirs(browser: Browser) -> Vec<PathBuf> {
+    let roots: Vec<PathBuf> = match browser {
+        Browser::Firefox => {
+            if cfg!(target_os = "windows") {
+                dirs::data_dir().map(|d| d.join("Mozilla").join("Firefox").join("Profiles")).into_iter().collect()
+            } else if cfg!(target_os = "macos") {
+                dirs::data_dir().map(|d| d.join("Firefox").join("Profiles")).into_iter().collect()
+            } else {
+                dirs::home_dir().map(|d| d.join(".mozilla").join("firefox")).into_iter().collect()
+            }
+        }
+    };
+
+    roots
+        .into_iter()
+        .filter_map(|root| fs::read_dir(root).ok())
+        .flat_map(|entries| entries.filter_map(|e| e.ok()).map(|e| e.path()))
+        .filter(|p| p.is_dir())
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn cookies_in_the_write_ahead_log() {
+        let dir = TempDir::new("browser");
+        let cookie_store = dir.join("cookies.sqlite");
+
+        // like a running Firefox, the connection is kept open so that the
+        // new cookie stays in the write-ahead log
+        let conn = Connection::open(&cookie_store).unwrap();
+        let mode: String = conn.query_row("PRAGMA journal_mode=WAL", rusqlite::NO_PARAMS, |row| row.get(0)).unwrap();
+        assert_eq!(mode, "wal");
+        conn.execute_batch(
+            "CREATE TABLE moz_cookies (host TEXT, name TEXT, value TEXT);
+             INSERT INTO moz_cookies VALUES ('.soundcloud.com', 'oauth_token', '2-123456-token');"
+        ).unwrap();
+        assert!(dir.join("cookies.sqlite-wal").is_file());
+
+        assert_eq!(read_token(Browser::Firefox, &cookie_store).unwrap().as_deref(), Some("2-123456-token"));
+        // the same copy isn't shared between reads
+        assert_eq!(read_token(Browser::Firefox, &cookie_store).unwrap().as_deref(), Some("2-123456-token"));
+        assert!(read_token(Browser::Firefox, &dir.join("missing.sqlite")).is_err());
+    }
+}
@@ -0,0 +1,34 @@
+//! The credentials used to access the API, and ways of obtaining them.
+
+#[cfg(feature = "browser-cookies")]
+pub mod browser;
+
+/// The credentials needed to construct a `Zester`.
+///
+/// See the crate README for instructions on how to obtain these.
+#[derive(Debug, Clone)]
+pub struct Credentials {
+    pub oauth_token: String,
+    pub client_id: String
+}
+
+/// Something that can supply a new OAuth token when the server rejects the
+/// current one.
+///
+/// Install one with `Zester::set_credentials_provider`. Whenever a request
+/// fails with a 401 the provider is asked for a fresh token, and the request is
+/// retried with it. This lets long-running zests survive the user's session
+/// expiring, for example by prompting the user to sign in again.
+pub trait CredentialsProvider: Send + Sync {
+    /// Return a new token to replace `expired_token`, or `None` to give up and
+    /// let the request fail.
+    ///
+    /// This will be called again if the new token is also rejected.
+    fn refresh_token(&self, expired_token: &str) -> Option<String>;
+}
+
+impl<F: Fn(&str) -> Option<String> + Send + Sync> CredentialsProvider for F {
+    fn refresh_token(&self, expired_token: &str) -> Option<String> {
+        self(expired_token)
+    }
+}
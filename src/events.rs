@@ -1,8 +1,21 @@
 use crate::api::common::Track;
+use crate::api::ids::TrackId;
+use crate::api::likes::LikesCollection;
 use crate::api::playlists::{PlaylistMeta, Playlist};
+use std::fmt;
 use std::io::Read;
-use serde_derive::Serialize;
+use std::path::Path;
+use std::time::Duration;
+use serde::Serialize;
+use serde_derive::{Serialize, Deserialize};
+use serde_json::Value;
 use crate::Error;
+use crate::filename::track_artist;
+use crate::manifest::FileMismatch;
+#[cfg(feature = "musicbrainz")]
+use crate::musicbrainz::Recording;
+use crate::progress::ZestProgress;
+use crate::retry::RetryPause;
 
 /// Events that can occur while zesting likes
 #[derive(Debug, Serialize)]
@@ -22,12 +35,107 @@ pub enum LikesZestingEvent {
         count: i64
     },
 
-    /// The server returned an error response and we are waiting for the given
-    /// amount of seconds before retrying the request.
+    /// The server returned an error response and we are waiting before
+    /// retrying the request.
     /// 
     /// This event can occur multiple times.
     PausedAfterServerError {
-        time_secs: u64
+        retry: RetryPause
+    }
+}
+
+/// Events that can occur while zesting lists of users, such as the likers or
+/// reposters of a track
+#[derive(Debug, Serialize)]
+pub enum UsersZestingEvent {
+    /// Finished downloading another page of users.
+    ///
+    /// This event can occur multiple times.
+    MoreUsersInfoDownloaded {
+        /// The number of additional users that info was downloaded for
+        count: i64
+    },
+
+    /// The server returned an error response and we are waiting before
+    /// retrying the request.
+    /// 
+    /// This event can occur multiple times.
+    PausedAfterServerError {
+        retry: RetryPause
+    }
+}
+
+/// Events that can occur while zesting lists of tracks, such as the tracks a
+/// user has uploaded
+#[derive(Debug, Serialize)]
+pub enum TracksZestingEvent {
+    /// The number of tracks that info is going to be downloaded for.
+    ///
+    /// This event occurs only once.
+    NumTracksInfoToDownload {
+        num: u64
+    },
+
+    /// Finished downloading another page of tracks.
+    ///
+    /// This event can occur multiple times.
+    MoreTracksInfoDownloaded {
+        /// The number of additional tracks that info was downloaded for
+        count: i64
+    },
+
+    /// The server returned an error response and we are waiting before
+    /// retrying the request.
+    /// 
+    /// This event can occur multiple times.
+    PausedAfterServerError {
+        retry: RetryPause
+    }
+}
+
+/// Events that can occur while zesting the comments on the user's tracks
+#[derive(Debug, Serialize)]
+pub enum CommentsZestingEvent<'a> {
+    /// The number of the user's tracks that comments are going to be downloaded
+    /// for.
+    ///
+    /// This event occurs only once.
+    NumTracksToDownload {
+        num: u64
+    },
+
+    /// Start of downloading the comments on another track.
+    ///
+    /// This event can occur multiple times.
+    StartTrackCommentsDownload {
+        track_info: &'a Track
+    },
+
+    /// End of downloading the comments on another track.
+    ///
+    /// This event can occur multiple times.
+    FinishTrackCommentsDownload {
+        track_info: &'a Track,
+        /// The number of comments that were downloaded for the track
+        count: i64
+    },
+
+    /// An error occured while downloading the comments on a track.
+    ///
+    /// The track is left out of the results.
+    ///
+    /// This event can occur multiple times.
+    TrackCommentsDownloadError {
+        track_info: &'a Track,
+        err: Error
+    },
+
+    /// The server returned an error response and we are waiting before
+    /// retrying the request.
+    /// 
+    /// This event can occur multiple times.
+    PausedAfterServerError {
+        retry: RetryPause
     }
 }
 
@@ -41,6 +149,30 @@ pub enum TracksAudioZestingEvent<'a> {
         num: u64
     },
 
+    /// An estimate of the total number of bytes that are going to be
+    /// downloaded.
+    ///
+    /// This event occurs only once, if enabled in the `Zester`'s config.
+    NumBytesToDownload {
+        num: u64
+    },
+
+    /// A track was excluded by the filter passed to one of the `_filtered`
+    /// audio zesting methods and won't be downloaded.
+    ///
+    /// This event can occur multiple times.
+    SkippedByFilter {
+        track_info: &'a Track
+    },
+
+    /// A track's audio is only available with DRM, so it won't be
+    /// downloaded.
+    ///
+    /// This event can occur multiple times.
+    SkippedDrmProtected {
+        track_info: &'a Track
+    },
+
     /// Start of downloading a track.
     ///
     /// This event can occur multiple times.
@@ -48,16 +180,67 @@ pub enum TracksAudioZestingEvent<'a> {
         track_info: &'a Track
     },
 
-    /// Finished downloading a track.
+    /// More of a track's audio was downloaded.
+    ///
+    /// `progress` describes the progress of the zest as a whole as well as
+    /// that of this track.
+    ///
+    /// This event can occur multiple times.
+    TrackDownloadProgress {
+        track_info: &'a Track,
+        progress: ZestProgress
+    },
+
+    /// A track's audio is ready to be read.
     ///
     /// `track_data` is a `Read` instance that you can use to access the data.
+    /// The audio is usually still being downloaded as it's read (with
+    /// `TrackDownloadProgress` events occuring along the way), so reading it
+    /// can fail partway; if it does, the error inside the `io::Error` is a
+    /// `reader::DownloadInterrupted`, and the failure is reported separately
+    /// by a `TrackDownloadError` (or, if the track is retried, by this event
+    /// occuring again for the same track).
     /// 
     /// This event can occur multiple times.
     FinishTrackDownload {
         track_info: &'a Track,
+        /// The file extension for the downloaded audio, based on the mime type
+        /// of the transcoding that was downloaded (if it's a known one)
+        file_extension: Option<&'static str>,
         // TODO: replace with impl Read when stable
         #[serde(skip_serializing)]
-        track_data: Box<dyn Read>
+        track_data: Box<dyn Read + 'a>
+    },
+
+    /// Only a preview snippet of a track's audio was available, and the
+    /// snippet was downloaded (see `ZesterConfig::download_snippets`).
+    ///
+    /// A `FinishTrackDownload` for the track follows.
+    ///
+    /// This event can occur multiple times.
+    SnippetOnlyDownloaded {
+        track_info: &'a Track
+    },
+
+    /// A track's download was stopped because the callback returned
+    /// `DownloadControl::SkipTrack` (see `Zester::tracks_audio_controlled`).
+    ///
+    /// This event can occur multiple times.
+    TrackSkipped {
+        track_info: &'a Track
+    },
+
+    /// A downloaded track's audio failed verification (see
+    /// `ZesterConfig::verify_downloads`).
+    ///
+    /// The track will be downloaded again if there are retries left, otherwise
+    /// a `TrackDownloadError` follows.
+    ///
+    /// This event can occur multiple times.
+    TrackVerificationFailed {
+        track_info: &'a Track,
+        /// A description of what was wrong with the audio
+        reason: String
     },
 
     /// An error occured while trying to download a track.
@@ -68,12 +251,12 @@ pub enum TracksAudioZestingEvent<'a> {
         err: Error
     },
 
-    /// The server returned an error response and we are waiting for the given
-    /// amount of seconds before retrying the request.
+    /// The server returned an error response and we are waiting before
+    /// retrying the request.
     /// 
     /// This event can occur multiple times.
     PausedAfterServerError {
-        time_secs: u64
+        retry: RetryPause
     }
 }
 
@@ -100,6 +283,12 @@ pub enum PlaylistsZestingEvent<'a> {
     /// This event occurs only once.
     FinishPlaylistMetaInfoDownloading,
 
+    /// An entry in the list of playlists was missing its "meta"-data and was
+    /// skipped.
+    ///
+    /// This event can occur multiple times.
+    PlaylistMetaNotPresent,
+
     /// Start of downloading full information for another playlist.
     ///
     /// This event can occur multiple times.
@@ -122,6 +311,10 @@ pub enum PlaylistsZestingEvent<'a> {
         err: Error
     },
 
+    /// Events related to completing the information about a playlist's tracks
+    /// (see `Playlist::complete_tracks_info`).
+    TracksInfoCompletion(TracksInfoCompletionEvent, &'a PlaylistMeta),
+
     /// An error occured while attempting to complete downloaded playlist information.
     /// 
     /// The information will still be returned, but it may not be complete.
@@ -132,12 +325,73 @@ pub enum PlaylistsZestingEvent<'a> {
         err: Error
     },
 
-    /// The server returned an error response and we are waiting for the given
-    /// amount of seconds before retrying the request.
+    /// The server returned an error response and we are waiting before
+    /// retrying the request.
     /// 
     /// This event can occur multiple times.
     PausedAfterServerError {
-        time_secs: u64
+        retry: RetryPause
+    }
+}
+
+/// Events that can occur while completing the information about a playlist's
+/// tracks
+///
+/// The tracks that are missing information are looked up in batches.
+#[derive(Debug, Serialize)]
+pub enum TracksInfoCompletionEvent {
+    /// The number of batches that are going to be downloaded, and the total
+    /// number of tracks in them.
+    ///
+    /// This event occurs only once.
+    NumBatchesToDownload {
+        num: u64,
+        tracks_num: u64
+    },
+
+    /// Start of downloading a batch.
+    ///
+    /// This event can occur multiple times.
+    StartBatchDownload {
+        /// The index of the batch, starting at 0
+        index: usize,
+        /// The number of tracks in the batch
+        num_tracks: usize
+    },
+
+    /// The playlist has more tracks than were included in its info, so the
+    /// ids of the rest of them were looked up.
+    ///
+    /// This event occurs at most once, before `NumBatchesToDownload`.
+    FetchedRemainingTrackIds {
+        /// The number of tracks that were added to the playlist
+        num: u64
+    },
+
+    /// Finished downloading a batch.
+    ///
+    /// This event can occur multiple times.
+    FinishBatchDownload {
+        index: usize,
+        /// The number of tracks that info was returned for, which can be less
+        /// than the number requested if some tracks are no longer available
+        count: usize
+    },
+
+    /// Info couldn't be found for some of the tracks (they may have been
+    /// deleted or made private), so they're left incomplete.
+    ///
+    /// This event occurs at most once, after all of the batches.
+    TracksUnavailable {
+        ids: Vec<TrackId>
+    },
+
+    /// The server returned an error response and we are waiting before
+    /// retrying the request.
+    /// 
+    /// This event can occur multiple times.
+    PausedAfterServerError {
+        retry: RetryPause
     }
 }
 
@@ -160,7 +414,17 @@ pub enum PlaylistsAudioZestingEvent<'a> {
     },
 
     /// Events related to the downloading of individual tracks.
-    TrackEvent(TracksAudioZestingEvent<'a>, &'a Playlist),
+    ///
+    /// For events about a particular track, `track_index` is the track's
+    /// position in the playlist (starting from 0), which is handy for
+    /// numbering files in playlist order.
+    TrackEvent {
+        event: TracksAudioZestingEvent<'a>,
+        playlist_info: &'a Playlist,
+        track_index: Option<usize>,
+        /// The number of tracks in the playlist
+        track_count: usize
+    },
 
     /// Finished downloading a playlist.
     /// 
@@ -169,3 +433,672 @@ pub enum PlaylistsAudioZestingEvent<'a> {
         playlist_info: &'a Playlist
     }
 }
+
+/// Events that can occur while enriching track metadata from MusicBrainz
+#[cfg(feature = "musicbrainz")]
+#[derive(Debug, Serialize)]
+pub enum EnrichmentEvent<'a> {
+    /// The number of tracks with an ISRC that are going to be looked up.
+    ///
+    /// This event occurs only once.
+    NumTracksToLookUp {
+        num: u64
+    },
+
+    /// A track's ISRC matched a recording, and the track's metadata was
+    /// filled in with the recording's.
+    ///
+    /// This event can occur multiple times.
+    Matched {
+        track_info: &'a Track,
+        recording: Recording
+    },
+
+    /// MusicBrainz doesn't know about a track's ISRC, so the track was left
+    /// as it was.
+    ///
+    /// This event can occur multiple times.
+    NotFound {
+        track_info: &'a Track
+    },
+
+    /// An error occured while looking up a track's ISRC.
+    ///
+    /// This event can occur multiple times.
+    LookupError {
+        track_info: &'a Track,
+        err: Error
+    },
+
+    /// The server returned an error response and we are waiting before
+    /// retrying the request.
+    ///
+    /// This event can occur multiple times.
+    PausedAfterServerError {
+        retry: RetryPause
+    }
+}
+
+/// What should happen to the current track's download after an event has
+/// been handled, as returned by the callback passed to
+/// `Zester::tracks_audio_controlled`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DownloadControl {
+    /// Carry on as normal
+    Continue,
+    /// Stop downloading the current track and move on to the next one
+    ///
+    /// This only has an effect when returned for `StartTrackDownload` and
+    /// `TrackDownloadProgress` events.
+    SkipTrack,
+}
+
+/// Events that can occur while archiving
+///
+/// Paths contained in these events are relative to the root of the archive.
+#[derive(Serialize)]
+pub enum ArchiveEvent<'a> {
+    /// Events related to zesting likes.
+    Likes(LikesZestingEvent),
+
+    /// Events related to zesting playlists.
+    Playlists(PlaylistsZestingEvent<'a>),
+
+    /// Events related to downloading track audio.
+    TrackAudio(TracksAudioZestingEvent<'a>),
+
+    /// Finished writing a metadata file.
+    ///
+    /// This event can occur multiple times.
+    MetadataWritten {
+        path: &'a Path
+    },
+
+    /// The user's spotlight couldn't be fetched, so it wasn't archived (see
+    /// `ArchiveOptions::spotlight`). The rest of the archive carries on.
+    ///
+    /// This event occurs at most once.
+    SpotlightError {
+        err: Error
+    },
+
+    /// A file in the archive that was named by an older version of this crate
+    /// was moved to `renamed`, where it belongs now, rather than being
+    /// downloaded again.
+    ///
+    /// This event can occur multiple times.
+    FileRenamed {
+        path: &'a Path,
+        renamed: &'a Path
+    },
+
+    /// A track's audio or artwork was found in the archive, but it doesn't
+    /// match what the archive's manifest says was written, so it will be
+    /// downloaded again.
+    ///
+    /// This is usually a file left incomplete by an archive that was
+    /// interrupted.
+    ///
+    /// This event can occur multiple times.
+    IncompleteFileFound {
+        track_info: &'a Track,
+        path: &'a Path,
+        mismatch: FileMismatch
+    },
+
+    /// A track would have been saved to the same path as a different track
+    /// (as tracks with the same artist and title are), so it's saved to
+    /// `renamed` instead.
+    ///
+    /// This event can occur multiple times.
+    FilenameCollision {
+        track_info: &'a Track,
+        path: &'a Path,
+        renamed: &'a Path
+    },
+
+    /// The audio for a track was already present in the archive (and matches
+    /// the archive's manifest), so it won't be downloaded again.
+    ///
+    /// This event can occur multiple times.
+    TrackAlreadyArchived {
+        track_info: &'a Track,
+        path: &'a Path
+    },
+
+    /// Finished saving a track's audio to disk.
+    ///
+    /// This event can occur multiple times.
+    TrackSaved {
+        track_info: &'a Track,
+        path: &'a Path
+    },
+
+    /// A track that belongs in more than one place in the archive was linked
+    /// to its audio at `target`, rather than the audio being saved again (see
+    /// `ArchiveOptions::link_shared_tracks`).
+    ///
+    /// This event can occur multiple times.
+    TrackLinked {
+        track_info: &'a Track,
+        path: &'a Path,
+        target: &'a Path
+    },
+
+    /// Progress was made transcoding a track's audio (see
+    /// `ArchiveOptions::transcode`).
+    ///
+    /// `duration` is the length of the track, if it's known.
+    ///
+    /// This event can occur multiple times.
+    TrackTranscodeProgress {
+        track_info: &'a Track,
+        transcoded: Duration,
+        duration: Option<Duration>
+    },
+
+    /// A track's audio couldn't be transcoded (see
+    /// `ArchiveOptions::transcode`), so it wasn't saved.
+    ///
+    /// This event can occur multiple times.
+    TrackTranscodeError {
+        track_info: &'a Track,
+        err: Error
+    },
+
+    /// An error occured while saving a track's audio to disk.
+    ///
+    /// This event can occur multiple times.
+    TrackSaveError {
+        track_info: &'a Track,
+        err: Error
+    },
+
+    /// A track's audio was saved, but its sidecar file (see
+    /// `ArchiveOptions::sidecars`) couldn't be written to `path`.
+    ///
+    /// This event can occur multiple times.
+    SidecarSaveError {
+        track_info: &'a Track,
+        path: &'a Path,
+        err: Error
+    },
+
+    /// A track's artwork was already present in the archive (and matches the
+    /// archive's manifest), so it won't be downloaded again.
+    ///
+    /// This event can occur multiple times.
+    ArtworkAlreadyArchived {
+        track_info: &'a Track,
+        path: &'a Path
+    },
+
+    /// Finished saving a track's artwork to disk.
+    ///
+    /// This event can occur multiple times.
+    ArtworkSaved {
+        track_info: &'a Track,
+        path: &'a Path
+    },
+
+    /// An error occured while downloading or saving a track's artwork.
+    ///
+    /// This event can occur multiple times.
+    ArtworkSaveError {
+        track_info: &'a Track,
+        err: Error
+    }
+}
+
+/// Events that can occur while watching for new likes
+#[derive(Serialize)]
+pub enum WatchLikesEvent<'a> {
+    /// The user liked tracks since the previous poll.
+    ///
+    /// The likes are ordered from most to least recent.
+    ///
+    /// This event can occur multiple times.
+    NewLikes {
+        likes: &'a [LikesCollection]
+    },
+
+    /// Events related to downloading the audio for new likes.
+    TrackAudio(TracksAudioZestingEvent<'a>),
+
+    /// An error occured while polling for new likes.
+    ///
+    /// Watching continues, and the next poll happens as normal.
+    ///
+    /// This event can occur multiple times.
+    PollError {
+        err: Error
+    }
+}
+
+/// An owned, serializable record of an event.
+///
+/// Events borrow the data they describe, so they can't outlive the callback
+/// they're passed to. Converting them into records lets an event stream be
+/// persisted (for example as structured logs) or sent to another thread.
+/// Audio data is left out of records.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventRecord {
+    /// The name of the event's variant, such as "StartTrackDownload"
+    pub event: String,
+    /// The event's fields, or `Value::Null` for events without any
+    pub data: Value
+}
+
+impl EventRecord {
+    /// Record the given event.
+    pub fn new<E: Serialize>(event: &E) -> Result<Self, Error> {
+        let (event, data) = match serde_json::to_value(event)? {
+            // unit variants are serialized as just their name, and the others
+            // as a map from their name to their fields
+            Value::String(name) => (name, Value::Null),
+            Value::Object(map) if map.len() == 1 => map.into_iter().next().unwrap(),
+            other => (String::new(), other)
+        };
+
+        Ok(Self { event, data })
+    }
+}
+
+// "Artist - Title", or as much of that as is known
+pub(crate) struct TrackName<'a>(pub(crate) &'a Track);
+
+impl fmt::Display for TrackName<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let title = self.0.title.as_deref().unwrap_or("Untitled");
+        match track_artist(self.0) {
+            Some(artist) => write!(f, "'{} - {}'", artist, title),
+            None => write!(f, "'{}'", title)
+        }
+    }
+}
+
+struct PlaylistName<'a>(&'a Playlist);
+
+impl fmt::Display for PlaylistName<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "'{}'", self.0.title.as_deref().unwrap_or("Untitled"))
+    }
+}
+
+impl fmt::Display for LikesZestingEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use LikesZestingEvent::*;
+
+        match self {
+            NumLikesInfoToDownload { num } => write!(f, "Downloading info for {} likes", num),
+            MoreLikesInfoDownloaded { count } => write!(f, "Downloaded {} more likes", count),
+            PausedAfterServerError { retry } => write!(f, "{}", retry)
+        }
+    }
+}
+
+impl fmt::Display for UsersZestingEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use UsersZestingEvent::*;
+
+        match self {
+            MoreUsersInfoDownloaded { count } => write!(f, "Downloaded {} more users", count),
+            PausedAfterServerError { retry } => write!(f, "{}", retry)
+        }
+    }
+}
+
+impl fmt::Display for TracksZestingEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use TracksZestingEvent::*;
+
+        match self {
+            NumTracksInfoToDownload { num } => write!(f, "Downloading info for {} tracks", num),
+            MoreTracksInfoDownloaded { count } => write!(f, "Downloaded {} more tracks", count),
+            PausedAfterServerError { retry } => write!(f, "{}", retry)
+        }
+    }
+}
+
+impl fmt::Display for CommentsZestingEvent<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use CommentsZestingEvent::*;
+
+        match self {
+            NumTracksToDownload { num } => write!(f, "Downloading comments for {} tracks", num),
+            StartTrackCommentsDownload { track_info } => {
+                write!(f, "Starting download of comments on {}", TrackName(track_info))
+            },
+            FinishTrackCommentsDownload { track_info, count } => {
+                write!(f, "Downloaded {} comments on {}", count, TrackName(track_info))
+            },
+            TrackCommentsDownloadError { track_info, err } => {
+                write!(f, "Failed to download comments on {}: {}", TrackName(track_info), err)
+            },
+            PausedAfterServerError { retry } => write!(f, "{}", retry)
+        }
+    }
+}
+
+impl<'a> TracksAudioZestingEvent<'a> {
+    /// The track the event is about, if it's about a particular track.
+    pub fn track_info(&self) -> Option<&'a Track> {
+        use TracksAudioZestingEvent::*;
+
+        match self {
+            SkippedByFilter { track_info }
+            | SkippedDrmProtected { track_info }
+            | StartTrackDownload { track_info }
+            | TrackDownloadProgress { track_info, .. }
+            | FinishTrackDownload { track_info, .. }
+            | SnippetOnlyDownloaded { track_info }
+            | TrackSkipped { track_info }
+            | TrackVerificationFailed { track_info, .. }
+            | TrackDownloadError { track_info, .. } => Some(track_info),
+            NumTracksToDownload { .. } | NumBytesToDownload { .. } | PausedAfterServerError { .. } => None
+        }
+    }
+}
+
+impl<'a> PlaylistsAudioZestingEvent<'a> {
+    /// Wrap an event from downloading the given playlist's tracks, working out
+    /// the position of its track in the playlist.
+    pub(crate) fn track_event(event: TracksAudioZestingEvent<'a>, playlist_info: &'a Playlist) -> Self {
+        let tracks = playlist_info.tracks.as_deref().unwrap_or_default();
+        // the events borrow the tracks from the playlist itself, so comparing
+        // addresses tells apart tracks that appear in the playlist twice
+        let track_index = event.track_info().and_then(|track| tracks.iter().position(|t| std::ptr::eq(t, track)));
+
+        PlaylistsAudioZestingEvent::TrackEvent { event, playlist_info, track_index, track_count: tracks.len() }
+    }
+}
+
+impl fmt::Display for TracksAudioZestingEvent<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use TracksAudioZestingEvent::*;
+
+        match self {
+            NumTracksToDownload { num } => write!(f, "Downloading audio for {} tracks", num),
+            NumBytesToDownload { num } => write!(f, "About {} bytes of audio to download", num),
+            SkippedByFilter { track_info } => write!(f, "Skipping {}", TrackName(track_info)),
+            SkippedDrmProtected { track_info } => write!(f, "Skipping {} (DRM protected)", TrackName(track_info)),
+            TrackSkipped { track_info } => write!(f, "Skipped {}", TrackName(track_info)),
+            StartTrackDownload { track_info } => write!(f, "Starting download of {}", TrackName(track_info)),
+            TrackDownloadProgress { track_info, progress } => match progress.track_total_bytes {
+                Some(total) => write!(
+                    f,
+                    "Downloaded {} of {} bytes of {}",
+                    progress.track_bytes,
+                    total,
+                    TrackName(track_info)
+                ),
+                None => write!(f, "Downloaded {} bytes of {}", progress.track_bytes, TrackName(track_info))
+            },
+            FinishTrackDownload { track_info, .. } => write!(f, "Finished download of {}", TrackName(track_info)),
+            SnippetOnlyDownloaded { track_info } => {
+                write!(f, "Only a preview snippet of {} is available; downloaded the snippet", TrackName(track_info))
+            },
+            TrackVerificationFailed { track_info, reason } => {
+                write!(f, "Audio for {} failed verification: {}", TrackName(track_info), reason)
+            },
+            TrackDownloadError { track_info, err } => {
+                write!(f, "Failed to download {}: {}", TrackName(track_info), err)
+            },
+            PausedAfterServerError { retry } => write!(f, "{}", retry)
+        }
+    }
+}
+
+impl fmt::Display for PlaylistsZestingEvent<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use PlaylistsZestingEvent::*;
+
+        match self {
+            NumPlaylistInfoToDownload { num } => write!(f, "Downloading info for {} playlists", num),
+            MorePlaylistMetaInfoDownloaded { count } => write!(f, "Downloaded {} more playlists", count),
+            FinishPlaylistMetaInfoDownloading => write!(f, "Finished downloading the list of playlists"),
+            PlaylistMetaNotPresent => write!(f, "Skipping a playlist with missing info"),
+            StartPlaylistInfoDownload { playlist_meta } => {
+                write!(f, "Starting download of playlist {}", PlaylistName(playlist_meta))
+            },
+            FinishPlaylistInfoDownload { playlist_meta } => {
+                write!(f, "Finished download of playlist {}", PlaylistName(playlist_meta))
+            },
+            PlaylistInfoDownloadError { playlist_meta, err } => {
+                write!(f, "Failed to download playlist {}: {}", PlaylistName(playlist_meta), err)
+            },
+            TracksInfoCompletion(e, playlist_meta) => write!(f, "[{}] {}", PlaylistName(playlist_meta), e),
+            PlaylistInfoCompletionError { playlist_meta, err } => {
+                write!(f, "Failed to complete the track info for playlist {}: {}", PlaylistName(playlist_meta), err)
+            },
+            PausedAfterServerError { retry } => write!(f, "{}", retry)
+        }
+    }
+}
+
+impl fmt::Display for TracksInfoCompletionEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use TracksInfoCompletionEvent::*;
+
+        match self {
+            NumBatchesToDownload { num, tracks_num } => {
+                write!(f, "Completing info for {} tracks in {} batches", tracks_num, num)
+            },
+            StartBatchDownload { index, num_tracks } => {
+                write!(f, "Starting download of batch {} ({} tracks)", index + 1, num_tracks)
+            },
+            FetchedRemainingTrackIds { num } => write!(f, "Found {} more tracks in the playlist", num),
+            FinishBatchDownload { index, count } => write!(f, "Downloaded info for {} tracks in batch {}", count, index + 1),
+            TracksUnavailable { ids } => write!(f, "Info for {} tracks is unavailable", ids.len()),
+            PausedAfterServerError { retry } => write!(f, "{}", retry)
+        }
+    }
+}
+
+#[cfg(feature = "musicbrainz")]
+impl fmt::Display for EnrichmentEvent<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use EnrichmentEvent::*;
+
+        match self {
+            NumTracksToLookUp { num } => write!(f, "Looking up {} tracks on MusicBrainz", num),
+            Matched { track_info, recording } => {
+                write!(f, "Matched {} to MusicBrainz recording {}", TrackName(track_info), recording.id)
+            },
+            NotFound { track_info } => write!(f, "{} isn't on MusicBrainz", TrackName(track_info)),
+            LookupError { track_info, err } => {
+                write!(f, "Failed to look up {} on MusicBrainz: {}", TrackName(track_info), err)
+            },
+            PausedAfterServerError { retry } => write!(f, "{}", retry)
+        }
+    }
+}
+
+impl fmt::Display for PlaylistsAudioZestingEvent<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use PlaylistsAudioZestingEvent::*;
+
+        match self {
+            NumItemsToDownload { playlists_num, tracks_num } => {
+                write!(f, "Downloading audio for {} tracks in {} playlists", tracks_num, playlists_num)
+            },
+            StartPlaylistDownload { playlist_info } => {
+                write!(f, "Starting download of playlist {}", PlaylistName(playlist_info))
+            },
+            TrackEvent { event, playlist_info, track_index: Some(i), track_count } => {
+                write!(f, "[{} {}/{}] {}", PlaylistName(playlist_info), i + 1, track_count, event)
+            },
+            TrackEvent { event, playlist_info, .. } => write!(f, "[{}] {}", PlaylistName(playlist_info), event),
+            FinishPlaylistDownload { playlist_info } => {
+                write!(f, "Finished download of playlist {}", PlaylistName(playlist_info))
+            }
+        }
+    }
+}
+
+impl fmt::Display for ArchiveEvent<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use ArchiveEvent::*;
+
+        match self {
+            Likes(e) => write!(f, "{}", e),
+            Playlists(e) => write!(f, "{}", e),
+            TrackAudio(e) => write!(f, "{}", e),
+            MetadataWritten { path } => write!(f, "Wrote {}", path.display()),
+            SpotlightError { err } => write!(f, "Failed to archive the spotlight: {}", err),
+            FileRenamed { path, renamed } => write!(f, "Moved {} to {}", path.display(), renamed.display()),
+            IncompleteFileFound { track_info, path, mismatch } => write!(
+                f,
+                "{} for {} is incomplete ({}), so it will be downloaded again",
+                path.display(),
+                TrackName(track_info),
+                mismatch
+            ),
+            FilenameCollision { track_info, path, renamed } => write!(
+                f,
+                "{} has the same path as another track ({}), so it will be saved to {}",
+                TrackName(track_info),
+                path.display(),
+                renamed.display()
+            ),
+            TrackAlreadyArchived { track_info, path } => {
+                write!(f, "{} is already archived at {}", TrackName(track_info), path.display())
+            },
+            TrackSaved { track_info, path } => write!(f, "Saved {} to {}", TrackName(track_info), path.display()),
+            TrackLinked { track_info, path, target } => {
+                write!(f, "Linked {} at {} to {}", TrackName(track_info), path.display(), target.display())
+            },
+            TrackTranscodeProgress { track_info, transcoded, duration } => {
+                write!(f, "Transcoding {}: {}s", TrackName(track_info), transcoded.as_secs())?;
+                if let Some(duration) = duration {
+                    write!(f, " of {}s", duration.as_secs())?;
+                }
+                Ok(())
+            },
+            TrackTranscodeError { track_info, err } => {
+                write!(f, "Failed to transcode {}: {}", TrackName(track_info), err)
+            },
+            TrackSaveError { track_info, err } => write!(f, "Failed to save {}: {}", TrackName(track_info), err),
+            SidecarSaveError { track_info, path, err } => {
+                write!(f, "Failed to save the sidecar for {} to {}: {}", TrackName(track_info), path.display(), err)
+            },
+            ArtworkAlreadyArchived { track_info, path } => {
+                write!(f, "Artwork for {} is already archived at {}", TrackName(track_info), path.display())
+            },
+            ArtworkSaved { track_info, path } => {
+                write!(f, "Saved artwork for {} to {}", TrackName(track_info), path.display())
+            },
+            ArtworkSaveError { track_info, err } => {
+                write!(f, "Failed to save artwork for {}: {}", TrackName(track_info), err)
+            }
+        }
+    }
+}
+
+impl fmt::Display for WatchLikesEvent<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use WatchLikesEvent::*;
+
+        match self {
+            NewLikes { likes } => write!(f, "Found {} new likes", likes.len()),
+            TrackAudio(e) => write!(f, "{}", e),
+            PollError { err } => write!(f, "Failed to poll for new likes: {}", err)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    fn track() -> Track {
+        serde_json::from_value(json!({ "id": 7, "title": "Song", "user": { "username": "Artist" } })).unwrap()
+    }
+
+    #[test]
+    fn event_records() {
+        let track = track();
+
+        let event = LikesZestingEvent::MoreLikesInfoDownloaded { count: 5 };
+        let record = EventRecord::new(&event).unwrap();
+        assert_eq!((record.event.as_str(), &record.data), ("MoreLikesInfoDownloaded", &json!({ "count": 5 })));
+        assert_eq!(event.to_string(), "Downloaded 5 more likes");
+
+        let event = PlaylistsZestingEvent::FinishPlaylistMetaInfoDownloading;
+        let record = EventRecord::new(&event).unwrap();
+        assert_eq!((record.event.as_str(), &record.data), ("FinishPlaylistMetaInfoDownloading", &Value::Null));
+        assert_eq!(event.to_string(), "Finished downloading the list of playlists");
+
+        // the audio is left out
+        let event = TracksAudioZestingEvent::FinishTrackDownload {
+            track_info: &track,
+            file_extension: Some("mp3"),
+            track_data: Box::new(&b"audio"[..])
+        };
+        let record = EventRecord::new(&event).unwrap();
+        assert_eq!(record.event, "FinishTrackDownload");
+        assert_eq!(record.data["track_info"]["id"], 7);
+        assert_eq!(record.data["file_extension"], "mp3");
+        assert!(record.data.get("track_data").is_none());
+        assert_eq!(event.to_string(), "Finished download of 'Artist - Song'");
+
+        // wrapped events keep the inner event's name
+        let event = ArchiveEvent::TrackAudio(TracksAudioZestingEvent::TrackSkipped { track_info: &track });
+        let record = EventRecord::new(&event).unwrap();
+        assert_eq!(record.event, "TrackAudio");
+        assert_eq!(record.data["TrackSkipped"]["track_info"]["title"], "Song");
+        assert_eq!(event.to_string(), "Skipped 'Artist - Song'");
+
+        // records can be written out and read back
+        let read_back: EventRecord = serde_json::from_str(&serde_json::to_string(&record).unwrap()).unwrap();
+        assert_eq!((read_back.event, read_back.data), (record.event, record.data));
+    }
+
+    #[test]
+    fn error_and_retry_events() {
+        let track = track();
+
+        let event = TracksAudioZestingEvent::TrackDownloadError {
+            track_info: &track,
+            err: Error::NotFound { url: "https://api.soundcloud.com/tracks/7".into(), body: "gone".into(), context: None }
+        };
+        assert_eq!(
+            event.to_string(),
+            "Failed to download 'Artist - Song': https://api.soundcloud.com/tracks/7 was not found"
+        );
+        let record = EventRecord::new(&event).unwrap();
+        assert_eq!(record.event, "TrackDownloadError");
+        assert_eq!(
+            record.data["err"],
+            json!({ "NotFound": { "url": "https://api.soundcloud.com/tracks/7", "body": "gone" } })
+        );
+
+        // the underlying IO error is only part of the message
+        let event = TracksAudioZestingEvent::TrackDownloadError {
+            track_info: &track,
+            err: Error::IoError(std::io::Error::new(std::io::ErrorKind::PermissionDenied, "read-only"))
+        };
+        assert_eq!(event.to_string(), "Failed to download 'Artist - Song': IO error: read-only");
+        assert_eq!(EventRecord::new(&event).unwrap().data["err"], "IoError");
+
+        let mut retry = RetryPause {
+            pause: Duration::from_secs(2),
+            attempt: 1,
+            max_consecutive_retries: Some(5),
+            total_retries: 3,
+            max_total_retries: None
+        };
+        let event = LikesZestingEvent::PausedAfterServerError { retry: retry.clone() };
+        assert_eq!(event.to_string(), "Server error, retrying in 2s (attempt 1 of 5)");
+        let record = EventRecord::new(&event).unwrap();
+        assert_eq!(record.event, "PausedAfterServerError");
+        assert_eq!(record.data["retry"]["attempt"], 1);
+        assert_eq!(record.data["retry"]["max_consecutive_retries"], 5);
+        assert_eq!(record.data["retry"]["max_total_retries"], Value::Null);
+
+        retry.max_consecutive_retries = None;
+        let event = TracksAudioZestingEvent::PausedAfterServerError { retry };
+        assert_eq!(event.to_string(), "Server error, retrying in 2s (attempt 1)");
+    }
+}
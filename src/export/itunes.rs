@@ -0,0 +1,374 @@
+use crate::{load_json, Error};
+use crate::api::{Likes, Playlists};
+use crate::api::common::Track;
+use crate::api::ids::TrackId;
+use crate::archive::{archived_tracks, playlist_stems, transcoded_extension, ArchiveOptions, TrackPaths, LIKES_DIR_NAME};
+use crate::filename::track_artist;
+use chrono::{Datelike, SecondsFormat};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// The name of the playlist that liked tracks are put in
+const LIKES_PLAYLIST_NAME: &str = "SoundCloud Likes";
+
+/// An iTunes (or Apple Music) Library XML file listing downloaded tracks and
+/// the playlists they belong to.
+///
+/// Importing the file into iTunes, Apple Music, or another player that
+/// understands the format (such as Rekordbox or Traktor) adds the tracks to
+/// the player's library with their playlists intact.
+#[derive(Debug, Default)]
+pub struct ItunesLibrary {
+    tracks: Vec<(Track, PathBuf)>,
+    playlists: Vec<(String, Vec<TrackId>)>,
+    ids: HashSet<TrackId>,
+    /// The album title, track number, and track count of tracks on albums
+    albums: HashMap<TrackId, (String, usize, usize)>
+}
+
+impl ItunesLibrary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build a library from the archive at the given path (see
+    /// `Zester::archive_to`).
+    ///
+    /// Liked tracks are put in a "SoundCloud Likes" playlist, and each archived
+    /// playlist becomes a playlist of its own (albums are added with
+    /// `add_album`). Tracks whose audio wasn't downloaded are left out.
+    ///
+    /// The archive is expected to have been made with the default options;
+    /// use `from_archive_with_options` if it wasn't.
+    pub fn from_archive<P: AsRef<Path>>(root: P) -> Result<Self, Error> {
+        Self::from_archive_with_options(root, &ArchiveOptions::default())
+    }
+
+    /// Build a library from the archive at the given path, which was made with
+    /// the given options (the layout of its tracks, and the format they were
+    /// transcoded into, decide where their audio is).
+    pub fn from_archive_with_options<P: AsRef<Path>>(root: P, options: &ArchiveOptions) -> Result<Self, Error> {
+        // the library has to refer to files by their absolute paths
+        let root = fs::canonicalize(root)?;
+        let mut library = Self::new();
+
+        let playlists_path = root.join("playlists.json");
+        let playlists: Option<Playlists> = match playlists_path.exists() {
+            true => Some(load_json(playlists_path)?),
+            false => None
+        };
+        let likes_path = root.join("likes.json");
+        let likes: Option<Likes> = match likes_path.exists() {
+            true => Some(load_json(likes_path)?),
+            false => None
+        };
+
+        // the paths are worked out the same way the archiver did, so that
+        // tracks that were renamed to avoid collisions are found
+        let tracks = archived_tracks(likes.as_ref(), playlists.as_ref());
+        let track_paths = TrackPaths::new(
+            &options.layout,
+            transcoded_extension(options),
+            likes.as_ref(),
+            playlists.as_ref(),
+            &tracks,
+            |_, _, _| {}
+        );
+
+        if let Some(likes) = &likes {
+            let tracks: Vec<&Track> = likes.collections.iter().map(|c| &c.track).collect();
+            library.add_archived_tracks(&root, tracks.iter().copied(), |t| track_paths.path_for(t, LIKES_DIR_NAME));
+            library.add_playlist(LIKES_PLAYLIST_NAME, tracks);
+        }
+
+        if let Some(playlists) = &playlists {
+            for (playlist, stem) in playlists.playlists.iter().zip(playlist_stems(playlists)) {
+                let tracks = playlist.tracks.iter().flatten();
+                library.add_archived_tracks(&root, tracks.clone(), |t| track_paths.path_for(t, &stem));
+                if playlist.is_album() {
+                    library.add_album(&stem, tracks);
+                } else {
+                    library.add_playlist(&stem, tracks);
+                }
+            }
+        }
+
+        Ok(library)
+    }
+
+    /// Add a track whose audio is stored at the given path.
+    ///
+    /// The path should be absolute. Tracks without an id, or that were already
+    /// added, are ignored.
+    pub fn add_track(&mut self, track: &Track, path: PathBuf) {
+        if let Some(id) = track.id {
+            if self.ids.insert(id) {
+                self.tracks.push((track.clone(), path));
+            }
+        }
+    }
+
+    /// Add a playlist with the given name containing the given tracks, in
+    /// order.
+    ///
+    /// Tracks that haven't been added to the library are left out.
+    pub fn add_playlist<'a, I: IntoIterator<Item = &'a Track>>(&mut self, name: &str, tracks: I) {
+        let ids = tracks
+            .into_iter()
+            .filter_map(|t| t.id)
+            .filter(|id| self.ids.contains(id))
+            .collect();
+
+        self.playlists.push((name.into(), ids));
+    }
+
+    /// Add an album with the given name containing the given tracks, in
+    /// order.
+    ///
+    /// This adds a playlist like `add_playlist`, and also sets the album and
+    /// track number of each track to match. Tracks keep the first album
+    /// they're added to.
+    pub fn add_album<'a, I: IntoIterator<Item = &'a Track>>(&mut self, name: &str, tracks: I) {
+        let tracks: Vec<&Track> = tracks.into_iter().collect();
+        for (i, id) in tracks.iter().enumerate().filter_map(|(i, t)| Some((i, t.id?))) {
+            self.albums.entry(id).or_insert_with(|| (name.into(), i + 1, tracks.len()));
+        }
+
+        self.add_playlist(name, tracks);
+    }
+
+    /// Write the library to a file at the given path.
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    /// Write the library as XML to the given writer.
+    pub fn write_to<W: Write>(&self, mut w: W) -> Result<(), Error> {
+        writeln!(w, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(w, r#"<!DOCTYPE plist PUBLIC "-//Apple Computer//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">"#)?;
+        writeln!(w, r#"<plist version="1.0">"#)?;
+        writeln!(w, "<dict>")?;
+        writeln!(w, "\t<key>Major Version</key><integer>1</integer>")?;
+        writeln!(w, "\t<key>Minor Version</key><integer>1</integer>")?;
+        writeln!(w, "\t<key>Application Version</key><string>orange-zest {}</string>", env!("CARGO_PKG_VERSION"))?;
+        writeln!(w, "\t<key>Tracks</key>")?;
+        writeln!(w, "\t<dict>")?;
+
+        for (track, path) in self.tracks.iter() {
+            let id = track.id.unwrap_or_default();
+            writeln!(w, "\t\t<key>{}</key>", id)?;
+            writeln!(w, "\t\t<dict>")?;
+            writeln!(w, "\t\t\t<key>Track ID</key><integer>{}</integer>", id)?;
+            write_string(&mut w, "Name", track.title.as_deref())?;
+            write_string(&mut w, "Artist", track_artist(track))?;
+            let album = self.albums.get(&id);
+            let album_title = album
+                .map(|(title, _, _)| title.as_str())
+                .or_else(|| track.publisher_metadata.as_ref().and_then(|m| m.album_title.as_deref()));
+            write_string(&mut w, "Album", album_title)?;
+            if let Some((_, number, count)) = album {
+                writeln!(w, "\t\t\t<key>Track Number</key><integer>{}</integer>", number)?;
+                writeln!(w, "\t\t\t<key>Track Count</key><integer>{}</integer>", count)?;
+                // albums on SoundCloud are a single disc
+                writeln!(w, "\t\t\t<key>Disc Number</key><integer>1</integer>")?;
+                writeln!(w, "\t\t\t<key>Disc Count</key><integer>1</integer>")?;
+            }
+            write_string(&mut w, "Genre", track.genre.as_deref().filter(|g| !g.is_empty()))?;
+            write_string(&mut w, "Kind", kind(path))?;
+            if let Some(duration) = track.duration {
+                writeln!(w, "\t\t\t<key>Total Time</key><integer>{}</integer>", duration)?;
+            }
+            if let Some(date) = track.release_date.or(track.created_at) {
+                writeln!(w, "\t\t\t<key>Year</key><integer>{}</integer>", date.year())?;
+            }
+            if let Some(created_at) = track.created_at {
+                let date = created_at.to_rfc3339_opts(SecondsFormat::Secs, true);
+                writeln!(w, "\t\t\t<key>Date Added</key><date>{}</date>", date)?;
+            }
+            write_string(&mut w, "Location", Some(&file_url(path)))?;
+            writeln!(w, "\t\t</dict>")?;
+        }
+
+        writeln!(w, "\t</dict>")?;
+        writeln!(w, "\t<key>Playlists</key>")?;
+        writeln!(w, "\t<array>")?;
+
+        for (index, (name, ids)) in self.playlists.iter().enumerate() {
+            writeln!(w, "\t\t<dict>")?;
+            writeln!(w, "\t\t\t<key>Name</key><string>{}</string>", escape(name))?;
+            writeln!(w, "\t\t\t<key>Playlist ID</key><integer>{}</integer>", index + 1)?;
+            writeln!(w, "\t\t\t<key>All Items</key><true/>")?;
+            writeln!(w, "\t\t\t<key>Playlist Items</key>")?;
+            writeln!(w, "\t\t\t<array>")?;
+            for id in ids {
+                writeln!(w, "\t\t\t\t<dict><key>Track ID</key><integer>{}</integer></dict>", id)?;
+            }
+            writeln!(w, "\t\t\t</array>")?;
+            writeln!(w, "\t\t</dict>")?;
+        }
+
+        writeln!(w, "\t</array>")?;
+        writeln!(w, "</dict>")?;
+        writeln!(w, "</plist>")?;
+
+        Ok(())
+    }
+
+    // Add the tracks whose audio is present in the archive, at the paths
+    // (relative to its root) given by `track_path`
+    fn add_archived_tracks<'a, I, F>(&mut self, root: &Path, tracks: I, track_path: F) where
+        I: Iterator<Item = &'a Track>,
+        F: Fn(&Track) -> Option<PathBuf>
+    {
+        for track in tracks {
+            if let Some(path) = track_path(track).map(|p| root.join(p)).filter(|p| p.exists()) {
+                self.add_track(track, path);
+            }
+        }
+    }
+}
+
+fn write_string<W: Write>(w: &mut W, key: &str, value: Option<&str>) -> Result<(), Error> {
+    if let Some(value) = value {
+        writeln!(w, "\t\t\t<key>{}</key><string>{}</string>", key, escape(value))?;
+    }
+
+    Ok(())
+}
+
+/// The description of the format of an audio file that iTunes shows
+fn kind(path: &Path) -> Option<&'static str> {
+    match path.extension()?.to_str()? {
+        "mp3" => Some("MPEG audio file"),
+        "m4a" | "aac" => Some("AAC audio file"),
+        "wav" => Some("WAV audio file"),
+        _ => None
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// The `file://` URL of the file at the given (absolute) path
+fn file_url(path: &Path) -> String {
+    // `fs::canonicalize` gives verbatim paths (like `\\?\C:\Music`) on
+    // Windows, which have to be turned back into ordinary ones for the URL
+    let path = path.to_string_lossy();
+    let path = match (path.strip_prefix(r"\\?\UNC\"), path.strip_prefix(r"\\?\")) {
+        (Some(unc), _) => format!(r"\\{}", unc),
+        (None, Some(local)) => local.to_string(),
+        (None, None) => path.into_owned()
+    };
+    let path = path.replace('\\', "/");
+
+    // network paths (`//server/share/...`) become `file://server/share/...`
+    let (start, path) = match path.strip_prefix("//") {
+        Some(unc) => ("file://", unc),
+        None if path.starts_with('/') => ("file://", &path[..]),
+        None => ("file:///", &path[..])
+    };
+    let mut url = String::from(start);
+
+    for b in path.bytes() {
+        if b.is_ascii_alphanumeric() || b"/-_.~:".contains(&b) {
+            url.push(b as char);
+        } else {
+            url.push_str(&format!("%{:02X}", b));
+        }
+    }
+
+    url
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::TempDir;
+    use serde_json::json;
+
+    #[test]
+    fn library_xml() -> Result<(), Error> {
+        let track = |id: u64, title: &str| -> Track {
+            serde_json::from_value(json!({ "id": id, "title": title, "duration": 1000 })).unwrap()
+        };
+        let (one, two) = (track(1, "Rock & Roll"), track(2, "Missing"));
+
+        let mut library = ItunesLibrary::new();
+        library.add_track(&one, PathBuf::from("/music/tracks/Rock & Roll.mp3"));
+        library.add_playlist("Mix", [&one, &two]);
+
+        let mut xml = vec![];
+        library.write_to(&mut xml)?;
+        let xml = String::from_utf8(xml).unwrap();
+
+        assert!(xml.contains("<key>Name</key><string>Rock &amp; Roll</string>"));
+        assert!(xml.contains("<key>Kind</key><string>MPEG audio file</string>"));
+        assert!(xml.contains("<string>file:///music/tracks/Rock%20%26%20Roll.mp3</string>"));
+        assert!(xml.contains("<key>Track ID</key><integer>1</integer></dict>"));
+        assert!(!xml.contains("<integer>2</integer>"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn file_urls() {
+        assert_eq!(file_url(Path::new("/music/a b.mp3")), "file:///music/a%20b.mp3");
+        assert_eq!(file_url(Path::new(r"C:\Music\a.mp3")), "file:///C:/Music/a.mp3");
+        assert_eq!(file_url(Path::new(r"\\?\C:\Music\a.mp3")), "file:///C:/Music/a.mp3");
+        assert_eq!(file_url(Path::new(r"\\?\UNC\server\share\a.mp3")), "file://server/share/a.mp3");
+        assert_eq!(file_url(Path::new(r"\\server\share\a.mp3")), "file://server/share/a.mp3");
+    }
+
+    #[test]
+    fn album_track_numbers() -> Result<(), Error> {
+        let track: Track = serde_json::from_value(json!({ "id": 7, "title": "Second" })).unwrap();
+        let intro: Track = serde_json::from_value(json!({ "id": 6, "title": "Intro" })).unwrap();
+
+        let mut library = ItunesLibrary::new();
+        library.add_track(&track, PathBuf::from("/music/tracks/Second.mp3"));
+        library.add_album("An EP", [&intro, &track]);
+
+        let mut xml = vec![];
+        library.write_to(&mut xml)?;
+        let xml = String::from_utf8(xml).unwrap();
+
+        assert!(xml.contains("<key>Album</key><string>An EP</string>"));
+        assert!(xml.contains("<key>Track Number</key><integer>2</integer>"));
+        assert!(xml.contains("<key>Track Count</key><integer>2</integer>"));
+        assert!(xml.contains("<key>Disc Number</key><integer>1</integer>"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn renamed_archive_tracks() -> Result<(), Error> {
+        let root = TempDir::new("itunes");
+        fs::create_dir_all(root.join("tracks"))?;
+        let likes = json!({ "collections": [
+            { "track": { "id": 2, "title": "Untitled" } },
+            { "track": { "id": 1, "title": "Untitled" } }
+        ] });
+        fs::write(root.join("likes.json"), likes.to_string())?;
+        fs::write(root.join("tracks/Untitled.mp3"), b"one")?;
+        fs::write(root.join("tracks/Untitled (2).mp3"), b"two")?;
+
+        // the track that was renamed to avoid a collision is found under its
+        // new name
+        let library = ItunesLibrary::from_archive(&root)?;
+        let paths: Vec<(Option<TrackId>, PathBuf)> = library.tracks.iter().map(|(t, p)| (t.id, p.clone())).collect();
+        let root = fs::canonicalize(&root)?;
+        assert_eq!(paths, vec![
+            (Some(TrackId(2)), root.join("tracks/Untitled (2).mp3")),
+            (Some(TrackId(1)), root.join("tracks/Untitled.mp3")),
+        ]);
+
+        Ok(())
+    }
+}
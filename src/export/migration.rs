@@ -0,0 +1,128 @@
+use crate::Error;
+use crate::api::Playlists;
+use crate::api::common::Track;
+use crate::api::playlists::Playlist;
+use crate::archive::playlist_stems;
+use crate::filename::track_artist;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// The header row of the CSV files, matching the exports of Spotify playlist
+/// tools that most migration services accept
+const HEADER: [&str; 5] = ["Track Name", "Artist Name(s)", "Album Name", "ISRC", "Duration (ms)"];
+
+/// Write a CSV listing the given tracks to `writer`.
+///
+/// Each track gets a row with its title, artist, album, ISRC, and duration.
+/// The artist, album, and ISRC come from the track's publisher metadata when
+/// it has any (the artist falls back to the uploader's username), and are
+/// left blank otherwise. Migration services match on the ISRC when it's
+/// present, which makes it by far the most reliable column.
+pub fn write_tracks_csv<'a, I, W>(tracks: I, writer: W) -> Result<(), Error> where
+    I: IntoIterator<Item = &'a Track>,
+    W: Write
+{
+    let mut csv = csv::Writer::from_writer(writer);
+    csv.write_record(HEADER)?;
+
+    for track in tracks {
+        let metadata = track.publisher_metadata.as_ref();
+        let album = metadata
+            .and_then(|m| m.album_title.as_deref().or(m.release_title.as_deref()))
+            .unwrap_or_default();
+        let isrc = metadata.and_then(|m| m.isrc.as_deref()).unwrap_or_default();
+        let duration = track.duration.map(|d| d.to_string()).unwrap_or_default();
+
+        csv.write_record([
+            track.title.as_deref().unwrap_or_default(),
+            track_artist(track).unwrap_or_default(),
+            album,
+            isrc,
+            &duration
+        ])?;
+    }
+
+    csv.flush()?;
+    Ok(())
+}
+
+/// Write a CSV listing the tracks in the given playlist to `writer` (see
+/// `write_tracks_csv`).
+pub fn write_playlist_csv<W: Write>(playlist: &Playlist, writer: W) -> Result<(), Error> {
+    write_tracks_csv(playlist.tracks.iter().flatten(), writer)
+}
+
+/// Write a CSV for each of the given playlists into the directory at the
+/// given path, creating it if needed.
+///
+/// The files are named after the playlists (like "Playlist Title.csv"), which
+/// is what most migration services use as the name of the imported playlist.
+/// Playlists with the same title get their id added to the name (like
+/// "Playlist Title (123).csv") rather than overwriting each other. The paths
+/// of the written files are returned, in the same order as the playlists.
+pub fn export_playlists<P: AsRef<Path>>(playlists: &Playlists, dir: P) -> Result<Vec<PathBuf>, Error> {
+    let dir = dir.as_ref();
+    fs::create_dir_all(dir)?;
+
+    let mut paths = vec![];
+    for (playlist, name) in playlists.playlists.iter().zip(playlist_stems(playlists)) {
+        let path = dir.join(format!("{}.csv", name));
+        write_playlist_csv(playlist, File::create(&path)?)?;
+        paths.push(path);
+    }
+
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::TempDir;
+    use serde_json::json;
+
+    #[test]
+    fn playlist_csv() -> Result<(), Error> {
+        let playlist: Playlist = serde_json::from_value(json!({
+            "title": "Mix",
+            "tracks": [
+                {
+                    "title": "Song, Part 1",
+                    "duration": 215000,
+                    "user": { "username": "uploader" },
+                    "publisher_metadata": { "artist": "Artist", "album_title": "Album", "isrc": "USABC2000001" }
+                },
+                { "title": "Untagged", "user": { "username": "someone" } }
+            ]
+        }))?;
+
+        let mut csv = vec![];
+        write_playlist_csv(&playlist, &mut csv)?;
+
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "Track Name,Artist Name(s),Album Name,ISRC,Duration (ms)\n\
+             \"Song, Part 1\",Artist,Album,USABC2000001,215000\n\
+             Untagged,someone,,,\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn playlists_with_the_same_title() -> Result<(), Error> {
+        let playlists: Playlists = serde_json::from_value(json!({ "playlists": [
+            { "id": 1, "title": "Mix" },
+            { "id": 2, "title": "mix" },
+            { "title": "Mix" }
+        ] }))?;
+        let dir = TempDir::new("migration");
+
+        let paths = export_playlists(&playlists, &dir)?;
+        let names: Vec<_> = paths.iter().map(|p| p.file_name().unwrap().to_string_lossy().into_owned()).collect();
+        assert_eq!(names, ["Mix.csv", "mix (2).csv", "Mix (3).csv"]);
+        assert!(paths.iter().all(|p| p.exists()));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,17 @@
+// Exporters that package zested data up in formats other than a plain
+// directory of files
+//
+// Each exporter lives behind a cargo feature of the same name so that you only
+// pay for the dependencies of the formats you use.
+
+#[cfg(feature = "zip")]
+pub mod zip;
+
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+
+#[cfg(feature = "migration")]
+pub mod migration;
+
+#[cfg(feature = "itunes")]
+pub mod itunes;
@@ -0,0 +1,361 @@
+use crate::Error;
+use crate::api::{Likes, Playlists};
+use crate::api::common::{Track, User};
+use crate::api::ids::{TrackId, UserId};
+use crate::api::playlists::Playlist;
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, Transaction};
+use std::path::Path;
+
+const SCHEMA: &str = "
+PRAGMA foreign_keys = ON;
+
+CREATE TABLE IF NOT EXISTS users (
+    id INTEGER PRIMARY KEY,
+    username TEXT,
+    full_name TEXT,
+    permalink_url TEXT,
+    avatar_url TEXT,
+    city TEXT,
+    country_code TEXT,
+    verified INTEGER
+);
+
+CREATE TABLE IF NOT EXISTS tracks (
+    id INTEGER PRIMARY KEY,
+    user_id INTEGER REFERENCES users (id),
+    title TEXT,
+    genre TEXT,
+    tag_list TEXT,
+    duration INTEGER,
+    created_at TEXT,
+    permalink_url TEXT,
+    artwork_url TEXT,
+    playback_count INTEGER,
+    likes_count INTEGER,
+    json TEXT
+);
+CREATE INDEX IF NOT EXISTS tracks_user_id ON tracks (user_id);
+CREATE INDEX IF NOT EXISTS tracks_genre ON tracks (genre);
+
+CREATE TABLE IF NOT EXISTS playlists (
+    id INTEGER PRIMARY KEY,
+    user_id INTEGER REFERENCES users (id),
+    title TEXT,
+    created_at TEXT,
+    permalink_url TEXT,
+    json TEXT
+);
+CREATE INDEX IF NOT EXISTS playlists_user_id ON playlists (user_id);
+
+CREATE TABLE IF NOT EXISTS playlist_tracks (
+    playlist_id INTEGER NOT NULL REFERENCES playlists (id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    track_id INTEGER NOT NULL REFERENCES tracks (id),
+    PRIMARY KEY (playlist_id, position)
+);
+CREATE INDEX IF NOT EXISTS playlist_tracks_track_id ON playlist_tracks (track_id);
+
+CREATE TABLE IF NOT EXISTS likes (
+    track_id INTEGER PRIMARY KEY REFERENCES tracks (id),
+    liked_at TEXT
+);
+CREATE INDEX IF NOT EXISTS likes_liked_at ON likes (liked_at);
+";
+
+/// Writes zested data into a normalized SQLite database.
+///
+/// Users, tracks, and playlists each get their own table, with the
+/// relationships between them stored as foreign keys. Writing the same item
+/// more than once updates the existing row, so an export can be refreshed by
+/// writing newly zested data into the same database. The full JSON for tracks
+/// and playlists is kept in a `json` column for anything the columns don't
+/// cover.
+pub struct SqliteExporter {
+    conn: Connection
+}
+
+impl SqliteExporter {
+    /// Open (or create) the database at the given path.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    /// Export into an already open connection.
+    pub fn from_connection(conn: Connection) -> Result<Self, Error> {
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self { conn })
+    }
+
+    /// Consume the exporter, returning the underlying connection.
+    pub fn into_connection(self) -> Connection {
+        self.conn
+    }
+
+    /// Write the given likes, along with the liked tracks and their uploaders.
+    pub fn write_likes(&mut self, likes: &Likes) -> Result<(), Error> {
+        let tx = self.conn.transaction()?;
+        for like in &likes.collections {
+            let track_id = match write_track(&tx, &like.track)? {
+                Some(id) => id,
+                None => continue
+            };
+
+            tx.execute(
+                "INSERT INTO likes (track_id, liked_at) VALUES (?1, ?2)
+                    ON CONFLICT (track_id) DO UPDATE SET liked_at = excluded.liked_at",
+                params![track_id, date(like.created_at)]
+            )?;
+        }
+
+        Ok(tx.commit()?)
+    }
+
+    /// Write the given playlists, along with their tracks and the users who
+    /// made them.
+    pub fn write_playlists(&mut self, playlists: &Playlists) -> Result<(), Error> {
+        let tx = self.conn.transaction()?;
+        for playlist in &playlists.playlists {
+            write_playlist(&tx, playlist)?;
+        }
+
+        Ok(tx.commit()?)
+    }
+
+    /// Write the given tracks and their uploaders.
+    pub fn write_tracks<'a, I: IntoIterator<Item = &'a Track>>(&mut self, tracks: I) -> Result<(), Error> {
+        let tx = self.conn.transaction()?;
+        for track in tracks {
+            write_track(&tx, track)?;
+        }
+
+        Ok(tx.commit()?)
+    }
+
+    /// Write the given users.
+    pub fn write_users<'a, I: IntoIterator<Item = &'a User>>(&mut self, users: I) -> Result<(), Error> {
+        let tx = self.conn.transaction()?;
+        for user in users {
+            write_user(&tx, user)?;
+        }
+
+        Ok(tx.commit()?)
+    }
+}
+
+fn date(date: Option<DateTime<Utc>>) -> Option<String> {
+    date.map(|d| d.to_rfc3339())
+}
+
+// Make sure a row exists for the user with the given id so that it can be
+// referenced before (or without) the full information being known
+fn ensure_user(tx: &Transaction, id: UserId) -> Result<(), Error> {
+    tx.execute("INSERT OR IGNORE INTO users (id) VALUES (?1)", params![id])?;
+    Ok(())
+}
+
+fn write_user(tx: &Transaction, user: &User) -> Result<Option<UserId>, Error> {
+    let id = match user.id {
+        Some(id) => id,
+        None => return Ok(None)
+    };
+
+    tx.execute(
+        "INSERT INTO users (id, username, full_name, permalink_url, avatar_url, city, country_code, verified)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+            ON CONFLICT (id) DO UPDATE SET
+                username = excluded.username,
+                full_name = excluded.full_name,
+                permalink_url = excluded.permalink_url,
+                avatar_url = excluded.avatar_url,
+                city = excluded.city,
+                country_code = excluded.country_code,
+                verified = excluded.verified",
+        params![
+            id,
+            user.username,
+            user.full_name,
+            user.permalink_url,
+            user.avatar_url,
+            user.city,
+            user.country_code,
+            user.verified
+        ]
+    )?;
+
+    Ok(Some(id))
+}
+
+// Write a track and its uploader, returning the track's id if it had one
+fn write_track(tx: &Transaction, track: &Track) -> Result<Option<TrackId>, Error> {
+    let id = match track.id {
+        Some(id) => id,
+        None => return Ok(None)
+    };
+
+    let user_id = match &track.user {
+        Some(user) => write_user(tx, user)?,
+        None => None
+    }.or(track.user_id);
+    if let Some(user_id) = user_id {
+        ensure_user(tx, user_id)?;
+    }
+
+    // Incomplete track info (just an id) shouldn't overwrite what we already
+    // know about the track
+    if track.title.is_none() {
+        tx.execute(
+            "INSERT OR IGNORE INTO tracks (id, user_id) VALUES (?1, ?2)",
+            params![id, user_id]
+        )?;
+        return Ok(Some(id));
+    }
+
+    tx.execute(
+        "INSERT INTO tracks (id, user_id, title, genre, tag_list, duration, created_at,
+                permalink_url, artwork_url, playback_count, likes_count, json)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
+            ON CONFLICT (id) DO UPDATE SET
+                user_id = excluded.user_id,
+                title = excluded.title,
+                genre = excluded.genre,
+                tag_list = excluded.tag_list,
+                duration = excluded.duration,
+                created_at = excluded.created_at,
+                permalink_url = excluded.permalink_url,
+                artwork_url = excluded.artwork_url,
+                playback_count = excluded.playback_count,
+                likes_count = excluded.likes_count,
+                json = excluded.json",
+        params![
+            id,
+            user_id,
+            track.title,
+            track.genre,
+            track.tag_list,
+            track.duration,
+            date(track.created_at),
+            track.permalink_url,
+            track.artwork_url,
+            track.playback_count,
+            track.likes_count,
+            serde_json::to_string(track)?
+        ]
+    )?;
+
+    Ok(Some(id))
+}
+
+fn write_playlist(tx: &Transaction, playlist: &Playlist) -> Result<(), Error> {
+    let id = match playlist.id {
+        Some(id) => id,
+        None => return Ok(())
+    };
+
+    let user_id = match &playlist.user {
+        Some(user) => write_user(tx, user)?,
+        None => None
+    }.or(playlist.user_id);
+    if let Some(user_id) = user_id {
+        ensure_user(tx, user_id)?;
+    }
+
+    tx.execute(
+        "INSERT INTO playlists (id, user_id, title, created_at, permalink_url, json)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            ON CONFLICT (id) DO UPDATE SET
+                user_id = excluded.user_id,
+                title = excluded.title,
+                created_at = excluded.created_at,
+                permalink_url = excluded.permalink_url,
+                json = excluded.json",
+        params![
+            id,
+            user_id,
+            playlist.title,
+            date(playlist.created_at),
+            playlist.permalink_url,
+            serde_json::to_string(playlist)?
+        ]
+    )?;
+
+    // The playlist's contents may have changed since it was last written
+    tx.execute("DELETE FROM playlist_tracks WHERE playlist_id = ?1", params![id])?;
+    for (position, track) in playlist.tracks.iter().flatten().enumerate() {
+        if let Some(track_id) = write_track(tx, track)? {
+            tx.execute(
+                "INSERT INTO playlist_tracks (playlist_id, position, track_id) VALUES (?1, ?2, ?3)",
+                params![id, position as i64, track_id]
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn sqlite_export() {
+        let mut exporter = SqliteExporter::from_connection(Connection::open_in_memory().unwrap()).unwrap();
+
+        let likes: Likes = serde_json::from_value(json!({ "collections": [{
+            "created_at": "2021-09-15T08:30:00Z",
+            "track": { "id": 1, "title": "Liked", "user": { "id": 10, "username": "artist" } }
+        }] })).unwrap();
+        exporter.write_likes(&likes).unwrap();
+
+        // the playlist only has the liked track's id, which shouldn't replace
+        // what's known about it
+        let playlist = |tracks| json!({ "playlists": [{
+            "id": 20,
+            "title": "Mix",
+            "user_id": 11,
+            "tracks": tracks
+        }] });
+        let playlists: Playlists = serde_json::from_value(playlist(json!([
+            { "id": 1 },
+            { "id": 2, "title": "Other", "user_id": 12 }
+        ]))).unwrap();
+        exporter.write_playlists(&playlists).unwrap();
+
+        let conn = exporter.into_connection();
+        let query = |sql: &str| -> Vec<(i64, Option<String>)> {
+            let mut stmt = conn.prepare(sql).unwrap();
+            let rows = stmt.query_map(rusqlite::NO_PARAMS, |row| Ok((row.get(0)?, row.get(1)?))).unwrap();
+            rows.map(Result::unwrap).collect()
+        };
+
+        assert_eq!(query("SELECT id, title FROM tracks ORDER BY id"), [
+            (1, Some("Liked".to_string())),
+            (2, Some("Other".to_string()))
+        ]);
+        assert_eq!(query("SELECT id, username FROM users ORDER BY id"), [
+            (10, Some("artist".to_string())),
+            (11, None),
+            (12, None)
+        ]);
+        assert_eq!(query("SELECT track_id, liked_at FROM likes"), [(1, Some("2021-09-15T08:30:00+00:00".to_string()))]);
+        assert_eq!(query("SELECT position, CAST(track_id AS TEXT) FROM playlist_tracks ORDER BY position"), [
+            (0, Some("1".to_string())),
+            (1, Some("2".to_string()))
+        ]);
+
+        // writing the playlist again replaces its contents
+        let mut exporter = SqliteExporter::from_connection(conn).unwrap();
+        let playlists: Playlists = serde_json::from_value(playlist(json!([{ "id": 2, "title": "Other" }]))).unwrap();
+        exporter.write_playlists(&playlists).unwrap();
+        let conn = exporter.into_connection();
+        let track_ids: Vec<i64> = conn
+            .prepare("SELECT track_id FROM playlist_tracks WHERE playlist_id = 20")
+            .unwrap()
+            .query_map(rusqlite::NO_PARAMS, |row| row.get(0))
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(track_ids, [2]);
+    }
+}
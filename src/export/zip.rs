@@ -0,0 +1,129 @@
+use crate::{Error, Zester};
+use crate::archive::{ArchiveOptions, ArchiveReport, ArchiveSink};
+use crate::events::ArchiveEvent;
+use ::zip::ZipWriter;
+use ::zip::result::ZipError;
+use ::zip::write::FileOptions;
+use std::collections::HashSet;
+use std::io::{self, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+
+impl From<ZipError> for Error {
+    fn from(err: ZipError) -> Self {
+        match err {
+            ZipError::Io(e) => Error::IoError(e),
+            e => Error::IoError(io::Error::other(e))
+        }
+    }
+}
+
+/// An `ArchiveSink` that writes files into a ZIP archive as they are archived,
+/// without staging anything on disk.
+///
+/// Entries can't be taken back out of a ZIP once they're started, so each
+/// file is read into memory completely before its entry is written. That way
+/// a download that fails partway doesn't leave a truncated entry behind.
+pub struct ZipSink<W: Write + Seek> {
+    zip: ZipWriter<W>,
+    written: HashSet<PathBuf>,
+}
+
+impl<W: Write + Seek> ZipSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            zip: ZipWriter::new(writer),
+            written: HashSet::new(),
+        }
+    }
+
+    /// Write the ZIP's central directory, returning the underlying writer.
+    ///
+    /// The archive is not valid until this has been called.
+    pub fn finish(mut self) -> Result<W, Error> {
+        Ok(self.zip.finish()?)
+    }
+}
+
+impl<W: Write + Seek> ArchiveSink for ZipSink<W> {
+    fn contains(&self, path: &Path) -> bool {
+        self.written.contains(path)
+    }
+
+    fn write(&mut self, path: &Path, data: &mut dyn Read) -> Result<(), Error> {
+        // ZIP entry names always use forward slashes
+        let name = path
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        let mut contents = vec![];
+        data.read_to_end(&mut contents)?;
+
+        self.zip.start_file(name, FileOptions::default())?;
+        self.zip.write_all(&contents)?;
+        self.written.insert(path.to_owned());
+        Ok(())
+    }
+
+    // a second entry with the same name would be added instead
+    fn supports_overwrite(&self) -> bool {
+        false
+    }
+}
+
+impl Zester {
+    /// Archive the user's data into a ZIP archive written to `writer`.
+    ///
+    /// The archive has the same layout as the directory created by
+    /// `Zester::archive_to`. Files are only added to the archive once they've
+    /// been downloaded completely (see `ZipSink`).
+    pub fn archive_to_zip<W: Write + Seek, F: Fn(ArchiveEvent)>(
+        &self,
+        writer: W,
+        options: ArchiveOptions,
+        cb: F
+    ) -> Result<ArchiveReport, Error> {
+        let mut sink = ZipSink::new(writer);
+        let report = self.archive_into(&mut sink, options, cb)?;
+        sink.finish()?;
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+
+    /// A reader that fails after handing out some data, like a download
+    /// that breaks partway
+    struct Broken(&'static [u8]);
+
+    impl Read for Broken {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.0.is_empty() {
+                return Err(io::Error::new(io::ErrorKind::ConnectionReset, "connection reset"));
+            }
+            let len = self.0.len().min(buf.len());
+            buf[..len].copy_from_slice(&self.0[..len]);
+            self.0 = &self.0[len..];
+            Ok(len)
+        }
+    }
+
+    #[test]
+    fn failed_writes_leave_no_entry() {
+        let mut sink = ZipSink::new(Cursor::new(vec![]));
+        assert!(sink.write(Path::new("tracks/broken.mp3"), &mut Broken(b"partial")).is_err());
+        assert!(!sink.contains(Path::new("tracks/broken.mp3")));
+        sink.write(Path::new("tracks/fine.mp3"), &mut &b"complete"[..]).unwrap();
+
+        let mut zip = ::zip::ZipArchive::new(sink.finish().unwrap()).unwrap();
+        assert_eq!(zip.len(), 1);
+        let mut contents = String::new();
+        zip.by_name("tracks/fine.mp3").unwrap().read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, "complete");
+    }
+}
@@ -0,0 +1,197 @@
+use crate::api::common::Track;
+use std::borrow::Cow;
+use std::path::Path;
+use unicode_normalization::UnicodeNormalization;
+
+/// Characters that aren't allowed in filenames on at least one major platform
+const RESERVED_CHARS: &[char] = &['/', '\\', ':', '*', '?', '"', '<', '>', '|'];
+
+/// Names that Windows reserves for devices, with or without an extension
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL",
+    "COM0", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9", "COM¹", "COM²", "COM³",
+    "LPT0", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9", "LPT¹", "LPT²", "LPT³",
+];
+
+/// The most bytes a sanitized name can take up.
+///
+/// Most Linux filesystems allow 255 bytes in a filename (and Windows and macOS
+/// allow 255 UTF-16 units, which is never less); this leaves room for an
+/// extension and anything added to tell apart files that would otherwise have
+/// the same name.
+pub const MAX_NAME_BYTES: usize = 200;
+
+/// Make the given string safe to use as a filename on any platform.
+///
+/// * The name is normalized to Unicode's composed form (NFC), so names that
+///   look the same are the same whichever way they were typed, and whether or
+///   not they've been through macOS (which decomposes them)
+/// * Reserved and control characters are replaced with underscores
+/// * Leading whitespace and trailing whitespace and dots are removed, which
+///   Windows would otherwise strip itself
+/// * Names that Windows reserves for devices (like `CON` or `lpt1.mp3`) get
+///   an underscore added
+/// * Long names are cut down to `MAX_NAME_BYTES` bytes, without splitting a
+///   character
+pub fn sanitize(name: &str) -> String {
+    let replaced: String = name
+        .nfc()
+        .map(|c| if RESERVED_CHARS.contains(&c) || c.is_control() { '_' } else { c })
+        .collect();
+    let trimmed = trim(truncate(trim(&replaced), MAX_NAME_BYTES));
+
+    if trimmed.is_empty() {
+        "_".into()
+    } else if let Some(len) = reserved_name_len(trimmed) {
+        format!("{}_{}", &trimmed[..len], &trimmed[len..])
+    } else {
+        trimmed.into()
+    }
+}
+
+fn trim(name: &str) -> &str {
+    name.trim_start().trim_end_matches(|c: char| c == '.' || c.is_whitespace())
+}
+
+// Cut a string down to at most `max_bytes` bytes, at a character boundary
+fn truncate(name: &str, max_bytes: usize) -> &str {
+    let end = name
+        .char_indices()
+        .map(|(i, c)| i + c.len_utf8())
+        .take_while(|&end| end <= max_bytes)
+        .last()
+        .unwrap_or(0);
+
+    &name[..end]
+}
+
+// The length of the device name at the start of the given name, if it's one
+// that Windows reserves; it ignores the extension, and any spaces before it,
+// when checking
+fn reserved_name_len(name: &str) -> Option<usize> {
+    let base = name.split('.').next().unwrap_or(name).trim_end();
+    RESERVED_NAMES
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(base))
+        .then_some(base.len())
+}
+
+/// The given path in a form that isn't subject to Windows' 260 character
+/// limit on path lengths (the `\\?\` "verbatim" form), if it isn't already.
+///
+/// Paths are left as they are on other platforms, which don't have the limit.
+pub fn long_path(path: &Path) -> Cow<'_, Path> {
+    #[cfg(windows)]
+    {
+        let absolute = match std::env::current_dir() {
+            Ok(dir) if path.is_relative() => dir.join(path),
+            _ => path.to_path_buf()
+        };
+
+        if let Some(verbatim) = absolute.to_str().and_then(verbatim_path) {
+            return Cow::Owned(verbatim.into());
+        }
+    }
+
+    Cow::Borrowed(path)
+}
+
+/// The verbatim form of an absolute Windows path, or `None` if it's already
+/// verbatim or can't be made so.
+///
+/// Verbatim paths are used exactly as given, so this refuses paths with `.` or
+/// `..` components, which wouldn't be resolved.
+#[cfg(any(windows, test))]
+fn verbatim_path(path: &str) -> Option<String> {
+    if path.starts_with("\\\\?\\") {
+        return None;
+    }
+
+    let path = path.replace('/', "\\");
+    if path.split('\\').any(|c| c == "." || c == "..") {
+        return None;
+    }
+
+    let bytes = path.as_bytes();
+    if let Some(unc) = path.strip_prefix("\\\\") {
+        Some(format!("\\\\?\\UNC\\{}", unc))
+    } else if bytes.len() >= 3 && bytes[0].is_ascii_alphabetic() && &bytes[1..3] == b":\\" {
+        Some(format!("\\\\?\\{}", path))
+    } else {
+        None
+    }
+}
+
+/// The name of the artist of the given track, preferring the artist listed in
+/// the publisher metadata over the uploader's username.
+pub fn track_artist(track: &Track) -> Option<&str> {
+    track.publisher_metadata
+        .as_ref()
+        .and_then(|m| m.artist.as_deref())
+        .filter(|a| !a.is_empty())
+        .or_else(|| track.user.as_ref().and_then(|u| u.username.as_deref()))
+}
+
+/// The title of the given track, or a stand-in for tracks without one.
+pub fn track_title(track: &Track) -> String {
+    match (&track.title, track.id) {
+        (Some(title), _) => title.clone(),
+        (None, Some(id)) => format!("Untitled ({})", id),
+        (None, None) => "Untitled".into()
+    }
+}
+
+/// A sanitized filename (without an extension) for the given track, in the
+/// form "Artist - Title".
+pub fn track_stem(track: &Track) -> String {
+    let title = track_title(track);
+
+    match track_artist(track) {
+        Some(artist) => sanitize(&format!("{} - {}", artist, title)),
+        None => sanitize(&title)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn sanitize_for_windows() {
+        assert_eq!(sanitize("AC/DC: Live?"), "AC_DC_ Live_");
+        assert_eq!(sanitize("con"), "con_");
+        assert_eq!(sanitize("LPT1.mp3"), "LPT1_.mp3");
+        assert_eq!(sanitize("Nul .tar.gz"), "Nul_ .tar.gz");
+        assert_eq!(sanitize("Console"), "Console");
+        assert_eq!(sanitize("  Ends with dots and spaces. . "), "Ends with dots and spaces");
+        assert_eq!(sanitize("..."), "_");
+
+        assert_eq!(verbatim_path("C:\\Music\\tracks/a.mp3").as_deref(), Some("\\\\?\\C:\\Music\\tracks\\a.mp3"));
+        assert_eq!(verbatim_path("\\\\nas\\music\\a.mp3").as_deref(), Some("\\\\?\\UNC\\nas\\music\\a.mp3"));
+        assert_eq!(verbatim_path("\\\\?\\C:\\a.mp3"), None);
+        assert_eq!(verbatim_path("C:\\Music\\..\\a.mp3"), None);
+        assert_eq!(verbatim_path("tracks\\a.mp3"), None);
+    }
+
+    #[test]
+    fn sanitize_for_macos() {
+        // "é" decomposed, the way macOS hands back filenames
+        let decomposed = "Caf\u{65}\u{301}";
+        assert_eq!(sanitize(decomposed), "Caf\u{e9}");
+        assert_eq!(sanitize(decomposed), sanitize("Café"));
+    }
+
+    #[test]
+    fn sanitize_for_linux() {
+        let long = "🍊".repeat(100);
+        let sanitized = sanitize(&long);
+        assert_eq!(sanitized.len(), MAX_NAME_BYTES);
+        assert!(sanitized.chars().all(|c| c == '🍊'));
+
+        // a multibyte character that would straddle the limit is dropped
+        // rather than split
+        let long = format!("{}é", "a".repeat(MAX_NAME_BYTES - 1));
+        assert_eq!(sanitize(&long), "a".repeat(MAX_NAME_BYTES - 1));
+        assert_eq!(sanitize("tab\tand\nnewline"), "tab_and_newline");
+    }
+}
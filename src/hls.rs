@@ -0,0 +1,237 @@
+//! Downloading audio that's only available over HLS, where it's split into
+//! many small segments listed in a playlist.
+
+use crate::api::download_media;
+use crate::remux::remux_hls;
+use crate::{Error, Zester};
+use std::io::prelude::*;
+
+/// The URLs of the media segments listed in an HLS media playlist, in order,
+/// resolved against the URL the playlist was fetched from.
+///
+/// If the playlist has an initialization section (`#EXT-X-MAP`, used for
+/// fragmented MP4 segments) its URL comes first.
+pub fn segment_urls(playlist: &str, playlist_url: &str) -> Vec<String> {
+    let mut urls = vec![];
+
+    for line in playlist.lines().map(str::trim) {
+        if let Some(attributes) = line.strip_prefix("#EXT-X-MAP:") {
+            if let Some(uri) = attribute(attributes, "URI") {
+                urls.push(resolve(playlist_url, uri));
+            }
+        } else if !line.is_empty() && !line.starts_with('#') {
+            urls.push(resolve(playlist_url, line));
+        }
+    }
+
+    urls
+}
+
+// Find the (quoted) value of the given attribute in a tag's attribute list
+fn attribute<'a>(attributes: &'a str, name: &str) -> Option<&'a str> {
+    attributes.split(',').find_map(|attr| {
+        let (key, value) = attr.split_once('=')?;
+        (key.trim() == name).then(|| value.trim().trim_matches('"'))
+    })
+}
+
+// Resolve a (possibly relative) URL in a playlist against the playlist's URL
+fn resolve(base: &str, url: &str) -> String {
+    if url.contains("://") {
+        return url.to_string();
+    }
+
+    let base = base.split(['?', '#']).next().unwrap_or(base);
+    if url.starts_with('/') {
+        let origin_end = base
+            .find("://")
+            .and_then(|i| base[i + 3..].find('/').map(|j| i + 3 + j))
+            .unwrap_or(base.len());
+        format!("{}{}", &base[..origin_end], url)
+    } else {
+        let dir_end = base.rfind('/').map_or(0, |i| i + 1);
+        format!("{}{}", &base[..dir_end], url)
+    }
+}
+
+/// An HLS download in progress.
+///
+/// Segments are downloaded one at a time, and the ones that have been
+/// downloaded are kept when a segment fails, so the download can pick up from
+/// the failed segment rather than starting over.
+pub(crate) struct HlsDownload {
+    segments: Vec<String>,
+    /// The segments downloaded so far, in order
+    data: Vec<Vec<u8>>,
+}
+
+impl HlsDownload {
+    /// Fetch the HLS playlist at the given URL (a track's media URL) to start
+    /// downloading its segments.
+    pub fn start(zester: &Zester, playlist_url: &str) -> Result<Self, Error> {
+        let mut playlist = String::new();
+        zester.call(&mut ureq::get(playlist_url))?.read_to_string(&mut playlist)?;
+
+        let segments = segment_urls(&playlist, playlist_url);
+        if segments.is_empty() {
+            return Err(Error::DataNotPresent("segments in HLS playlist".into()));
+        }
+
+        Ok(Self { segments, data: vec![] })
+    }
+
+    /// Download the segments that haven't been downloaded yet, calling
+    /// `on_segment` with the size of each one after it's downloaded.
+    ///
+    /// `on_segment` returns whether to carry on; if it returns false, this
+    /// stops and returns false too. Otherwise true is returned once every
+    /// segment is downloaded.
+    ///
+    /// If a segment fails to download the error is returned, and calling this
+    /// again resumes from that segment.
+    pub fn download_remaining(&mut self, zester: &Zester, mut on_segment: impl FnMut(u64) -> bool) -> Result<bool, Error> {
+        while let Some(url) = self.segments.get(self.data.len()) {
+            let mut segment = vec![];
+            download_media(zester, url)?.0.read_to_end(&mut segment)?;
+
+            let len = segment.len() as u64;
+            self.data.push(segment);
+            if !on_segment(len) {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// The audio of the whole track as a single file, once all of the segments
+    /// are downloaded (see `remux::remux_hls`).
+    pub fn into_audio(self, mime_type: &str) -> Vec<u8> {
+        remux_hls(&self.data, mime_type)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::api::common::Track;
+    use crate::events::{DownloadControl, TracksAudioZestingEvent};
+    use crate::transport::Transport;
+    use serde_json::json;
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+    use std::io::Cursor;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    const TRANSCODING_URL: &str = "https://api-v2.soundcloud.com/media/soundcloud:tracks:7/stream/hls";
+    const PLAYLIST_URL: &str = "https://cf-hls-media.sndcdn.com/playlist/7.128.mp3/playlist.m3u8";
+
+    /// Serves an HLS playlist of three segments, the second of which fails
+    /// the first time it's requested, counting the requests for each URL.
+    #[derive(Clone, Default)]
+    struct SegmentTransport {
+        requests: Arc<Mutex<HashMap<String, u32>>>
+    }
+
+    impl Transport for SegmentTransport {
+        fn send(&self, request: &ureq::Request, _body: Option<&str>) -> Result<(Box<dyn Read + Send>, Option<u64>), Error> {
+            let url = request.get_url().to_string();
+            let count = {
+                let mut requests = self.requests.lock().unwrap();
+                let count = requests.entry(url.clone()).or_default();
+                *count += 1;
+                *count
+            };
+
+            let body = if url == TRANSCODING_URL {
+                json!({ "url": PLAYLIST_URL }).to_string()
+            } else if url == PLAYLIST_URL {
+                "#EXTM3U\n#EXTINF:10,\nsegment-1.mp3\n#EXTINF:10,\nsegment-2.mp3\n#EXTINF:10,\nsegment-3.mp3\n#EXT-X-ENDLIST\n".into()
+            } else if url.ends_with("segment-2.mp3") && count == 1 {
+                return Err(Error::ServerError { status: 503, url, body: "Service Unavailable".into(), context: None });
+            } else if let Some(segment) = url.rsplit('/').next().filter(|s| s.starts_with("segment-")) {
+                segment.replace(".mp3", ";")
+            } else {
+                return Err(Error::NotFound { url, body: "Not Found".into(), context: None });
+            };
+
+            let len = body.len() as u64;
+            Ok((Box::new(Cursor::new(body.into_bytes())), Some(len)))
+        }
+    }
+
+    #[test]
+    fn hls_segment_urls() {
+        let playlist = "#EXTM3U\n\
+            #EXT-X-VERSION:6\n\
+            #EXT-X-MAP:URI=\"init.mp4\"\n\
+            #EXTINF:9.98,\n\
+            https://cf-hls-media.sndcdn.com/media/0/159517/abc.m4s?Policy=x\n\
+            #EXTINF:9.98,\n\
+            segment-2.m4s\n\
+            \n\
+            #EXTINF:1.2,\n\
+            /other/segment-3.m4s\n\
+            #EXT-X-ENDLIST\n";
+
+        assert_eq!(segment_urls(playlist, "https://playback.media-streaming.soundcloud.cloud/x/aac/playlist.m3u8?s=1"), vec![
+            "https://playback.media-streaming.soundcloud.cloud/x/aac/init.mp4",
+            "https://cf-hls-media.sndcdn.com/media/0/159517/abc.m4s?Policy=x",
+            "https://playback.media-streaming.soundcloud.cloud/x/aac/segment-2.m4s",
+            "https://playback.media-streaming.soundcloud.cloud/other/segment-3.m4s"
+        ]);
+    }
+
+    #[test]
+    fn resumed_segments() {
+        let transport = SegmentTransport::default();
+        let mut zester = Zester::anonymous("test-client-id".into());
+        zester.config.retry.pause = Duration::ZERO;
+        zester.config.retry.jitter = Duration::ZERO;
+        zester.set_transport(transport.clone());
+
+        let track: Track = serde_json::from_value(json!({
+            "id": 7,
+            "title": "Mix",
+            "duration": 30000,
+            "full_duration": 30000,
+            "media": {
+                "transcodings": [{
+                    "url": TRANSCODING_URL,
+                    "preset": "mp3_1_0",
+                    "duration": 30000,
+                    "snipped": false,
+                    "format": { "protocol": "hls", "mime_type": "audio/mpeg" },
+                    "quality": "sq"
+                }]
+            }
+        })).unwrap();
+
+        let audio = RefCell::new(vec![]);
+        let retries = RefCell::new(0);
+        let report = zester.tracks_audio_controlled(std::iter::once(&track), |e| {
+            match e {
+                TracksAudioZestingEvent::FinishTrackDownload { mut track_data, .. } => {
+                    track_data.read_to_end(&mut audio.borrow_mut()).unwrap();
+                },
+                TracksAudioZestingEvent::PausedAfterServerError { .. } => *retries.borrow_mut() += 1,
+                _ => {}
+            }
+            DownloadControl::Continue
+        }).unwrap();
+
+        assert_eq!(report.downloaded, [track.id.unwrap()]);
+        assert_eq!(*retries.borrow(), 1);
+        assert_eq!(audio.into_inner(), b"segment-1;segment-2;segment-3;");
+
+        // the failed segment was retried on its own, without starting the
+        // track over
+        let requests = transport.requests.lock().unwrap();
+        let requests_for = |end: &str| requests.iter().filter(|(url, _)| url.ends_with(end)).map(|(_, &n)| n).sum::<u32>();
+        assert_eq!(requests_for("playlist.m3u8"), 1);
+        assert_eq!(requests_for("segment-1.mp3"), 1);
+        assert_eq!(requests_for("segment-2.mp3"), 2);
+        assert_eq!(requests_for("segment-3.mp3"), 1);
+    }
+}
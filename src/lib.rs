@@ -1,41 +1,448 @@
+#[cfg(not(any(feature = "rustls", feature = "native-tls")))]
+compile_error!("either the `rustls` or the `native-tls` feature must be enabled");
+
+pub mod anonymize;
 pub mod api;
+pub mod archive;
+pub mod client;
+pub mod config;
+pub mod credentials;
 pub mod events;
+pub mod export;
+pub mod filename;
+pub mod hls;
+pub mod manifest;
+pub mod metrics;
+pub mod middleware;
+#[cfg(feature = "musicbrainz")]
+pub mod musicbrainz;
+pub mod offline;
+pub mod pool;
+pub mod progress;
+pub mod reader;
+pub mod recorder;
+pub mod remux;
+pub mod report;
+pub mod retry;
+pub mod snapshot;
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(feature = "ffmpeg")]
+pub mod transcode;
+pub mod transport;
+pub mod verify;
+pub mod watch;
+#[cfg(test)]
+mod test_util;
 
 use api::{Likes, Playlists};
 use api::likes::LikesRaw;
 use api::me::Me;
-use api::common::Track;
-use api::playlists::{Playlist, PlaylistsRaw};
+use api::comments::Comment;
+use api::common::{Page, Track, User};
+use api::ids::{PlaylistId, TrackId, UserId};
+use api::playlists::{Playlist, PlaylistKind, PlaylistMeta, PlaylistsRaw};
+use api::raw::KeepRaw;
+use api::spotlight::SpotlightItem;
+use api::stats::{StatsRange, TrackStats};
+use api::system_playlists::{Selection, SystemPlaylist};
+use config::ZesterConfig;
+use credentials::{Credentials, CredentialsProvider};
+use metrics::{Metrics, ResponseMetadata};
+use middleware::Middleware;
+use transport::Transport;
 use events::*;
-use std::thread;
-use std::time::Duration;
-use std::path::Path;
-use std::fs::File;
+use hls::HlsDownload;
+use chrono::{DateTime, Utc};
+use progress::{ProgressHandle, ProgressTracker};
+use reader::{DeadlineReader, MeteredReader, Throttle};
+use report::{SkipReason, ZestReport};
+use retry::{RetryPause, Retrier};
+use std::path::{Path, PathBuf};
+use std::fs::{self, File};
 use std::cmp::min;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::sync::{mpsc, Arc, Mutex, RwLock};
+use std::panic;
+use std::thread;
+use std::time::{Duration, Instant};
 use std::io::prelude::*;
+use std::io::{BufReader, Cursor};
 use serde::Serialize;
 use serde_derive::Serialize;
 use serde::de::DeserializeOwned;
 
 const API_BASE: &str = "https://api-v2.soundcloud.com/";
-/// Amount of time to pause after a 500 is returned from the server
-const PAUSE_SECS: u64 = 2;
+/// The maximum number of bytes of an error response's body to keep around
+const MAX_ERROR_BODY_LEN: u64 = 4 * 1024;
 
 #[derive(Debug, Serialize)]
 pub enum Error {
     IoError(#[serde(skip_serializing)] std::io::Error),
     JsonDecodeError(#[serde(skip_serializing)] serde_json::Error),
-    /// Contains the response status code
-    HttpError(u16),
+    /// The server responded with 404 Not Found
+    NotFound {
+        /// The URL the request was sent to (without query parameters)
+        url: String,
+        /// The start of the response body, which usually explains the error
+        body: String,
+        /// The API request the error happened during, if it's known
+        #[serde(skip_serializing_if = "Option::is_none")]
+        context: Option<Box<ErrorContext>>
+    },
+    /// The server responded with 429 Too Many Requests
+    RateLimited {
+        /// How long the server asked for us to wait before trying again, if it
+        /// said
+        retry_after: Option<Duration>,
+        url: String,
+        body: String,
+        /// The API request the error happened during, if it's known
+        #[serde(skip_serializing_if = "Option::is_none")]
+        context: Option<Box<ErrorContext>>
+    },
+    /// The server rejected the OAuth token (or its absence) with 401
+    /// Unauthorized
+    Unauthorized {
+        url: String,
+        body: String,
+        /// The API request the error happened during, if it's known
+        #[serde(skip_serializing_if = "Option::is_none")]
+        context: Option<Box<ErrorContext>>
+    },
+    /// The server responded with a 5xx status, which is usually temporary
+    ServerError {
+        status: u16,
+        url: String,
+        body: String,
+        /// The API request the error happened during, if it's known
+        #[serde(skip_serializing_if = "Option::is_none")]
+        context: Option<Box<ErrorContext>>
+    },
+    /// The server responded with some other error status
+    Other {
+        status: u16,
+        url: String,
+        body: String,
+        /// The API request the error happened during, if it's known
+        #[serde(skip_serializing_if = "Option::is_none")]
+        context: Option<Box<ErrorContext>>
+    },
+    /// A request timed out, either while connecting or while waiting for data
+    ///
+    /// (The URL is included when the request it happened to is known.)
+    Timeout {
+        url: Option<String>,
+        /// The API request the error happened during, if it's known
+        #[serde(skip_serializing_if = "Option::is_none")]
+        context: Option<Box<ErrorContext>>
+    },
+    /// A request couldn't be sent, or the connection broke before the whole
+    /// response arrived
+    Network {
+        url: String,
+        /// A description of what went wrong
+        message: String,
+        /// The API request the error happened during, if it's known
+        #[serde(skip_serializing_if = "Option::is_none")]
+        context: Option<Box<ErrorContext>>
+    },
     /// Something we needed wasn't present in the JSON
     ///
     /// (The "something" will be described by the string.)
-    DataNotPresent(String)
+    DataNotPresent(String),
+    /// Downloaded audio failed verification
+    ///
+    /// (The string describes what was wrong with it.)
+    VerificationFailed(String),
+    /// The method needs to act as a user, but the `Zester` was constructed in
+    /// anonymous mode without an OAuth token
+    NotAuthenticated,
+    /// The track's audio is only available with DRM, so it can't be
+    /// downloaded
+    DrmProtected,
+    /// Requests kept failing because of server errors, and the operation used
+    /// up its retry budget (see `RetryPolicy::max_total_retries`)
+    RetryBudgetExhausted {
+        /// The number of retries that were made before giving up
+        retries: u32
+    },
+    /// A snapshot was saved with a version of the format that this version of
+    /// the crate doesn't know how to read
+    UnsupportedSnapshotVersion(u64),
+    /// No data arrived while downloading for longer than
+    /// `ZesterConfig::stall_timeout`
+    Stalled(Duration),
+    /// ffmpeg failed to transcode a track's audio
+    ///
+    /// (The string holds what ffmpeg reported.)
+    TranscodeFailed(String),
+    /// Downloading a track's audio took longer than
+    /// `ZesterConfig::track_timeout`
+    TrackTimedOut(Duration),
+    /// Only a preview snippet of the track's audio is available, and
+    /// `ZesterConfig::download_snippets` is off
+    SnippetOnly,
+    /// A worker thread panicked, so the part of the operation it was doing
+    /// couldn't be finished
+    ///
+    /// (The string says what kind of worker it was.)
+    WorkerPanicked(&'static str),
+    /// A pagination cursor passed to one of the `_page` methods wasn't a URL
+    /// pointing at the API, so it can't have come from a previous page
+    ///
+    /// (The string holds the cursor.)
+    InvalidCursor(String)
+}
+
+// The underlying io and JSON errors can't be cloned, so clones of those carry
+// over their kind and message instead
+impl Clone for Error {
+    fn clone(&self) -> Self {
+        match self {
+            Self::IoError(e) => Self::IoError(std::io::Error::new(e.kind(), e.to_string())),
+            Self::JsonDecodeError(e) => Self::JsonDecodeError(serde::de::Error::custom(e)),
+            Self::NotFound { url, body, context } => Self::NotFound {
+                url: url.clone(),
+                body: body.clone(),
+                context: context.clone()
+            },
+            Self::RateLimited { retry_after, url, body, context } => Self::RateLimited {
+                retry_after: *retry_after,
+                url: url.clone(),
+                body: body.clone(),
+                context: context.clone()
+            },
+            Self::Unauthorized { url, body, context } => Self::Unauthorized {
+                url: url.clone(),
+                body: body.clone(),
+                context: context.clone()
+            },
+            Self::ServerError { status, url, body, context } => Self::ServerError {
+                status: *status,
+                url: url.clone(),
+                body: body.clone(),
+                context: context.clone()
+            },
+            Self::Other { status, url, body, context } => Self::Other {
+                status: *status,
+                url: url.clone(),
+                body: body.clone(),
+                context: context.clone()
+            },
+            Self::Timeout { url, context } => Self::Timeout { url: url.clone(), context: context.clone() },
+            Self::Network { url, message, context } => Self::Network {
+                url: url.clone(),
+                message: message.clone(),
+                context: context.clone()
+            },
+            Self::DataNotPresent(s) => Self::DataNotPresent(s.clone()),
+            Self::VerificationFailed(s) => Self::VerificationFailed(s.clone()),
+            Self::NotAuthenticated => Self::NotAuthenticated,
+            Self::DrmProtected => Self::DrmProtected,
+            Self::RetryBudgetExhausted { retries } => Self::RetryBudgetExhausted { retries: *retries },
+            Self::UnsupportedSnapshotVersion(v) => Self::UnsupportedSnapshotVersion(*v),
+            Self::Stalled(d) => Self::Stalled(*d),
+            Self::TranscodeFailed(s) => Self::TranscodeFailed(s.clone()),
+            Self::TrackTimedOut(d) => Self::TrackTimedOut(*d),
+            Self::SnippetOnly => Self::SnippetOnly,
+            Self::WorkerPanicked(s) => Self::WorkerPanicked(s),
+            Self::InvalidCursor(s) => Self::InvalidCursor(s.clone())
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::IoError(e) => write!(f, "IO error: {}", e),
+            Self::JsonDecodeError(e) => write!(f, "error decoding JSON: {}", e),
+            Self::NotFound { url, .. } => write!(f, "{} was not found", url),
+            Self::RateLimited { retry_after: Some(d), url, .. } => {
+                write!(f, "rate limited by the server for {} (retry after {:?})", url, d)
+            },
+            Self::RateLimited { url, .. } => write!(f, "rate limited by the server for {}", url),
+            Self::Unauthorized { url, .. } => write!(f, "not authorized to access {}", url),
+            Self::ServerError { status, url, .. } | Self::Other { status, url, .. } => {
+                write!(f, "server responded with status {} for {}", status, url)
+            },
+            Self::Timeout { url: Some(url), .. } => write!(f, "request to {} timed out", url),
+            Self::Timeout { url: None, .. } => write!(f, "request timed out"),
+            Self::Network { url, message, .. } => write!(f, "network error for {}: {}", url, message),
+            Self::DataNotPresent(s) => write!(f, "data not present: {}", s),
+            Self::VerificationFailed(s) => write!(f, "audio failed verification: {}", s),
+            Self::NotAuthenticated => write!(f, "an OAuth token is required"),
+            Self::DrmProtected => write!(f, "the audio is DRM protected"),
+            Self::RetryBudgetExhausted { retries } => write!(f, "gave up after {} retries", retries),
+            Self::UnsupportedSnapshotVersion(v) => write!(f, "unsupported snapshot version {}", v),
+            Self::Stalled(d) => write!(f, "download stalled (no data received for {:?})", d),
+            Self::TranscodeFailed(s) => write!(f, "failed to transcode audio: {}", s),
+            Self::TrackTimedOut(d) => write!(f, "track download took longer than {:?}", d),
+            Self::SnippetOnly => write!(f, "only a preview snippet of the audio is available"),
+            Self::WorkerPanicked(s) => write!(f, "a {} worker panicked", s),
+            Self::InvalidCursor(s) => write!(f, "invalid pagination cursor: {}", s)
+        }?;
+
+        match self.context() {
+            Some(context) => write!(f, " ({})", context),
+            None => Ok(())
+        }
+    }
+}
+
+impl Error {
+    /// The error for a response with the given (error) status.
+    pub(crate) fn from_status(status: u16, url: String, body: String, retry_after: Option<Duration>) -> Self {
+        let context = None;
+        match status {
+            401 => Self::Unauthorized { url, body, context },
+            404 => Self::NotFound { url, body, context },
+            429 => Self::RateLimited { retry_after, url, body, context },
+            status if is_500(status) => Self::ServerError { status, url, body, context },
+            status => Self::Other { status, url, body, context }
+        }
+    }
+
+    /// Information about the API request that this error happened during, if
+    /// it's known.
+    pub fn context(&self) -> Option<&ErrorContext> {
+        match self {
+            Self::NotFound { context, .. }
+            | Self::RateLimited { context, .. }
+            | Self::Unauthorized { context, .. }
+            | Self::ServerError { context, .. }
+            | Self::Other { context, .. }
+            | Self::Timeout { context, .. }
+            | Self::Network { context, .. } => context.as_deref(),
+            _ => None
+        }
+    }
+
+    /// The error with information about the API request it happened during
+    /// added, for errors that are about a request.
+    fn with_context(mut self, new_context: ErrorContext) -> Self {
+        match &mut self {
+            Self::NotFound { context, .. }
+            | Self::RateLimited { context, .. }
+            | Self::Unauthorized { context, .. }
+            | Self::ServerError { context, .. }
+            | Self::Other { context, .. }
+            | Self::Timeout { context, .. }
+            | Self::Network { context, .. } => *context = Some(Box::new(new_context)),
+            _ => {}
+        }
+        self
+    }
+
+    /// The HTTP status code of the response, if this error was caused by the
+    /// server responding with an error status.
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            Self::NotFound { .. } => Some(404),
+            Self::RateLimited { .. } => Some(429),
+            Self::Unauthorized { .. } => Some(401),
+            Self::ServerError { status, .. } | Self::Other { status, .. } => Some(*status),
+            _ => None
+        }
+    }
+
+    /// Whether or not this error was caused by the server responding with a 5xx
+    /// status, which is usually temporary.
+    pub fn is_server_error(&self) -> bool {
+        matches!(self, Self::ServerError { .. })
+    }
+
+    /// Whether or not this error is likely to go away if the request is tried
+    /// again: a server error, a timeout, a network error, or a stalled
+    /// download.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, Self::ServerError { .. } | Self::Timeout { .. } | Self::Network { .. } | Self::Stalled(_))
+    }
+}
+
+/// Information about the API request an error happened during, to help track
+/// down where it came from.
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorContext {
+    /// The URL of the endpoint the request was sent to (without query
+    /// parameters)
+    pub endpoint: String,
+    /// The request's query parameters, with the client id redacted
+    pub query: Vec<(String, String)>,
+    /// What was being zested when the error happened, like "zesting
+    /// playlists", if the request was made by a zesting method
+    pub phase: Option<&'static str>
+}
+
+impl ErrorContext {
+    fn new(endpoint: &str, query_params: &[(&str, &str)], add_client_id: bool) -> Self {
+        let mut query: Vec<_> = query_params.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        if add_client_id {
+            query.push(("client_id".into(), "<redacted>".into()));
+        }
+
+        Self { endpoint: endpoint.to_string(), query, phase: Phase::current() }
+    }
+}
+
+impl fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(phase) = self.phase {
+            write!(f, "while {}, ", phase)?;
+        }
+        write!(f, "requesting {}", self.endpoint)?;
+
+        for (i, (key, value)) in self.query.iter().enumerate() {
+            write!(f, "{}{}={}", if i == 0 { '?' } else { '&' }, key, value)?;
+        }
+        Ok(())
+    }
+}
+
+thread_local! {
+    // The name of the zesting method currently running on this thread
+    static PHASE: Cell<Option<&'static str>> = const { Cell::new(None) };
+}
+
+/// Records which zesting method is running on the current thread (for
+/// `ErrorContext::phase`) until it's dropped.
+struct Phase(Option<&'static str>);
+
+impl Phase {
+    fn enter(name: &'static str) -> Self {
+        Self(PHASE.with(|p| p.replace(Some(name))))
+    }
+
+    fn current() -> Option<&'static str> {
+        PHASE.with(Cell::get)
+    }
+}
+
+impl Drop for Phase {
+    fn drop(&mut self) {
+        PHASE.with(|p| p.set(self.0));
+    }
+}
+
+/// The name of the worker that resolves the next track's media URL ahead of
+/// time, for `Error::WorkerPanicked`
+const PREFETCH_WORKER: &str = "media URL prefetch";
+
+/// Runs a worker thread's job, turning a panic into an error so that the
+/// thread waiting for the job's result is never left waiting forever.
+pub(crate) fn catch_worker_panic<T>(worker: &'static str, job: impl FnOnce() -> Result<T, Error>) -> Result<T, Error> {
+    panic::catch_unwind(panic::AssertUnwindSafe(job)).unwrap_or_else(|_| Err(Error::WorkerPanicked(worker)))
 }
 
 impl From<std::io::Error> for Error {
     fn from(err: std::io::Error) -> Self {
-        Self::IoError(err)
+        match err.get_ref().and_then(|e| e.downcast_ref::<reader::Stalled>()) {
+            Some(stalled) => Self::Stalled(stalled.0),
+            None if err.kind() == std::io::ErrorKind::TimedOut => Self::Timeout { url: None, context: None },
+            None => Self::IoError(err)
+        }
     }
 }
 
@@ -45,26 +452,206 @@ impl From<serde_json::Error> for Error {
     }
 }
 
+// SQLite is used by more than one optional feature
+#[cfg(feature = "rusqlite")]
+impl From<rusqlite::Error> for Error {
+    fn from(err: rusqlite::Error) -> Self {
+        Self::IoError(std::io::Error::other(err))
+    }
+}
+
+#[cfg(feature = "migration")]
+impl From<csv::Error> for Error {
+    fn from(err: csv::Error) -> Self {
+        Self::IoError(err.into())
+    }
+}
+
 /// Load an object from a JSON file at the given path.
+///
+/// Snapshots saved by older versions of this crate are migrated to the current
+/// format (see the `snapshot` module).
+///
+/// With the `gzip` feature enabled, files whose names end in ".gz" are
+/// decompressed.
 pub fn load_json<P: AsRef<Path>, O: DeserializeOwned>(path: P) -> Result<O, Error> {
-    let mut file = File::open(path)?;
-    let mut string = String::new();
-    file.read_to_string(&mut string)?;
+    load_json_from(open_json_file(path.as_ref())?)
+}
+
+// Open a JSON file for reading, decompressing it if necessary
+fn open_json_file(path: &Path) -> Result<Box<dyn Read>, Error> {
+    let file = File::open(path)?;
+
+    #[cfg(feature = "gzip")]
+    {
+        if is_gzip_path(path) {
+            return Ok(Box::new(flate2::read::GzDecoder::new(file)));
+        }
+    }
+
+    Ok(Box::new(file))
+}
+
+// The contents of a JSON file at the given path, compressed if necessary,
+// written by the given function
+#[cfg_attr(not(feature = "gzip"), allow(unused_variables))]
+fn json_file_bytes<F: FnOnce(&mut dyn Write) -> Result<(), Error>>(path: &Path, write: F) -> Result<Vec<u8>, Error> {
+    #[cfg(feature = "gzip")]
+    {
+        if is_gzip_path(path) {
+            let mut encoder = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+            write(&mut encoder)?;
+            return Ok(encoder.finish()?);
+        }
+    }
+
+    let mut bytes = vec![];
+    write(&mut bytes)?;
+    Ok(bytes)
+}
+
+// Whether the given path is for a gzipped file
+#[cfg(feature = "gzip")]
+fn is_gzip_path(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e.eq_ignore_ascii_case("gz"))
+}
+
+/// Load an object from JSON read from the given reader, such as stdin or a
+/// network socket.
+///
+/// The JSON is deserialized as it's read rather than being read into memory
+/// first, so loading large snapshots doesn't take much more memory than the
+/// loaded object itself.
+pub fn load_json_from<R: Read, O: DeserializeOwned>(reader: R) -> Result<O, Error> {
+    snapshot::read(BufReader::new(reader))
+}
 
-    Ok(serde_json::from_str(&string)?)
+/// Write an object as JSON to the given writer, such as stdout or an
+/// in-memory buffer.
+///
+/// The object is written as-is; use `write_snapshot_to` for zested data.
+pub fn write_json_to<W: Write, O: Serialize>(object: &O, mut writer: W, pretty_print: bool) -> Result<(), Error> {
+    if pretty_print {
+        serde_json::to_writer_pretty(&mut writer, object)?;
+    } else {
+        serde_json::to_writer(&mut writer, object)?;
+    }
+
+    Ok(writer.flush()?)
+}
+
+/// Write zested data as a snapshot to the given writer.
+///
+/// Same as `write_json_to`, except that the data is wrapped in an envelope
+/// recording the version of the snapshot format (see the `snapshot` module),
+/// so that `load_json` can still load it if the format changes.
+pub fn write_snapshot_to<W: Write, O: Serialize>(object: &O, writer: W, pretty_print: bool) -> Result<(), Error> {
+    write_json_to(&snapshot::wrap(object), writer, pretty_print)
 }
 
 /// Write an object to a JSON file at the given path.
+///
+/// With the `gzip` feature enabled, files whose names end in ".gz" are
+/// compressed.
+///
+/// The JSON is written to a temporary file in the same directory which is then
+/// renamed into place, so a crash part way through writing leaves any previous
+/// file at the path intact.
 pub fn write_json<P: AsRef<Path>, O: Serialize>(object: &O, path: P, pretty_print: bool) -> Result<(), Error> {
-    let bytes = if pretty_print {
-        serde_json::to_string_pretty(object)?.into_bytes()
-    } else {
-        serde_json::to_string(object)?.into_bytes()
+    let path = path.as_ref();
+    write_atomic(path, &json_file_bytes(path, |w| write_json_to(object, w, pretty_print))?, false)
+}
+
+/// Same as `write_json`, but keeps a copy of the file previously at the path
+/// (if there was one) alongside it with ".bak" appended to its name.
+pub fn write_json_with_backup<P: AsRef<Path>, O: Serialize>(object: &O, path: P, pretty_print: bool) -> Result<(), Error> {
+    let path = path.as_ref();
+    write_atomic(path, &json_file_bytes(path, |w| write_json_to(object, w, pretty_print))?, true)
+}
+
+/// Write zested data as a snapshot to a JSON file at the given path.
+///
+/// Same as `write_json`, except that the data is wrapped in a versioned
+/// envelope like `write_snapshot_to` does.
+pub fn write_snapshot<P: AsRef<Path>, O: Serialize>(object: &O, path: P, pretty_print: bool) -> Result<(), Error> {
+    write_json(&snapshot::wrap(object), path, pretty_print)
+}
+
+/// Load the items in a [JSON Lines](https://jsonlines.org/) file at the given
+/// path, one at a time.
+///
+/// This is useful for collections that are too large to comfortably load into
+/// memory at once. Like `load_json`, gzipped files are decompressed with the
+/// `gzip` feature enabled.
+pub fn load_jsonl<P: AsRef<Path>, O: DeserializeOwned>(path: P) -> Result<impl Iterator<Item = Result<O, Error>>, Error> {
+    Ok(load_jsonl_from(open_json_file(path.as_ref())?))
+}
+
+/// Load the items in JSON Lines read from the given reader, one at a time.
+pub fn load_jsonl_from<R: Read, O: DeserializeOwned>(reader: R) -> impl Iterator<Item = Result<O, Error>> {
+    serde_json::Deserializer::from_reader(BufReader::new(reader))
+        .into_iter()
+        .map(|item| item.map_err(Error::from))
+}
+
+/// Write the given items to a JSON Lines file at the given path, one item per
+/// line.
+///
+/// Like `write_json`, the file is written atomically and gzipped files are
+/// compressed with the `gzip` feature enabled. Unlike `write_snapshot`, the
+/// items aren't wrapped in a versioned envelope.
+pub fn write_jsonl<P, I, O>(items: I, path: P) -> Result<(), Error> where
+    P: AsRef<Path>,
+    I: IntoIterator<Item = O>,
+    O: Serialize
+{
+    let path = path.as_ref();
+    write_atomic(path, &json_file_bytes(path, |w| write_jsonl_to(items, w))?, false)
+}
+
+/// Write the given items as JSON Lines to the given writer, one item per line.
+pub fn write_jsonl_to<W, I, O>(items: I, mut writer: W) -> Result<(), Error> where
+    W: Write,
+    I: IntoIterator<Item = O>,
+    O: Serialize
+{
+    for item in items {
+        serde_json::to_writer(&mut writer, &item)?;
+        writer.write_all(b"\n")?;
+    }
+
+    Ok(writer.flush()?)
+}
+
+// Write the given bytes to a file via a temporary file, so that whatever was
+// previously at the path is left intact if writing fails part way through
+fn write_atomic(path: &Path, bytes: &[u8], backup: bool) -> Result<(), Error> {
+    let with_suffix = |suffix: &str| {
+        let mut p = path.as_os_str().to_owned();
+        p.push(suffix);
+        PathBuf::from(p)
     };
 
-    let mut file = File::create(path)?;
-    file.write_all(&bytes)?;
-    Ok(())
+    let part_path = with_suffix(".part");
+    let result = (|| {
+        let mut file = File::create(&part_path)?;
+        file.write_all(bytes)?;
+        file.sync_all()?;
+        drop(file);
+
+        // copy rather than rename so that there's never a moment where
+        // nothing is at the path
+        if backup && path.exists() {
+            fs::copy(path, with_suffix(".bak"))?;
+        }
+
+        fs::rename(&part_path, path)
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&part_path);
+    }
+    Ok(result?)
 }
 
 /// An enum of values to return from the body of a `retry_loop`
@@ -72,13 +659,15 @@ enum LoopControl {
     /// Continue on to the next iteration
     Next,
     /// Retry the current iteration
-    Retry
+    Retry,
+    /// Stop looping because of the given error
+    Abort(Error)
 }
 
 
 /// Loop through the given iterator with the ability to "retry" any iteration
 /// from the top of the loop body.
-fn retry_loop<'a, T, I, F>(mut iter: I, mut loop_body: F)
+fn retry_loop<T, I, F>(mut iter: I, mut loop_body: F) -> Result<(), Error>
     where
         T: Copy,
         I: Iterator<Item = T>,
@@ -90,13 +679,191 @@ fn retry_loop<'a, T, I, F>(mut iter: I, mut loop_body: F)
         match loop_body(val) {
             LoopControl::Next => maybe_val = iter.next(),
             LoopControl::Retry => {},
+            LoopControl::Abort(e) => return Err(e)
+        }
+    }
+
+    Ok(())
+}
+
+/// The point in time by which the current track has to have finished
+/// downloading (see `ZesterConfig::track_timeout`).
+#[derive(Clone, Copy)]
+struct TrackDeadline {
+    timeout: Duration,
+    at: Instant
+}
+
+impl TrackDeadline {
+    fn has_passed(self) -> bool {
+        Instant::now() >= self.at
+    }
+
+    fn check(self) -> Result<(), Error> {
+        if self.has_passed() {
+            Err(Error::TrackTimedOut(self.timeout))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// A track's audio as far as the download loop is concerned.
+enum TrackAudio {
+    /// The audio was read into memory, and has yet to be handed over
+    Buffered(Vec<u8>, Option<u64>),
+    /// The audio was streamed to the callback as it was downloaded
+    Streamed
+}
+
+/// How reading a track's audio (see `AudioStream`) went, for the zest to act
+/// on once the callback is done with it.
+#[derive(Default)]
+struct StreamOutcome {
+    /// Whether the callback asked for the track to be skipped
+    skipped: bool,
+    /// The error that reading the audio failed with
+    error: Option<Error>
+}
+
+/// A track's audio as it's downloaded, reporting progress as it's read.
+///
+/// Progress is reported after every `chunk_size` bytes. If the callback asks
+/// for the track to be skipped or the track runs out of time, reading fails
+/// from then on, and the reason is left in `outcome`.
+struct AudioStream<'s, R, F> {
+    inner: R,
+    chunk_size: u64,
+    /// The number of bytes read since progress was last reported
+    unreported: u64,
+    deadline: Option<TrackDeadline>,
+    track: &'s Track,
+    progress: &'s mut ProgressTracker,
+    outcome: &'s RefCell<StreamOutcome>,
+    cb: &'s F
+}
+
+impl<'s, R: Read, F: Fn(TracksAudioZestingEvent) -> DownloadControl> AudioStream<'s, R, F> {
+    fn report_progress(&mut self) -> std::io::Result<()> {
+        self.unreported = 0;
+        let control = (self.cb)(TracksAudioZestingEvent::TrackDownloadProgress {
+            track_info: self.track,
+            progress: self.progress.snapshot()
+        });
+
+        if control == DownloadControl::SkipTrack {
+            self.outcome.borrow_mut().skipped = true;
+            return Err(self.interrupted("the track was skipped".to_string()));
+        }
+        Ok(())
+    }
+
+    fn fail(&mut self, err: Error) -> std::io::Error {
+        let interrupted = self.interrupted(err.to_string());
+        self.outcome.borrow_mut().error = Some(err);
+        interrupted
+    }
+
+    fn interrupted(&self, reason: String) -> std::io::Error {
+        std::io::Error::other(reader::DownloadInterrupted(reason))
+    }
+}
+
+impl<'s, R: Read, F: Fn(TracksAudioZestingEvent) -> DownloadControl> Read for AudioStream<'s, R, F> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let stopped = {
+            let outcome = self.outcome.borrow();
+            outcome.skipped || outcome.error.is_some()
+        };
+        if stopped {
+            return Err(self.interrupted("the download was stopped".to_string()));
+        }
+
+        if let Err(e) = self.deadline.map_or(Ok(()), TrackDeadline::check) {
+            return Err(self.fail(e));
+        }
+
+        match self.inner.read(buf) {
+            Ok(0) => {
+                // report whatever's left over from the last chunk
+                if self.unreported > 0 {
+                    self.report_progress()?;
+                }
+                Ok(0)
+            },
+            Ok(read) => {
+                self.progress.add_bytes(read as u64);
+                self.unreported += read as u64;
+                if self.unreported >= self.chunk_size {
+                    self.report_progress()?;
+                }
+                Ok(read)
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => Err(e),
+            Err(e) => Err(self.fail(e.into()))
+        }
+    }
+}
+
+/// Download the audio of a track that's only available over HLS, reporting
+/// progress after each segment.
+///
+/// If a segment fails to download, the download is left in `resume` along with
+/// the index of the track so that retrying the track picks up from the failed
+/// segment. Each segment that downloads successfully resets the retrier, so
+/// retries are counted per segment rather than for the whole track.
+///
+/// `None` is returned if the callback asks for the track to be skipped.
+#[allow(clippy::too_many_arguments)]
+fn read_hls_audio<F: Fn(TracksAudioZestingEvent) -> DownloadControl>(
+    zester: &Zester,
+    media_url: &str,
+    index: usize,
+    deadline: Option<TrackDeadline>,
+    resume: &mut Option<(usize, HlsDownload)>,
+    track: &Track,
+    retrier: &mut Retrier,
+    progress: &mut ProgressTracker,
+    cb: &F
+) -> Result<Option<Vec<u8>>, Error> {
+    let mut download = match resume.take() {
+        Some((resume_index, download)) if resume_index == index => download,
+        _ => {
+            progress.start_track(track, None);
+            HlsDownload::start(zester, media_url)?
+        }
+    };
+
+    let mut timed_out = false;
+    let result = download.download_remaining(zester, |bytes| {
+        if deadline.is_some_and(TrackDeadline::has_passed) {
+            timed_out = true;
+            return false;
+        }
+
+        retrier.reset();
+        progress.add_bytes(bytes);
+        let control = cb(TracksAudioZestingEvent::TrackDownloadProgress {
+            track_info: track,
+            progress: progress.snapshot()
+        });
+        control == DownloadControl::Continue
+    });
+
+    match result {
+        Ok(true) => Ok(Some(download.into_audio(&track.download_transcoding()?.format.mime_type))),
+        Ok(false) if timed_out => deadline.map_or(Ok(()), TrackDeadline::check).map(|_| None),
+        Ok(false) => Ok(None),
+        Err(e) => {
+            *resume = Some((index, download));
+            Err(e)
         }
     }
 }
 
 /// Returns true if the given HTTP status code is a 500
 fn is_500(code: u16) -> bool {
-    code >= 500 && code < 600
+    (500..600).contains(&code)
 }
 
 /// The `Zester` provides the functionality to "zest" SoundCloud for data once
@@ -104,10 +871,21 @@ fn is_500(code: u16) -> bool {
 /// 
 /// Documentation on methods that mentions "the user" is referring to the user
 /// whose credentials are provided when creating the struct.
+///
+/// Request behavior (such as timeouts) can be tuned through `config`.
 pub struct Zester {
-    oauth_token: String,
+    oauth_token: RwLock<Option<String>>,
     client_id: String,
-    pub me: Option<Me>
+    pub me: Option<Me>,
+    pub config: ZesterConfig,
+    throttle: Arc<Throttle>,
+    progress: ProgressHandle,
+    user_id: Option<UserId>,
+    credentials: Option<Box<dyn CredentialsProvider>>,
+    middleware: Vec<Box<dyn Middleware>>,
+    metrics: Option<Arc<dyn Metrics>>,
+    transport: Option<Box<dyn Transport>>,
+    track_cache: Mutex<HashMap<TrackId, Track>>
 }
 
 impl Zester {
@@ -119,7 +897,27 @@ impl Zester {
         query_params: &[(&str, &str)],
         add_client_id: bool
     ) -> Result<String, Error> {
-        let mut r = ureq::get(path);
+        self.api_req_method("GET", path, query_params, add_client_id, None)
+    }
+
+    // Same as `api_req_full`, but with the given HTTP method and optionally a
+    // JSON request body
+    fn api_req_method(
+        &self,
+        method: &str,
+        path: &str,
+        query_params: &[(&str, &str)],
+        add_client_id: bool,
+        json_body: Option<&str>
+    ) -> Result<String, Error> {
+        let with_context = |err: Error| {
+            err.with_context(ErrorContext::new(path.split('?').next().unwrap_or(path), query_params, add_client_id))
+        };
+
+        let mut r = ureq::request(method, path);
+        if json_body.is_some() {
+            r.set("Content-Type", "application/json");
+        }
 
         for param in query_params {
             r.query(param.0, param.1);
@@ -128,16 +926,199 @@ impl Zester {
         if add_client_id {
             r.query("client_id", &self.client_id);
         }
-        r.set("Authorization", &format!("OAuth {}", &self.oauth_token));
-        r.timeout_connect(10_000); // 10 second timeout
 
-        let resp = r.call();
+        if let Some(locale) = &self.config.locale {
+            r.query("app_locale", locale);
+            r.set("Accept-Language", locale);
+        }
+
+        loop {
+            let token = self.oauth_token.read().unwrap_or_else(|e| e.into_inner()).clone();
+            if let Some(token) = &token {
+                r.set("Authorization", &format!("OAuth {}", token));
+            }
+
+            let mut body = String::new();
+            match self.call_with_body(&mut r, json_body) {
+                Ok((mut reader, _)) => {
+                    // the connection breaking partway through the response is
+                    // a network error like any other
+                    reader.read_to_string(&mut body).map_err(|e| match Error::from(e) {
+                        Error::IoError(e) => with_context(Error::Network { url: path.to_string(), message: e.to_string(), context: None }),
+                        e => with_context(e)
+                    })?;
+                    return Ok(body);
+                },
+                // The token was rejected; give the credentials provider a
+                // chance to supply a new one before failing
+                Err(e) if e.status() == Some(401) && self.credentials.is_some() => {
+                    let new_token = match self.credentials
+                        .as_ref()
+                        .and_then(|c| c.refresh_token(token.as_deref().unwrap_or("")))
+                    {
+                        Some(new_token) => new_token,
+                        None => return Err(with_context(e))
+                    };
+                    *self.oauth_token.write().unwrap_or_else(|e| e.into_inner()) = Some(new_token);
+                },
+                Err(e) => return Err(with_context(e))
+            }
+        }
+    }
+
+    /// Set the provider that is asked for a new OAuth token when the server
+    /// rejects the current one.
+    ///
+    /// Requests that fail because the token expired are retried with the new
+    /// token rather than failing the zest.
+    pub fn set_credentials_provider<C: CredentialsProvider + 'static>(&mut self, provider: C) {
+        self.credentials = Some(Box::new(provider));
+    }
+
+    /// Add middleware that will see every request this `Zester` sends and
+    /// every response it receives.
+    ///
+    /// Middleware is run in the order it was added.
+    pub fn add_middleware<M: Middleware + 'static>(&mut self, middleware: M) {
+        self.middleware.push(Box::new(middleware));
+    }
+
+    /// Set the metrics that this `Zester` reports its requests, downloaded
+    /// bytes, and retries into.
+    pub fn set_metrics<M: Metrics + 'static>(&mut self, metrics: M) {
+        self.metrics = Some(Arc::new(metrics));
+    }
+
+    /// Set the transport that answers this `Zester`'s requests in place of the
+    /// network.
+    pub fn set_transport<T: Transport + 'static>(&mut self, transport: T) {
+        self.transport = Some(Box::new(transport));
+    }
+
+    /// A handle for checking on all of the downloads this `Zester` is making,
+    /// including ones made by zests running at the same time on other
+    /// threads.
+    ///
+    /// The handle can be queried at any time (for example from a UI thread),
+    /// rather than only when a progress event arrives.
+    pub fn progress_handle(&self) -> ProgressHandle {
+        self.progress.clone()
+    }
+
+    /// Forget the track information cached while completing playlists (see
+    /// `ZesterConfig::cache_track_info`).
+    pub fn clear_track_cache(&self) {
+        self.track_cache.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+
+    // Look up a track in the cache
+    pub(crate) fn cached_track(&self, id: TrackId) -> Option<Track> {
+        if !self.config.cache_track_info {
+            return None;
+        }
+
+        self.track_cache.lock().unwrap_or_else(|e| e.into_inner()).get(&id).cloned()
+    }
+
+    // Add a track to the cache
+    pub(crate) fn cache_track(&self, track: &Track) {
+        if let (true, Some(id)) = (self.config.cache_track_info, track.id) {
+            self.track_cache.lock().unwrap_or_else(|e| e.into_inner()).insert(id, track.clone());
+        }
+    }
+
+    /// Send the given request with the configured timeouts applied, returning
+    /// the response if the request succeeded.
+    pub(crate) fn send(&self, r: &mut ureq::Request) -> Result<ureq::Response, Error> {
+        self.send_with_body(r, None)
+    }
+
+    /// Same as `send`, but sends the given string as the body of the request.
+    pub(crate) fn send_with_body(&self, r: &mut ureq::Request, body: Option<&str>) -> Result<ureq::Response, Error> {
+        r.timeout_connect(self.config.connect_timeout.as_millis() as u64);
+        if let Some(read_timeout) = self.config.read_timeout {
+            r.timeout_read(read_timeout.as_millis() as u64);
+        }
+
+        for m in &self.middleware {
+            m.on_request(r);
+        }
+
+        let start = Instant::now();
+        let resp = match body {
+            Some(body) => r.send_string(body),
+            None => r.call()
+        };
+        if let Some(metrics) = &self.metrics {
+            let endpoint = metrics::endpoint(r.get_url());
+            metrics.request(&endpoint, resp.status(), start.elapsed());
+            if !resp.synthetic() {
+                metrics.response(&endpoint, &ResponseMetadata::from_response(&resp));
+            }
+        }
+
+        for m in &self.middleware {
+            m.on_response(r, &resp);
+        }
 
         if resp.ok() {
-            Ok(resp.into_string()?)
-        } else {
-            Err(Error::HttpError(resp.status()))
+            return Ok(resp);
         }
+
+        // ureq reports failures to get a response at all as responses with
+        // made up statuses
+        let url = r.get_url().to_string();
+        match resp.synthetic_error() {
+            Some(ureq::Error::Io(e)) if matches!(e.kind(), std::io::ErrorKind::TimedOut | std::io::ErrorKind::WouldBlock) => {
+                return Err(Error::Timeout { url: Some(url), context: None });
+            },
+            Some(e) => return Err(Error::Network { url, message: e.to_string(), context: None }),
+            None => {}
+        }
+
+        let status = resp.status();
+        let retry_after = resp
+            .header("Retry-After")
+            .and_then(|s| s.trim().parse().ok())
+            .map(Duration::from_secs);
+        let mut body = vec![];
+        // the body is only for diagnostics, so failing to read it is fine
+        let _ = resp.into_reader().take(MAX_ERROR_BODY_LEN).read_to_end(&mut body);
+
+        Err(Error::from_status(status, url, String::from_utf8_lossy(&body).into_owned(), retry_after))
+    }
+
+    /// Send the given request with the configured timeouts applied, returning
+    /// a reader over the response body if the request succeeded.
+    ///
+    /// This is used for both API requests and media downloads.
+    pub(crate) fn call(&self, r: &mut ureq::Request) -> Result<impl Read, Error> {
+        Ok(self.call_with_len(r)?.0)
+    }
+
+    /// Same as `call`, but also returns the length of the response body if the
+    /// server reported it.
+    pub(crate) fn call_with_len(&self, r: &mut ureq::Request) -> Result<(impl Read, Option<u64>), Error> {
+        self.call_with_body(r, None)
+    }
+
+    /// Same as `call_with_len`, but sends the given string as the body of the
+    /// request.
+    pub(crate) fn call_with_body(&self, r: &mut ureq::Request, body: Option<&str>) -> Result<(impl Read, Option<u64>), Error> {
+        // the request's time starts now, not once its headers have arrived
+        let deadline = self.config.request_timeout.map(|t| Instant::now() + t);
+        let (reader, len): (Box<dyn Read + Send>, _) = match &self.transport {
+            Some(transport) => transport.send(r, body)?,
+            None => {
+                let resp = self.send_with_body(r, body)?;
+                let len = resp.header("Content-Length").and_then(|l| l.parse().ok());
+                (Box::new(resp.into_reader()), len)
+            }
+        };
+
+        let reader = MeteredReader::new(reader, self.metrics.clone(), metrics::endpoint(r.get_url()));
+
+        Ok((DeadlineReader::with_deadline(reader, deadline), len))
     }
 
     // Calls the above but concats with the base URL inside the fn to avoid verbosity
@@ -145,6 +1126,31 @@ impl Zester {
         self.api_req_full(&format!("{}{}", API_BASE, path), query_params, true)
     }
 
+    // Information about the user, used to clamp the number of items requested.
+    //
+    // If the "/me" request was skipped at construction this falls back to
+    // fetching the user's public profile.
+    fn with_me_or_profile<T>(&self, f: impl FnOnce(&Me) -> T) -> Result<T, Error> {
+        match &self.me {
+            Some(me) => Ok(f(me)),
+            None => Ok(f(&self.user_profile(self.my_id()?)?))
+        }
+    }
+
+    // A user's public profile, which has the same shape as "/me" minus the
+    // private fields
+    fn user_profile(&self, user_id: UserId) -> Result<Me, Error> {
+        Ok(serde_json::from_str(&self.api_req(&format!("users/{}", user_id), &[])?)?)
+    }
+
+    fn my_id(&self) -> Result<UserId, Error> {
+        self.require_auth()?;
+        self.me.as_ref()
+            .and_then(|me| me.id)
+            .or(self.user_id)
+            .ok_or_else(|| Error::DataNotPresent("the user's id".into()))
+    }
+
     /// Construct a new `Zester` with the given credentials.
     /// 
     /// This will send a request to the "/me" api route to determine the id of
@@ -153,35 +1159,130 @@ impl Zester {
     /// See the crate README for instructions on how to obtain these credentials.
     pub fn new(oauth_token: String, client_id: String) -> Result<Self, Error> {
         let mut zester = Self {
-            oauth_token,
+            oauth_token: RwLock::new(Some(oauth_token)),
             client_id,
-            me: None
+            me: None,
+            config: ZesterConfig::default(),
+            throttle: Arc::new(Throttle::new()),
+            progress: ProgressHandle::default(),
+            user_id: None,
+            credentials: None,
+            middleware: vec![],
+            metrics: None,
+            transport: None,
+            track_cache: Mutex::new(HashMap::new())
         };
 
         zester.me = Some(zester.me()?);
         Ok(zester)
     }
 
-    /// Get information about the user.
-    pub fn me(&self) -> Result<Me, Error> {
-        let json_string = self.api_req("me", &[])?;
-        Ok(serde_json::from_str(&json_string)?)
+    /// Construct a new `Zester` with the given credentials.
+    ///
+    /// This is the same as `new`, but takes a `Credentials` struct such as the
+    /// one returned by the browser cookie helpers.
+    pub fn from_credentials(credentials: Credentials) -> Result<Self, Error> {
+        Self::new(credentials.oauth_token, credentials.client_id)
     }
 
-    /// Get `num_recent` of the user's liked tracks.
+    /// Construct a new `Zester` for the user with the given id without sending
+    /// any requests.
     ///
-    /// The callback you provide will be called when various events occur,
-    /// allowing you to handle them as you please.
-    pub fn likes<F: Fn(LikesZestingEvent)>(
-        &self,
-        num_recent: u64,
-        cb: F
-    ) -> Result<Likes, Error> {
+    /// Unlike `new`, this doesn't hit the "/me" api route, so `me` will be
+    /// `None` until you fill it in yourself. The zesting methods will look up
+    /// the user's public profile when they need to know how many items exist.
+    pub fn with_user_id(oauth_token: String, client_id: String, user_id: UserId) -> Self {
+        Self {
+            oauth_token: RwLock::new(Some(oauth_token)),
+            client_id,
+            me: None,
+            config: ZesterConfig::default(),
+            throttle: Arc::new(Throttle::new()),
+            progress: ProgressHandle::default(),
+            user_id: Some(user_id),
+            credentials: None,
+            middleware: vec![],
+            metrics: None,
+            transport: None,
+            track_cache: Mutex::new(HashMap::new())
+        }
+    }
+
+    /// Construct a new `Zester` that only has a client ID, without sending any
+    /// requests.
+    ///
+    /// Public data (such as tracks, playlists, and resolving URLs) can be
+    /// fetched without an OAuth token. Methods that act as the user, such as
+    /// `me`, `likes`, and `playlists`, return `Error::NotAuthenticated`.
+    pub fn anonymous(client_id: String) -> Self {
+        Self {
+            oauth_token: RwLock::new(None),
+            client_id,
+            me: None,
+            config: ZesterConfig::default(),
+            throttle: Arc::new(Throttle::new()),
+            progress: ProgressHandle::default(),
+            user_id: None,
+            credentials: None,
+            middleware: vec![],
+            metrics: None,
+            transport: None,
+            track_cache: Mutex::new(HashMap::new())
+        }
+    }
+
+    /// Whether this `Zester` is in anonymous mode (has no OAuth token).
+    pub fn is_anonymous(&self) -> bool {
+        self.oauth_token.read().unwrap_or_else(|e| e.into_inner()).is_none()
+    }
+
+    // Fail with a clear error when a user-scoped method is called anonymously
+    fn require_auth(&self) -> Result<(), Error> {
+        if self.is_anonymous() {
+            Err(Error::NotAuthenticated)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Get information about the user.
+    pub fn me(&self) -> Result<Me, Error> {
+        let _phase = Phase::enter("getting the user's information");
+        self.require_auth()?;
+        let json_string = self.api_req("me", &[])?;
+        Ok(serde_json::from_str(&json_string)?)
+    }
+
+    /// Fetch up-to-date information about the user and store it in `me`.
+    ///
+    /// `me` is only fetched when the `Zester` is constructed, and the zesting
+    /// methods use the counts in it to decide how many items to request. Call
+    /// this before zesting again in a long-running application so that likes
+    /// and playlists added in the meantime aren't missed.
+    pub fn refresh_me(&mut self) -> Result<&Me, Error> {
+        let me = self.me()?;
+        Ok(self.me.insert(me))
+    }
+
+    /// Get `num_recent` of the user's liked tracks.
+    ///
+    /// The callback you provide will be called when various events occur,
+    /// allowing you to handle them as you please.
+    pub fn likes<F: Fn(LikesZestingEvent)>(
+        &self,
+        num_recent: u64,
+        cb: F
+    ) -> Result<Likes, Error> {
         use LikesZestingEvent::*;
 
+        let _phase = Phase::enter("zesting likes");
+
         // Make sure num_recent is a sensible value and return early if we have nothing to do
         // TODO: don't use cached likes count
-        let num_recent = min(num_recent, self.me.as_ref().unwrap().likes_count.unwrap() as u64);
+        let num_recent = match self.with_me_or_profile(|me| me.likes_count)? {
+            Some(likes_count) => min(num_recent, likes_count as u64),
+            None => num_recent
+        };
         cb(NumLikesInfoToDownload { num: num_recent });
         if num_recent == 0 {
             return Ok(Likes { collections: vec![] });
@@ -190,24 +1291,29 @@ impl Zester {
         let mut collections = vec![];
         let mut total_likes_count = 0;
 
-        // If num_recent is small, limit the amount of playlist info we grab in a batch
-        let limit = if num_recent < 500 {
-            num_recent
-        } else {
-            500
+        // If num_recent is small, limit the amount of likes info we grab in a batch
+        let limit = min(num_recent, self.config.page_sizes.likes.max(1)).to_string();
+        let path = format!("users/{}/track_likes", self.my_id()?);
+
+        // the first page is retried after server errors, like the rest
+        let mut retrier = Retrier::new(self);
+        let json_string = loop {
+            match self.api_req(&path, &[("limit", &limit), ("offset", "0"), ("linked_partitioning", "1")]) {
+                Ok(s) => break s,
+                Err(e) => {
+                    if !(e.is_retryable() && retrier.pause(|retry| cb(PausedAfterServerError { retry }))?) {
+                        return Err(e);
+                    }
+                }
+            }
         };
-        let json_string = self.api_req(
-            &format!("users/{}/track_likes", self.me.as_ref().unwrap().id.unwrap()),
-            &[
-                ("limit", &limit.to_string()),
-                ("offset", "0"),
-                ("linked_partitioning", "1")
-            ]
-        )?;
+        retrier.reset();
 
-        let mut likes_raw: LikesRaw = serde_json::from_str(&json_string)?;
-        let likes_count = likes_raw.collection.as_ref().unwrap().len();
-        collections.extend(likes_raw.collection.unwrap().into_iter());
+        // A page without a collection is treated as empty rather than failing the zest
+        let mut likes_raw: LikesRaw = self.parse(&json_string)?;
+        let page = likes_raw.collection.take().unwrap_or_default();
+        let likes_count = page.len();
+        collections.extend(page);
 
         total_likes_count += likes_count;
         cb(MoreLikesInfoDownloaded { count: likes_count as i64 });
@@ -221,21 +1327,18 @@ impl Zester {
 
             let json_string = match self.api_req_full(next_href, &[], true) {
                 Ok(s) => s,
-                Err(Error::HttpError(code)) if is_500(code) => {
-                    // the server responded with an error. waiting a couple of seconds
-                    // and then trying again seems to resolve this, so that's
-                    // what we'll do
-                    // TODO: completely bail out if max retry count reached?
-
-                    cb(PausedAfterServerError { time_secs: PAUSE_SECS });
-                    thread::sleep(Duration::from_secs(PAUSE_SECS));
-                    continue;
-                },
-                Err(e) => return Err(e)
+                Err(e) => {
+                    if e.is_retryable() && retrier.pause(|retry| cb(PausedAfterServerError { retry }))? {
+                        continue;
+                    }
+                    return Err(e);
+                }
             };
+            retrier.reset();
 
-            likes_raw = serde_json::from_str(&json_string)?;
-            let likes_count = likes_raw.collection.as_ref().unwrap().len();
+            likes_raw = self.parse(&json_string)?;
+            let page = likes_raw.collection.take().unwrap_or_default();
+            let likes_count = page.len();
             total_likes_count += likes_count;
 
             // Make sure we don't take more info than we need to
@@ -245,8 +1348,7 @@ impl Zester {
                 likes_count
             };
             collections.extend(
-                likes_raw.collection
-                    .unwrap()
+                page
                     .into_iter()
                     .take(extend_count)
             );
@@ -256,6 +1358,22 @@ impl Zester {
         Ok(Likes { collections })
     }
 
+    /// Get a single page of the user's liked tracks, for applications that
+    /// want to drive pagination themselves.
+    ///
+    /// Pass `None` as the cursor to get the first (most recent) page, or the
+    /// `next_href` of a previously returned page to get the page after it.
+    /// `next_href` is `None` on the last page. Cursors can be stored and used
+    /// to resume later. A cursor that doesn't point at the API is rejected with
+    /// `Error::InvalidCursor`.
+    ///
+    /// Unlike `likes`, requests that fail because of a server error aren't
+    /// retried, leaving it up to you how to back off.
+    pub fn likes_page(&self, cursor: Option<&str>) -> Result<LikesRaw, Error> {
+        let path = format!("users/{}/track_likes", self.my_id()?);
+        self.page(&path, cursor, self.config.page_sizes.likes)
+    }
+
     /// Download the audio files for the given `Likes`.
     ///
     /// The provided callback will be called when various events occur,
@@ -266,23 +1384,57 @@ impl Zester {
     /// best for your use-case.
     ///
     /// `num_recent` specifies the number of recent likes to download.
+    ///
+    /// The returned report summarizes which tracks were downloaded and which
+    /// weren't.
     pub fn likes_audio<F: Fn(TracksAudioZestingEvent)>(
         &self,
         likes: &Likes,
         num_recent: u64,
         cb: F
-    ) -> Result<(), Error> {
+    ) -> Result<ZestReport, Error> {
+        self.likes_audio_filtered(likes, num_recent, |_| true, cb)
+    }
+
+    /// Download the audio files for the tracks in the given `Likes` that the
+    /// user liked during the given period.
+    ///
+    /// `since` is inclusive and `until` is exclusive; `None` leaves that end of
+    /// the period unbounded. Likes whose date is unknown are skipped.
+    ///
+    /// The provided callback will be called when various events occur,
+    /// allowing you to handle them as you please.
+    pub fn likes_audio_between<F: Fn(TracksAudioZestingEvent)>(
+        &self,
+        likes: &Likes,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+        cb: F
+    ) -> Result<ZestReport, Error> {
+        self.tracks_audio(likes.liked_between(since, until).map(|c| &c.track), cb)
+    }
+
+    /// Same as `likes_audio`, but only downloads the audio for tracks that
+    /// `filter` returns true for.
+    ///
+    /// Tracks that are filtered out still count towards `num_recent`.
+    pub fn likes_audio_filtered<P: Fn(&Track) -> bool, F: Fn(TracksAudioZestingEvent)>(
+        &self,
+        likes: &Likes,
+        num_recent: u64,
+        filter: P,
+        cb: F
+    ) -> Result<ZestReport, Error> {
         use TracksAudioZestingEvent::*;
 
         let download_num = min(num_recent as usize, likes.collections.len());
         cb(NumTracksToDownload { num: download_num as u64 });
 
-        self.tracks_audio(
+        self.tracks_audio_filtered(
             likes.collections.iter().map(|c| &c.track).take(download_num),
-            |e| cb(e)
-        )?;
-
-        Ok(())
+            filter,
+            cb
+        )
     }
 
     /// Get `num_recent` of the user's liked and created playlists.
@@ -296,36 +1448,41 @@ impl Zester {
     ) -> Result<Playlists, Error> {
         use PlaylistsZestingEvent::*;
 
+        let _phase = Phase::enter("zesting playlists");
+
         // Make sure num_recent is a sensible value and return early if we have nothing to do
-                // TODO: don't use cached playlist count
-        let num_recent = min(num_recent, self.me.as_ref().unwrap().total_playlist_count() as u64);
+        // TODO: don't use cached playlist count
+        let num_recent = min(num_recent, self.with_me_or_profile(Me::total_playlist_count)? as u64);
         cb(NumPlaylistInfoToDownload { num: num_recent });
         if num_recent == 0 {
             return Ok(Playlists { playlists: vec![] });
         }
 
         let mut playlists_info = vec![];
-        let mut playlists = vec![];
         let mut total_playlists_count = 0;
 
         // If num_recent is small, limit the amount of playlist info we grab in a batch
-        let limit = if num_recent < 50 {
-            num_recent
-        } else {
-            50
+        let limit = min(num_recent, self.config.page_sizes.playlists.max(1)).to_string();
+        let path = format!("users/{}/playlists/liked_and_owned", self.my_id()?);
+
+        // the first page is retried after server errors, like the rest
+        let mut retrier = Retrier::new(self);
+        let json_string = loop {
+            match self.api_req(&path, &[("limit", &limit), ("offset", "0"), ("linked_partitioning", "1")]) {
+                Ok(s) => break s,
+                Err(e) => {
+                    if !(e.is_retryable() && retrier.pause(|retry| cb(PausedAfterServerError { retry }))?) {
+                        return Err(e);
+                    }
+                }
+            }
         };
-        let json_string = self.api_req(
-            &format!("users/{}/playlists/liked_and_owned", self.me.as_ref().unwrap().id.unwrap()),
-            &[
-                ("limit", &limit.to_string()),
-                ("offset", "0"),
-                ("linked_partitioning", "1")
-            ]
-        )?;
+        retrier.reset();
 
-        let mut playlists_raw: PlaylistsRaw = serde_json::from_str(&json_string)?;
-        let mut playlists_count = playlists_raw.collection.as_ref().unwrap().len();
-        playlists_info.extend(playlists_raw.collection.unwrap().into_iter());
+        let mut playlists_raw: PlaylistsRaw = self.parse(&json_string)?;
+        let page = playlists_raw.collection.take().unwrap_or_default();
+        let mut playlists_count = page.len();
+        playlists_info.extend(page);
 
         total_playlists_count += playlists_count;
         cb(MorePlaylistMetaInfoDownloaded { count: playlists_count as i64});
@@ -338,21 +1495,19 @@ impl Zester {
 
             let json_string = match self.api_req_full(next_href, &[], true) {
                 Ok(s) => s,
-                Err(Error::HttpError(code)) if code >= 500 && code < 600 => {
-                    // the server responded with an error. waiting a couple of seconds
-                    // and then trying again seems to resolve this, so that's
-                    // what we'll do
-                    cb(PausedAfterServerError { time_secs: PAUSE_SECS });
-                    thread::sleep(Duration::from_secs(PAUSE_SECS));
-
-                    continue;
-                },
-                Err(e) => return Err(e)
+                Err(e) => {
+                    if e.is_retryable() && retrier.pause(|retry| cb(PausedAfterServerError { retry }))? {
+                        continue;
+                    }
+                    return Err(e);
+                }
             };
+            retrier.reset();
 
-            playlists_raw = serde_json::from_str(&json_string)?;
+            playlists_raw = self.parse(&json_string)?;
 
-            playlists_count = playlists_raw.collection.as_ref().unwrap().len();
+            let page = playlists_raw.collection.take().unwrap_or_default();
+            playlists_count = page.len();
             total_playlists_count += playlists_count;
 
             // Make sure we don't take more info than we need to 
@@ -361,7 +1516,7 @@ impl Zester {
             } else {
                 playlists_count
             };
-            playlists_info.extend(playlists_raw.collection.unwrap().into_iter().take(extend_count));
+            playlists_info.extend(page.into_iter().take(extend_count));
 
             cb(MorePlaylistMetaInfoDownloaded { count: extend_count as i64 });
         }
@@ -370,50 +1525,122 @@ impl Zester {
         
         // now we need to get the full information about all the playlists, which
         // is what we're actually returning
-        retry_loop(playlists_info.iter().take(num_recent as usize), |c| {
-            let pmeta = c.playlist.as_ref().unwrap();
-            cb(StartPlaylistInfoDownload { playlist_meta: &pmeta });
-
-            // TODO: don't unwrap
-            let uri = pmeta.uri.as_ref().unwrap();
-            match self.api_req_full(&uri.replace("api.", "api-v2."), &[("representation", "full")], true) {
-                Ok(s) => {
-                    let mut playlist: Playlist = match serde_json::from_str(&s) {
-                        Ok(p) => p,
-                        Err(e) => {
-                            cb(PlaylistInfoDownloadError { playlist_meta: &pmeta, err: Error::from(e) });
-                            return LoopControl::Next;
-                        }
-                    };
+        let mut pending = VecDeque::new();
+        for (index, c) in playlists_info.iter().take(num_recent as usize).enumerate() {
+            let pmeta = match c.playlist.as_ref() {
+                Some(pmeta) => pmeta,
+                None => {
+                    cb(PlaylistMetaNotPresent);
+                    continue;
+                }
+            };
+
+            match pmeta.uri.as_ref() {
+                Some(uri) => pending.push_back((index, pmeta, uri.replace("api.", "api-v2."))),
+                None => cb(PlaylistInfoDownloadError {
+                    playlist_meta: pmeta,
+                    err: Error::DataNotPresent("playlist uri".into())
+                })
+            }
+        }
 
-                    // Make sure the track information is complete
-                    if let Err(e) = playlist.complete_tracks_info(self) {
-                        cb(PlaylistInfoCompletionError { playlist_meta: &pmeta, err: e });
+        // Playlists are fetched on worker threads, while events, retries, and
+        // completing the playlists' track info are dealt with here so that
+        // the callback doesn't need to be thread-safe. The events for each
+        // playlist still arrive in order, starting with
+        // `StartPlaylistInfoDownload` and ending with
+        // `FinishPlaylistInfoDownload` (or an error).
+        let workers = self.config.playlist_info_concurrency.max(1);
+        let mut fetched: Vec<Option<Playlist>> = playlists_info.iter().map(|_| None).collect();
+        thread::scope(|s| -> Result<(), Error> {
+            let (tx, rx) = mpsc::channel();
+            let mut in_flight = 0;
+            let mut started = HashSet::new();
+
+            loop {
+                while in_flight < workers {
+                    let (index, pmeta, url) = match pending.pop_front() {
+                        Some(next) => next,
+                        None => break
                     };
-                    playlists.push(playlist);
-        
-                    cb(FinishPlaylistInfoDownload { playlist_meta: &pmeta });
-                    LoopControl::Next
-                },
-                Err(Error::HttpError(code)) if is_500(code) => {
-                    // the server responded with an error. waiting a couple of seconds
-                    // and then trying again seems to resolve this, so that's
-                    // what we'll do
-
-                    cb(PausedAfterServerError { time_secs: PAUSE_SECS });
-                    thread::sleep(Duration::from_secs(PAUSE_SECS));
-                    LoopControl::Retry
-                },
-                Err(e) => {
-                    cb(PlaylistInfoDownloadError { playlist_meta: &pmeta, err: e });
-                    LoopControl::Next
+
+                    // retried playlists have already been started
+                    if started.insert(index) {
+                        cb(StartPlaylistInfoDownload { playlist_meta: pmeta });
+                    }
+                    let tx = tx.clone();
+                    let phase = Phase::current();
+                    s.spawn(move || {
+                        let _phase = phase.map(Phase::enter);
+                        let result = catch_worker_panic("playlist info", || {
+                            self.api_req_full(&url, &[("representation", "full")], true)
+                        });
+                        let _ = tx.send((index, pmeta, url, result));
+                    });
+                    in_flight += 1;
+                }
+
+                if in_flight == 0 {
+                    return Ok(());
                 }
+
+                let (index, pmeta, url, result) = rx.recv().expect("playlist info worker exited without sending");
+                in_flight -= 1;
+                let json_string = match result {
+                    Ok(s) => {
+                        retrier.reset();
+                        s
+                    },
+                    Err(e) => {
+                        if e.is_retryable() && retrier.pause(|retry| cb(PausedAfterServerError { retry }))? {
+                            pending.push_front((index, pmeta, url));
+                            continue;
+                        }
+
+                        retrier.reset();
+                        cb(PlaylistInfoDownloadError { playlist_meta: pmeta, err: e });
+                        continue;
+                    }
+                };
+
+                let mut playlist: Playlist = match self.parse(&json_string) {
+                    Ok(p) => p,
+                    Err(e) => {
+                        cb(PlaylistInfoDownloadError { playlist_meta: pmeta, err: e });
+                        continue;
+                    }
+                };
+
+                // Make sure the track information is complete
+                let completion_cb = |e| cb(TracksInfoCompletion(e, pmeta));
+                match playlist.complete_tracks_info_with(self, &mut retrier, completion_cb) {
+                    Ok(()) => {},
+                    Err(e @ Error::RetryBudgetExhausted { .. }) => return Err(e),
+                    Err(e) => cb(PlaylistInfoCompletionError { playlist_meta: pmeta, err: e })
+                }
+                fetched[index] = Some(playlist);
+
+                cb(FinishPlaylistInfoDownload { playlist_meta: pmeta });
             }
-        });
+        })?;
+
+        // keep the playlists in the order they were listed in
+        let playlists = fetched.into_iter().flatten().collect();
 
         Ok(Playlists { playlists })
     }
 
+    /// Get a single page of the user's liked and owned playlists, for
+    /// applications that want to drive pagination themselves.
+    ///
+    /// The returned playlists only contain "meta"-data; use `playlist` to get
+    /// the full information about one. Cursors work the same way as for
+    /// `likes_page`.
+    pub fn playlists_page(&self, cursor: Option<&str>) -> Result<PlaylistsRaw, Error> {
+        let path = format!("users/{}/playlists/liked_and_owned", self.my_id()?);
+        self.page(&path, cursor, self.config.page_sizes.playlists)
+    }
+
     /// Download the the audio files for each playlist in the given iterator.
     ///
     /// The optionally-provided callback will be called when various events occur,
@@ -422,36 +1649,61 @@ impl Zester {
     /// Of particular note, one of the events the callback will hand you gives
     /// you access to the downloaded audio data for you to use however works
     /// best for your use-case.
+    ///
+    /// The returned report summarizes the outcome for the tracks of all of the
+    /// playlists combined.
     pub fn playlists_audio<'a, I, F>(
         &self,
         playlists: I,
         cb: F
-    ) -> Result<(), Error> where
+    ) -> Result<ZestReport, Error> where
         I: Iterator<Item = &'a Playlist>,
         F: Fn(PlaylistsAudioZestingEvent)
+    {
+        self.playlists_audio_filtered(playlists, |_| true, cb)
+    }
+
+    /// Same as `playlists_audio`, but only downloads the audio for tracks that
+    /// `filter` returns true for.
+    pub fn playlists_audio_filtered<'a, I, P, F>(
+        &self,
+        playlists: I,
+        filter: P,
+        cb: F
+    ) -> Result<ZestReport, Error> where
+        I: Iterator<Item = &'a Playlist>,
+        P: Fn(&Track) -> bool,
+        F: Fn(PlaylistsAudioZestingEvent)
     {
         use PlaylistsAudioZestingEvent::*;
         
         let playlist_refs: Vec<_> = playlists.collect();
-        let tracks_num = playlist_refs.iter().map(|p| p.tracks.as_ref().unwrap().len() as u64).sum();
+        let tracks_num = playlist_refs.iter().map(|p| p.tracks.as_ref().map_or(0, |t| t.len()) as u64).sum();
         cb(NumItemsToDownload { playlists_num: playlist_refs.len() as u64, tracks_num });
     
+        let mut report = ZestReport::default();
+        let mut retrier = Retrier::new(self);
         let mut playlists_iter = playlist_refs.into_iter();
         let mut maybe_playlist = playlists_iter.next();
 
         while let Some(playlist_info) = maybe_playlist.as_ref() {
             cb(StartPlaylistDownload { playlist_info });
 
-            self.tracks_audio(
-                playlist_info.tracks.as_ref().unwrap().iter(),
-                |e| cb(TrackEvent(e, playlist_info))
-            )?;
+            report.merge(self.tracks_audio_with(
+                playlist_info.tracks.iter().flatten(),
+                &filter,
+                &mut retrier,
+                |e| {
+                    cb(PlaylistsAudioZestingEvent::track_event(e, playlist_info));
+                    DownloadControl::Continue
+                }
+            )?);
 
             cb(FinishPlaylistDownload { playlist_info });
             maybe_playlist = playlists_iter.next();
         }
     
-        Ok(())
+        Ok(report)
     }
 
     /// Download the audio files for each track in the given iterator.
@@ -461,46 +1713,341 @@ impl Zester {
     ///
     /// Of particular note, one of the events the callback will hand you gives
     /// you access to the downloaded audio data for you to use however works
-    /// best for your use-case.
+    /// best for your use-case. The audio is streamed to you as it downloads,
+    /// with the progress of the download (including the transfer speed and
+    /// estimated time remaining) reported as you read it, so it isn't held in
+    /// memory unless you hold it there. The exceptions are tracks that are
+    /// only available over HLS (whose segments have to be put back together
+    /// first) and downloads that are verified (see
+    /// `ZesterConfig::verify_downloads`), which are read into memory before
+    /// the event occurs.
+    ///
+    /// Failures to download individual tracks don't cause this to return an
+    /// error; instead, they're recorded in the returned report.
     pub fn tracks_audio<'a, I: Iterator<Item = &'a Track>, F: Fn(TracksAudioZestingEvent)>(
         &self,
         tracks: I,
         cb: F
-    ) -> Result<(), Error> {
+    ) -> Result<ZestReport, Error> {
+        self.tracks_audio_filtered(tracks, |_| true, cb)
+    }
+
+    /// Same as `tracks_audio`, but only downloads the audio for tracks that
+    /// `filter` returns true for.
+    ///
+    /// Tracks that are filtered out are recorded as skipped in the returned
+    /// report.
+    pub fn tracks_audio_filtered<'a, I, P, F>(
+        &self,
+        tracks: I,
+        filter: P,
+        cb: F
+    ) -> Result<ZestReport, Error> where
+        I: Iterator<Item = &'a Track>,
+        P: Fn(&Track) -> bool,
+        F: Fn(TracksAudioZestingEvent)
+    {
+        self.tracks_audio_with(tracks, filter, &mut Retrier::new(self), |e| {
+            cb(e);
+            DownloadControl::Continue
+        })
+    }
+
+    /// Same as `tracks_audio`, but the callback decides whether each download
+    /// carries on.
+    ///
+    /// Returning `DownloadControl::SkipTrack` from the callback for a
+    /// `StartTrackDownload` or `TrackDownloadProgress` event stops downloading
+    /// that track and moves on to the next one, so a UI can let the user skip
+    /// a huge track without giving up on the rest. Skipped tracks are recorded
+    /// as such in the returned report, and reported with a `TrackSkipped`
+    /// event.
+    pub fn tracks_audio_controlled<'a, I, F>(
+        &self,
+        tracks: I,
+        cb: F
+    ) -> Result<ZestReport, Error> where
+        I: Iterator<Item = &'a Track>,
+        F: Fn(TracksAudioZestingEvent) -> DownloadControl
+    {
+        self.tracks_audio_with(tracks, |_| true, &mut Retrier::new(self), cb)
+    }
+
+    // Implementation of `tracks_audio_filtered` and `tracks_audio_controlled`,
+    // counting retries with the given retrier
+    fn tracks_audio_with<'a, I, P, F>(
+        &self,
+        tracks: I,
+        filter: P,
+        retrier: &mut Retrier,
+        cb: F
+    ) -> Result<ZestReport, Error> where
+        I: Iterator<Item = &'a Track>,
+        P: Fn(&Track) -> bool,
+        F: Fn(TracksAudioZestingEvent) -> DownloadControl
+    {
         use TracksAudioZestingEvent::*;
 
-        let track_refs: Vec<_> = tracks.collect();
+        let _phase = Phase::enter("downloading track audio");
+
+        let mut report = ZestReport::default();
+        let mut track_refs = vec![];
+        for track in tracks {
+            if !filter(track) {
+                report.record_skipped(track, SkipReason::Filtered);
+                cb(SkippedByFilter { track_info: track });
+            } else if track.is_drm_protected() {
+                report.record_skipped(track, SkipReason::DrmProtected);
+                cb(SkippedDrmProtected { track_info: track });
+            } else {
+                track_refs.push(track);
+            }
+        }
+
         cb(NumTracksToDownload { num: track_refs.len() as u64 });
+        let estimated_bytes = track_refs.iter().map(|t| t.estimated_size().unwrap_or(0)).sum();
+        if self.config.estimate_download_size {
+            cb(NumBytesToDownload { num: estimated_bytes });
+        }
 
-        retry_loop(track_refs.into_iter(), |track| {
-            cb(StartTrackDownload { track_info: &track });
+        let mut progress = ProgressTracker::new(track_refs.len() as u64, estimated_bytes, self.progress.clone());
+        let mut verification_retries = 0;
+        thread::scope(|s| {
+            // The next track's media URL is resolved in the background while the
+            // current track downloads, so the request overlaps with the transfer
+            let mut prefetched: Option<(usize, thread::ScopedJoinHandle<Result<String, Error>>)> = None;
+            // An HLS download whose last segment failed, kept so that retrying
+            // the track doesn't download the other segments again
+            let mut hls_download = None;
+            // When the current track's first download attempt started, so that
+            // retries count towards `ZesterConfig::track_timeout`
+            let mut track_started = None;
+
+            retry_loop(0..track_refs.len(), |i| {
+                let track = track_refs[i];
+                let started = match track_started {
+                    Some((index, started)) if index == i => started,
+                    _ => track_started.insert((i, Instant::now())).1
+                };
+                let deadline = self.config.track_timeout.map(|timeout| TrackDeadline { timeout, at: started + timeout });
+                let skip = |report: &mut ZestReport, progress: &mut ProgressTracker| {
+                    report.record_skipped(track, SkipReason::Cancelled);
+                    progress.finish_track();
+                    cb(TrackSkipped { track_info: track });
+                };
+
+                if cb(StartTrackDownload { track_info: track }) == DownloadControl::SkipTrack {
+                    skip(&mut report, &mut progress);
+                    return LoopControl::Next;
+                }
 
-            match track.download(self) {
-                Ok(r) => {
-                    cb(FinishTrackDownload { track_info: track, track_data: Box::new(r) });
-                    LoopControl::Next
-                },
-                Err(Error::HttpError(code)) if is_500(code) => {
-                    // the server responded with an error. waiting a couple of seconds
-                    // and then trying again seems to resolve this, so that's
-                    // what we'll do
-
-                    cb(PausedAfterServerError { time_secs: PAUSE_SECS });
-                    thread::sleep(Duration::from_secs(PAUSE_SECS));
-                    LoopControl::Retry
-                },
-                Err(e) => {
-                    cb(TrackDownloadError { track_info: track, err: e });
-                    LoopControl::Next
+                let snippet_only = track.is_snippet_only();
+                if snippet_only && !self.config.download_snippets {
+                    report.record_failed(track, Error::SnippetOnly);
+                    progress.finish_track();
+                    cb(TrackDownloadError { track_info: track, err: Error::SnippetOnly });
+                    return LoopControl::Next;
                 }
-            }
-        });
 
-        Ok(())
+                let (media_url, was_prefetched) = match prefetched.take() {
+                    Some((index, handle)) if index == i => {
+                        let media_url = handle.join().unwrap_or(Err(Error::WorkerPanicked(PREFETCH_WORKER)));
+                        (media_url, true)
+                    },
+                    other => {
+                        // a URL prefetched for a track that was skipped is no
+                        // use any more
+                        prefetched = other.filter(|(index, _)| *index > i);
+                        (track.media_url(self), false)
+                    }
+                };
+                if let (None, true, Some(&next)) = (&prefetched, self.config.prefetch_media_urls, track_refs.get(i + 1)) {
+                    let phase = Phase::current();
+                    prefetched = Some((i + 1, s.spawn(move || {
+                        let _phase = phase.map(Phase::enter);
+                        // a panic fails the prefetched track rather than the
+                        // whole zest
+                        catch_worker_panic(PREFETCH_WORKER, || next.media_url(self))
+                    })));
+                }
+
+                let file_extension = track.download_transcoding().ok().and_then(|t| t.format.file_extension());
+                let result = if track.downloads_over_hls() {
+                    // the segments have to be remuxed, so the audio can't be
+                    // streamed
+                    media_url
+                        .and_then(|url| read_hls_audio(self, &url, i, deadline, &mut hls_download, track, retrier, &mut progress, &cb))
+                        .map(|data| data.map(|data| {
+                            let len = Some(data.len() as u64);
+                            TrackAudio::Buffered(data, len)
+                        }))
+                } else {
+                    media_url
+                        .and_then(|url| api::download_media(self, &url))
+                        .and_then(|(reader, len)| {
+                            progress.start_track(track, len);
+                            let outcome = RefCell::new(StreamOutcome::default());
+                            let mut stream = AudioStream {
+                                inner: reader,
+                                chunk_size: self.config.download_chunk_size.max(1) as u64,
+                                unreported: 0,
+                                deadline,
+                                track,
+                                progress: &mut progress,
+                                outcome: &outcome,
+                                cb: &cb
+                            };
+
+                            if self.config.verify_downloads {
+                                // the audio has to be checked before it's
+                                // handed over
+                                let mut data = vec![];
+                                let read = stream.read_to_end(&mut data);
+                                let outcome = outcome.into_inner();
+                                return match (outcome.skipped, outcome.error, read) {
+                                    (true, _, _) => Ok(None),
+                                    (_, Some(e), _) => Err(e),
+                                    (_, None, Err(e)) => Err(e.into()),
+                                    (_, None, Ok(_)) => Ok(Some(TrackAudio::Buffered(data, len)))
+                                };
+                            }
+
+                            if snippet_only {
+                                cb(SnippetOnlyDownloaded { track_info: track });
+                            }
+                            cb(FinishTrackDownload {
+                                track_info: track,
+                                file_extension,
+                                track_data: Box::new(stream)
+                            });
+                            let outcome = outcome.into_inner();
+                            match (outcome.skipped, outcome.error) {
+                                (true, _) => Ok(None),
+                                (_, Some(e)) => Err(e),
+                                (_, None) => Ok(Some(TrackAudio::Streamed))
+                            }
+                        })
+                };
+
+                match result {
+                    Ok(None) => {
+                        retrier.reset();
+                        verification_retries = 0;
+                        hls_download = None;
+                        skip(&mut report, &mut progress);
+                        LoopControl::Next
+                    },
+                    Ok(Some(TrackAudio::Streamed)) => {
+                        retrier.reset();
+                        verification_retries = 0;
+                        report.record_downloaded(track);
+                        progress.finish_track();
+                        if snippet_only {
+                            report.record_snippet(track);
+                        }
+                        LoopControl::Next
+                    },
+                    Ok(Some(TrackAudio::Buffered(data, len))) => {
+                        if self.config.verify_downloads {
+                            let mime_type = track.download_transcoding().ok().map(|t| t.format.mime_type.as_str());
+
+                            if let Err(reason) = verify::verify_audio(&data, len, mime_type) {
+                                cb(TrackVerificationFailed { track_info: track, reason: reason.clone() });
+                                let out_of_time = deadline.is_some_and(TrackDeadline::has_passed);
+                                if verification_retries < self.config.max_verification_retries && !out_of_time {
+                                    verification_retries += 1;
+                                    return LoopControl::Retry;
+                                }
+
+                                verification_retries = 0;
+                                let err = Error::VerificationFailed(reason);
+                                report.record_failed(track, err.clone());
+                                progress.finish_track();
+                                cb(TrackDownloadError { track_info: track, err });
+                                return LoopControl::Next;
+                            }
+                        }
+
+                        retrier.reset();
+                        verification_retries = 0;
+                        report.record_downloaded(track);
+                        progress.finish_track();
+                        if snippet_only {
+                            report.record_snippet(track);
+                            cb(SnippetOnlyDownloaded { track_info: track });
+                        }
+                        cb(FinishTrackDownload {
+                            track_info: track,
+                            file_extension,
+                            track_data: Box::new(Cursor::new(data))
+                        });
+                        LoopControl::Next
+                    },
+                    // media URLs expire, and a prefetched one can have expired
+                    // by the time its track's turn comes around. retrying
+                    // resolves the URL again (only once, since the retry's URL
+                    // isn't a prefetched one)
+                    Err(e) if was_prefetched && e.status() == Some(403) => LoopControl::Retry,
+                    Err(e) => {
+                        // a track that's out of time isn't retried, and the
+                        // error that used up its time is the one reported
+                        if e.is_retryable() && !deadline.is_some_and(TrackDeadline::has_passed) {
+                            match retrier.pause(|retry| { cb(PausedAfterServerError { retry }); }) {
+                                Ok(true) => return LoopControl::Retry,
+                                Ok(false) => {},
+                                Err(e) => return LoopControl::Abort(e)
+                            }
+                        }
+
+                        retrier.reset();
+                        verification_retries = 0;
+                        hls_download = None;
+                        report.record_failed(track, e.clone());
+                        progress.finish_track();
+                        cb(TrackDownloadError { track_info: track, err: e });
+                        LoopControl::Next
+                    }
+                }
+            })
+        })?;
+
+        Ok(report)
+    }
+
+    /// Estimate the total number of bytes that downloading the audio for the
+    /// given tracks will take, without downloading anything.
+    ///
+    /// If `precise` is true, a HEAD request is made for each track's audio file
+    /// to determine its exact size (this requires resolving the file's URL
+    /// first, so it takes two requests per track). Otherwise, and for any
+    /// tracks whose size can't be determined that way, the size is estimated
+    /// from the track's duration and bitrate.
+    ///
+    /// Tracks that can't be downloaded at all don't count towards the total.
+    pub fn estimate_download_size<'a, I: Iterator<Item = &'a Track>>(
+        &self,
+        tracks: I,
+        precise: bool
+    ) -> u64 {
+        tracks
+            .map(|track| {
+                let content_length = if precise {
+                    track.media_url(self)
+                        .and_then(|url| self.send(&mut ureq::head(&url)))
+                        .ok()
+                        .and_then(|resp| resp.header("Content-Length").and_then(|l| l.parse().ok()))
+                } else {
+                    None
+                };
+
+                content_length.or_else(|| track.estimated_size()).unwrap_or(0)
+            })
+            .sum()
     }
 
     /// Get information for the specified track IDs.
-    pub fn tracks_info<A: AsRef<[u64]>>(&self, ids: A) -> Result<Vec<Track>, Error> {
+    pub fn tracks_info<A: AsRef<[TrackId]>>(&self, ids: A) -> Result<Vec<Track>, Error> {
+        let _phase = Phase::enter("completing track information");
         let mut ids_string = String::new();
 
         for id in ids.as_ref() {
@@ -509,16 +2056,621 @@ impl Zester {
         }
         ids_string.pop();
 
-        Ok(serde_json::from_str(&self.api_req(
+        self.parse(&self.api_req(
             "tracks",
             &[("ids", &ids_string)]
+        )?)
+    }
+
+    /// Get full information about a single track, ready to be downloaded.
+    pub fn track(&self, id: TrackId) -> Result<Track, Error> {
+        self.parse(&self.api_req(&format!("tracks/{}", id), &[])?)
+    }
+
+    /// Get full information about a single track given its URL (such as
+    /// `https://soundcloud.com/user/track`), ready to be downloaded.
+    pub fn track_by_url(&self, url: &str) -> Result<Track, Error> {
+        let track: Track = self.resolve_parsed(url)?;
+        if track.kind.as_deref() != Some("track") {
+            return Err(Error::DataNotPresent("track at the given url".into()));
+        }
+
+        // resolved tracks usually come with everything we need, but make sure
+        if track.media.is_some() {
+            Ok(track)
+        } else {
+            let id = track.id.ok_or_else(|| Error::DataNotPresent("track id".into()))?;
+            self.track(id)
+        }
+    }
+
+    /// Look up whatever the given SoundCloud URL (such as
+    /// `https://soundcloud.com/user/sets/playlist`) points to.
+    ///
+    /// The returned information may not be complete; use the more specific
+    /// methods to get everything about a given item. Those also keep the raw
+    /// JSON of tracks and playlists (see `ZesterConfig::keep_raw_json`), which
+    /// this doesn't.
+    pub fn resolve<T: DeserializeOwned>(&self, url: &str) -> Result<T, Error> {
+        Ok(serde_json::from_str(&self.api_req("resolve", &[("url", url)])?)?)
+    }
+
+    // Same as `resolve`, but for tracks and playlists, whose raw JSON is kept
+    // if `ZesterConfig::keep_raw_json` is enabled
+    fn resolve_parsed<T: DeserializeOwned + KeepRaw>(&self, url: &str) -> Result<T, Error> {
+        self.parse(&self.api_req("resolve", &[("url", url)])?)
+    }
+
+    /// Get full information about a single playlist, given either its id or
+    /// its URL.
+    ///
+    /// The information about the playlist's tracks is completed (see
+    /// `Playlist::complete_tracks_info`) before it is returned.
+    pub fn playlist(&self, id_or_url: &str) -> Result<Playlist, Error> {
+        let id = match id_or_url.parse::<PlaylistId>() {
+            Ok(id) => id,
+            Err(_) => {
+                let resolved: Playlist = self.resolve_parsed(id_or_url)?;
+                if !matches!(resolved.kind, Some(ref kind) if *kind == PlaylistKind::Playlist || kind.is_album()) {
+                    return Err(Error::DataNotPresent("playlist at the given url".into()));
+                }
+
+                resolved.id.ok_or_else(|| Error::DataNotPresent("playlist id".into()))?
+            }
+        };
+
+        let mut playlist: Playlist = self.parse(&self.api_req(
+            &format!("playlists/{}", id),
+            &[("representation", "full")]
+        )?)?;
+        playlist.complete_tracks_info(self, |_| {})?;
+
+        Ok(playlist)
+    }
+
+    /// Get information about any user, given either their id or their
+    /// permalink (the part of their profile URL after "soundcloud.com/"; a
+    /// full profile URL works too).
+    ///
+    /// This only needs public data, so it works for anonymous `Zester`s.
+    pub fn user(&self, id_or_permalink: &str) -> Result<User, Error> {
+        if let Ok(id) = id_or_permalink.parse::<UserId>() {
+            return Ok(serde_json::from_str(&self.api_req(&format!("users/{}", id), &[])?)?);
+        }
+
+        let url = if id_or_permalink.contains("://") {
+            id_or_permalink.to_string()
+        } else {
+            format!("https://soundcloud.com/{}", id_or_permalink.trim_matches('/'))
+        };
+        let user: User = self.resolve(&url)?;
+        if user.kind.as_deref() != Some("user") {
+            return Err(Error::DataNotPresent("user at the given url".into()));
+        }
+
+        Ok(user)
+    }
+
+    /// Get all of the tracks that the user with the given id has liked.
+    ///
+    /// This is the same as `likes`, but works for any user whose likes are
+    /// public.
+    ///
+    /// The callback you provide will be called when various events occur,
+    /// allowing you to handle them as you please.
+    pub fn user_likes<F: Fn(LikesZestingEvent)>(&self, user_id: UserId, cb: F) -> Result<Likes, Error> {
+        use LikesZestingEvent::*;
+
+        let _phase = Phase::enter("zesting likes");
+
+        let num = self.user_profile(user_id)?.likes_count.unwrap_or(0);
+        cb(NumLikesInfoToDownload { num: num as u64 });
+
+        let collections = self.paginate(
+            &format!("users/{}/track_likes", user_id),
+            &mut Retrier::new(self),
+            |count| cb(MoreLikesInfoDownloaded { count: count as i64 }),
+            |retry| cb(PausedAfterServerError { retry })
+        )?;
+
+        Ok(Likes { collections })
+    }
+
+    /// Get all of the playlists that the user with the given id has created,
+    /// with their tracks' information completed.
+    ///
+    /// The callback you provide will be called when various events occur,
+    /// allowing you to handle them as you please.
+    pub fn user_playlists<F: Fn(PlaylistsZestingEvent)>(&self, user_id: UserId, cb: F) -> Result<Playlists, Error> {
+        self.listed_playlists(&format!("users/{}/playlists", user_id), cb)
+    }
+
+    /// Get all of the albums (including EPs, singles, and compilations) that
+    /// the user has released, with their tracks' information completed.
+    ///
+    /// The callback you provide will be called when various events occur,
+    /// allowing you to handle them as you please.
+    pub fn albums<F: Fn(PlaylistsZestingEvent)>(&self, cb: F) -> Result<Playlists, Error> {
+        self.listed_playlists(&format!("users/{}/albums", self.my_id()?), cb)
+    }
+
+    /// Get the tracks and playlists that the user with the given id has pinned
+    /// to the "spotlight" section of their profile, in order.
+    ///
+    /// Users who haven't set up a spotlight have an empty one.
+    pub fn spotlight(&self, user_id: UserId) -> Result<Vec<SpotlightItem>, Error> {
+        let _phase = Phase::enter("zesting a spotlight");
+        self.paginate(&format!("users/{}/spotlight", user_id), &mut Retrier::new(self), |_| {}, |_| {})
+    }
+
+    /// Get all of the tracks that the user with the given id has uploaded
+    /// (that are visible to this `Zester`).
+    ///
+    /// Combined with `tracks_audio`, this can be used to archive an artist's
+    /// whole catalogue.
+    ///
+    /// If the id is the user's own, this is the same as `my_tracks`.
+    ///
+    /// The callback you provide will be called when various events occur,
+    /// allowing you to handle them as you please.
+    pub fn user_tracks<F: Fn(TracksZestingEvent)>(&self, user_id: UserId, cb: F) -> Result<Vec<Track>, Error> {
+        if self.my_id().ok() == Some(user_id) {
+            return self.my_tracks(cb);
+        }
+
+        let num = self.user_profile(user_id)?.track_count.unwrap_or(0);
+        self.uploaded_tracks(user_id, num, &[], cb)
+    }
+
+    /// Get all of the tracks that the user has uploaded, including their
+    /// private and unlisted ones.
+    ///
+    /// The tracks are requested in the representation SoundCloud shows to
+    /// their owner, so private tracks come with the `secret_token` needed to
+    /// download their audio (which `Track::media_url` passes along).
+    ///
+    /// The callback you provide will be called when various events occur,
+    /// allowing you to handle them as you please.
+    pub fn my_tracks<F: Fn(TracksZestingEvent)>(&self, cb: F) -> Result<Vec<Track>, Error> {
+        let user_id = self.my_id()?;
+        // the user's own track count includes their private tracks
+        let num = self.with_me_or_profile(|me| me.track_count.unwrap_or(0))?;
+        self.uploaded_tracks(user_id, num, &[("representation", "owner")], cb)
+    }
+
+    fn uploaded_tracks<F: Fn(TracksZestingEvent)>(
+        &self,
+        user_id: UserId,
+        num: i64,
+        query_params: &[(&str, &str)],
+        cb: F
+    ) -> Result<Vec<Track>, Error> {
+        use TracksZestingEvent::*;
+
+        let _phase = Phase::enter("zesting tracks");
+
+        cb(NumTracksInfoToDownload { num: num as u64 });
+
+        self.paginate_with_query(
+            &format!("users/{}/tracks", user_id),
+            query_params,
+            &mut Retrier::new(self),
+            |count| cb(MoreTracksInfoDownloaded { count: count as i64 }),
+            |retry| cb(PausedAfterServerError { retry })
+        )
+    }
+
+    /// Get the radio queue that SoundCloud generates for the track with the
+    /// given id (its "station"), made up of similar tracks.
+    ///
+    /// The seed track itself is usually first in the queue.
+    pub fn track_station(&self, track_id: TrackId) -> Result<Vec<Track>, Error> {
+        self.paginate(
+            &format!("stations/soundcloud:track-stations:{}/tracks", track_id),
+            &mut Retrier::new(self),
+            |_| {},
+            |_| {}
+        )
+    }
+
+    /// Get all of the users that the user follows.
+    ///
+    /// The callback you provide will be called when various events occur,
+    /// allowing you to handle them as you please.
+    pub fn followings<F: Fn(UsersZestingEvent)>(&self, cb: F) -> Result<Vec<User>, Error> {
+        let _phase = Phase::enter("zesting followings");
+        self.users(&format!("users/{}/followings", self.my_id()?), cb)
+    }
+
+    /// Follow the user with the given id.
+    ///
+    /// Together with `followings`, this can be used to move the users someone
+    /// follows over to a new account. Following a user that is already
+    /// followed succeeds without doing anything.
+    pub fn follow_user(&self, user_id: UserId) -> Result<(), Error> {
+        self.require_auth()?;
+        self.api_req_method("POST", &format!("{}me/followings/{}", API_BASE, user_id), &[], true, None)?;
+        Ok(())
+    }
+
+    /// Stop following the user with the given id.
+    pub fn unfollow_user(&self, user_id: UserId) -> Result<(), Error> {
+        self.require_auth()?;
+        self.api_req_method("DELETE", &format!("{}me/followings/{}", API_BASE, user_id), &[], true, None)?;
+        Ok(())
+    }
+
+    /// Leave a comment on the track with the given id, at the given position
+    /// in the track (in milliseconds), returning the posted comment.
+    ///
+    /// This can be used to restore comments archived with
+    /// `comments_on_my_tracks` (the `body` and `timestamp` of each `Comment`).
+    pub fn comment_on_track(&self, track_id: TrackId, body: &str, timestamp_ms: i64) -> Result<Comment, Error> {
+        self.require_auth()?;
+        let json_body = serde_json::json!({
+            "comment": { "body": body, "timestamp": timestamp_ms }
+        });
+
+        Ok(serde_json::from_str(&self.api_req_method(
+            "POST",
+            &format!("{}tracks/{}/comments", API_BASE, track_id),
+            &[],
+            true,
+            Some(&json_body.to_string())
         )?)?)
     }
+
+    /// Repost the track with the given id.
+    ///
+    /// Reposting a track that is already reposted succeeds without doing
+    /// anything.
+    pub fn repost_track(&self, track_id: TrackId) -> Result<(), Error> {
+        self.set_reposted("track", track_id.0, true)
+    }
+
+    /// Remove the repost of the track with the given id.
+    pub fn unrepost_track(&self, track_id: TrackId) -> Result<(), Error> {
+        self.set_reposted("track", track_id.0, false)
+    }
+
+    /// Repost the playlist with the given id.
+    ///
+    /// Reposting a playlist that is already reposted succeeds without doing
+    /// anything.
+    pub fn repost_playlist(&self, playlist_id: PlaylistId) -> Result<(), Error> {
+        self.set_reposted("playlist", playlist_id.0, true)
+    }
+
+    /// Remove the repost of the playlist with the given id.
+    pub fn unrepost_playlist(&self, playlist_id: PlaylistId) -> Result<(), Error> {
+        self.set_reposted("playlist", playlist_id.0, false)
+    }
+
+    // `kind` is either "track" or "playlist"
+    fn set_reposted(&self, kind: &str, id: u64, reposted: bool) -> Result<(), Error> {
+        self.require_auth()?;
+        let method = if reposted { "PUT" } else { "DELETE" };
+        self.api_req_method(method, &format!("{}me/{}_reposts/{}", API_BASE, kind, id), &[], true, None)?;
+        Ok(())
+    }
+
+    /// Get the selections of playlists on the user's discover page, including
+    /// their personalized system playlists.
+    pub fn mixed_selections(&self) -> Result<Vec<Selection>, Error> {
+        let _phase = Phase::enter("zesting system playlists");
+        self.require_auth()?;
+        self.paginate("mixed-selections", &mut Retrier::new(self), |_| {}, |_| {})
+    }
+
+    /// Get all of the system playlists (such as weekly mixes) that SoundCloud
+    /// currently has for the user, with their tracks' information completed.
+    ///
+    /// Their contents rotate regularly, so this is useful for keeping
+    /// snapshots of them. Each playlist is only included once, even if it
+    /// appears in multiple selections.
+    pub fn system_playlists(&self) -> Result<Vec<SystemPlaylist>, Error> {
+        let _phase = Phase::enter("zesting system playlists");
+        let mut urns = vec![];
+        for selection in self.mixed_selections()? {
+            for playlist in selection.system_playlists() {
+                if let Some(urn) = playlist.urn.or(playlist.id) {
+                    if !urns.contains(&urn) {
+                        urns.push(urn);
+                    }
+                }
+            }
+        }
+
+        urns.iter().map(|urn| self.system_playlist(urn)).collect()
+    }
+
+    /// Get full information about the system playlist with the given URN
+    /// (such as "soundcloud:system-playlists:weekly:1234").
+    pub fn system_playlist(&self, urn: &str) -> Result<SystemPlaylist, Error> {
+        let _phase = Phase::enter("zesting system playlists");
+        let mut playlist: SystemPlaylist = serde_json::from_str(&self.api_req(&format!("system-playlists/{}", urn), &[])?)?;
+
+        // like other playlists, only some of the tracks come with full info
+        let tracks = playlist.tracks.get_or_insert_with(Vec::new);
+        let ids: Vec<TrackId> = tracks.iter().filter(|t| t.media.is_none()).filter_map(|t| t.id).collect();
+        let mut info = HashMap::new();
+        for batch in ids.chunks(self.config.track_info_batch_size.max(1)) {
+            for track in self.tracks_info(batch)? {
+                if let Some(id) = track.id {
+                    info.insert(id, track);
+                }
+            }
+        }
+        for track in tracks.iter_mut() {
+            if let Some(complete) = track.id.and_then(|id| info.get(&id)) {
+                *track = complete.clone();
+            }
+        }
+
+        Ok(playlist)
+    }
+
+    /// Get all of the users who liked the track with the given id.
+    ///
+    /// The callback you provide will be called when various events occur,
+    /// allowing you to handle them as you please.
+    pub fn track_likers<F: Fn(UsersZestingEvent)>(&self, track_id: TrackId, cb: F) -> Result<Vec<User>, Error> {
+        self.users(&format!("tracks/{}/likers", track_id), cb)
+    }
+
+    /// Get all of the users who reposted the track with the given id.
+    ///
+    /// The callback you provide will be called when various events occur,
+    /// allowing you to handle them as you please.
+    pub fn track_reposters<F: Fn(UsersZestingEvent)>(&self, track_id: TrackId, cb: F) -> Result<Vec<User>, Error> {
+        self.users(&format!("tracks/{}/reposters", track_id), cb)
+    }
+
+    /// Get the comments left on each of the user's own tracks (including
+    /// private ones), keyed by track id.
+    ///
+    /// Tracks whose comments couldn't be downloaded are reported through the
+    /// callback and left out of the results.
+    ///
+    /// The callback you provide will be called when various events occur,
+    /// allowing you to handle them as you please.
+    pub fn comments_on_my_tracks<F: Fn(CommentsZestingEvent)>(&self, cb: F) -> Result<HashMap<TrackId, Vec<Comment>>, Error> {
+        use CommentsZestingEvent::*;
+
+        let _phase = Phase::enter("zesting comments");
+
+        let mut retrier = Retrier::new(self);
+        let on_pause = |retry| cb(PausedAfterServerError { retry });
+        let tracks: Vec<Track> = self.paginate_with_query(
+            &format!("users/{}/tracks", self.my_id()?),
+            &[("representation", "owner")],
+            &mut retrier,
+            |_| {},
+            on_pause
+        )?;
+        cb(NumTracksToDownload { num: tracks.len() as u64 });
+
+        let mut comments = HashMap::new();
+        for track in &tracks {
+            let id = match track.id {
+                Some(id) => id,
+                None => {
+                    cb(TrackCommentsDownloadError {
+                        track_info: track,
+                        err: Error::DataNotPresent("track id".into())
+                    });
+                    continue;
+                }
+            };
+
+            cb(StartTrackCommentsDownload { track_info: track });
+            match self.paginate::<Comment>(&format!("tracks/{}/comments", id), &mut retrier, |_| {}, on_pause) {
+                Ok(track_comments) => {
+                    cb(FinishTrackCommentsDownload { track_info: track, count: track_comments.len() as i64 });
+                    comments.insert(id, track_comments);
+                },
+                Err(err @ Error::RetryBudgetExhausted { .. }) => return Err(err),
+                Err(err) => cb(TrackCommentsDownloadError { track_info: track, err })
+            }
+        }
+
+        Ok(comments)
+    }
+
+    /// Get the plays, likes, downloads, and top listeners of one of the user's
+    /// tracks over the given period.
+    ///
+    /// SoundCloud only keeps detailed stats around for a limited time, so this
+    /// is useful for archiving them before they disappear. Stats are only
+    /// available for tracks the user owns.
+    pub fn track_stats(&self, track_id: TrackId, range: StatsRange) -> Result<TrackStats, Error> {
+        let _phase = Phase::enter("zesting track stats");
+        self.require_auth()?;
+
+        Ok(serde_json::from_str(&self.api_req(
+            &format!("tracks/{}/stats", track_id),
+            &[
+                ("from", &range.since.to_rfc3339()),
+                ("to", &range.until.to_rfc3339()),
+                ("interval", range.interval.as_str())
+            ]
+        )?)?)
+    }
+
+    // Grab every playlist in the list at the given API path, along with its
+    // full information
+    fn listed_playlists<F: Fn(PlaylistsZestingEvent)>(&self, path: &str, cb: F) -> Result<Playlists, Error> {
+        use PlaylistsZestingEvent::*;
+
+        let _phase = Phase::enter("zesting playlists");
+
+        // the lists don't come with a total, so the whole list is fetched
+        // before the number of playlists is reported
+        let mut retrier = Retrier::new(self);
+        let metas: Vec<PlaylistMeta> = self.paginate(path, &mut retrier, |_| {}, |retry| cb(PausedAfterServerError { retry }))?;
+        cb(NumPlaylistInfoToDownload { num: metas.len() as u64 });
+        if !metas.is_empty() {
+            cb(MorePlaylistMetaInfoDownloaded { count: metas.len() as i64 });
+        }
+        cb(FinishPlaylistMetaInfoDownloading);
+
+        // the listed playlists' track information is partial, so the full
+        // information is fetched for each, the same as for `playlists`
+        let mut playlists = vec![];
+        for playlist_meta in &metas {
+            let id = match playlist_meta.id {
+                Some(id) => id,
+                None => {
+                    cb(PlaylistInfoDownloadError {
+                        playlist_meta,
+                        err: Error::DataNotPresent("playlist id".into())
+                    });
+                    continue;
+                }
+            };
+
+            cb(StartPlaylistInfoDownload { playlist_meta });
+            let json_string = loop {
+                match self.api_req(&format!("playlists/{}", id), &[("representation", "full")]) {
+                    Ok(s) => break Ok(s),
+                    Err(e) => {
+                        if !(e.is_retryable() && retrier.pause(|retry| cb(PausedAfterServerError { retry }))?) {
+                            break Err(e);
+                        }
+                    }
+                }
+            };
+            retrier.reset();
+
+            let mut playlist: Playlist = match json_string.and_then(|s| self.parse(&s)) {
+                Ok(p) => p,
+                Err(err) => {
+                    cb(PlaylistInfoDownloadError { playlist_meta, err });
+                    continue;
+                }
+            };
+
+            let completion_cb = |e| cb(TracksInfoCompletion(e, playlist_meta));
+            match playlist.complete_tracks_info_with(self, &mut retrier, completion_cb) {
+                Ok(()) => {},
+                Err(e @ Error::RetryBudgetExhausted { .. }) => return Err(e),
+                Err(e) => cb(PlaylistInfoCompletionError { playlist_meta, err: e })
+            }
+            playlists.push(playlist);
+
+            cb(FinishPlaylistInfoDownload { playlist_meta });
+        }
+
+        Ok(Playlists { playlists })
+    }
+
+    // Grab every page of a list of users at the given API path
+    fn users<F: Fn(UsersZestingEvent)>(&self, path: &str, cb: F) -> Result<Vec<User>, Error> {
+        use UsersZestingEvent::*;
+
+        self.paginate(
+            path,
+            &mut Retrier::new(self),
+            |count| cb(MoreUsersInfoDownloaded { count: count as i64 }),
+            |retry| cb(PausedAfterServerError { retry })
+        )
+    }
+
+    // Grab a single page of a paginated collection at the given API path,
+    // either the first or the one the given cursor points to
+    fn page<T: DeserializeOwned + KeepRaw>(&self, path: &str, cursor: Option<&str>, limit: u64) -> Result<Page<T>, Error> {
+        let json_string = match cursor {
+            // the cursor is sent with the user's token, so make sure it
+            // actually points at the API
+            Some(cursor) if !cursor.starts_with(API_BASE) => {
+                return Err(Error::InvalidCursor(cursor.into()));
+            },
+            Some(cursor) => self.api_req_full(cursor, &[], true)?,
+            None => self.api_req(path, &[("limit", &limit.max(1).to_string()), ("linked_partitioning", "1")])?
+        };
+
+        self.parse(&json_string)
+    }
+
+    // Deserialize an API response, keeping the raw JSON of the tracks and
+    // playlists in it if the config says to
+    fn parse<T: DeserializeOwned + KeepRaw>(&self, json: &str) -> Result<T, Error> {
+        let mut parsed: T = serde_json::from_str(json)?;
+        if self.config.keep_raw_json {
+            parsed.keep_raw(serde_json::from_str(json)?);
+        }
+
+        Ok(parsed)
+    }
+
+    // Grab every page of a paginated collection, starting at the given API path
+    //
+    // `on_page` is called with the number of items in each page, and `on_pause`
+    // whenever we're waiting before retrying after a server error. Retries are
+    // counted by the given retrier so that they can share the budget of the
+    // zest as a whole.
+    fn paginate<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        retrier: &mut Retrier,
+        on_page: impl Fn(usize),
+        on_pause: impl Fn(RetryPause)
+    ) -> Result<Vec<T>, Error> {
+        self.paginate_with_query(path, &[], retrier, on_page, on_pause)
+    }
+
+    // Same as `paginate`, with extra query parameters for the first page (the
+    // API carries them over into the `next_href`s)
+    fn paginate_with_query<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query_params: &[(&str, &str)],
+        retrier: &mut Retrier,
+        on_page: impl Fn(usize),
+        on_pause: impl Fn(RetryPause)
+    ) -> Result<Vec<T>, Error> {
+        let limit = self.config.page_sizes.other.max(1).to_string();
+        let mut query = vec![("limit", limit.as_str()), ("linked_partitioning", "1")];
+        query.extend_from_slice(query_params);
+
+        // Every page, including the first, is retried after server errors
+        let mut fetch_page = |url: &str, query: &[(&str, &str)]| -> Result<Page<T>, Error> {
+            let page = loop {
+                match self.api_req_full(url, query, true) {
+                    Ok(s) => break serde_json::from_str(&s)?,
+                    Err(e) => {
+                        if !(e.is_retryable() && retrier.pause(&on_pause)?) {
+                            return Err(e);
+                        }
+                    }
+                }
+            };
+            retrier.reset();
+            Ok(page)
+        };
+
+        let mut items = vec![];
+        let mut page = fetch_page(&format!("{}{}", API_BASE, path), &query)?;
+
+        loop {
+            let collection = page.collection.take().unwrap_or_default();
+            on_page(collection.len());
+            items.extend(collection);
+
+            page = match page.next_href.take() {
+                Some(next_href) => fetch_page(&next_href, &[])?,
+                None => break
+            };
+        }
+
+        Ok(items)
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::test_util::TempDir;
 
     // TODO: temporary test, remove or improve
     #[test]
@@ -527,4 +2679,295 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn error_kinds() {
+        let error = |status| Error::from_status(status, "https://api-v2.soundcloud.com/me".into(), String::new(), None);
+
+        assert!(matches!(error(401), Error::Unauthorized { .. }));
+        assert!(matches!(error(404), Error::NotFound { .. }));
+        assert!(matches!(error(429), Error::RateLimited { retry_after: None, .. }));
+        assert!(matches!(error(503), Error::ServerError { status: 503, .. }));
+        assert!(matches!(error(403), Error::Other { status: 403, .. }));
+        assert_eq!(error(404).status(), Some(404));
+        assert!(error(502).is_retryable() && !error(404).is_retryable());
+    }
+
+    #[test]
+    fn error_context() {
+        let _phase = Phase::enter("zesting likes");
+        let error = Error::from_status(503, "https://api-v2.soundcloud.com/users/1/track_likes".into(), String::new(), None)
+            .with_context(ErrorContext::new("https://api-v2.soundcloud.com/users/1/track_likes", &[("limit", "10")], true));
+
+        // the context doesn't hide what kind of error it is
+        assert!(matches!(error, Error::ServerError { status: 503, .. }));
+        assert!(error.is_server_error());
+        assert_eq!(error.status(), Some(503));
+        assert_eq!(
+            error.context().unwrap().to_string(),
+            "while zesting likes, requesting https://api-v2.soundcloud.com/users/1/track_likes?limit=10&client_id=<redacted>"
+        );
+    }
+
+    #[test]
+    fn json_and_snapshots() {
+        let data = serde_json::json!({ "files": {} });
+
+        let mut plain = vec![];
+        write_json_to(&data, &mut plain, false).unwrap();
+        assert_eq!(serde_json::from_slice::<serde_json::Value>(&plain).unwrap(), data);
+
+        let mut wrapped = vec![];
+        write_snapshot_to(&data, &mut wrapped, false).unwrap();
+        assert_eq!(serde_json::from_slice::<serde_json::Value>(&wrapped).unwrap()["data"], data);
+
+        // either can be loaded back
+        assert_eq!(load_json_from::<_, serde_json::Value>(plain.as_slice()).unwrap(), data);
+        assert_eq!(load_json_from::<_, serde_json::Value>(wrapped.as_slice()).unwrap(), data);
+    }
+
+    #[test]
+    fn worker_panics() {
+        // a panicking worker still hands back a result rather than leaving the
+        // thread waiting on it hanging
+        let (tx, rx) = mpsc::channel();
+        thread::scope(|s| {
+            s.spawn(move || {
+                let _ = tx.send(catch_worker_panic("test", || -> Result<(), Error> { panic!("oops") }));
+            });
+        });
+        assert!(matches!(rx.recv().unwrap(), Err(Error::WorkerPanicked("test"))));
+        assert!(matches!(catch_worker_panic("test", || Ok(1)), Ok(1)));
+    }
+
+    #[test]
+    fn atomic_json_writes() {
+        let dir = TempDir::new("write-json");
+        let path = dir.join("likes.json");
+        let load = |path: &Path| load_json::<_, serde_json::Value>(path).unwrap();
+
+        write_json(&serde_json::json!({ "version": 1 }), &path, false).unwrap();
+        write_json_with_backup(&serde_json::json!({ "version": 2 }), &path, true).unwrap();
+
+        // the previous file is kept alongside the new one, and the temporary
+        // file is gone
+        assert_eq!(load(&path)["version"], 2);
+        assert_eq!(load(&dir.join("likes.json.bak"))["version"], 1);
+        assert!(!dir.join("likes.json.part").exists());
+
+        // a failed write leaves whatever was at the path untouched and cleans
+        // up after itself
+        let blocked = dir.join("blocked.json");
+        fs::create_dir(&blocked).unwrap();
+        fs::write(blocked.join("keep"), "").unwrap();
+        let err = write_json(&serde_json::json!({}), &blocked, false).unwrap_err();
+        assert!(matches!(err, Error::IoError(_)));
+        assert!(err.to_string().starts_with("IO error: "));
+        assert_eq!(serde_json::to_value(&err).unwrap(), "IoError");
+        assert!(blocked.join("keep").exists());
+        assert!(!dir.join("blocked.json.part").exists());
+    }
+
+    /// Serves the audio of track 1 a byte at a time, slowly; of track 2 all at
+    /// once; and fails every request for track 3 with a server error.
+    struct TimeoutTransport {
+        requests: Arc<Mutex<Vec<String>>>
+    }
+
+    /// Takes 10ms to read each byte.
+    struct SlowReader;
+
+    impl Read for SlowReader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            thread::sleep(Duration::from_millis(10));
+            buf[0] = 0;
+            Ok(1)
+        }
+    }
+
+    impl Transport for TimeoutTransport {
+        fn send(&self, request: &ureq::Request, _body: Option<&str>) -> Result<(Box<dyn Read + Send>, Option<u64>), Error> {
+            let url = request.get_url().to_string();
+            self.requests.lock().unwrap().push(url.clone());
+
+            let media_prefix = "https://cf-media.sndcdn.com/test/";
+            if let Some(id) = url.strip_prefix("https://api-v2.soundcloud.com/media/soundcloud:tracks:") {
+                let id = id.split('/').next().unwrap_or_default();
+                let json = serde_json::json!({ "url": format!("{}{}.mp3", media_prefix, id) }).to_string();
+                return Ok((Box::new(Cursor::new(json.into_bytes())), None));
+            }
+
+            match url.strip_prefix(media_prefix) {
+                Some("1.mp3") => Ok((Box::new(SlowReader), Some(100_000))),
+                Some("2.mp3") => Ok((Box::new(Cursor::new(vec![0; 1000])), Some(1000))),
+                Some(_) => Err(Error::ServerError { status: 503, url, body: String::new(), context: None }),
+                None => Err(Error::NotFound { url, body: String::new(), context: None })
+            }
+        }
+    }
+
+    #[test]
+    fn first_page_retries() {
+        /// Fails the first request with a server error, then answers with a
+        /// single like.
+        struct FlakyTransport(Mutex<u32>);
+
+        impl Transport for FlakyTransport {
+            fn send(&self, request: &ureq::Request, _body: Option<&str>) -> Result<(Box<dyn Read + Send>, Option<u64>), Error> {
+                let url = request.get_url().to_string();
+                let mut requests = self.0.lock().unwrap();
+                *requests += 1;
+                if *requests == 1 {
+                    return Err(Error::ServerError { status: 503, url, body: String::new(), context: None });
+                }
+
+                let json = serde_json::json!({ "collection": [{ "track": { "id": 7, "title": "Song" } }] });
+                Ok((Box::new(Cursor::new(json.to_string().into_bytes())), None))
+            }
+        }
+
+        let mut zester = Zester::anonymous("test-client-id".into());
+        *zester.oauth_token.write().unwrap() = Some("test-token".into());
+        zester.me = Some(serde_json::from_value(serde_json::json!({ "id": 1, "likes_count": 1 })).unwrap());
+        zester.config.retry.pause = Duration::ZERO;
+        zester.config.retry.jitter = Duration::ZERO;
+        zester.set_transport(FlakyTransport(Mutex::new(0)));
+
+        let pauses = Cell::new(0);
+        let likes = zester.likes(10, |e| {
+            if let LikesZestingEvent::PausedAfterServerError { .. } = e {
+                pauses.set(pauses.get() + 1);
+            }
+        }).unwrap();
+        assert_eq!(pauses.get(), 1);
+        assert_eq!(likes.collections[0].track.id, Some(TrackId(7)));
+    }
+
+    #[test]
+    fn track_by_url_keeps_raw_json() {
+        struct ResolveTransport;
+
+        impl Transport for ResolveTransport {
+            fn send(&self, request: &ureq::Request, _body: Option<&str>) -> Result<(Box<dyn Read + Send>, Option<u64>), Error> {
+                assert!(request.get_url().ends_with("/resolve"), "unexpected request to {}", request.get_url());
+                let json = serde_json::json!({
+                    "id": 7,
+                    "kind": "track",
+                    "title": "Song",
+                    "media": { "transcodings": [] },
+                    "new_field": true
+                });
+                Ok((Box::new(Cursor::new(json.to_string().into_bytes())), None))
+            }
+        }
+
+        let mut zester = Zester::anonymous("test-client-id".into());
+        zester.set_transport(ResolveTransport);
+
+        let track = zester.track_by_url("https://soundcloud.com/artist/song").unwrap();
+        assert_eq!(track.id, Some(TrackId(7)));
+        assert_eq!(track.raw, None);
+
+        zester.config.keep_raw_json = true;
+        let track = zester.track_by_url("https://soundcloud.com/artist/song").unwrap();
+        assert_eq!(track.raw.as_ref().map(|raw| &raw["new_field"]), Some(&serde_json::Value::Bool(true)));
+    }
+
+    /// Serves the audio of every track, except that resolving the media URL of
+    /// track 2 panics.
+    struct PanickingTransport;
+
+    impl Transport for PanickingTransport {
+        fn send(&self, request: &ureq::Request, _body: Option<&str>) -> Result<(Box<dyn Read + Send>, Option<u64>), Error> {
+            let url = request.get_url().to_string();
+            if let Some(id) = url.strip_prefix("https://api-v2.soundcloud.com/media/soundcloud:tracks:") {
+                let id = id.split('/').next().unwrap_or_default();
+                assert_ne!(id, "2", "transport bug");
+                let json = serde_json::json!({ "url": format!("https://cf-media.sndcdn.com/test/{}.mp3", id) }).to_string();
+                return Ok((Box::new(Cursor::new(json.into_bytes())), None));
+            }
+
+            Ok((Box::new(Cursor::new(vec![0; 1000])), Some(1000)))
+        }
+    }
+
+    #[test]
+    fn prefetch_panics() {
+        let mut zester = Zester::anonymous("test-client-id".into());
+        zester.config.retry.pause = Duration::ZERO;
+        zester.config.retry.jitter = Duration::ZERO;
+        zester.set_transport(PanickingTransport);
+
+        let tracks: Vec<Track> = (1..=3).map(|id| serde_json::from_value(serde_json::json!({
+            "id": id,
+            "title": format!("Track {}", id),
+            "media": {
+                "transcodings": [{
+                    "url": format!("https://api-v2.soundcloud.com/media/soundcloud:tracks:{}/stream/progressive", id),
+                    "preset": "mp3_0_0",
+                    "duration": 181000,
+                    "snipped": false,
+                    "format": { "protocol": "progressive", "mime_type": "audio/mpeg" },
+                    "quality": "sq"
+                }]
+            }
+        })).unwrap()).collect();
+
+        // track 2's URL is prefetched while track 1 downloads, and its panic
+        // only fails track 2
+        assert!(zester.config.prefetch_media_urls);
+        let report = zester.tracks_audio(tracks.iter(), |e| {
+            if let TracksAudioZestingEvent::FinishTrackDownload { mut track_data, .. } = e {
+                let _ = std::io::copy(&mut track_data, &mut std::io::sink());
+            }
+        }).unwrap();
+
+        assert_eq!(report.downloaded, [TrackId(1), TrackId(3)]);
+        assert!(matches!(report.failed.as_slice(), [(TrackId(2), Error::WorkerPanicked(PREFETCH_WORKER))]));
+    }
+
+    #[test]
+    fn track_timeouts() {
+        let requests = Arc::new(Mutex::new(vec![]));
+        let mut zester = Zester::anonymous("test-client-id".into());
+        zester.config.track_timeout = Some(Duration::from_millis(200));
+        zester.config.retry.pause = Duration::from_millis(60);
+        zester.config.retry.jitter = Duration::ZERO;
+        zester.set_transport(TimeoutTransport { requests: requests.clone() });
+
+        let tracks: Vec<Track> = (1..=3).map(|id| serde_json::from_value(serde_json::json!({
+            "id": id,
+            "title": format!("Track {}", id),
+            "media": {
+                "transcodings": [{
+                    "url": format!("https://api-v2.soundcloud.com/media/soundcloud:tracks:{}/stream/progressive", id),
+                    "preset": "mp3_0_0",
+                    "duration": 181000,
+                    "snipped": false,
+                    "format": { "protocol": "progressive", "mime_type": "audio/mpeg" },
+                    "quality": "sq"
+                }]
+            }
+        })).unwrap()).collect();
+
+        let started = Instant::now();
+        let report = zester.tracks_audio(tracks.iter(), |e| {
+            if let TracksAudioZestingEvent::FinishTrackDownload { mut track_data, .. } = e {
+                let _ = std::io::copy(&mut track_data, &mut std::io::sink());
+            }
+        }).unwrap();
+
+        // the slow track runs out of time, and the zest moves on to the next
+        // one rather than waiting the 1000s it would take
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(report.downloaded, [TrackId(2)]);
+        let failed: HashMap<TrackId, Error> = report.failed.into_iter().collect();
+        assert!(matches!(failed[&TrackId(1)], Error::TrackTimedOut(_)));
+
+        // retries count towards the time limit, and the error that used it up
+        // is the one reported, rather than the track being retried forever
+        assert!(matches!(failed[&TrackId(3)], Error::ServerError { status: 503, .. }));
+        let attempts = requests.lock().unwrap().iter().filter(|url| url.ends_with("/3.mp3")).count();
+        assert!((2..=5).contains(&attempts), "track 3 was requested {} times", attempts);
+    }
 }
@@ -0,0 +1,223 @@
+//! The manifest of an archive, recording the size and hash of each audio and
+//! artwork file the archiver wrote.
+//!
+//! Files are checked against the manifest when an archive is resumed, so that
+//! files left incomplete by a crash (or damaged since) are downloaded again
+//! rather than being mistaken for complete ones.
+
+use crate::archive::ArchiveSink;
+use serde_derive::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, Read};
+use std::path::Path;
+
+/// The path of the manifest within an archive
+pub const MANIFEST_PATH: &str = "manifest.json";
+
+/// The size and hash of the files in an archive.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchiveManifest {
+    /// The entry for each file, by its path within the archive (always with
+    /// `/` separators)
+    pub files: BTreeMap<String, ManifestEntry>,
+}
+
+/// What a file in an archive should contain.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// The size of the file in bytes
+    pub size: u64,
+    /// The SHA-256 hash of the file, in lowercase hex
+    pub sha256: String,
+}
+
+impl ManifestEntry {
+    /// The entry for a file containing the given data.
+    pub fn new(data: &[u8]) -> Self {
+        Self {
+            size: data.len() as u64,
+            sha256: format!("{:x}", Sha256::digest(data)),
+        }
+    }
+}
+
+/// A `Read` wrapper that works out the manifest entry for the data read
+/// through it, so that a file can be streamed into an archive.
+pub struct HashingReader<R> {
+    inner: R,
+    hasher: Sha256,
+    size: u64,
+}
+
+impl<R: Read> HashingReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self { inner, hasher: Sha256::new(), size: 0 }
+    }
+
+    /// The entry for a file containing the data read so far.
+    pub fn entry(&self) -> ManifestEntry {
+        ManifestEntry {
+            size: self.size,
+            sha256: format!("{:x}", self.hasher.clone().finalize()),
+        }
+    }
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.hasher.update(&buf[..read]);
+        self.size += read as u64;
+        Ok(read)
+    }
+}
+
+/// The ways that a file can fail to match its manifest entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FileMismatch {
+    /// The file isn't the size it should be, which is usually because writing
+    /// it was interrupted
+    Size { expected: u64, actual: u64 },
+    /// The file is the right size, but its contents have changed
+    Hash,
+    /// The file couldn't be read to check it
+    Unreadable,
+}
+
+impl fmt::Display for FileMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FileMismatch::Size { expected, actual } => {
+                write!(f, "it's {} bytes long rather than {}", actual, expected)
+            },
+            FileMismatch::Hash => write!(f, "its contents don't match its hash"),
+            FileMismatch::Unreadable => write!(f, "it couldn't be read")
+        }
+    }
+}
+
+impl ArchiveManifest {
+    /// The manifest in the given sink, or an empty one if there isn't one (or
+    /// the sink can't read it back).
+    pub fn load<S: ArchiveSink + ?Sized>(sink: &S) -> Self {
+        sink.open(Path::new(MANIFEST_PATH))
+            .and_then(|r| serde_json::from_reader(r).ok())
+            .unwrap_or_default()
+    }
+
+    /// The entry for the file at the given path, if there is one.
+    pub fn get(&self, path: &Path) -> Option<&ManifestEntry> {
+        self.files.get(&manifest_key(path))
+    }
+
+    /// Record what the file at the given path contains.
+    pub fn insert(&mut self, path: &Path, entry: ManifestEntry) {
+        self.files.insert(manifest_key(path), entry);
+    }
+
+    /// Move the entry for the file at `from` to `to`, for a file that's been
+    /// moved.
+    pub fn rename(&mut self, from: &Path, to: &Path) {
+        if let Some(entry) = self.files.remove(&manifest_key(from)) {
+            self.files.insert(manifest_key(to), entry);
+        }
+    }
+
+    /// Check the file at the given path in the sink against its entry,
+    /// returning how it doesn't match if it doesn't.
+    ///
+    /// The file's size is always checked, but it's only hashed if `hash` is
+    /// true, since that means reading all of it. Files without an entry, and
+    /// files in sinks that can't read back what's in them, are assumed to be
+    /// complete.
+    pub fn check<S: ArchiveSink + ?Sized>(&self, sink: &S, path: &Path, hash: bool) -> Option<FileMismatch> {
+        let entry = self.get(path)?;
+
+        match sink.size(path) {
+            Some(size) if size != entry.size => return Some(FileMismatch::Size { expected: entry.size, actual: size }),
+            _ => {}
+        }
+
+        if !hash {
+            return None;
+        }
+
+        let mut file = sink.open(path)?;
+        let mut hasher = Sha256::new();
+        let mut buf = [0; 64 * 1024];
+        let mut size = 0;
+        loop {
+            match file.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => {
+                    hasher.update(&buf[..n]);
+                    size += n as u64;
+                },
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(_) => return Some(FileMismatch::Unreadable)
+            }
+        }
+
+        if size != entry.size {
+            Some(FileMismatch::Size { expected: entry.size, actual: size })
+        } else if format!("{:x}", hasher.finalize()) != entry.sha256 {
+            Some(FileMismatch::Hash)
+        } else {
+            None
+        }
+    }
+}
+
+// Paths are stored with forward slashes so that a manifest written on one
+// platform can be used on another
+fn manifest_key(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::TempDir;
+    use crate::archive::DirSink;
+    use std::fs;
+
+    #[test]
+    fn manifest_checks() {
+        let root = TempDir::new("manifest");
+        let mut sink = DirSink::new(&*root);
+        let mut manifest = ArchiveManifest::default();
+
+        let audio = b"complete audio".to_vec();
+        for name in ["complete.mp3", "truncated.mp3", "changed.mp3"] {
+            let path = Path::new("tracks").join(name);
+            sink.write(&path, &mut audio.as_slice()).unwrap();
+            manifest.insert(&path, ManifestEntry::new(&audio));
+        }
+        fs::write(root.join("tracks/truncated.mp3"), &audio[..8]).unwrap();
+        fs::write(root.join("tracks/changed.mp3"), b"complete audiO").unwrap();
+        sink.write(Path::new("tracks/unlisted.mp3"), &mut &b"?"[..]).unwrap();
+
+        let check = |name: &str, hash| manifest.check(&sink, &Path::new("tracks").join(name), hash);
+        assert_eq!(check("complete.mp3", true), None);
+        assert_eq!(check("truncated.mp3", false), Some(FileMismatch::Size { expected: 14, actual: 8 }));
+        assert_eq!(check("changed.mp3", false), None);
+        assert_eq!(check("changed.mp3", true), Some(FileMismatch::Hash));
+        assert_eq!(check("unlisted.mp3", true), None);
+
+        // streamed files get the same entry as ones written from memory
+        let mut reader = HashingReader::new(audio.as_slice());
+        io::copy(&mut reader, &mut io::sink()).unwrap();
+        assert_eq!(reader.entry(), ManifestEntry::new(&audio));
+
+        // the manifest survives being saved and loaded again
+        let json = serde_json::to_vec(&manifest).unwrap();
+        sink.write(Path::new(MANIFEST_PATH), &mut json.as_slice()).unwrap();
+        assert_eq!(ArchiveManifest::load(&sink), manifest);
+        assert!(manifest.files.contains_key("tracks/complete.mp3"));
+    }
+}
@@ -0,0 +1,148 @@
+//! Reporting metrics about the requests a `Zester` makes.
+
+use serde_derive::Serialize;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Something that collects metrics about the requests a `Zester` makes, for
+/// example to export them to a monitoring system.
+///
+/// Install one with `Zester::set_metrics`. Requests are attributed to an
+/// endpoint: for API requests this is the host and path of the URL with any
+/// numeric ids replaced by `{id}`, and for everything else (such as media
+/// downloads, whose URLs are unique) it's just the host. This keeps the number
+/// of distinct endpoints small.
+///
+/// All of the methods do nothing by default, so only the ones that are needed
+/// have to be implemented. `Metrics` is implemented for `Arc<M>`, so you can
+/// hold on to a handle to the metrics while the `Zester` reports into them.
+pub trait Metrics: Send + Sync {
+    /// A request to `endpoint` got a response with the given status after
+    /// `duration`.
+    ///
+    /// The duration covers sending the request and receiving the response
+    /// headers, but not reading the body.
+    fn request(&self, _endpoint: &str, _status: u16, _duration: Duration) {}
+
+    /// A response from `endpoint` arrived with the given metadata, for
+    /// keeping an eye on things like how close the `Zester` is to being rate
+    /// limited.
+    ///
+    /// This isn't called for requests that failed before getting a response.
+    fn response(&self, _endpoint: &str, _metadata: &ResponseMetadata) {}
+
+    /// `bytes` more bytes of a response body from `endpoint` were read.
+    fn bytes_downloaded(&self, _endpoint: &str, _bytes: u64) {}
+
+    /// A request failed with a server error and is about to be retried.
+    fn retry(&self) {}
+}
+
+impl<M: Metrics + ?Sized> Metrics for Arc<M> {
+    fn request(&self, endpoint: &str, status: u16, duration: Duration) {
+        (**self).request(endpoint, status, duration)
+    }
+
+    fn response(&self, endpoint: &str, metadata: &ResponseMetadata) {
+        (**self).response(endpoint, metadata)
+    }
+
+    fn bytes_downloaded(&self, endpoint: &str, bytes: u64) {
+        (**self).bytes_downloaded(endpoint, bytes)
+    }
+
+    fn retry(&self) {
+        (**self).retry()
+    }
+}
+
+/// The interesting parts of a response's headers.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ResponseMetadata {
+    /// The response's status code
+    pub status: u16,
+    /// The id the server gave the request, which is handy when reporting a
+    /// problem to SoundCloud
+    pub request_id: Option<String>,
+    /// The number of requests that can be made before being rate limited, if
+    /// the server said
+    pub rate_limit_remaining: Option<u64>,
+    /// The number of requests allowed in each rate limiting window, if the
+    /// server said
+    pub rate_limit_limit: Option<u64>,
+    /// The length of the response body, if the server reported it
+    pub content_length: Option<u64>,
+}
+
+impl ResponseMetadata {
+    pub(crate) fn from_response(response: &ureq::Response) -> Self {
+        let number = |name| response.header(name).and_then(|v| v.trim().parse().ok());
+
+        Self {
+            status: response.status(),
+            request_id: response.header("X-Request-Id").map(str::to_string),
+            rate_limit_remaining: number("X-RateLimit-Remaining"),
+            rate_limit_limit: number("X-RateLimit-Limit"),
+            content_length: number("Content-Length"),
+        }
+    }
+}
+
+/// The endpoint that requests to the given URL are attributed to.
+pub(crate) fn endpoint(url: &str) -> String {
+    let url = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let url = url.split(['?', '#']).next().unwrap_or_default();
+    let (host, path) = url.split_once('/').unwrap_or((url, ""));
+
+    if !host.starts_with("api") {
+        return host.to_string();
+    }
+
+    let mut endpoint = host.to_string();
+    for segment in path.split('/').filter(|s| !s.is_empty()) {
+        endpoint.push('/');
+        if segment.bytes().all(|b| b.is_ascii_digit()) {
+            endpoint.push_str("{id}");
+        } else {
+            endpoint.push_str(segment);
+        }
+    }
+
+    endpoint
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn endpoints() {
+        assert_eq!(
+            endpoint("https://api-v2.soundcloud.com/users/1234/track_likes?limit=200&offset=5"),
+            "api-v2.soundcloud.com/users/{id}/track_likes"
+        );
+        assert_eq!(endpoint("https://api.soundcloud.com/me"), "api.soundcloud.com/me");
+        assert_eq!(
+            endpoint("https://cf-media.sndcdn.com/AbCdEf123.128.mp3?Policy=abc"),
+            "cf-media.sndcdn.com"
+        );
+    }
+
+    #[test]
+    fn response_metadata() {
+        let response: ureq::Response = "HTTP/1.1 200 OK\r\n\
+            x-request-id: abc123\r\n\
+            X-RateLimit-Remaining: 42\r\n\
+            Content-Length: 2\r\n\
+            \r\n\
+            {}".parse().unwrap();
+
+        assert_eq!(ResponseMetadata::from_response(&response), ResponseMetadata {
+            status: 200,
+            request_id: Some("abc123".into()),
+            rate_limit_remaining: Some(42),
+            rate_limit_limit: None,
+            content_length: Some(2),
+        });
+    }
+}
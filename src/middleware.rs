@@ -0,0 +1,21 @@
+//! Hooks for inspecting and modifying the requests a `Zester` sends.
+
+/// Something that gets to see every request a `Zester` sends and every
+/// response it receives.
+///
+/// Install one with `Zester::add_middleware`. This can be used to inject extra
+/// headers, log traffic, or implement a custom auth scheme. Both API requests
+/// and media downloads pass through middleware.
+///
+/// Both methods do nothing by default, so only the ones that are needed have
+/// to be implemented.
+pub trait Middleware: Send + Sync {
+    /// Called just before a request is sent, after the `Zester` has finished
+    /// setting it up (including the query parameters, the Authorization
+    /// header, and the timeouts).
+    fn on_request(&self, _request: &mut ureq::Request) {}
+
+    /// Called with the response to a request before it is handled, including
+    /// responses with an error status.
+    fn on_response(&self, _request: &ureq::Request, _response: &ureq::Response) {}
+}
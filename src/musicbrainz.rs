@@ -0,0 +1,238 @@
+//! Filling in canonical track metadata from MusicBrainz.
+//!
+//! Many tracks on SoundCloud (particularly ones uploaded by labels and
+//! distributors) carry an ISRC in their publisher metadata. MusicBrainz can
+//! map an ISRC to the recording it identifies, along with the releases it
+//! appeared on, which gives much better artist, album, and year information
+//! than uploaders tend to provide.
+
+use crate::{Error, Zester};
+use crate::api::common::{datetime, PublisherMetadata, Track};
+use crate::events::EnrichmentEvent;
+use crate::retry::Retrier;
+use serde_derive::{Deserialize, Serialize};
+use std::io::Read;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const API_BASE: &str = "https://musicbrainz.org/ws/2/";
+
+/// MusicBrainz allows clients one request per second
+const MIN_REQUEST_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Canonical metadata for a track, found by looking up its ISRC.
+#[derive(Debug, Clone, Serialize)]
+pub struct Recording {
+    /// The MusicBrainz id of the recording
+    pub id: String,
+    pub title: String,
+    /// The credited artist(s), joined the way MusicBrainz displays them
+    pub artist: Option<String>,
+    /// The title of the earliest release the recording appeared on
+    pub album: Option<String>,
+    /// The date of the earliest release the recording appeared on, as given
+    /// by MusicBrainz (`YYYY`, `YYYY-MM`, or `YYYY-MM-DD`)
+    pub release_date: Option<String>,
+}
+
+impl Recording {
+    /// The year the recording was first released, if known.
+    pub fn year(&self) -> Option<i32> {
+        self.release_date.as_ref()?.get(..4)?.parse().ok()
+    }
+
+    // Fill in the given track's metadata with this recording's
+    fn apply(&self, track: &mut Track) {
+        let metadata = track.publisher_metadata.get_or_insert_with(PublisherMetadata::default);
+        if self.artist.is_some() {
+            metadata.artist.clone_from(&self.artist);
+        }
+        if self.album.is_some() {
+            metadata.album_title.clone_from(&self.album);
+        }
+
+        // dates missing the month or day are filled in with the first one
+        let release_date = self.release_date.as_deref().and_then(|d| match d.len() {
+            4 => datetime::parse(&format!("{}-01-01", d)),
+            7 => datetime::parse(&format!("{}-01", d)),
+            _ => datetime::parse(d)
+        });
+        if release_date.is_some() {
+            track.release_date = release_date;
+        }
+    }
+}
+
+// The response to an ISRC lookup
+#[derive(Deserialize)]
+struct IsrcLookup {
+    #[serde(default)]
+    recordings: Vec<RecordingJson>,
+}
+
+#[derive(Deserialize)]
+struct RecordingJson {
+    id: String,
+    title: String,
+    #[serde(default, rename = "artist-credit")]
+    artist_credit: Vec<ArtistCredit>,
+    #[serde(default)]
+    releases: Vec<Release>,
+}
+
+#[derive(Deserialize)]
+struct ArtistCredit {
+    name: String,
+    #[serde(default)]
+    joinphrase: String,
+}
+
+#[derive(Deserialize)]
+struct Release {
+    title: String,
+    date: Option<String>,
+}
+
+impl IsrcLookup {
+    // The recording the ISRC most likely refers to; ISRCs are occasionally
+    // attached to more than one recording, in which case the first is used
+    fn into_recording(self) -> Option<Recording> {
+        let recording = self.recordings.into_iter().next()?;
+
+        let artist: String = recording.artist_credit
+            .iter()
+            .flat_map(|c| [c.name.as_str(), c.joinphrase.as_str()])
+            .collect();
+        // releases without a date sort last
+        let earliest = recording.releases
+            .into_iter()
+            .filter(|r| r.date.as_ref().is_some_and(|d| !d.is_empty()))
+            .min_by(|a, b| a.date.cmp(&b.date));
+
+        Some(Recording {
+            id: recording.id,
+            title: recording.title,
+            artist: Some(artist).filter(|a| !a.is_empty()),
+            album: earliest.as_ref().map(|r| r.title.clone()),
+            release_date: earliest.and_then(|r| r.date),
+        })
+    }
+}
+
+impl Zester {
+    /// Look up the ISRCs of the given tracks on MusicBrainz and fill in their
+    /// metadata with what's found.
+    ///
+    /// For tracks that match a recording, the artist and album title in the
+    /// track's publisher metadata and its release date are replaced with the
+    /// canonical ones, so anything the track is later tagged or exported with
+    /// picks them up. Tracks without an ISRC are left alone.
+    ///
+    /// MusicBrainz limits clients to one request per second, so this takes at
+    /// least a second per track with an ISRC. Failed lookups don't cause this
+    /// to return an error; they're reported through the callback instead.
+    pub fn enrich_from_musicbrainz<'a, I, F>(&self, tracks: I, cb: F) -> Result<(), Error> where
+        I: IntoIterator<Item = &'a mut Track>,
+        F: Fn(EnrichmentEvent)
+    {
+        use EnrichmentEvent::*;
+
+        let tracks: Vec<&mut Track> = tracks.into_iter().filter(|t| isrc(t).is_some()).collect();
+        cb(NumTracksToLookUp { num: tracks.len() as u64 });
+
+        let mut retrier = Retrier::new(self);
+        let mut last_request: Option<Instant> = None;
+        for track in tracks {
+            let isrc = isrc(track).unwrap_or_default().to_string();
+
+            let lookup = loop {
+                if let Some(elapsed) = last_request.map(|t| t.elapsed()) {
+                    thread::sleep(MIN_REQUEST_INTERVAL.saturating_sub(elapsed));
+                }
+                last_request = Some(Instant::now());
+
+                // MusicBrainz responds with a 503 when it's being asked too
+                // much, which is retried like any other server error
+                match self.lookup_isrc(&isrc) {
+                    Err(e) if e.is_retryable() && retrier.pause(|retry| cb(PausedAfterServerError { retry }))? => {},
+                    result => break result
+                }
+            };
+            retrier.reset();
+
+            match lookup {
+                Ok(Some(recording)) => {
+                    recording.apply(track);
+                    cb(Matched { track_info: track, recording });
+                },
+                // MusicBrainz responds with a 404 for ISRCs it doesn't know
+                Ok(None) => cb(NotFound { track_info: track }),
+                Err(e) if e.status() == Some(404) => cb(NotFound { track_info: track }),
+                Err(err) => cb(LookupError { track_info: track, err })
+            }
+        }
+
+        Ok(())
+    }
+
+    fn lookup_isrc(&self, isrc: &str) -> Result<Option<Recording>, Error> {
+        let mut r = ureq::get(&format!("{}isrc/{}", API_BASE, isrc));
+        r.query("inc", "artist-credits+releases");
+        r.query("fmt", "json");
+        r.set("Accept", "application/json");
+        // MusicBrainz asks that clients identify themselves
+        r.set("User-Agent", concat!("orange-zest/", env!("CARGO_PKG_VERSION"), " ( https://github.com/Cldfire/orange-zest )"));
+
+        let mut body = String::new();
+        self.call(&mut r)?.read_to_string(&mut body)?;
+        let lookup: IsrcLookup = serde_json::from_str(&body)?;
+
+        Ok(lookup.into_recording())
+    }
+}
+
+fn isrc(track: &Track) -> Option<&str> {
+    track.publisher_metadata
+        .as_ref()
+        .and_then(|m| m.isrc.as_deref())
+        .filter(|isrc| !isrc.is_empty())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn isrc_lookup() {
+        let lookup: IsrcLookup = serde_json::from_value(json!({
+            "isrc": "USABC2000001",
+            "recordings": [{
+                "id": "a1b2",
+                "title": "Song",
+                "artist-credit": [
+                    { "name": "First", "joinphrase": " & " },
+                    { "name": "Second", "joinphrase": "" }
+                ],
+                "releases": [
+                    { "title": "Compilation", "date": "2015-06-01" },
+                    { "title": "Undated", "date": "" },
+                    { "title": "Original Album", "date": "2001" }
+                ]
+            }]
+        })).unwrap();
+        let recording = lookup.into_recording().unwrap();
+
+        assert_eq!(recording.artist.as_deref(), Some("First & Second"));
+        assert_eq!(recording.album.as_deref(), Some("Original Album"));
+        assert_eq!(recording.year(), Some(2001));
+
+        let mut track: Track = serde_json::from_value(json!({ "title": "Song" })).unwrap();
+        recording.apply(&mut track);
+        assert_eq!(track.publisher_metadata.unwrap().album_title.as_deref(), Some("Original Album"));
+        assert_eq!(track.release_date.map(|d| d.to_rfc3339()).as_deref(), Some("2001-01-01T00:00:00+00:00"));
+
+        let empty: IsrcLookup = serde_json::from_value(json!({ "recordings": [] })).unwrap();
+        assert!(empty.into_recording().is_none());
+    }
+}
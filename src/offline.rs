@@ -0,0 +1,152 @@
+//! Querying previously archived data without credentials or network access.
+
+use crate::api::{Likes, Playlists};
+use crate::api::common::Track;
+use crate::api::ids::TrackId;
+use crate::api::me::Me;
+use crate::events::{LikesZestingEvent, PlaylistsZestingEvent};
+use crate::{load_json, Error};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Answers the same queries as a `Zester` using the JSON files saved in an
+/// archive (see `Zester::archive_to`).
+///
+/// This lets applications be developed and tested without credentials or a
+/// network connection. Files that weren't archived are reported as IO errors
+/// when the data they hold is requested.
+pub struct OfflineZester {
+    root: PathBuf,
+    pub me: Option<Me>
+}
+
+impl OfflineZester {
+    /// Open the archive at the given path.
+    ///
+    /// Information about the user is loaded from "me.json" if the archive
+    /// contains it.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let root = path.as_ref().to_path_buf();
+        let me_path = root.join("me.json");
+        let me = if me_path.exists() {
+            Some(load_json(me_path)?)
+        } else {
+            None
+        };
+
+        Ok(Self { root, me })
+    }
+
+    /// Get `num_recent` of the user's liked tracks from the archive.
+    ///
+    /// The callback receives the same events as `Zester::likes`, minus the
+    /// ones that only make sense for network requests.
+    pub fn likes<F: Fn(LikesZestingEvent)>(
+        &self,
+        num_recent: u64,
+        cb: F
+    ) -> Result<Likes, Error> {
+        use LikesZestingEvent::*;
+
+        let mut likes: Likes = load_json(self.root.join("likes.json"))?;
+        likes.collections.truncate(num_recent as usize);
+
+        cb(NumLikesInfoToDownload { num: likes.collections.len() as u64 });
+        if !likes.collections.is_empty() {
+            cb(MoreLikesInfoDownloaded { count: likes.collections.len() as i64 });
+        }
+
+        Ok(likes)
+    }
+
+    /// Get `num_recent` of the user's playlists from the archive.
+    ///
+    /// The callback receives the same events as `Zester::playlists`, minus the
+    /// ones that only make sense for network requests.
+    pub fn playlists<F: Fn(PlaylistsZestingEvent)>(
+        &self,
+        num_recent: u64,
+        cb: F
+    ) -> Result<Playlists, Error> {
+        use PlaylistsZestingEvent::*;
+
+        let mut playlists: Playlists = load_json(self.root.join("playlists.json"))?;
+        playlists.playlists.truncate(num_recent as usize);
+
+        cb(NumPlaylistInfoToDownload { num: playlists.playlists.len() as u64 });
+        if !playlists.playlists.is_empty() {
+            cb(MorePlaylistMetaInfoDownloaded { count: playlists.playlists.len() as i64 });
+        }
+        cb(FinishPlaylistMetaInfoDownloading);
+
+        Ok(playlists)
+    }
+
+    /// Get information for the specified track IDs from the archive.
+    ///
+    /// Like the API, tracks that can't be found are left out of the result
+    /// rather than causing an error. Both likes and playlists are searched.
+    pub fn tracks_info<A: AsRef<[TrackId]>>(&self, ids: A) -> Result<Vec<Track>, Error> {
+        let mut tracks = self.archived_tracks()?;
+
+        Ok(ids.as_ref().iter().filter_map(|id| tracks.remove(id)).collect())
+    }
+
+    // All of the tracks present in the archive that have ids, by id. Tracks
+    // that are both liked and in a playlist are taken from the likes.
+    fn archived_tracks(&self) -> Result<HashMap<TrackId, Track>, Error> {
+        let mut tracks = HashMap::new();
+
+        let likes_path = self.root.join("likes.json");
+        if likes_path.exists() {
+            let likes: Likes = load_json(likes_path)?;
+            for track in likes.collections.into_iter().map(|c| c.track) {
+                if let Some(id) = track.id {
+                    tracks.entry(id).or_insert(track);
+                }
+            }
+        }
+
+        let playlists_path = self.root.join("playlists.json");
+        if playlists_path.exists() {
+            let playlists: Playlists = load_json(playlists_path)?;
+            for track in playlists.playlists.into_iter().flat_map(|p| p.tracks.unwrap_or_default()) {
+                if let Some(id) = track.id {
+                    tracks.entry(id).or_insert(track);
+                }
+            }
+        }
+
+        Ok(tracks)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::TempDir;
+    use serde_json::json;
+    use std::fs;
+
+    #[test]
+    fn offline_tracks_info() {
+        let root = TempDir::new("offline");
+        let likes = json!({ "collections": [
+            { "track": { "id": 1, "title": "Liked" } },
+            { "track": { "id": 2, "title": "Also Liked" } }
+        ] });
+        let playlists = json!({ "playlists": [
+            { "title": "Mix", "tracks": [{ "id": 1, "title": "In A Playlist" }, { "id": 3, "title": "Only In A Playlist" }] }
+        ] });
+        fs::write(root.join("likes.json"), likes.to_string()).unwrap();
+        fs::write(root.join("playlists.json"), playlists.to_string()).unwrap();
+
+        let zester = OfflineZester::open(&root).unwrap();
+        let tracks = zester.tracks_info([TrackId(3), TrackId(1), TrackId(99), TrackId(1)]).unwrap();
+        let found: Vec<_> = tracks.iter().map(|t| (t.id, t.title.as_deref())).collect();
+
+        // tracks come back in the order asked for, once each, and missing ones
+        // are left out
+        assert_eq!(found, [(Some(TrackId(3)), Some("Only In A Playlist")), (Some(TrackId(1)), Some("Liked"))]);
+    }
+}
@@ -0,0 +1,108 @@
+//! Zesting data from multiple accounts at once.
+
+use crate::api::{Likes, Playlists};
+use crate::credentials::Credentials;
+use crate::events::{LikesZestingEvent, PlaylistsZestingEvent};
+use crate::{Error, Zester};
+
+/// Manages a `Zester` for each of a set of named accounts, and runs the same
+/// zest across all of them.
+///
+/// Accounts are kept in the order they were added, and zests are run against
+/// them in that order. A failure for one account doesn't stop the others from
+/// being zested; each account's result is returned alongside its name.
+#[derive(Default)]
+pub struct ZesterPool {
+    accounts: Vec<(String, Zester)>
+}
+
+impl ZesterPool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Construct a `Zester` for the given credentials and add it to the pool
+    /// under the given name.
+    ///
+    /// This sends a request to the "/me" api route, just like `Zester::new`.
+    pub fn add<S: Into<String>>(&mut self, name: S, credentials: Credentials) -> Result<(), Error> {
+        let zester = Zester::from_credentials(credentials)?;
+        self.add_zester(name, zester);
+        Ok(())
+    }
+
+    /// Add an already constructed `Zester` to the pool under the given name.
+    ///
+    /// If an account with the same name is already present it is replaced.
+    pub fn add_zester<S: Into<String>>(&mut self, name: S, zester: Zester) {
+        let name = name.into();
+        match self.accounts.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, existing)) => *existing = zester,
+            None => self.accounts.push((name, zester))
+        }
+    }
+
+    /// Remove the account with the given name from the pool, returning its
+    /// `Zester`.
+    pub fn remove(&mut self, name: &str) -> Option<Zester> {
+        let pos = self.accounts.iter().position(|(n, _)| n == name)?;
+        Some(self.accounts.remove(pos).1)
+    }
+
+    /// Get the `Zester` for the account with the given name.
+    pub fn get(&self, name: &str) -> Option<&Zester> {
+        self.accounts.iter().find(|(n, _)| n == name).map(|(_, z)| z)
+    }
+
+    /// Get a mutable reference to the `Zester` for the account with the given
+    /// name, for example to change its config.
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Zester> {
+        self.accounts.iter_mut().find(|(n, _)| n == name).map(|(_, z)| z)
+    }
+
+    /// The names and `Zester`s of all of the accounts in the pool.
+    pub fn accounts(&self) -> impl Iterator<Item = (&str, &Zester)> {
+        self.accounts.iter().map(|(n, z)| (n.as_str(), z))
+    }
+
+    pub fn len(&self) -> usize {
+        self.accounts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty()
+    }
+
+    /// Run the given zest against every account in the pool.
+    ///
+    /// The closure receives the name of the account along with its `Zester`,
+    /// so events can be attributed to the account they came from.
+    pub fn run<T, F: Fn(&str, &Zester) -> Result<T, Error>>(&self, zest: F) -> Vec<(String, Result<T, Error>)> {
+        self.accounts
+            .iter()
+            .map(|(name, zester)| (name.clone(), zest(name, zester)))
+            .collect()
+    }
+
+    /// Get `num_recent` of each account's liked tracks.
+    ///
+    /// The callback receives the name of the account each event is for.
+    pub fn likes<F: Fn(&str, LikesZestingEvent)>(
+        &self,
+        num_recent: u64,
+        cb: F
+    ) -> Vec<(String, Result<Likes, Error>)> {
+        self.run(|name, zester| zester.likes(num_recent, |e| cb(name, e)))
+    }
+
+    /// Get `num_recent` of each account's playlists.
+    ///
+    /// The callback receives the name of the account each event is for.
+    pub fn playlists<F: Fn(&str, PlaylistsZestingEvent)>(
+        &self,
+        num_recent: u64,
+        cb: F
+    ) -> Vec<(String, Result<Playlists, Error>)> {
+        self.run(|name, zester| zester.playlists(num_recent, |e| cb(name, e)))
+    }
+}
@@ -0,0 +1,335 @@
+//! Tracking the progress of zests.
+
+#[cfg(feature = "progress-bars")]
+pub mod bars;
+
+use crate::api::common::Track;
+use crate::api::ids::TrackId;
+use serde_derive::Serialize;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// The window of time that transfer speeds are averaged over
+const SPEED_WINDOW: Duration = Duration::from_secs(5);
+
+/// A snapshot of the progress of an audio zest.
+#[derive(Debug, Clone, Serialize)]
+pub struct ZestProgress {
+    /// The number of bytes downloaded so far for the current track
+    pub track_bytes: u64,
+    /// The size of the current track's audio file, if the server reported it
+    pub track_total_bytes: Option<u64>,
+    /// The current transfer speed of the current track in bytes per second
+    pub track_bytes_per_sec: f64,
+    /// The number of bytes downloaded so far across all tracks
+    pub total_bytes: u64,
+    /// The estimated number of bytes that will be downloaded across all tracks
+    pub estimated_total_bytes: u64,
+    /// The number of tracks that have been dealt with so far
+    pub tracks_done: u64,
+    /// The number of tracks that are going to be dealt with
+    pub tracks_total: u64,
+    /// The current transfer speed in bytes per second
+    pub bytes_per_sec: f64,
+    /// The current transfer speed of all of the `Zester`'s downloads combined
+    /// (including ones made by other zests running at the same time) in bytes
+    /// per second
+    pub global_bytes_per_sec: f64,
+    /// The estimated amount of time until all tracks are downloaded
+    ///
+    /// This is `None` until enough data has been transferred to know the
+    /// transfer speed.
+    pub eta: Option<Duration>,
+}
+
+/// Measures a transfer speed from recent samples of the number of bytes
+/// transferred.
+#[derive(Debug, Default)]
+struct SpeedMeter {
+    /// Recent (time, total bytes) samples
+    samples: VecDeque<(Instant, u64)>,
+}
+
+impl SpeedMeter {
+    fn record(&mut self, now: Instant, total_bytes: u64) {
+        self.samples.push_back((now, total_bytes));
+        while self.samples.len() > 2 && now.duration_since(self.samples[0].0) > SPEED_WINDOW {
+            self.samples.pop_front();
+        }
+    }
+
+    /// The average speed over the samples in the window, in bytes per second.
+    fn bytes_per_sec(&self) -> f64 {
+        match (self.samples.front(), self.samples.back()) {
+            (Some(&(oldest_time, oldest_bytes)), Some(&(newest_time, newest_bytes))) => {
+                let elapsed = newest_time.duration_since(oldest_time).as_secs_f64();
+                if elapsed > 0.0 {
+                    (newest_bytes - oldest_bytes) as f64 / elapsed
+                } else {
+                    0.0
+                }
+            },
+            _ => 0.0
+        }
+    }
+
+    /// Same as `bytes_per_sec`, but takes into account that nothing may have
+    /// been transferred since the last sample, so that the speed drops off
+    /// when transfers stall.
+    fn bytes_per_sec_at(&self, now: Instant) -> f64 {
+        match (self.samples.iter().find(|(time, _)| now.duration_since(*time) <= SPEED_WINDOW), self.samples.back()) {
+            (Some(&(oldest_time, oldest_bytes)), Some(&(_, newest_bytes))) => {
+                let elapsed = now.duration_since(oldest_time).as_secs_f64();
+                if elapsed > 0.0 {
+                    (newest_bytes - oldest_bytes) as f64 / elapsed
+                } else {
+                    0.0
+                }
+            },
+            _ => 0.0
+        }
+    }
+}
+
+/// The progress of a single track download that's in progress.
+#[derive(Debug, Clone, Serialize)]
+pub struct DownloadProgress {
+    pub track_id: Option<TrackId>,
+    pub title: Option<String>,
+    /// The number of bytes downloaded so far
+    pub bytes: u64,
+    /// The size of the track's audio file, if the server reported it
+    pub total_bytes: Option<u64>,
+    /// The current transfer speed of the download in bytes per second
+    pub bytes_per_sec: f64,
+}
+
+/// A snapshot of all of the downloads a `Zester` is making.
+#[derive(Debug, Clone, Serialize)]
+pub struct GlobalProgress {
+    /// The downloads currently in progress, in the order they started
+    pub downloads: Vec<DownloadProgress>,
+    /// The number of bytes downloaded so far by the `Zester`
+    pub total_bytes: u64,
+    /// The combined transfer speed of all of the downloads in bytes per second
+    pub bytes_per_sec: f64,
+}
+
+#[derive(Debug, Default)]
+struct Downloads {
+    next_id: u64,
+    active: BTreeMap<u64, ActiveDownload>,
+    total_bytes: u64,
+    meter: SpeedMeter,
+}
+
+#[derive(Debug)]
+struct ActiveDownload {
+    track_id: Option<TrackId>,
+    title: Option<String>,
+    bytes: u64,
+    total_bytes: Option<u64>,
+    meter: SpeedMeter,
+}
+
+/// A handle for querying the progress of all of a `Zester`'s downloads at any
+/// time, from any thread (see `Zester::progress_handle`).
+///
+/// Unlike the progress reported in events, which only covers the zest that
+/// reported it, this covers every download the `Zester` is making, including
+/// concurrent ones, so it can drive something like a global speedometer.
+#[derive(Debug, Clone, Default)]
+pub struct ProgressHandle {
+    downloads: Arc<Mutex<Downloads>>,
+}
+
+impl ProgressHandle {
+    /// The progress of the downloads as of right now.
+    pub fn snapshot(&self) -> GlobalProgress {
+        let downloads = self.lock();
+        let now = Instant::now();
+
+        GlobalProgress {
+            downloads: downloads.active.values().map(|d| DownloadProgress {
+                track_id: d.track_id,
+                title: d.title.clone(),
+                bytes: d.bytes,
+                total_bytes: d.total_bytes,
+                bytes_per_sec: d.meter.bytes_per_sec_at(now),
+            }).collect(),
+            total_bytes: downloads.total_bytes,
+            bytes_per_sec: downloads.meter.bytes_per_sec_at(now),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Downloads> {
+        self.downloads.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn start_download(&self, track: &Track, total_bytes: Option<u64>) -> u64 {
+        let mut downloads = self.lock();
+        let id = downloads.next_id;
+        downloads.next_id += 1;
+        downloads.active.insert(id, ActiveDownload {
+            track_id: track.id,
+            title: track.title.clone(),
+            bytes: 0,
+            total_bytes,
+            meter: SpeedMeter::default(),
+        });
+        id
+    }
+
+    // Record more bytes for the given download, returning the speed of the
+    // download and the overall speed
+    fn add_bytes(&self, id: u64, bytes: u64, now: Instant) -> (f64, f64) {
+        let mut downloads = self.lock();
+        downloads.total_bytes += bytes;
+        let total_bytes = downloads.total_bytes;
+        downloads.meter.record(now, total_bytes);
+        let global_speed = downloads.meter.bytes_per_sec();
+
+        let track_speed = match downloads.active.get_mut(&id) {
+            Some(download) => {
+                download.bytes += bytes;
+                download.meter.record(now, download.bytes);
+                download.meter.bytes_per_sec()
+            },
+            None => 0.0
+        };
+
+        (track_speed, global_speed)
+    }
+
+    fn finish_download(&self, id: u64) {
+        self.lock().active.remove(&id);
+    }
+}
+
+/// Keeps track of the data needed to produce `ZestProgress` snapshots.
+pub(crate) struct ProgressTracker {
+    progress: ZestProgress,
+    meter: SpeedMeter,
+    /// Where the progress of the current track is shared, and its id there
+    handle: ProgressHandle,
+    current: Option<u64>,
+}
+
+impl ProgressTracker {
+    pub fn new(tracks_total: u64, estimated_total_bytes: u64, handle: ProgressHandle) -> Self {
+        Self {
+            progress: ZestProgress {
+                track_bytes: 0,
+                track_total_bytes: None,
+                track_bytes_per_sec: 0.0,
+                total_bytes: 0,
+                estimated_total_bytes,
+                tracks_done: 0,
+                tracks_total,
+                bytes_per_sec: 0.0,
+                global_bytes_per_sec: 0.0,
+                eta: None,
+            },
+            meter: SpeedMeter::default(),
+            handle,
+            current: None,
+        }
+    }
+
+    /// Start tracking a new download of the given track, discarding any
+    /// progress made by a previous attempt at downloading it.
+    pub fn start_track(&mut self, track: &Track, track_total_bytes: Option<u64>) {
+        if let Some(id) = self.current.take() {
+            self.handle.finish_download(id);
+        }
+        self.current = Some(self.handle.start_download(track, track_total_bytes));
+
+        self.progress.total_bytes -= self.progress.track_bytes;
+        self.progress.track_bytes = 0;
+        self.progress.track_total_bytes = track_total_bytes;
+        self.progress.track_bytes_per_sec = 0.0;
+    }
+
+    /// Record that `bytes` more bytes of the current track were downloaded.
+    pub fn add_bytes(&mut self, bytes: u64) {
+        self.progress.track_bytes += bytes;
+        self.progress.total_bytes += bytes;
+
+        let now = Instant::now();
+        self.meter.record(now, self.progress.total_bytes);
+        self.progress.bytes_per_sec = self.meter.bytes_per_sec();
+        if let Some(id) = self.current {
+            let (track_speed, global_speed) = self.handle.add_bytes(id, bytes, now);
+            self.progress.track_bytes_per_sec = track_speed;
+            self.progress.global_bytes_per_sec = global_speed;
+        }
+
+        self.progress.eta = if self.progress.bytes_per_sec > 0.0 {
+            let remaining = self.progress.estimated_total_bytes.saturating_sub(self.progress.total_bytes);
+            Some(Duration::from_secs_f64(remaining as f64 / self.progress.bytes_per_sec))
+        } else {
+            None
+        };
+    }
+
+    /// Record that the current track has been dealt with, whether or not it
+    /// was downloaded successfully.
+    pub fn finish_track(&mut self) {
+        if let Some(id) = self.current.take() {
+            self.handle.finish_download(id);
+        }
+
+        self.progress.tracks_done += 1;
+        self.progress.track_bytes = 0;
+        self.progress.track_total_bytes = None;
+        self.progress.track_bytes_per_sec = 0.0;
+    }
+
+    pub fn snapshot(&self) -> ZestProgress {
+        self.progress.clone()
+    }
+}
+
+// Downloads that end without being finished (such as when a zest is
+// abandoned) shouldn't linger in the handle
+impl Drop for ProgressTracker {
+    fn drop(&mut self) {
+        if let Some(id) = self.current.take() {
+            self.handle.finish_download(id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn global_progress() {
+        let handle = ProgressHandle::default();
+        let track = |id: u64| -> Track { serde_json::from_value(serde_json::json!({ "id": id })).unwrap() };
+
+        let mut first = ProgressTracker::new(1, 100, handle.clone());
+        let mut second = ProgressTracker::new(1, 100, handle.clone());
+        first.start_track(&track(1), Some(60));
+        second.start_track(&track(2), None);
+        first.add_bytes(10);
+        second.add_bytes(5);
+        first.add_bytes(20);
+
+        let global = handle.snapshot();
+        assert_eq!(global.total_bytes, 35);
+        assert_eq!(global.downloads.iter().map(|d| d.bytes).collect::<Vec<_>>(), vec![30, 5]);
+        assert_eq!(global.downloads[0].total_bytes, Some(60));
+
+        // retrying a track replaces its download
+        second.start_track(&track(2), None);
+        assert_eq!(handle.snapshot().downloads[1].bytes, 0);
+
+        first.finish_track();
+        drop(second);
+        assert!(handle.snapshot().downloads.is_empty());
+        assert_eq!(handle.snapshot().total_bytes, 35);
+    }
+}
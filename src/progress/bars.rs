@@ -0,0 +1,183 @@
+//! Ready-made `indicatif` progress bars driven by zesting events.
+
+use crate::events::*;
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+
+const OVERALL_TEMPLATE: &str = "{msg:30} [{bar:40}] {pos}/{len} ({eta})";
+const ITEM_TEMPLATE: &str = "  {wide_msg} [{bar:40}] {bytes}/{total_bytes} {bytes_per_sec}";
+
+/// A pair of progress bars showing how a zest is going: one for overall
+/// progress, and one for the item (such as a track download) that's
+/// currently in progress.
+///
+/// Pass the events you receive from a `Zester` to the method matching their
+/// type, for example:
+///
+/// ```ignore
+/// let bars = ZestBars::new();
+/// let likes = zester.likes(u64::MAX, |e| bars.likes(&e))?;
+/// zester.likes_audio(&likes, |e| bars.tracks_audio(&e))?;
+/// bars.finish();
+/// ```
+///
+/// Events are taken by reference so that you can go on to handle them
+/// yourself. Errors and pauses are printed above the bars.
+pub struct ZestBars {
+    overall: ProgressBar,
+    item: ProgressBar
+}
+
+impl ZestBars {
+    /// Draw the bars to stderr.
+    pub fn new() -> Self {
+        Self::with_multi(&MultiProgress::new())
+    }
+
+    /// Add the bars to an existing `MultiProgress`, so they can be shown
+    /// alongside bars of your own.
+    pub fn with_multi(multi: &MultiProgress) -> Self {
+        let overall = multi.add(ProgressBar::new(0));
+        overall.set_style(style(OVERALL_TEMPLATE));
+        let item = multi.add(ProgressBar::new(0));
+        item.set_style(style(ITEM_TEMPLATE));
+
+        Self { overall, item }
+    }
+
+    /// Update the bars for an event from `Zester::likes`.
+    pub fn likes(&self, event: &LikesZestingEvent) {
+        use LikesZestingEvent::*;
+
+        match event {
+            NumLikesInfoToDownload { num } => self.start("Zesting likes", *num),
+            MoreLikesInfoDownloaded { count } => self.overall.inc(*count as u64),
+            PausedAfterServerError { .. } => self.overall.println(event.to_string())
+        }
+    }
+
+    /// Update the bars for an event from `Zester::playlists`.
+    pub fn playlists(&self, event: &PlaylistsZestingEvent) {
+        use PlaylistsZestingEvent::*;
+
+        match event {
+            NumPlaylistInfoToDownload { num } => self.start("Zesting playlists", *num),
+            MorePlaylistMetaInfoDownloaded { count } => self.overall.inc(*count as u64),
+            FinishPlaylistMetaInfoDownloading => {
+                let len = self.overall.position();
+                self.start("Zesting playlist info", len);
+            },
+            StartPlaylistInfoDownload { playlist_meta } => {
+                self.item.reset();
+                self.item.set_length(0);
+                self.item.set_message(playlist_meta.title.clone().unwrap_or_default());
+            },
+            FinishPlaylistInfoDownload { .. } => self.overall.inc(1),
+            PlaylistInfoDownloadError { .. } => {
+                self.overall.println(event.to_string());
+                self.overall.inc(1);
+            },
+            TracksInfoCompletion(e, _) => match e {
+                TracksInfoCompletionEvent::NumBatchesToDownload { tracks_num, .. } => {
+                    self.item.set_length(*tracks_num);
+                },
+                TracksInfoCompletionEvent::FinishBatchDownload { count, .. } => self.item.inc(*count as u64),
+                TracksInfoCompletionEvent::StartBatchDownload { .. } |
+                TracksInfoCompletionEvent::FetchedRemainingTrackIds { .. } => {},
+                TracksInfoCompletionEvent::TracksUnavailable { .. } |
+                TracksInfoCompletionEvent::PausedAfterServerError { .. } => self.overall.println(e.to_string())
+            },
+            PlaylistMetaNotPresent | PlaylistInfoCompletionError { .. } | PausedAfterServerError { .. } => {
+                self.overall.println(event.to_string())
+            }
+        }
+    }
+
+    /// Update the bars for an event from one of the track audio zesting
+    /// methods, such as `Zester::likes_audio`.
+    pub fn tracks_audio(&self, event: &TracksAudioZestingEvent) {
+        use TracksAudioZestingEvent::*;
+
+        match event {
+            NumTracksToDownload { num } => self.start("Downloading tracks", *num),
+            NumBytesToDownload { .. } => {},
+            StartTrackDownload { track_info } => {
+                self.item.reset();
+                self.item.set_length(0);
+                self.item.set_message(TrackName(track_info).to_string());
+            },
+            TrackDownloadProgress { progress, .. } => {
+                self.item.set_length(progress.track_total_bytes.unwrap_or(0));
+                self.item.set_position(progress.track_bytes);
+            },
+            FinishTrackDownload { .. } | SkippedByFilter { .. } => self.overall.inc(1),
+            SkippedDrmProtected { .. } | TrackSkipped { .. } | TrackDownloadError { .. } => {
+                self.overall.println(event.to_string());
+                self.overall.inc(1);
+            },
+            TrackVerificationFailed { .. } | SnippetOnlyDownloaded { .. } | PausedAfterServerError { .. } => {
+                self.overall.println(event.to_string())
+            }
+        }
+    }
+
+    /// Update the bars for an event from `Zester::playlists_audio`.
+    pub fn playlists_audio(&self, event: &PlaylistsAudioZestingEvent) {
+        use PlaylistsAudioZestingEvent::*;
+
+        match event {
+            NumItemsToDownload { tracks_num, .. } => self.start("Downloading playlists", *tracks_num),
+            StartPlaylistDownload { playlist_info } => {
+                let title = playlist_info.title.as_deref().unwrap_or("Untitled");
+                self.overall.set_message(format!("Downloading '{}'", title));
+            },
+            TrackEvent { event: TracksAudioZestingEvent::NumTracksToDownload { .. }, .. } => {},
+            TrackEvent { event, .. } => self.tracks_audio(event),
+            FinishPlaylistDownload { .. } => {}
+        }
+    }
+
+    /// Update the bars for an event from `Zester::archive_to`.
+    pub fn archive(&self, event: &ArchiveEvent) {
+        use ArchiveEvent::*;
+
+        match event {
+            Likes(e) => self.likes(e),
+            Playlists(e) => self.playlists(e),
+            TrackAudio(e) => self.tracks_audio(e),
+            SpotlightError { .. }
+            | TrackSaveError { .. }
+            | TrackTranscodeError { .. }
+            | SidecarSaveError { .. }
+            | ArtworkSaveError { .. } => self.overall.println(event.to_string()),
+            _ => {}
+        }
+    }
+
+    /// Mark the zest as finished, leaving the overall bar on screen.
+    pub fn finish(&self) {
+        self.item.finish_and_clear();
+        self.overall.finish();
+    }
+
+    // Start a new stage of the zest, with `len` items to get through
+    fn start(&self, message: &'static str, len: u64) {
+        self.overall.reset();
+        self.overall.set_length(len);
+        self.overall.set_message(message);
+        self.item.reset();
+        self.item.set_length(0);
+        self.item.set_message("");
+    }
+}
+
+impl Default for ZestBars {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn style(template: &str) -> ProgressStyle {
+    ProgressStyle::with_template(template)
+        .expect("progress bar template is valid")
+        .progress_chars("=> ")
+}
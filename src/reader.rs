@@ -0,0 +1,283 @@
+use crate::metrics::Metrics;
+use std::cmp::min;
+use std::error;
+use std::fmt;
+use std::io::{self, Read};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// A `Read` wrapper that fails with `io::ErrorKind::TimedOut` once a deadline
+/// has passed.
+///
+/// The underlying socket's read timeout keeps individual reads from blocking
+/// forever; this makes sure a response that trickles in slowly can't take
+/// longer than the configured request timeout either.
+pub struct DeadlineReader<R> {
+    inner: R,
+    deadline: Option<Instant>,
+}
+
+impl<R: Read> DeadlineReader<R> {
+    /// Wrap `inner`, allowing it to be read from for at most `timeout`.
+    pub fn new(inner: R, timeout: Option<Duration>) -> Self {
+        Self::with_deadline(inner, timeout.map(|t| Instant::now() + t))
+    }
+
+    /// Wrap `inner`, allowing it to be read from until `deadline`.
+    ///
+    /// This is what a request's response body is wrapped in, with the deadline
+    /// worked out before the request was sent, so that the time spent waiting
+    /// for the response's headers counts towards the request's timeout.
+    pub fn with_deadline(inner: R, deadline: Option<Instant>) -> Self {
+        Self { inner, deadline }
+    }
+}
+
+impl<R: Read> Read for DeadlineReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if let Some(deadline) = self.deadline {
+            if Instant::now() >= deadline {
+                return Err(io::Error::new(io::ErrorKind::TimedOut, "request timeout exceeded"));
+            }
+        }
+
+        self.inner.read(buf)
+    }
+}
+
+/// Bandwidth limiting state shared between all of the downloads made by a
+/// `Zester`, so that the limit applies to them as a whole.
+pub(crate) struct Throttle {
+    /// The point in time at which the bandwidth used so far will have been
+    /// "paid off"
+    next_free: Mutex<Instant>,
+}
+
+/// How far behind the schedule the throttle is allowed to fall, which allows
+/// for short bursts after idle periods
+const THROTTLE_BURST: Duration = Duration::from_secs(1);
+
+impl Throttle {
+    pub fn new() -> Self {
+        Self {
+            next_free: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Account for `bytes` having been transferred, sleeping as long as
+    /// necessary to keep the overall rate at or below `bytes_per_sec`.
+    fn consume(&self, bytes: u64, bytes_per_sec: u64) {
+        let wait = {
+            let mut next_free = self.next_free.lock().unwrap_or_else(|e| e.into_inner());
+            let now = Instant::now();
+
+            let start = if *next_free + THROTTLE_BURST < now {
+                now - THROTTLE_BURST
+            } else {
+                *next_free
+            };
+            *next_free = start + Duration::from_secs_f64(bytes as f64 / bytes_per_sec.max(1) as f64);
+
+            next_free.saturating_duration_since(now)
+        };
+
+        if wait > Duration::from_millis(0) {
+            thread::sleep(wait);
+        }
+    }
+}
+
+/// A `Read` wrapper that limits the rate data is read at using a shared
+/// `Throttle`.
+pub struct ThrottledReader<R> {
+    inner: R,
+    throttle: Arc<Throttle>,
+    /// `None` means no limit
+    bytes_per_sec: Option<u64>,
+}
+
+impl<R: Read> ThrottledReader<R> {
+    pub(crate) fn new(inner: R, throttle: Arc<Throttle>, bytes_per_sec: Option<u64>) -> Self {
+        Self {
+            inner,
+            throttle,
+            bytes_per_sec,
+        }
+    }
+}
+
+impl<R: Read> Read for ThrottledReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let bytes_per_sec = match self.bytes_per_sec {
+            Some(bytes_per_sec) => bytes_per_sec,
+            None => return self.inner.read(buf)
+        };
+
+        // Don't read more than a second's worth at a time, so that the rate
+        // stays smooth
+        let max_len = min(buf.len(), bytes_per_sec.max(1) as usize);
+        let read = self.inner.read(&mut buf[..max_len])?;
+        self.throttle.consume(read as u64, bytes_per_sec);
+
+        Ok(read)
+    }
+}
+
+/// A `Read` wrapper that reports the number of bytes read to a `Zester`'s
+/// metrics.
+pub struct MeteredReader<R> {
+    inner: R,
+    metrics: Option<Arc<dyn Metrics>>,
+    endpoint: String,
+}
+
+impl<R: Read> MeteredReader<R> {
+    pub(crate) fn new(inner: R, metrics: Option<Arc<dyn Metrics>>, endpoint: String) -> Self {
+        Self {
+            inner,
+            metrics,
+            endpoint,
+        }
+    }
+}
+
+impl<R: Read> Read for MeteredReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        if let Some(metrics) = &self.metrics {
+            metrics.bytes_downloaded(&self.endpoint, read as u64);
+        }
+
+        Ok(read)
+    }
+}
+
+/// The error inside the `io::Error` returned by a `StallReader` when no data
+/// arrives in time.
+#[derive(Debug)]
+pub(crate) struct Stalled(pub Duration);
+
+impl fmt::Display for Stalled {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "no data received for {:?}", self.0)
+    }
+}
+
+impl error::Error for Stalled {}
+
+/// The error inside the `io::Error` returned when reading a track's audio from
+/// a `FinishTrackDownload` event fails because downloading it did.
+///
+/// The download's failure is reported separately by the zest, so a callback
+/// that's saving the audio can use this to avoid reporting it twice.
+#[derive(Debug)]
+pub struct DownloadInterrupted(pub(crate) String);
+
+impl fmt::Display for DownloadInterrupted {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "the download failed: {}", self.0)
+    }
+}
+
+impl error::Error for DownloadInterrupted {}
+
+impl DownloadInterrupted {
+    /// Returns true if the given error came from reading audio whose download
+    /// failed.
+    pub fn caused(err: &crate::Error) -> bool {
+        match err {
+            crate::Error::IoError(e) => e.get_ref().is_some_and(|e| e.is::<DownloadInterrupted>()),
+            _ => false
+        }
+    }
+}
+
+/// A `Read` wrapper that fails with `io::ErrorKind::TimedOut` if no data
+/// arrives for a while.
+///
+/// A connection can trickle in data slowly enough that the socket's read
+/// timeout never fires while no usable data arrives (for example in the middle
+/// of a TLS record). Each read is timed, and one that took longer than the
+/// window fails even if it eventually returned data. A read that blocks
+/// completely is still only given up on once the socket's read timeout fires,
+/// which is why the window should be shorter than that timeout.
+pub struct StallReader<R> {
+    inner: R,
+    /// `None` means stalls aren't detected
+    window: Option<Duration>,
+}
+
+impl<R: Read> StallReader<R> {
+    /// Wrap `inner`, failing if no data arrives for `window`.
+    pub(crate) fn new(inner: R, window: Option<Duration>) -> Self {
+        Self { inner, window }
+    }
+}
+
+impl<R: Read> Read for StallReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let window = match self.window {
+            Some(window) => window,
+            None => return self.inner.read(buf)
+        };
+
+        let started = Instant::now();
+        let result = self.inner.read(buf);
+        if started.elapsed() >= window {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, Stalled(window)));
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Error;
+
+    // Hands out its data one byte at a time, then blocks for a while
+    struct Trickle(Vec<u8>, Duration);
+
+    impl Read for Trickle {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.0.is_empty() {
+                thread::sleep(self.1);
+                return Ok(0);
+            }
+
+            buf[0] = self.0.remove(0);
+            Ok(1)
+        }
+    }
+
+    #[test]
+    fn stall_detection() {
+        let mut data = vec![];
+        let mut reader = StallReader::new(Trickle(b"abc".to_vec(), Duration::from_millis(10)), Some(Duration::from_secs(5)));
+        reader.read_to_end(&mut data).unwrap();
+        assert_eq!(data, b"abc");
+
+        let mut data = vec![];
+        let mut reader = StallReader::new(Trickle(b"abc".to_vec(), Duration::from_millis(200)), Some(Duration::from_millis(50)));
+        let err = Error::from(reader.read_to_end(&mut data).unwrap_err());
+        assert_eq!(data, b"abc");
+        assert!(matches!(err, Error::Stalled(_)));
+        assert!(err.is_retryable());
+    }
+
+    #[test]
+    fn deadline_includes_time_before_reading() {
+        // a deadline that passed while waiting for the response's headers
+        // fails the very first read
+        let mut reader = DeadlineReader::with_deadline(&b"abc"[..], Some(Instant::now()));
+        let err = reader.read(&mut [0; 3]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+
+        let mut data = vec![];
+        let mut reader = DeadlineReader::with_deadline(&b"abc"[..], Some(Instant::now() + Duration::from_secs(60)));
+        reader.read_to_end(&mut data).unwrap();
+        assert_eq!(data, b"abc");
+    }
+}
@@ -0,0 +1,376 @@
+//! Recording the events of a zest to a JSON Lines file, as an audit log of
+//! exactly what was downloaded, skipped, retried and failed during a session.
+//!
+//! A log can be read back into a `SessionSummary`, so that the outcome of a
+//! previous session can be shown without querying the API again.
+//!
+//! ```ignore
+//! let recorder = EventRecorder::create("zest-events.jsonl")?;
+//! zester.tracks_audio(tracks.iter(), |e| {
+//!     let _ = recorder.record(&e);
+//!     // ...
+//! })?;
+//! ```
+
+use crate::api::common::datetime;
+use crate::api::ids::TrackId;
+use crate::events::EventRecord;
+use crate::report::SkipReason;
+use crate::Error;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_derive::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+/// A line of an event log: an event and when it occurred.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoggedEvent {
+    /// When the event occurred, to the second (`None` if the log line didn't
+    /// have a readable timestamp)
+    #[serde(default, with = "datetime")]
+    pub time: Option<DateTime<Utc>>,
+    #[serde(flatten)]
+    pub record: EventRecord,
+}
+
+/// Writes every event it's given to a JSON Lines file, one `LoggedEvent` per
+/// line.
+///
+/// Events are recorded through a shared reference, so a recorder can be used
+/// from inside the `Fn` callbacks that zesting functions take. Each line is
+/// flushed as it's written, so the log is complete up to the last event even
+/// if the process dies partway through a zest.
+pub struct EventRecorder<W: Write = BufWriter<File>> {
+    writer: Mutex<W>,
+}
+
+impl EventRecorder {
+    /// Record events into a new file at the given path, replacing any file
+    /// that's already there.
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Ok(Self::new(BufWriter::new(File::create(path)?)))
+    }
+
+    /// Record events at the end of the file at the given path, creating it if
+    /// it doesn't exist, so that several sessions can share a log.
+    pub fn append<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self::new(BufWriter::new(file)))
+    }
+}
+
+impl<W: Write> EventRecorder<W> {
+    /// Record events into the given writer.
+    pub fn new(writer: W) -> Self {
+        Self { writer: Mutex::new(writer) }
+    }
+
+    /// Write the given event to the log, timestamped with the current time.
+    pub fn record<E: Serialize>(&self, event: &E) -> Result<(), Error> {
+        let logged = LoggedEvent {
+            time: Some(Utc::now()),
+            record: EventRecord::new(event)?,
+        };
+        let mut line = serde_json::to_vec(&logged)?;
+        line.push(b'\n');
+
+        // a panic while another thread was writing doesn't leave anything
+        // worth protecting against
+        let mut writer = self.writer.lock().unwrap_or_else(|e| e.into_inner());
+        writer.write_all(&line)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Stop recording, returning the writer that events were written to.
+    pub fn into_inner(self) -> W {
+        self.writer.into_inner().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// A summary of a previous session, reconstructed from its event log.
+///
+/// This covers the same ground as the `ZestReport`s returned during the
+/// session, except that errors are kept as they were recorded (as JSON) since
+/// they can't be brought back to life.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct SessionSummary {
+    /// When the first event in the log occurred
+    #[serde(with = "datetime")]
+    pub started: Option<DateTime<Utc>>,
+    /// When the last event in the log occurred
+    #[serde(with = "datetime")]
+    pub finished: Option<DateTime<Utc>>,
+    /// Tracks whose audio was downloaded successfully
+    pub downloaded: Vec<TrackId>,
+    /// Tracks that were deliberately not downloaded
+    pub skipped: Vec<(TrackId, SkipReason)>,
+    /// Tracks whose audio couldn't be downloaded, with the error that was
+    /// recorded for them
+    pub failed: Vec<(TrackId, Value)>,
+    /// Tracks in `downloaded` for which only a preview snippet was downloaded
+    pub snippets: Vec<TrackId>,
+    /// The number of tracks that were going to be downloaded
+    pub tracks_total: u64,
+    /// The number of bytes of audio downloaded for the tracks that finished
+    pub bytes_downloaded: u64,
+    /// The number of times a request was retried after a server error
+    pub retries: u64,
+    /// The number of lines of the log that couldn't be read, like the last
+    /// line of a log that was cut off when the process writing it was killed
+    pub unreadable_lines: u64,
+}
+
+impl SessionSummary {
+    /// Summarize the event log at the given path.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Self::from_reader(BufReader::new(File::open(path)?))
+    }
+
+    /// Summarize an event log read from the given reader.
+    ///
+    /// Lines that aren't valid events are skipped (and counted in
+    /// `unreadable_lines`) rather than failing the whole summary, so that a
+    /// log left half-written by a crash can still be summarized.
+    pub fn from_reader<R: BufRead>(reader: R) -> Result<Self, Error> {
+        let mut summary = Self::default();
+        let mut current = CurrentTrack::default();
+
+        for line in reader.split(b'\n') {
+            let line = line?;
+            if line.iter().all(u8::is_ascii_whitespace) {
+                continue;
+            }
+
+            let logged: LoggedEvent = match serde_json::from_slice(&line) {
+                Ok(logged) => logged,
+                Err(_) => {
+                    summary.unreadable_lines += 1;
+                    continue;
+                }
+            };
+            summary.started = summary.started.or(logged.time);
+            summary.finished = logged.time.or(summary.finished);
+            summary.apply(unnest(logged.record), &mut current);
+        }
+
+        Ok(summary)
+    }
+
+    fn apply(&mut self, record: EventRecord, current: &mut CurrentTrack) {
+        let data = &record.data;
+        let track_id = || serde_json::from_value(data["track_info"]["id"].clone()).unwrap_or_default();
+
+        match record.event.as_str() {
+            "NumTracksToDownload" => self.tracks_total += data["num"].as_u64().unwrap_or(0),
+            "StartTrackDownload" => {
+                let id = track_id();
+                current.bytes = 0;
+                current.handed_over = current.handed_over.filter(|&(handed_over, _)| handed_over == id);
+            },
+            "TrackDownloadProgress" => {
+                let bytes = data["progress"]["track_bytes"].as_u64().unwrap_or(0);
+                if let Some((_, counted)) = &mut current.handed_over {
+                    let more = bytes.saturating_sub(current.bytes);
+                    self.bytes_downloaded += more;
+                    *counted += more;
+                }
+                current.bytes = bytes;
+            },
+            "FinishTrackDownload" => {
+                // the track's audio being handed over again means the last
+                // attempt failed after it was
+                let id = track_id();
+                self.undo_download(current, id);
+                self.downloaded.push(id);
+                self.bytes_downloaded += current.bytes;
+                current.handed_over = Some((id, current.bytes));
+            },
+            "SnippetOnlyDownloaded" => self.snippets.push(track_id()),
+            "SkippedByFilter" => self.skipped.push((track_id(), SkipReason::Filtered)),
+            "SkippedDrmProtected" => self.skipped.push((track_id(), SkipReason::DrmProtected)),
+            "TrackSkipped" => self.skipped.push((track_id(), SkipReason::Cancelled)),
+            "TrackAlreadyArchived" => self.skipped.push((track_id(), SkipReason::AlreadyArchived)),
+            "TrackDownloadError" => {
+                // a streamed download can fail after its audio was handed over
+                self.undo_download(current, track_id());
+                self.failed.push((track_id(), data["err"].clone()));
+            },
+            // the track was downloaded, but its audio couldn't be saved
+            "TrackSaveError" | "TrackTranscodeError" => {
+                let id = track_id();
+                if let Some(pos) = self.downloaded.iter().position(|&d| d == id) {
+                    self.downloaded.remove(pos);
+                }
+                self.snippets.retain(|&s| s != id);
+                self.failed.push((id, data["err"].clone()));
+            },
+            "PausedAfterServerError" => self.retries += 1,
+            _ => {}
+        }
+    }
+
+    // Take back the recording of the given track as downloaded, if it's the
+    // current track and its audio was handed over before its download failed
+    fn undo_download(&mut self, current: &mut CurrentTrack, id: TrackId) {
+        if let Some((_, counted)) = current.handed_over.filter(|&(handed_over, _)| handed_over == id) {
+            current.handed_over = None;
+            if let Some(pos) = self.downloaded.iter().rposition(|&d| d == id) {
+                self.downloaded.remove(pos);
+            }
+            if let Some(pos) = self.snippets.iter().rposition(|&s| s == id) {
+                self.snippets.remove(pos);
+            }
+            self.bytes_downloaded -= counted.min(self.bytes_downloaded);
+        }
+    }
+
+    /// Whether or not every track was either downloaded or skipped.
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+impl fmt::Display for SessionSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} downloaded, {} skipped, {} failed",
+            self.downloaded.len(),
+            self.skipped.len(),
+            self.failed.len()
+        )
+    }
+}
+
+/// The track being downloaded at the current point in an event log.
+#[derive(Default)]
+struct CurrentTrack {
+    /// The number of bytes downloaded for it so far in the current attempt
+    bytes: u64,
+    /// The track's ID and the number of its bytes counted as downloaded, once
+    /// its audio has been handed over. Streamed audio carries on downloading
+    /// (and can still fail) after that.
+    handed_over: Option<(TrackId, u64)>
+}
+
+// Events that wrap the events of another zesting function (like
+// `ArchiveEvent::TrackAudio`) are recorded under the wrapper's name; this digs
+// out the wrapped event
+fn unnest(record: EventRecord) -> EventRecord {
+    let inner = match (record.event.as_str(), &record.data) {
+        ("TrackAudio", _) | ("Likes", _) | ("Playlists", _) => record.data.clone(),
+        // `PlaylistsAudioZestingEvent::TrackEvent` also holds the playlist
+        ("TrackEvent", data) => data["event"].clone(),
+        _ => return record
+    };
+
+    match EventRecord::new(&inner) {
+        Ok(inner) if !inner.event.is_empty() => unnest(inner),
+        _ => record
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::events::TracksZestingEvent;
+    use crate::retry::RetryPause;
+    use std::time::Duration;
+
+    #[test]
+    fn recorded_events() {
+        let recorder = EventRecorder::new(vec![]);
+        recorder.record(&TracksZestingEvent::NumTracksInfoToDownload { num: 2 }).unwrap();
+        recorder.record(&TracksZestingEvent::PausedAfterServerError {
+            retry: RetryPause {
+                pause: Duration::from_secs(2),
+                attempt: 1,
+                max_consecutive_retries: None,
+                total_retries: 1,
+                max_total_retries: None,
+            }
+        }).unwrap();
+
+        let log = String::from_utf8(recorder.into_inner()).unwrap();
+        let events: Vec<LoggedEvent> = log.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].record.event, "NumTracksInfoToDownload");
+        assert_eq!(events[0].record.data["num"], 2);
+        assert_eq!(events[1].record.event, "PausedAfterServerError");
+        assert_eq!(events[1].record.data["retry"]["attempt"], 1);
+        assert!(events[0].time <= events[1].time);
+    }
+
+    #[test]
+    fn session_summary() {
+        let log = [
+            r#"{"time":"2024-05-01T10:00:00Z","event":"NumTracksToDownload","data":{"num":4}}"#,
+            r#"{"time":"2024-05-01T10:00:01Z","event":"StartTrackDownload","data":{"track_info":{"id":1}}}"#,
+            r#"{"time":"2024-05-01T10:00:02Z","event":"TrackDownloadProgress","data":{"track_info":{"id":1},"progress":{"track_bytes":1000}}}"#,
+            r#"{"time":"2024-05-01T10:00:03Z","event":"FinishTrackDownload","data":{"track_info":{"id":1},"file_extension":"mp3"}}"#,
+            r#"{"time":"2024-05-01T10:00:04Z","event":"PausedAfterServerError","data":{"retry":{"attempt":1}}}"#,
+            r#"{"time":"2024-05-01T10:00:05Z","event":"TrackDownloadError","data":{"track_info":{"id":2},"err":"DrmProtected"}}"#,
+            "",
+            r#"{"time":"2024-05-01T10:00:06Z","event":"TrackAudio","data":{"SkippedByFilter":{"track_info":{"id":3}}}}"#,
+            r#"{"time":"2024-05-01T10:00:07Z","event":"TrackEvent","data":{"event":{"TrackSkipped":{"track_info":{"id":4}}},"playlist_info":{"id":9},"track_index":0,"track_count":1}}"#,
+        ].join("\n");
+
+        let summary = SessionSummary::from_reader(log.as_bytes()).unwrap();
+        assert_eq!(summary.to_string(), "1 downloaded, 2 skipped, 1 failed");
+        assert_eq!(summary.downloaded, vec![TrackId(1)]);
+        assert_eq!(summary.skipped, vec![(TrackId(3), SkipReason::Filtered), (TrackId(4), SkipReason::Cancelled)]);
+        assert_eq!(summary.failed, vec![(TrackId(2), Value::from("DrmProtected"))]);
+        assert_eq!((summary.tracks_total, summary.bytes_downloaded, summary.retries), (4, 1000, 1));
+        assert_eq!(summary.finished.unwrap() - summary.started.unwrap(), chrono::Duration::seconds(7));
+        assert_eq!(summary.unreadable_lines, 0);
+    }
+
+    #[test]
+    fn truncated_session_log() {
+        // a log cut off partway through a line (even partway through a
+        // character) is summarized up to where it was cut off
+        let mut log = [
+            r#"{"time":"2024-05-01T10:00:00Z","event":"StartTrackDownload","data":{"track_info":{"id":1}}}"#,
+            r#"{"time":"2024-05-01T10:00:01Z","event":"FinishTrackDownload","data":{"track_info":{"id":1},"file_extension":"mp3"}}"#,
+            r#"{"time":"2024-05-01T10:00:02Z","event":"TrackDownloadError","data":{"track_info":{"id":2},"err":"caf"#,
+        ].join("\n").into_bytes();
+        log.push(0xc3);
+
+        let summary = SessionSummary::from_reader(log.as_slice()).unwrap();
+        assert_eq!(summary.downloaded, vec![TrackId(1)]);
+        assert!(summary.failed.is_empty());
+        assert_eq!(summary.unreadable_lines, 1);
+    }
+
+    #[test]
+    fn streamed_session_summary() {
+        // streamed audio is handed over before it's downloaded, so progress
+        // follows `FinishTrackDownload` and the download can still fail
+        let log = [
+            r#"{"time":"2024-05-01T10:00:00Z","event":"StartTrackDownload","data":{"track_info":{"id":1}}}"#,
+            r#"{"time":"2024-05-01T10:00:01Z","event":"FinishTrackDownload","data":{"track_info":{"id":1},"file_extension":"mp3"}}"#,
+            r#"{"time":"2024-05-01T10:00:02Z","event":"TrackDownloadProgress","data":{"track_info":{"id":1},"progress":{"track_bytes":500}}}"#,
+            r#"{"time":"2024-05-01T10:00:03Z","event":"PausedAfterServerError","data":{"retry":{"attempt":1}}}"#,
+            r#"{"time":"2024-05-01T10:00:04Z","event":"StartTrackDownload","data":{"track_info":{"id":1}}}"#,
+            r#"{"time":"2024-05-01T10:00:05Z","event":"FinishTrackDownload","data":{"track_info":{"id":1},"file_extension":"mp3"}}"#,
+            r#"{"time":"2024-05-01T10:00:06Z","event":"TrackDownloadProgress","data":{"track_info":{"id":1},"progress":{"track_bytes":800}}}"#,
+            r#"{"time":"2024-05-01T10:00:07Z","event":"TrackDownloadProgress","data":{"track_info":{"id":1},"progress":{"track_bytes":1000}}}"#,
+            r#"{"time":"2024-05-01T10:00:08Z","event":"StartTrackDownload","data":{"track_info":{"id":2}}}"#,
+            r#"{"time":"2024-05-01T10:00:09Z","event":"FinishTrackDownload","data":{"track_info":{"id":2},"file_extension":"mp3"}}"#,
+            r#"{"time":"2024-05-01T10:00:10Z","event":"TrackDownloadProgress","data":{"track_info":{"id":2},"progress":{"track_bytes":300}}}"#,
+            r#"{"time":"2024-05-01T10:00:11Z","event":"TrackDownloadError","data":{"track_info":{"id":2},"err":"Stalled"}}"#,
+        ].join("\n");
+
+        let summary = SessionSummary::from_reader(log.as_bytes()).unwrap();
+        assert_eq!(summary.downloaded, vec![TrackId(1)]);
+        assert_eq!(summary.failed, vec![(TrackId(2), Value::from("Stalled"))]);
+        assert_eq!((summary.bytes_downloaded, summary.retries), (1000, 1));
+    }
+}
@@ -0,0 +1,386 @@
+//! Turning the segments of an HLS download into a single, ordinary audio
+//! file.
+//!
+//! Simply concatenating HLS segments gives a file that some players struggle
+//! with: packed audio segments each start with their own ID3 tag, MPEG-TS
+//! segments wrap the audio in transport stream packets, and the first MP3
+//! frame's Xing header only describes the first segment. The remux is done in
+//! pure Rust and keeps the container implied by the transcoding's mime type,
+//! so the audio ends up in the same kind of file as a progressive download.
+
+use crate::api::extension_for_mime_type;
+
+/// The number of samples in each AAC frame
+const AAC_FRAME_SAMPLES: u32 = 1024;
+
+/// The sample rates that ADTS sampling frequency indexes refer to
+const AAC_SAMPLE_RATES: [u32; 13] = [96000, 88200, 64000, 48000, 44100, 32000, 24000, 22050, 16000, 12000, 11025, 8000, 7350];
+
+/// Combine the downloaded segments of an HLS stream into a single audio file,
+/// given the mime type of the transcoding they came from.
+///
+/// * MP3 audio is joined into a raw MP3 stream, without per-segment ID3 tags
+///   or a stale Xing/Info header
+/// * AAC audio that's to be saved as M4A is muxed into an MP4 container
+/// * Raw AAC audio is joined into a single ADTS stream
+///
+/// Remuxing fragmented MP4 segments (an init segment followed by `moof`/`mdat`
+/// fragments) isn't supported. They're concatenated as they are instead,
+/// which gives a valid fragmented MP4 file; most players are fine with those,
+/// but some older ones (and some DJ software) can't seek in them or won't play
+/// them at all. Audio in other formats (or that doesn't look the way it
+/// should) is concatenated as it is too.
+pub fn remux_hls(segments: &[Vec<u8>], mime_type: &str) -> Vec<u8> {
+    let extension = extension_for_mime_type(mime_type);
+    let is_fragmented_mp4 = segments
+        .first()
+        .is_some_and(|s| matches!(s.get(4..8), Some(b"ftyp") | Some(b"styp") | Some(b"moov")));
+
+    if is_fragmented_mp4 || !matches!(extension, Some("mp3") | Some("m4a") | Some("aac")) {
+        return segments.concat();
+    }
+
+    let mut stream = vec![];
+    for segment in segments {
+        match demux_ts(segment) {
+            Some(audio) => stream.extend_from_slice(strip_id3(&audio)),
+            None => stream.extend_from_slice(strip_id3(segment))
+        }
+    }
+
+    match extension {
+        Some("mp3") => strip_xing_frame(stream),
+        Some("m4a") => mux_adts_to_mp4(&stream).unwrap_or(stream),
+        _ => stream
+    }
+}
+
+// Skip past any ID3v2 tags at the start of the data
+fn strip_id3(mut data: &[u8]) -> &[u8] {
+    while data.len() >= 10 && data.starts_with(b"ID3") {
+        // the tag size is a 28 bit "syncsafe" integer
+        let size = data[6..10].iter().fold(0, |size, &b| (size << 7) | (b & 0x7f) as usize);
+        let footer = if data[5] & 0x10 != 0 { 10 } else { 0 };
+        data = data.get(10 + size + footer..).unwrap_or(&[]);
+    }
+
+    data
+}
+
+/// Extract the audio from an MPEG transport stream, returning `None` if the
+/// data isn't one.
+///
+/// The first audio stream found is used.
+fn demux_ts(data: &[u8]) -> Option<Vec<u8>> {
+    const PACKET_LEN: usize = 188;
+
+    if data.is_empty() || data.chunks(PACKET_LEN).any(|p| p.len() != PACKET_LEN || p[0] != 0x47) {
+        return None;
+    }
+
+    let mut audio_pid = None;
+    let mut audio = vec![];
+    for packet in data.chunks(PACKET_LEN) {
+        let pid = ((packet[1] as u16 & 0x1f) << 8) | packet[2] as u16;
+        let payload_start = packet[1] & 0x40 != 0;
+        let offset = match (packet[3] >> 4) & 0x3 {
+            0b01 => 4,
+            0b11 => 5 + packet[4] as usize,
+            // no payload
+            _ => continue
+        };
+        let payload = match packet.get(offset..) {
+            Some(payload) => payload,
+            None => continue
+        };
+
+        if payload_start && payload.starts_with(&[0, 0, 1]) && payload.len() > 8 {
+            // a new PES packet; audio streams have ids 0xc0 to 0xdf
+            let is_audio = (0xc0..=0xdf).contains(&payload[3]);
+            if is_audio && audio_pid.is_none() {
+                audio_pid = Some(pid);
+            }
+
+            if audio_pid == Some(pid) {
+                audio.extend_from_slice(payload.get(9 + payload[8] as usize..).unwrap_or(&[]));
+            }
+        } else if audio_pid == Some(pid) {
+            audio.extend_from_slice(payload);
+        }
+    }
+
+    audio_pid.map(|_| audio)
+}
+
+/// The length of the MPEG layer III frame starting at the given header, and
+/// the offset that a Xing/Info header would be at in the frame.
+fn mp3_frame(header: &[u8]) -> Option<(usize, usize)> {
+    const MPEG1_BITRATES: [u32; 15] = [0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320];
+    const MPEG2_BITRATES: [u32; 15] = [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160];
+
+    if header.len() < 4 || header[0] != 0xff || header[1] & 0xe0 != 0xe0 || (header[1] >> 1) & 0x3 != 0b01 {
+        return None;
+    }
+
+    let version = (header[1] >> 3) & 0x3;
+    let mpeg1 = version == 0b11;
+    let bitrates = if mpeg1 { &MPEG1_BITRATES } else { &MPEG2_BITRATES };
+    let bitrate = bitrates.get((header[2] >> 4) as usize)? * 1000;
+    // MPEG 2 and 2.5 have half and a quarter of MPEG 1's sample rates
+    let sample_rate = [44100u32, 48000, 32000].get(((header[2] >> 2) & 0x3) as usize)? >> match version {
+        0b11 => 0,
+        0b10 => 1,
+        _ => 2
+    };
+    let padding = ((header[2] >> 1) & 0x1) as u32;
+    let mono = header[3] >> 6 == 0b11;
+
+    if bitrate == 0 {
+        return None;
+    }
+
+    let len = if mpeg1 { 144 * bitrate / sample_rate } else { 72 * bitrate / sample_rate } + padding;
+    let side_info_len = match (mpeg1, mono) {
+        (true, false) => 32,
+        (true, true) | (false, false) => 17,
+        (false, true) => 9
+    };
+
+    Some((len as usize, 4 + side_info_len))
+}
+
+// Drop the first frame of an MP3 stream if it's a Xing/Info (or VBRI) header,
+// since it only describes the first segment of the stream
+fn strip_xing_frame(mut stream: Vec<u8>) -> Vec<u8> {
+    if let Some((len, xing_offset)) = mp3_frame(&stream) {
+        let has_header = [xing_offset, 36].iter().any(|&offset| matches!(
+            stream.get(offset..offset + 4),
+            Some(b"Xing") | Some(b"Info") | Some(b"VBRI")
+        ));
+
+        if has_header && len <= stream.len() {
+            stream.drain(..len);
+        }
+    }
+
+    stream
+}
+
+/// Mux a stream of ADTS frames into an MP4 (M4A) file, returning `None` if the
+/// data isn't ADTS.
+fn mux_adts_to_mp4(stream: &[u8]) -> Option<Vec<u8>> {
+    let mut frames = vec![];
+    let mut config = None;
+    let mut rest = stream;
+
+    while !rest.is_empty() {
+        if rest.len() < 7 || rest[0] != 0xff || rest[1] & 0xf0 != 0xf0 {
+            return None;
+        }
+
+        let object_type = (rest[2] >> 6) + 1;
+        let frequency_index = (rest[2] >> 2) & 0xf;
+        let channels = ((rest[2] & 0x1) << 2) | (rest[3] >> 6);
+        let frame_len = ((rest[3] as usize & 0x3) << 11) | ((rest[4] as usize) << 3) | (rest[5] as usize >> 5);
+        let header_len = if rest[1] & 0x1 == 0 { 9 } else { 7 };
+
+        if frame_len < header_len || frame_len > rest.len() {
+            return None;
+        }
+
+        match config {
+            None => config = Some((object_type, frequency_index, channels)),
+            Some(c) if c != (object_type, frequency_index, channels) => return None,
+            _ => {}
+        }
+
+        frames.push(&rest[header_len..frame_len]);
+        rest = &rest[frame_len..];
+    }
+
+    let (object_type, frequency_index, channels) = config?;
+    let sample_rate = *AAC_SAMPLE_RATES.get(frequency_index as usize)?;
+    let audio_specific_config = ((object_type as u16) << 11) | ((frequency_index as u16) << 7) | ((channels as u16) << 3);
+
+    let ftyp = mp4_box(b"ftyp", &[b"M4A ", &0u32.to_be_bytes()[..], b"M4A ", b"mp42", b"isom"].concat());
+
+    // the moov box is the same size whatever the offset of the audio is, so
+    // it's built once to find out where the audio will start
+    let track = Mp4Track { frames: &frames, sample_rate, channels: channels as u16, audio_specific_config };
+    let moov_len = track.moov(0).len();
+    let moov = track.moov((ftyp.len() + moov_len + 8) as u32);
+
+    let mut mp4 = ftyp;
+    mp4.extend_from_slice(&moov);
+    mp4.extend_from_slice(&mp4_box(b"mdat", &frames.concat()));
+    Some(mp4)
+}
+
+/// A single AAC audio track to be written as an MP4 `moov` box.
+struct Mp4Track<'a> {
+    frames: &'a [&'a [u8]],
+    sample_rate: u32,
+    channels: u16,
+    audio_specific_config: u16,
+}
+
+impl Mp4Track<'_> {
+    // The `moov` box for the track, whose audio data starts at the given
+    // offset in the file
+    fn moov(&self, data_offset: u32) -> Vec<u8> {
+        let num_frames = self.frames.len() as u32;
+        let duration = num_frames * AAC_FRAME_SAMPLES;
+        let matrix: Vec<u8> = [0x0001_0000u32, 0, 0, 0, 0x0001_0000, 0, 0, 0, 0x4000_0000]
+            .iter()
+            .flat_map(|v| v.to_be_bytes())
+            .collect();
+
+        let mvhd = full_box(b"mvhd", 0, &[
+            &0u32.to_be_bytes()[..], &0u32.to_be_bytes(), &self.sample_rate.to_be_bytes(), &duration.to_be_bytes(),
+            &0x0001_0000u32.to_be_bytes(), &0x0100u16.to_be_bytes(), &[0; 10], &matrix, &[0; 24], &2u32.to_be_bytes()
+        ].concat());
+
+        let tkhd = full_box(b"tkhd", 0x7, &[
+            &0u32.to_be_bytes()[..], &0u32.to_be_bytes(), &1u32.to_be_bytes(), &[0; 4], &duration.to_be_bytes(),
+            &[0; 8], &0u16.to_be_bytes(), &0u16.to_be_bytes(), &0x0100u16.to_be_bytes(), &[0; 2], &matrix,
+            &0u32.to_be_bytes(), &0u32.to_be_bytes()
+        ].concat());
+
+        let mdhd = full_box(b"mdhd", 0, &[
+            &0u32.to_be_bytes()[..], &0u32.to_be_bytes(), &self.sample_rate.to_be_bytes(), &duration.to_be_bytes(),
+            // the "und" language code
+            &0x55c4u16.to_be_bytes(), &[0; 2]
+        ].concat());
+        let hdlr = full_box(b"hdlr", 0, &[&[0; 4][..], b"soun", &[0; 12], b"SoundHandler\0"].concat());
+
+        let dinf = mp4_box(b"dinf", &full_box(b"dref", 0, &[&1u32.to_be_bytes()[..], &full_box(b"url ", 0x1, &[])].concat()));
+
+        let stbl = mp4_box(b"stbl", &[
+            self.stsd(),
+            full_box(b"stts", 0, &[&1u32.to_be_bytes()[..], &num_frames.to_be_bytes(), &AAC_FRAME_SAMPLES.to_be_bytes()].concat()),
+            // all of the frames go in a single chunk
+            full_box(b"stsc", 0, &[&1u32.to_be_bytes()[..], &1u32.to_be_bytes(), &num_frames.to_be_bytes(), &1u32.to_be_bytes()].concat()),
+            full_box(b"stsz", 0, &[
+                &0u32.to_be_bytes()[..],
+                &num_frames.to_be_bytes(),
+                &self.frames.iter().flat_map(|f| (f.len() as u32).to_be_bytes()).collect::<Vec<_>>()
+            ].concat()),
+            full_box(b"stco", 0, &[&1u32.to_be_bytes()[..], &data_offset.to_be_bytes()].concat())
+        ].concat());
+
+        let minf = mp4_box(b"minf", &[full_box(b"smhd", 0, &[0; 4]), dinf, stbl].concat());
+        let mdia = mp4_box(b"mdia", &[mdhd, hdlr, minf].concat());
+        let trak = mp4_box(b"trak", &[tkhd, mdia].concat());
+
+        mp4_box(b"moov", &[mvhd, trak].concat())
+    }
+
+    // The sample description, with the decoder configuration in an `esds` box
+    fn stsd(&self) -> Vec<u8> {
+        let decoder_specific_info = descriptor(0x05, &self.audio_specific_config.to_be_bytes());
+        let decoder_config = descriptor(0x04, &[
+            // MPEG-4 audio, in an audio stream
+            &[0x40, 0x15][..], &[0; 3], &0u32.to_be_bytes(), &0u32.to_be_bytes(), &decoder_specific_info
+        ].concat());
+        let es = descriptor(0x03, &[&1u16.to_be_bytes()[..], &[0], &decoder_config, &descriptor(0x06, &[0x02])].concat());
+
+        let mp4a = mp4_box(b"mp4a", &[
+            &[0; 6][..], &1u16.to_be_bytes(), &[0; 8], &self.channels.to_be_bytes(), &16u16.to_be_bytes(), &[0; 4],
+            &(self.sample_rate.min(u16::MAX as u32) << 16).to_be_bytes(), &full_box(b"esds", 0, &es)
+        ].concat());
+
+        full_box(b"stsd", 0, &[&1u32.to_be_bytes()[..], &mp4a].concat())
+    }
+}
+
+fn mp4_box(kind: &[u8; 4], contents: &[u8]) -> Vec<u8> {
+    [&(contents.len() as u32 + 8).to_be_bytes()[..], kind, contents].concat()
+}
+
+fn full_box(kind: &[u8; 4], flags: u32, contents: &[u8]) -> Vec<u8> {
+    // the version (always 0 here) goes in the top byte
+    mp4_box(kind, &[&(flags & 0x00ff_ffff).to_be_bytes()[..], contents].concat())
+}
+
+// An MPEG-4 descriptor, which are all small enough for a single length byte
+fn descriptor(tag: u8, contents: &[u8]) -> Vec<u8> {
+    [&[tag, contents.len() as u8][..], contents].concat()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::convert::TryInto;
+
+    fn adts_frame(payload: &[u8]) -> Vec<u8> {
+        // AAC LC, 44.1kHz, stereo, no CRC
+        let len = payload.len() + 7;
+        let mut frame = vec![0xff, 0xf1, 0x50, 0x80 | (len >> 11) as u8, (len >> 3) as u8, ((len & 0x7) << 5) as u8 | 0x1f, 0xfc];
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    fn ts_packets(pid: u16, pes: &[u8]) -> Vec<u8> {
+        pes.chunks(182).enumerate().flat_map(|(i, chunk)| {
+            let start = if i == 0 { 0x40 } else { 0 };
+            // an adaptation field pads out each packet
+            let stuffing = 182 - chunk.len();
+            let mut packet = vec![0x47, start | (pid >> 8) as u8, pid as u8, 0x30, stuffing as u8 + 1, 0];
+            packet.resize(6 + stuffing, 0xff);
+            packet.extend_from_slice(chunk);
+            packet
+        }).collect()
+    }
+
+    #[test]
+    fn remux_mp3_segments() {
+        // a 128kbps 44.1kHz stereo frame is 417 bytes long
+        let frame = |fill: u8| [&[0xff, 0xfb, 0x90, 0x00][..], &[fill; 413]].concat();
+        let mut xing = frame(0);
+        xing[36..40].copy_from_slice(b"Info");
+        let id3 = [&b"ID3\x04\x00\x00\x00\x00\x00\x02"[..], &[0; 2]].concat();
+
+        let segments = vec![
+            [&id3[..], &xing, &frame(1)].concat(),
+            [&id3[..], &frame(2)].concat()
+        ];
+
+        assert_eq!(remux_hls(&segments, "audio/mpeg"), [frame(1), frame(2)].concat());
+    }
+
+    #[test]
+    fn remux_ts_to_m4a() {
+        let frames = [adts_frame(&[1; 10]), adts_frame(&[2; 300])];
+        let pes = [&[0, 0, 1, 0xc0, 0, 0, 0x80, 0x80, 0x05, 0, 0, 0, 0, 0][..], &frames.concat()].concat();
+        let segments = vec![ts_packets(0x100, &pes)];
+
+        assert_eq!(demux_ts(&segments[0]).unwrap().len(), frames.concat().len());
+
+        let m4a = remux_hls(&segments, "audio/mp4; codecs=\"mp4a.40.2\"");
+        assert_eq!(&m4a[4..8], b"ftyp");
+        assert!(m4a.ends_with(&[&[1; 10][..], &[2; 300]].concat()));
+
+        // the audio data is where the chunk offset says it is
+        let stco = m4a.windows(4).position(|w| w == b"stco").unwrap();
+        let offset = u32::from_be_bytes(m4a[stco + 12..stco + 16].try_into().unwrap()) as usize;
+        assert_eq!(&m4a[offset - 4..offset], b"mdat");
+        assert_eq!(m4a.len() - offset, 310);
+
+        // fragmented MP4 segments are left alone
+        let fmp4 = vec![mp4_box(b"ftyp", b"iso6"), mp4_box(b"moof", &[])];
+        assert_eq!(remux_hls(&fmp4, "audio/mp4"), fmp4.concat());
+    }
+
+    #[test]
+    fn fragmented_mp4_is_concatenated() {
+        // remuxing fragmented MP4 isn't supported, so an init segment and its
+        // fragments (each starting with a `styp` or `moof` box) are joined as
+        // they are
+        let init = [mp4_box(b"ftyp", b"iso6"), mp4_box(b"moov", &[])].concat();
+        let fragment = |fill: u8| [mp4_box(b"styp", b"msdh"), mp4_box(b"moof", &[]), mp4_box(b"mdat", &[fill; 8])].concat();
+        let segments = vec![init, fragment(1), fragment(2)];
+
+        assert_eq!(remux_hls(&segments, "audio/mp4; codecs=\"mp4a.40.2\""), segments.concat());
+        assert_eq!(remux_hls(&segments[1..], "audio/mp4"), segments[1..].concat());
+    }
+}
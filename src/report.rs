@@ -0,0 +1,85 @@
+use crate::Error;
+use crate::api::common::Track;
+use crate::api::ids::TrackId;
+use serde_derive::Serialize;
+
+/// The reason a track's audio was skipped rather than downloaded
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum SkipReason {
+    /// The track's audio was already present in an archive
+    AlreadyArchived,
+    /// The track was excluded by a filter
+    Filtered,
+    /// The track's audio is only available with DRM
+    DrmProtected,
+    /// The download was skipped part way through at the request of the
+    /// callback (see `DownloadControl::SkipTrack`)
+    Cancelled,
+}
+
+/// A summary of the outcome of zesting audio for a set of tracks.
+///
+/// Tracks are identified by their id (or 0 if the API didn't provide one).
+#[derive(Debug, Default, Serialize)]
+pub struct ZestReport {
+    /// Tracks whose audio was downloaded successfully
+    pub downloaded: Vec<TrackId>,
+    /// Tracks that were deliberately not downloaded
+    pub skipped: Vec<(TrackId, SkipReason)>,
+    /// Tracks whose audio couldn't be downloaded
+    pub failed: Vec<(TrackId, Error)>,
+    /// Tracks in `downloaded` for which only a preview snippet was available,
+    /// so only the snippet was downloaded (see
+    /// `ZesterConfig::download_snippets`)
+    pub snippets: Vec<TrackId>,
+}
+
+impl ZestReport {
+    /// Whether or not every track was either downloaded or skipped.
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty()
+    }
+
+    /// The total number of tracks covered by this report.
+    pub fn len(&self) -> usize {
+        self.downloaded.len() + self.skipped.len() + self.failed.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub(crate) fn record_downloaded(&mut self, track: &Track) {
+        self.downloaded.push(track.id.unwrap_or_default());
+    }
+
+    pub(crate) fn record_skipped(&mut self, track: &Track, reason: SkipReason) {
+        self.skipped.push((track.id.unwrap_or_default(), reason));
+    }
+
+    pub(crate) fn record_snippet(&mut self, track: &Track) {
+        self.snippets.push(track.id.unwrap_or_default());
+    }
+
+    pub(crate) fn record_failed(&mut self, track: &Track, err: Error) {
+        self.failed.push((track.id.unwrap_or_default(), err));
+    }
+
+    /// Record that a track previously recorded as downloaded failed after all
+    /// (for example because its audio couldn't be saved).
+    pub(crate) fn downgrade_to_failed(&mut self, id: TrackId, err: Error) {
+        if let Some(pos) = self.downloaded.iter().position(|&d| d == id) {
+            self.downloaded.remove(pos);
+        }
+        self.snippets.retain(|&s| s != id);
+        self.failed.push((id, err));
+    }
+
+    /// Add the outcomes recorded in another report to this one.
+    pub fn merge(&mut self, other: ZestReport) {
+        self.downloaded.extend(other.downloaded);
+        self.skipped.extend(other.skipped);
+        self.failed.extend(other.failed);
+        self.snippets.extend(other.snippets);
+    }
+}
@@ -0,0 +1,161 @@
+//! Retrying requests that fail because of server errors.
+
+use crate::config::RetryPolicy;
+use crate::metrics::Metrics;
+use crate::{Error, Zester};
+use serde_derive::Serialize;
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::thread;
+use std::time::Duration;
+
+/// Information about a pause before a request is retried.
+#[derive(Debug, Clone, Serialize)]
+pub struct RetryPause {
+    /// The amount of time we're waiting before retrying
+    pub pause: Duration,
+    /// How many times in a row the request has been retried, including this
+    /// time
+    pub attempt: u32,
+    /// The maximum number of times in a row the request will be retried, if
+    /// there is a limit
+    pub max_consecutive_retries: Option<u32>,
+    /// How many retries have been made over the whole zest, including this one
+    pub total_retries: u32,
+    /// The maximum number of retries that will be made over the whole zest, if
+    /// there is a limit
+    pub max_total_retries: Option<u32>,
+}
+
+impl fmt::Display for RetryPause {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Server error, retrying in {:?} (attempt {}", self.pause, self.attempt)?;
+        if let Some(max) = self.max_consecutive_retries {
+            write!(f, " of {}", max)?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// Keeps track of retries according to a `RetryPolicy`.
+///
+/// A single retrier should be used for every request made by a zesting call,
+/// so that the retries all count towards the same budget.
+pub(crate) struct Retrier<'a> {
+    policy: &'a RetryPolicy,
+    metrics: Option<&'a dyn Metrics>,
+    consecutive: u32,
+    total: u32,
+}
+
+impl<'a> Retrier<'a> {
+    /// Create a retrier that follows the given `Zester`'s retry policy and
+    /// reports into its metrics.
+    pub fn new(zester: &'a Zester) -> Self {
+        Self {
+            policy: &zester.config.retry,
+            metrics: zester.metrics.as_deref(),
+            consecutive: 0,
+            total: 0,
+        }
+    }
+
+    /// Called after a request failed with a server error.
+    ///
+    /// If the request may be retried, `on_pause` is called, the configured
+    /// pause is waited out, and true is returned. If the request has been
+    /// retried too many times in a row, false is returned and the request
+    /// should be given up on. If the overall retry budget has been used up, an
+    /// error is returned and the whole zest should be abandoned.
+    pub fn pause<F: FnOnce(RetryPause)>(&mut self, on_pause: F) -> Result<bool, Error> {
+        if self.policy.max_total_retries.is_some_and(|max| self.total >= max) {
+            return Err(Error::RetryBudgetExhausted { retries: self.total });
+        }
+
+        if self.policy.max_consecutive_retries.is_some_and(|max| self.consecutive >= max) {
+            self.consecutive = 0;
+            return Ok(false);
+        }
+
+        self.consecutive += 1;
+        self.total += 1;
+        if let Some(metrics) = self.metrics {
+            metrics.retry();
+        }
+
+        let pause = self.policy.pause + random_up_to(self.policy.jitter);
+        on_pause(RetryPause {
+            pause,
+            attempt: self.consecutive,
+            max_consecutive_retries: self.policy.max_consecutive_retries,
+            total_retries: self.total,
+            max_total_retries: self.policy.max_total_retries,
+        });
+        thread::sleep(pause);
+        Ok(true)
+    }
+
+    /// Called after a request succeeded (or failed for some other reason), so
+    /// that the next failure starts counting from scratch.
+    pub fn reset(&mut self) {
+        self.consecutive = 0;
+    }
+}
+
+// A random duration between zero and `max`. Every `RandomState` is seeded
+// differently, so the hash of nothing is a good enough random number without
+// pulling in a crate for it
+fn random_up_to(max: Duration) -> Duration {
+    if max.is_zero() {
+        return max;
+    }
+
+    let random = RandomState::new().build_hasher().finish();
+    max.mul_f64(random as f64 / u64::MAX as f64)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn retry_budget() {
+        let mut zester = Zester::anonymous(String::new());
+        zester.config.retry = RetryPolicy {
+            pause: Duration::from_secs(0),
+            jitter: Duration::from_secs(0),
+            max_consecutive_retries: Some(2),
+            max_total_retries: Some(3)
+        };
+        let mut retrier = Retrier::new(&zester);
+
+        assert!(retrier.pause(|_| ()).unwrap());
+        assert!(retrier.pause(|r| assert_eq!(r.attempt, 2)).unwrap());
+        // the request has been retried too many times in a row
+        assert!(!retrier.pause(|_| ()).unwrap());
+
+        assert!(retrier.pause(|r| assert_eq!((r.attempt, r.total_retries), (1, 3))).unwrap());
+        assert!(matches!(retrier.pause(|_| ()), Err(Error::RetryBudgetExhausted { retries: 3 })));
+    }
+
+    #[test]
+    fn retry_jitter() {
+        let mut zester = Zester::anonymous(String::new());
+        zester.config.retry = RetryPolicy {
+            pause: Duration::from_millis(1),
+            jitter: Duration::from_millis(2),
+            ..RetryPolicy::default()
+        };
+        let mut retrier = Retrier::new(&zester);
+
+        let mut pauses = vec![];
+        for _ in 0..5 {
+            retrier.pause(|r| pauses.push(r.pause)).unwrap();
+        }
+
+        assert!(pauses.iter().all(|p| (Duration::from_millis(1)..=Duration::from_millis(3)).contains(p)));
+        assert!(pauses.windows(2).any(|w| w[0] != w[1]));
+    }
+}
@@ -0,0 +1,219 @@
+//! The versioned format that zested data is saved in.
+//!
+//! `write_snapshot` and `write_snapshot_to` wrap the data they save in an
+//! envelope recording the version of the format, like
+//! `{ "format": "orange-zest-snapshot", "version": 1, "data": ... }`. The
+//! `format` tag tells envelopes apart from saved data that just happens to
+//! have `version` and `data` fields. When a snapshot saved by an older version
+//! of this crate is loaded, the data is migrated forward to the current format
+//! before being deserialized, so changes to the API structs don't break
+//! existing archives.
+
+use crate::Error;
+use serde::{Deserializer, Serialize};
+use serde::de::{self, DeserializeOwned, DeserializeSeed, MapAccess, SeqAccess, Visitor};
+use serde::de::value::SeqAccessDeserializer;
+use serde_derive::Serialize;
+use serde_json::{Map, Value};
+use std::fmt;
+use std::io::Read;
+use std::marker::PhantomData;
+
+/// The value of the `format` field that marks an object as a snapshot
+/// envelope.
+pub const FORMAT_TAG: &str = "orange-zest-snapshot";
+
+/// The version of the snapshot format written by this version of the crate.
+///
+/// Files saved before the format was versioned (bare JSON without an
+/// envelope) are treated as version 1.
+pub const VERSION: u32 = 1;
+
+/// Converts data saved with a given version of the format into the next
+/// version.
+type Migration = fn(Value) -> Result<Value, Error>;
+
+/// `MIGRATIONS[i]` converts data from version `i + 1` to version `i + 2`.
+///
+/// When the layout of saved data changes, bump `VERSION` and add a migration
+/// for the previous version to the end of this list.
+const MIGRATIONS: &[Migration] = &[];
+
+#[derive(Serialize)]
+pub(crate) struct Envelope<'a, T> {
+    format: &'static str,
+    version: u32,
+    data: &'a T
+}
+
+/// Wrap the given data in an envelope for the current version.
+pub(crate) fn wrap<T: Serialize>(data: &T) -> Envelope<'_, T> {
+    Envelope { format: FORMAT_TAG, version: VERSION, data }
+}
+
+/// Unwrap saved data from its envelope (if it has one) and migrate it to the
+/// current version.
+pub(crate) fn unwrap(value: Value) -> Result<Value, Error> {
+    let (version, mut data) = match value {
+        Value::Object(mut map) if is_envelope(&map) => {
+            // an envelope without a usable version can't be migrated
+            let version = map.get("version").and_then(Value::as_u64).unwrap_or(0);
+            (version, map.remove("data").unwrap_or_default())
+        },
+        other => (1, other)
+    };
+
+    if version == 0 || version > VERSION as u64 {
+        return Err(Error::UnsupportedSnapshotVersion(version));
+    }
+
+    for migrate in &MIGRATIONS[version as usize - 1..] {
+        data = migrate(data)?;
+    }
+
+    Ok(data)
+}
+
+// Whether the given object is a snapshot envelope rather than bare data
+fn is_envelope(map: &Map<String, Value>) -> bool {
+    map.len() == 3
+        && map.get("format").and_then(Value::as_str) == Some(FORMAT_TAG)
+        && map.contains_key("version")
+        && map.contains_key("data")
+}
+
+/// Read a snapshot of a `T` from the given reader.
+///
+/// Data saved with the current version of the format is deserialized straight
+/// into a `T` as it's read. Older data has to be loaded into memory as a whole
+/// to be migrated first.
+pub(crate) fn read<R: Read, T: DeserializeOwned>(reader: R) -> Result<T, Error> {
+    let mut snapshot_err = None;
+    let mut de = serde_json::Deserializer::from_reader(reader);
+
+    let result = SnapshotSeed { err: &mut snapshot_err, _marker: PhantomData }
+        .deserialize(&mut de)
+        .and_then(|data| de.end().map(|_| data));
+
+    match (result, snapshot_err) {
+        (Ok(data), _) => Ok(data),
+        // errors from unwrapping the snapshot are passed out on the side so
+        // that they don't all turn into JSON errors
+        (Err(_), Some(e)) => Err(e),
+        (Err(e), None) => Err(e.into())
+    }
+}
+
+struct SnapshotSeed<'a, T> {
+    err: &'a mut Option<Error>,
+    _marker: PhantomData<T>
+}
+
+impl<'de, T: DeserializeOwned> DeserializeSeed<'de> for SnapshotSeed<'_, T> {
+    type Value = T;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<T, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de, T: DeserializeOwned> Visitor<'de> for SnapshotSeed<'_, T> {
+    type Value = T;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a saved snapshot")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<T, A::Error> {
+        let mut format = None;
+        let mut version = None;
+        let mut rest = Map::new();
+
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "format" if format.is_none() && version.is_none() && rest.is_empty() => {
+                    format = Some(map.next_value::<Value>()?);
+                },
+                "version" if version.is_none() && rest.is_empty() => {
+                    version = Some(map.next_value::<Value>()?);
+                },
+                // the common case: data saved with the current version of the
+                // format can be deserialized directly
+                "data" if rest.is_empty()
+                    && format.as_ref().and_then(Value::as_str) == Some(FORMAT_TAG)
+                    && version.as_ref().and_then(Value::as_u64) == Some(VERSION as u64) => {
+                    return map.next_value();
+                },
+                _ => {
+                    rest.insert(key, map.next_value()?);
+                }
+            }
+        }
+
+        // an older version of the format, or data saved before the envelope
+        // existed
+        if let Some(format) = format {
+            rest.insert("format".into(), format);
+        }
+        if let Some(version) = version {
+            rest.insert("version".into(), version);
+        }
+        let data = unwrap(Value::Object(rest)).map_err(|e| {
+            let msg = e.to_string();
+            *self.err = Some(e);
+            de::Error::custom(msg)
+        })?;
+
+        T::deserialize(data).map_err(de::Error::custom)
+    }
+
+    // data saved as a bare list before the envelope existed
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<T, A::Error> {
+        T::deserialize(SeqAccessDeserializer::new(seq))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn snapshot_versions() {
+        let data = json!({ "collection": [1, 2, 3] });
+
+        let wrapped = serde_json::to_value(wrap(&data)).unwrap();
+        assert_eq!(wrapped, json!({ "format": FORMAT_TAG, "version": VERSION, "data": data }));
+        assert_eq!(unwrap(wrapped).unwrap(), data);
+
+        // snapshots from before the envelope existed
+        assert_eq!(unwrap(data.clone()).unwrap(), data);
+
+        // data that only looks like an envelope is left alone
+        let lookalike = json!({ "version": 7, "data": "not a snapshot" });
+        assert_eq!(unwrap(lookalike.clone()).unwrap(), lookalike);
+
+        assert!(matches!(
+            unwrap(json!({ "format": FORMAT_TAG, "version": VERSION + 1, "data": data })),
+            Err(Error::UnsupportedSnapshotVersion(_))
+        ));
+    }
+
+    #[test]
+    fn snapshot_reading() {
+        let data = json!({ "collection": [1, 2, 3] });
+        let read_str = |s: &str| read::<_, Value>(s.as_bytes());
+
+        let wrapped = serde_json::to_string(&wrap(&data)).unwrap();
+        assert_eq!(read_str(&wrapped).unwrap(), data);
+        assert_eq!(read_str(&data.to_string()).unwrap(), data);
+        assert_eq!(read_str("[1, 2]").unwrap(), json!([1, 2]));
+
+        let lookalike = json!({ "version": VERSION, "data": "not a snapshot" });
+        assert_eq!(read_str(&lookalike.to_string()).unwrap(), lookalike);
+
+        let too_new = json!({ "format": FORMAT_TAG, "version": VERSION + 1, "data": data }).to_string();
+        assert!(matches!(read_str(&too_new), Err(Error::UnsupportedSnapshotVersion(_))));
+        assert!(matches!(read_str(&wrapped[1..]), Err(Error::JsonDecodeError(_))));
+    }
+}
@@ -0,0 +1,50 @@
+//! Helpers shared by the crate's tests.
+
+use std::env;
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Used to give each temporary directory its own name
+static NEXT_DIR: AtomicUsize = AtomicUsize::new(0);
+
+/// An empty temporary directory, which is deleted along with everything in it
+/// when this is dropped (including when a test panics).
+pub(crate) struct TempDir(PathBuf);
+
+impl TempDir {
+    /// Create a directory whose name starts with "orange-zest-" and the given
+    /// name, followed by something unique to this call so that tests running
+    /// at the same time never share one.
+    pub(crate) fn new(name: &str) -> Self {
+        let n = NEXT_DIR.fetch_add(1, Ordering::Relaxed);
+        let path = env::temp_dir().join(format!("orange-zest-{}-{}-{}", name, process::id(), n));
+        // left behind by an earlier run that happened to have the same pid
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+
+        Self(path)
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
@@ -0,0 +1,577 @@
+//! A simulated `Zester` for developing applications without credentials or
+//! network access.
+
+use crate::{Error, Zester};
+use crate::api::{merge_track_ids, Likes, Playlists};
+use crate::api::common::Track;
+use crate::api::ids::{PlaylistId, TrackId};
+use crate::api::likes::LikesRaw;
+use crate::api::me::Me;
+use crate::api::playlists::{Playlist, PlaylistsRaw};
+use crate::client::ZestClient;
+use crate::config::RetryPolicy;
+use crate::events::*;
+use crate::progress::ProgressHandle;
+use crate::report::ZestReport;
+use crate::retry::RetryPause;
+use crate::transport::Transport;
+use std::collections::HashSet;
+use std::io::{Cursor, Read};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+const ME_FIXTURE: &str = include_str!("testing/fixtures/me.json");
+const LIKES_FIXTURE: &str = include_str!("testing/fixtures/likes.json");
+const PLAYLISTS_FIXTURE: &str = include_str!("testing/fixtures/playlists.json");
+
+/// The number of likes in each simulated page of likes
+const LIKES_PAGE_SIZE: usize = 2;
+/// The size of the simulated audio for every track
+const FAKE_AUDIO_LEN: u64 = 48 * 1024;
+/// The number of bytes of simulated audio reported in each progress event
+const FAKE_CHUNK_SIZE: u64 = 16 * 1024;
+/// The start of the transcoding URLs in the fixtures, which are followed by the
+/// track's id
+const TRANSCODING_URL_PREFIX: &str = "https://api-v2.soundcloud.com/media/soundcloud:tracks:";
+/// The start of the simulated media URLs, which are followed by the track's id
+const MEDIA_URL_PREFIX: &str = "https://cf-media.sndcdn.com/fixture/";
+
+/// Answers the same queries as a `Zester` using a small set of anonymized
+/// fixtures bundled with the crate, reporting every kind of event along the
+/// way.
+///
+/// This is meant for developing and testing the progress and error handling
+/// of applications deterministically. Nothing is actually sent over the
+/// network, and retries are reported without pausing. The fixtures hold five
+/// liked tracks and three playlists (plus one entry that's missing its
+/// "meta"-data), set up so that by default:
+///
+/// * one liked track is only available with DRM
+/// * the second page of likes, the first playlist's track info completion,
+///   and the first track download of each zest hit a server error once before
+///   succeeding
+/// * the first playlist includes a track that's no longer available
+/// * the second playlist has more tracks than were included in its info, and
+///   completing its track info fails
+/// * the full info for the third playlist fails to download
+/// * the first download of the second liked track fails verification
+/// * every download of the fifth liked track fails
+///
+/// The fields can be changed to simulate other scenarios. Audio is downloaded
+/// by a real `Zester` whose requests are answered from the fixtures (see
+/// `transport`), so the download events come from the same code as in a real
+/// zest. Downloaded audio is a short, recognizable stand-in for an MP3 file.
+#[derive(Debug, Clone)]
+pub struct FakeZester {
+    /// Tracks whose audio always fails to download
+    pub failing_tracks: HashSet<TrackId>,
+    /// Tracks whose audio fails verification the first time it's downloaded
+    pub corrupt_tracks: HashSet<TrackId>,
+    /// Playlists whose full info fails to download
+    pub failing_playlists: HashSet<PlaylistId>,
+    /// Playlists whose track info fails to be completed
+    pub incomplete_playlists: HashSet<PlaylistId>,
+    /// Whether or not some requests should hit a server error and be retried
+    pub server_errors: bool,
+    /// Where the progress of simulated downloads is shared, like
+    /// `Zester::progress_handle`
+    pub progress: ProgressHandle
+}
+
+impl Default for FakeZester {
+    fn default() -> Self {
+        Self {
+            failing_tracks: HashSet::from([TrackId(1005)]),
+            corrupt_tracks: HashSet::from([TrackId(1002)]),
+            failing_playlists: HashSet::from([PlaylistId(2003)]),
+            incomplete_playlists: HashSet::from([PlaylistId(2002)]),
+            server_errors: true,
+            progress: ProgressHandle::default()
+        }
+    }
+}
+
+impl FakeZester {
+    /// Create a `FakeZester` that simulates the default scenario described
+    /// above.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Same as `tracks_audio`, but only "downloads" the audio for tracks that
+    /// `filter` returns true for.
+    pub fn tracks_audio_filtered<'a, I, P, F>(
+        &self,
+        tracks: I,
+        filter: P,
+        cb: F
+    ) -> Result<ZestReport, Error> where
+        I: Iterator<Item = &'a Track>,
+        P: Fn(&Track) -> bool,
+        F: Fn(TracksAudioZestingEvent)
+    {
+        self.zester().tracks_audio_filtered(tracks, filter, cb)
+    }
+
+    /// Same as `tracks_audio`, but the callback decides whether each download
+    /// carries on, like `Zester::tracks_audio_controlled`.
+    pub fn tracks_audio_controlled<'a, I, F>(
+        &self,
+        tracks: I,
+        cb: F
+    ) -> Result<ZestReport, Error> where
+        I: Iterator<Item = &'a Track>,
+        F: Fn(TracksAudioZestingEvent) -> DownloadControl
+    {
+        self.zester().tracks_audio_controlled(tracks, cb)
+    }
+
+    // A `Zester` whose requests are answered from the fixtures, so that audio
+    // is "downloaded" by the same code as in a real zest
+    fn zester(&self) -> Zester {
+        let mut zester = Zester::anonymous("fixture-client-id".into());
+        zester.config.verify_downloads = true;
+        zester.config.download_chunk_size = FAKE_CHUNK_SIZE as usize;
+        zester.config.retry.pause = Duration::ZERO;
+        zester.config.retry.jitter = Duration::ZERO;
+        zester.progress = self.progress.clone();
+        zester.set_transport(FakeTransport {
+            failing_tracks: self.failing_tracks.clone(),
+            corrupt_tracks: self.corrupt_tracks.clone(),
+            server_error: AtomicBool::new(self.server_errors),
+            corrupted: Mutex::new(HashSet::new())
+        });
+
+        zester
+    }
+
+    // Every complete track in the fixtures, which are the tracks that can be
+    // looked up by id
+    fn fixture_tracks(&self) -> Result<Vec<Track>, Error> {
+        let likes: LikesRaw = serde_json::from_str(LIKES_FIXTURE)?;
+        let playlists: PlaylistsRaw = serde_json::from_str(PLAYLISTS_FIXTURE)?;
+
+        let mut tracks: Vec<Track> = likes.collection.unwrap_or_default().into_iter().map(|c| c.track).collect();
+        let playlist_tracks = playlists.collection
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|c| c.playlist)
+            .flat_map(|p| p.tracks.unwrap_or_default());
+        for track in playlist_tracks {
+            if track.title.is_some() && !tracks.iter().any(|t| t.id == track.id) {
+                tracks.push(track);
+            }
+        }
+
+        Ok(tracks)
+    }
+
+    // Fill in the playlist's stub tracks (and the ones that were left out of
+    // its info) from the fixtures, like `Playlist::complete_tracks_info`
+    fn complete_tracks_info<F: Fn(TracksInfoCompletionEvent)>(
+        &self,
+        playlist: &mut Playlist,
+        retries: &mut FakeRetries,
+        cb: F
+    ) -> Result<(), Error> {
+        use TracksInfoCompletionEvent::*;
+
+        // fixtures list the ids of tracks left out of a playlist's info under
+        // a field of their own
+        if let Some(remaining) = playlist.extra.remove("_fixture_remaining_track_ids") {
+            let embedded = playlist.tracks.take().unwrap_or_default();
+            let num_embedded = embedded.len();
+            let mut ids: Vec<TrackId> = embedded.iter().filter_map(|t| t.id).collect();
+            ids.extend(serde_json::from_value::<Vec<TrackId>>(remaining)?);
+
+            let merged = merge_track_ids(embedded, &ids);
+            cb(FetchedRemainingTrackIds { num: merged.len().saturating_sub(num_embedded) as u64 });
+            playlist.tracks = Some(merged);
+        }
+        let tracks = playlist.tracks.get_or_insert_with(Vec::new);
+
+        let missing: Vec<TrackId> = tracks.iter()
+            .filter(|t| t.title.is_none())
+            .filter_map(|t| t.id)
+            .collect();
+        if missing.is_empty() {
+            return Ok(());
+        }
+
+        cb(NumBatchesToDownload { num: 1, tracks_num: missing.len() as u64 });
+        cb(StartBatchDownload { index: 0, num_tracks: missing.len() });
+
+        if playlist.id.is_some_and(|id| self.incomplete_playlists.contains(&id)) {
+            return Err(Error::ServerError {
+                status: 503,
+                url: "https://api-v2.soundcloud.com/tracks".into(),
+                body: "Service Unavailable".into(),
+                context: None
+            });
+        }
+        if self.server_errors && retries.total == 0 {
+            cb(PausedAfterServerError { retry: retries.pause() });
+        }
+
+        let found = self.tracks_info(&missing)?;
+        cb(FinishBatchDownload { index: 0, count: found.len() });
+
+        let unavailable: Vec<TrackId> = missing.iter()
+            .copied()
+            .filter(|&id| !found.iter().any(|t| t.id == Some(id)))
+            .collect();
+        for track in tracks.iter_mut().filter(|t| t.title.is_none()) {
+            if let Some(info) = found.iter().find(|t| t.id == track.id) {
+                *track = info.clone();
+            }
+        }
+        if !unavailable.is_empty() {
+            cb(TracksUnavailable { ids: unavailable });
+        }
+
+        Ok(())
+    }
+}
+
+impl ZestClient for FakeZester {
+    fn me(&self) -> Result<Me, Error> {
+        Ok(serde_json::from_str(ME_FIXTURE)?)
+    }
+
+    fn likes(&self, num_recent: u64, cb: &dyn Fn(LikesZestingEvent)) -> Result<Likes, Error> {
+        use LikesZestingEvent::*;
+
+        let likes: LikesRaw = serde_json::from_str(LIKES_FIXTURE)?;
+        let mut collections = likes.collection.unwrap_or_default();
+        collections.truncate(num_recent as usize);
+        cb(NumLikesInfoToDownload { num: collections.len() as u64 });
+
+        let mut retries = FakeRetries::default();
+        for (i, page) in collections.chunks(LIKES_PAGE_SIZE).enumerate() {
+            // the first page is never retried
+            if i == 1 && self.server_errors {
+                cb(PausedAfterServerError { retry: retries.pause() });
+            }
+            cb(MoreLikesInfoDownloaded { count: page.len() as i64 });
+        }
+
+        Ok(Likes { collections })
+    }
+
+    fn playlists(&self, num_recent: u64, cb: &dyn Fn(PlaylistsZestingEvent)) -> Result<Playlists, Error> {
+        use PlaylistsZestingEvent::*;
+
+        // the metas handed to the callback and the returned playlists are
+        // separate copies, the same as when they come from the API
+        let metas: PlaylistsRaw = serde_json::from_str(PLAYLISTS_FIXTURE)?;
+        let mut full: PlaylistsRaw = serde_json::from_str(PLAYLISTS_FIXTURE)?;
+        let mut metas = metas.collection.unwrap_or_default();
+        metas.truncate(num_recent as usize);
+
+        cb(NumPlaylistInfoToDownload { num: metas.len() as u64 });
+        if metas.is_empty() {
+            return Ok(Playlists { playlists: vec![] });
+        }
+        cb(MorePlaylistMetaInfoDownloaded { count: metas.len() as i64 });
+        cb(FinishPlaylistMetaInfoDownloading);
+
+        let mut retries = FakeRetries::default();
+        let mut playlists = vec![];
+        let full_playlists = full.collection.take().unwrap_or_default().into_iter().map(|c| c.playlist);
+        for (meta, playlist) in metas.iter().map(|c| c.playlist.as_ref()).zip(full_playlists) {
+            let (pmeta, mut playlist) = match (meta, playlist) {
+                (Some(pmeta), Some(playlist)) => (pmeta, playlist),
+                _ => {
+                    cb(PlaylistMetaNotPresent);
+                    continue;
+                }
+            };
+
+            cb(StartPlaylistInfoDownload { playlist_meta: pmeta });
+            if pmeta.id.is_some_and(|id| self.failing_playlists.contains(&id)) {
+                let url = format!("https://api-v2.soundcloud.com/playlists/{}", pmeta.id.unwrap_or_default());
+                cb(PlaylistInfoDownloadError { playlist_meta: pmeta, err: not_found(&url) });
+                continue;
+            }
+
+            let completion_cb = |e| cb(TracksInfoCompletion(e, pmeta));
+            if let Err(e) = self.complete_tracks_info(&mut playlist, &mut retries, completion_cb) {
+                cb(PlaylistInfoCompletionError { playlist_meta: pmeta, err: e });
+            }
+            playlists.push(playlist);
+
+            cb(FinishPlaylistInfoDownload { playlist_meta: pmeta });
+        }
+
+        Ok(Playlists { playlists })
+    }
+
+    fn tracks_info(&self, ids: &[TrackId]) -> Result<Vec<Track>, Error> {
+        let mut tracks = self.fixture_tracks()?;
+
+        Ok(ids.iter()
+            .filter_map(|&id| {
+                let pos = tracks.iter().position(|t| t.id == Some(id))?;
+                Some(tracks.swap_remove(pos))
+            })
+            .collect())
+    }
+
+    fn likes_audio(
+        &self,
+        likes: &Likes,
+        num_recent: u64,
+        cb: &dyn Fn(TracksAudioZestingEvent)
+    ) -> Result<ZestReport, Error> {
+        self.zester().likes_audio(likes, num_recent, cb)
+    }
+
+    fn playlists_audio<'a>(
+        &self,
+        playlists: &mut dyn Iterator<Item = &'a Playlist>,
+        cb: &dyn Fn(PlaylistsAudioZestingEvent)
+    ) -> Result<ZestReport, Error> {
+        self.zester().playlists_audio(playlists, cb)
+    }
+
+    fn tracks_audio<'a>(
+        &self,
+        tracks: &mut dyn Iterator<Item = &'a Track>,
+        cb: &dyn Fn(TracksAudioZestingEvent)
+    ) -> Result<ZestReport, Error> {
+        self.zester().tracks_audio(tracks, cb)
+    }
+}
+
+/// Answers the requests made to download the fixtures' audio: resolving each
+/// track's media URL, and then the media itself.
+struct FakeTransport {
+    failing_tracks: HashSet<TrackId>,
+    corrupt_tracks: HashSet<TrackId>,
+    /// Whether the next media download should hit a server error
+    server_error: AtomicBool,
+    /// The corrupt tracks that have already been downloaded (corrupted) once
+    corrupted: Mutex<HashSet<TrackId>>
+}
+
+impl Transport for FakeTransport {
+    fn send(&self, request: &ureq::Request, _body: Option<&str>) -> Result<(Box<dyn Read + Send>, Option<u64>), Error> {
+        let url = request.get_url();
+
+        // the fixtures' transcoding URLs point to some JSON containing the
+        // media URL
+        if let Some(id) = fixture_track_id(url, TRANSCODING_URL_PREFIX, '/') {
+            let json = serde_json::json!({ "url": format!("{}{}.128.mp3", MEDIA_URL_PREFIX, id) }).to_string();
+            let len = json.len() as u64;
+            return Ok((Box::new(Cursor::new(json.into_bytes())), Some(len)));
+        }
+
+        let id = match fixture_track_id(url, MEDIA_URL_PREFIX, '.') {
+            Some(id) if !self.failing_tracks.contains(&id) => id,
+            _ => return Err(not_found(url))
+        };
+        if self.server_error.swap(false, Ordering::SeqCst) {
+            return Err(Error::ServerError {
+                status: 503,
+                url: url.into(),
+                body: "Service Unavailable".into(),
+                context: None
+            });
+        }
+
+        let corrupt = self.corrupt_tracks.contains(&id)
+            && self.corrupted.lock().unwrap_or_else(|e| e.into_inner()).insert(id);
+        Ok((Box::new(Cursor::new(fake_audio(id, corrupt))), Some(FAKE_AUDIO_LEN)))
+    }
+}
+
+// The id of the fixture track in a URL made up of `prefix`, the id, and then
+// `end`
+fn fixture_track_id(url: &str, prefix: &str, end: char) -> Option<TrackId> {
+    let rest = url.strip_prefix(prefix)?;
+    rest[..rest.find(end)?].parse().ok()
+}
+
+/// Counts the simulated retries made over a zest.
+#[derive(Default)]
+struct FakeRetries {
+    total: u32
+}
+
+impl FakeRetries {
+    fn pause(&mut self) -> RetryPause {
+        self.total += 1;
+        let policy = RetryPolicy::default();
+
+        RetryPause {
+            pause: policy.pause,
+            attempt: 1,
+            max_consecutive_retries: policy.max_consecutive_retries,
+            total_retries: self.total,
+            max_total_retries: policy.max_total_retries
+        }
+    }
+}
+
+fn not_found(url: &str) -> Error {
+    Error::NotFound { url: url.into(), body: "Not Found".into(), context: None }
+}
+
+// Something that passes for an MP3 file, unless it's meant to be corrupt
+fn fake_audio(id: TrackId, corrupt: bool) -> Vec<u8> {
+    let mut data = Vec::with_capacity(FAKE_AUDIO_LEN as usize);
+    data.extend_from_slice(if corrupt { b"\0\0\0" } else { b"ID3" });
+    data.extend_from_slice(&[4, 0, 0, 0, 0, 0, 0]);
+    let id_bytes = id.0.to_be_bytes();
+    while (data.len() as u64) < FAKE_AUDIO_LEN {
+        data.push(id_bytes[data.len() % id_bytes.len()]);
+    }
+
+    data
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::report::SkipReason;
+    use crate::verify::verify_audio;
+    use std::cell::RefCell;
+
+    #[test]
+    fn fake_zester_events() {
+        let zester = FakeZester::new();
+        assert_eq!(zester.me().unwrap().username.as_deref(), Some("fixture-user"));
+
+        let likes_events = RefCell::new(vec![]);
+        let likes = zester.likes(u64::MAX, &|e| likes_events.borrow_mut().push(format!("{:?}", e))).unwrap();
+        assert_eq!(likes.collections.len(), 5);
+        assert!(likes_events.borrow().iter().any(|e| e.starts_with("PausedAfterServerError")));
+
+        let playlists_events = RefCell::new(vec![]);
+        let playlists = zester.playlists(u64::MAX, &|e| playlists_events.borrow_mut().push(format!("{:?}", e))).unwrap();
+        let playlists_events = playlists_events.into_inner();
+        for name in [
+            "PlaylistMetaNotPresent",
+            "PlaylistInfoDownloadError",
+            "PlaylistInfoCompletionError",
+            "TracksInfoCompletion(FetchedRemainingTrackIds",
+            "TracksInfoCompletion(PausedAfterServerError",
+            "TracksInfoCompletion(TracksUnavailable"
+        ] {
+            assert!(playlists_events.iter().any(|e| e.starts_with(name)), "no {} event", name);
+        }
+
+        // the unavailable track is left as a stub, the other one is filled in
+        let first_tracks = playlists.playlists[0].tracks.as_ref().unwrap();
+        assert_eq!(first_tracks.len(), 4);
+        assert_eq!(first_tracks[2].title.as_deref(), Some("Track Two"));
+        assert!(first_tracks[3].title.is_none());
+        assert_eq!(playlists.playlists.len(), 2);
+
+        let audio_events = RefCell::new(vec![]);
+        let report = zester.likes_audio(&likes, u64::MAX, &|e| {
+            use TracksAudioZestingEvent::*;
+
+            let name = match e {
+                FinishTrackDownload { mut track_data, .. } => {
+                    let mut data = vec![];
+                    track_data.read_to_end(&mut data).unwrap();
+                    assert!(verify_audio(&data, Some(FAKE_AUDIO_LEN), Some("audio/mpeg")).is_ok());
+                    "FinishTrackDownload"
+                },
+                NumTracksToDownload { .. } => "NumTracksToDownload",
+                NumBytesToDownload { .. } => "NumBytesToDownload",
+                SkippedByFilter { .. } => "SkippedByFilter",
+                SkippedDrmProtected { .. } => "SkippedDrmProtected",
+                StartTrackDownload { .. } => "StartTrackDownload",
+                TrackDownloadProgress { .. } => "TrackDownloadProgress",
+                TrackSkipped { .. } => "TrackSkipped",
+                SnippetOnlyDownloaded { .. } => "SnippetOnlyDownloaded",
+                TrackVerificationFailed { .. } => "TrackVerificationFailed",
+                TrackDownloadError { .. } => "TrackDownloadError",
+                PausedAfterServerError { .. } => "PausedAfterServerError"
+            };
+            audio_events.borrow_mut().push(name);
+        }).unwrap();
+
+        assert_eq!(report.downloaded, [TrackId(1001), TrackId(1002), TrackId(1003)]);
+        assert_eq!(report.skipped, [(TrackId(1004), SkipReason::DrmProtected)]);
+        assert_eq!(report.failed.len(), 1);
+        let audio_events = audio_events.into_inner();
+        for name in ["SkippedDrmProtected", "TrackVerificationFailed", "TrackDownloadError", "PausedAfterServerError"] {
+            assert!(audio_events.contains(&name), "no {} event", name);
+        }
+        assert_eq!(audio_events.iter().filter(|&&e| e == "FinishTrackDownload").count(), 3);
+    }
+
+    #[test]
+    fn filtered_audio() {
+        let zester = FakeZester::new();
+        let likes = zester.likes(u64::MAX, &|_| {}).unwrap();
+
+        let filtered = RefCell::new(vec![]);
+        let report = zester.tracks_audio_filtered(
+            likes.collections.iter().map(|c| &c.track),
+            |t| t.id != Some(TrackId(1003)),
+            |e| if let TracksAudioZestingEvent::SkippedByFilter { track_info } = e {
+                filtered.borrow_mut().push(track_info.id);
+            }
+        ).unwrap();
+
+        assert_eq!(filtered.into_inner(), [Some(TrackId(1003))]);
+        assert_eq!(report.downloaded, [TrackId(1001), TrackId(1002)]);
+        assert!(report.skipped.contains(&(TrackId(1003), SkipReason::Filtered)));
+    }
+
+    #[test]
+    fn skipped_audio() {
+        let zester = FakeZester::new();
+        let likes = zester.likes(u64::MAX, &|_| {}).unwrap();
+
+        // one track is skipped as soon as it starts, and another partway
+        // through downloading
+        let skipped = RefCell::new(vec![]);
+        let report = zester.tracks_audio_controlled(likes.collections.iter().map(|c| &c.track), |e| match e {
+            TracksAudioZestingEvent::StartTrackDownload { track_info } if track_info.id == Some(TrackId(1001)) => {
+                DownloadControl::SkipTrack
+            },
+            TracksAudioZestingEvent::TrackDownloadProgress { track_info, .. } if track_info.id == Some(TrackId(1003)) => {
+                DownloadControl::SkipTrack
+            },
+            TracksAudioZestingEvent::TrackSkipped { track_info } => {
+                skipped.borrow_mut().push(track_info.id);
+                DownloadControl::Continue
+            },
+            _ => DownloadControl::Continue
+        }).unwrap();
+
+        assert_eq!(skipped.into_inner(), [Some(TrackId(1001)), Some(TrackId(1003))]);
+        assert_eq!(report.downloaded, [TrackId(1002)]);
+        assert!(report.skipped.contains(&(TrackId(1001), SkipReason::Cancelled)));
+        assert!(report.skipped.contains(&(TrackId(1003), SkipReason::Cancelled)));
+    }
+
+    #[test]
+    fn playlist_track_positions() {
+        // retried downloads start again, so there are no retries here to keep
+        // one start per track
+        let zester = FakeZester { server_errors: false, corrupt_tracks: HashSet::new(), ..FakeZester::default() };
+        let playlists = zester.playlists(1, &|_| {}).unwrap();
+        let playlist = &playlists.playlists[0];
+        let track_count = playlist.tracks.as_ref().map_or(0, Vec::len);
+        assert!(track_count > 1);
+
+        let positions = RefCell::new(vec![]);
+        zester.playlists_audio(&mut playlists.playlists.iter(), &|e| {
+            if let PlaylistsAudioZestingEvent::TrackEvent { event: TracksAudioZestingEvent::StartTrackDownload { .. }, playlist_info, track_index, track_count } = e {
+                if std::ptr::eq(playlist_info, playlist) {
+                    positions.borrow_mut().push((track_index, track_count));
+                }
+            }
+        }).unwrap();
+
+        let expected: Vec<_> = (0..track_count).map(|i| (Some(i), track_count)).collect();
+        assert_eq!(positions.into_inner(), expected);
+    }
+}
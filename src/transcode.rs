@@ -0,0 +1,253 @@
+//! Transcoding downloaded audio into a different format with ffmpeg.
+//!
+//! ffmpeg isn't bundled; it has to be installed separately, built with the
+//! encoders for the formats you want to transcode into.
+
+use crate::Error;
+use std::env;
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::process::{self, Child, Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
+/// Used to give each transcode its own temporary files
+static NEXT_TRANSCODE: AtomicUsize = AtomicUsize::new(0);
+
+/// The formats that audio can be transcoded into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TranscodeFormat {
+    /// MP3, encoded with LAME
+    Mp3,
+    /// Opus in an Ogg container
+    Opus,
+    /// AAC in an MP4 container
+    M4a,
+    /// Lossless FLAC, which doesn't make the audio sound any better but can be
+    /// handy for players that need it
+    Flac,
+}
+
+impl TranscodeFormat {
+    /// The file extension for audio in this format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            TranscodeFormat::Mp3 => "mp3",
+            TranscodeFormat::Opus => "opus",
+            TranscodeFormat::M4a => "m4a",
+            TranscodeFormat::Flac => "flac",
+        }
+    }
+
+    // The ffmpeg arguments choosing the encoder and container
+    fn ffmpeg_args(self) -> [&'static str; 4] {
+        match self {
+            TranscodeFormat::Mp3 => ["-c:a", "libmp3lame", "-f", "mp3"],
+            TranscodeFormat::Opus => ["-c:a", "libopus", "-f", "opus"],
+            TranscodeFormat::M4a => ["-c:a", "aac", "-f", "ipod"],
+            TranscodeFormat::Flac => ["-c:a", "flac", "-f", "flac"],
+        }
+    }
+}
+
+/// Transcodes audio by running ffmpeg.
+///
+/// ```ignore
+/// let options = ArchiveOptions {
+///     transcode: Some(Transcoder::new(TranscodeFormat::Mp3).bitrate(320)),
+///     ..ArchiveOptions::default()
+/// };
+/// ```
+#[derive(Debug, Clone)]
+pub struct Transcoder {
+    /// The format to transcode into
+    pub format: TranscodeFormat,
+    /// The bitrate to encode at, in kilobits per second
+    ///
+    /// `None` uses ffmpeg's default for the encoder. This is ignored for
+    /// lossless formats.
+    pub bitrate_kbps: Option<u32>,
+    /// The ffmpeg executable to run, which is looked up on the `PATH` unless
+    /// it's a path
+    pub ffmpeg: PathBuf,
+}
+
+impl Transcoder {
+    pub fn new(format: TranscodeFormat) -> Self {
+        Self {
+            format,
+            bitrate_kbps: None,
+            ffmpeg: PathBuf::from("ffmpeg"),
+        }
+    }
+
+    /// Encode at the given bitrate, in kilobits per second.
+    pub fn bitrate(mut self, kbps: u32) -> Self {
+        self.bitrate_kbps = Some(kbps);
+        self
+    }
+
+    /// Transcode the given audio, which can be in any format ffmpeg
+    /// understands.
+    ///
+    /// `on_progress` is called with the amount of audio that has been
+    /// transcoded so far as ffmpeg reports it.
+    pub fn transcode(&self, audio: &[u8], on_progress: impl Fn(Duration)) -> Result<Vec<u8>, Error> {
+        self.transcode_tagged(audio, &[], on_progress)
+    }
+
+    /// Same as `transcode`, but also writes the given metadata tags (like
+    /// `("track", "2/10")`) into the transcoded audio.
+    ///
+    /// Tags use ffmpeg's generic names, which it maps to the right ones for
+    /// each format. The audio is read from a `Read` instance, so it doesn't
+    /// need to be in memory.
+    pub fn transcode_tagged(
+        &self,
+        mut audio: impl Read,
+        tags: &[(&str, String)],
+        on_progress: impl Fn(Duration)
+    ) -> Result<Vec<u8>, Error> {
+        // ffmpeg needs to be able to seek around in some containers, so it's
+        // given files rather than pipes
+        let n = NEXT_TRANSCODE.fetch_add(1, Ordering::Relaxed);
+        let stem = env::temp_dir().join(format!("orange-zest-transcode-{}-{}", process::id(), n));
+        let input = stem.with_extension("in");
+        let output = stem.with_extension(self.format.extension());
+
+        let result = File::create(&input)
+            .and_then(|mut file| io::copy(&mut audio, &mut file))
+            .map_err(Error::from)
+            .and_then(|_| self.run(&input, &output, tags, on_progress))
+            .and_then(|_| Ok(fs::read(&output)?));
+
+        let _ = fs::remove_file(&input);
+        let _ = fs::remove_file(&output);
+        result
+    }
+
+    // The arguments to run ffmpeg with
+    fn args(&self, input: &Path, output: &Path, tags: &[(&str, String)]) -> Vec<OsString> {
+        let mut args: Vec<OsString> = ["-hide_banner", "-nostdin", "-loglevel", "error", "-y", "-i"]
+            .iter()
+            .map(OsString::from)
+            .collect();
+        args.push(input.into());
+        args.push("-vn".into());
+        args.extend(self.format.ffmpeg_args().iter().map(OsString::from));
+        if let (Some(kbps), false) = (self.bitrate_kbps, self.format == TranscodeFormat::Flac) {
+            args.push("-b:a".into());
+            args.push(format!("{}k", kbps).into());
+        }
+        for (key, value) in tags {
+            args.push("-metadata".into());
+            args.push(format!("{}={}", key, value).into());
+        }
+        args.push("-progress".into());
+        args.push("pipe:1".into());
+        args.push(output.into());
+        args
+    }
+
+    fn run(&self, input: &Path, output: &Path, tags: &[(&str, String)], on_progress: impl Fn(Duration)) -> Result<(), Error> {
+        let child = Command::new(&self.ffmpeg)
+            .args(self.args(input, output, tags))
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let mut child = ChildGuard(child);
+
+        // errors are read on another thread so that ffmpeg can't get stuck
+        // writing them while we're reading progress
+        let mut stderr = child.0.stderr.take();
+        let errors = thread::spawn(move || {
+            let mut errors = String::new();
+            if let Some(stderr) = &mut stderr {
+                let _ = stderr.read_to_string(&mut errors);
+            }
+            errors
+        });
+
+        if let Some(stdout) = child.0.stdout.take() {
+            for line in BufReader::new(stdout).lines() {
+                if let Some(transcoded) = progress_time(&line?) {
+                    on_progress(transcoded);
+                }
+            }
+        }
+
+        let status = child.0.wait()?;
+        let errors = errors.join().unwrap_or_default();
+        if status.success() {
+            Ok(())
+        } else if errors.trim().is_empty() {
+            Err(Error::TranscodeFailed(format!("ffmpeg exited with {}", status)))
+        } else {
+            Err(Error::TranscodeFailed(errors.trim().to_string()))
+        }
+    }
+}
+
+/// Kills ffmpeg if it's still running when transcoding is given up on, so
+/// that it doesn't outlive the transcode.
+struct ChildGuard(Child);
+
+impl Drop for ChildGuard {
+    fn drop(&mut self) {
+        if let Ok(None) = self.0.try_wait() {
+            let _ = self.0.kill();
+            let _ = self.0.wait();
+        }
+    }
+}
+
+/// The amount of audio transcoded so far, if the line of ffmpeg's progress
+/// output reports it.
+///
+/// Only `out_time_us` is used; ffmpeg reports the same time in `out_time_ms`
+/// (in microseconds too, despite its name), so using both would report each
+/// update twice.
+fn progress_time(line: &str) -> Option<Duration> {
+    let (key, value) = line.split_once('=')?;
+    if key != "out_time_us" {
+        return None;
+    }
+    value.trim().parse().ok().map(Duration::from_micros)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn ffmpeg_args() {
+        let args = |transcoder: Transcoder, tags: &[(&str, String)]| -> Vec<String> {
+            transcoder
+                .args(Path::new("in"), Path::new("out.mp3"), tags)
+                .into_iter()
+                .map(|a| a.into_string().unwrap())
+                .collect()
+        };
+
+        assert_eq!(
+            args(Transcoder::new(TranscodeFormat::Mp3).bitrate(320), &[("track", "2/10".into())]).join(" "),
+            "-hide_banner -nostdin -loglevel error -y -i in -vn -c:a libmp3lame -f mp3 -b:a 320k \
+             -metadata track=2/10 -progress pipe:1 out.mp3"
+        );
+        // the bitrate doesn't apply to lossless formats
+        assert!(!args(Transcoder::new(TranscodeFormat::Flac).bitrate(320), &[]).contains(&"-b:a".to_string()));
+    }
+
+    #[test]
+    fn progress_lines() {
+        assert_eq!(progress_time("out_time_us=1500000"), Some(Duration::from_millis(1500)));
+        assert_eq!(progress_time("out_time_ms=1500000"), None);
+        assert_eq!(progress_time("out_time_us=N/A"), None);
+        assert_eq!(progress_time("out_time=00:00:01.500000"), None);
+        assert_eq!(progress_time("progress=continue"), None);
+    }
+}
@@ -0,0 +1,25 @@
+//! Replacing the network underneath a `Zester`, for simulating the API.
+
+use crate::Error;
+use std::io::Read;
+
+/// Something that answers the requests a `Zester` sends in place of the
+/// network.
+///
+/// Install one with `Zester::set_transport`. Everything else the `Zester` does
+/// (retries, events, progress, verification, and so on) is unchanged, so a
+/// transport answering with canned responses exercises the same code as a real
+/// zest. This is how `testing::FakeZester` downloads its simulated audio.
+///
+/// Requests sent through a transport don't pass through middleware, and the
+/// HEAD requests made to measure the exact size of audio files are still sent
+/// over the network.
+pub trait Transport: Send + Sync {
+    /// Answer the given request (sending `body` with it, if there is one),
+    /// returning the response body and its length if it's known.
+    ///
+    /// Requests that fail should fail with the error the `Zester` would have
+    /// returned for the equivalent response, such as `Error::NotFound` for a
+    /// 404 or `Error::ServerError` for a 503.
+    fn send(&self, request: &ureq::Request, body: Option<&str>) -> Result<(Box<dyn Read + Send>, Option<u64>), Error>;
+}
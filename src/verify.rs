@@ -0,0 +1,102 @@
+//! Sanity checks for downloaded audio.
+
+use crate::api::extension_for_mime_type;
+
+/// Check that downloaded audio looks complete and is in the expected format.
+///
+/// `expected_len` is the length the server reported for the file, if any, and
+/// `mime_type` is the mime type of the transcoding that was downloaded. Checks
+/// that can't be made because of missing information (or an unfamiliar mime
+/// type) are skipped. On failure, a description of the problem is returned.
+pub fn verify_audio(data: &[u8], expected_len: Option<u64>, mime_type: Option<&str>) -> Result<(), String> {
+    if let Some(expected_len) = expected_len {
+        if data.len() as u64 != expected_len {
+            return Err(format!("received {} bytes but expected {}", data.len(), expected_len));
+        }
+    }
+
+    let extension = match mime_type.and_then(extension_for_mime_type) {
+        Some(extension) => extension,
+        None => return Ok(())
+    };
+
+    let matches = match extension {
+        // either an ID3 tag or straight into an MPEG frame sync
+        "mp3" => data.starts_with(b"ID3") || is_frame_sync(data),
+        "m4a" => data.get(4..8) == Some(b"ftyp"),
+        "aac" => is_frame_sync(data),
+        "ogg" | "opus" => data.starts_with(b"OggS"),
+        "webm" => data.starts_with(&[0x1a, 0x45, 0xdf, 0xa3]),
+        "flac" => data.starts_with(b"fLaC"),
+        "wav" => data.starts_with(b"RIFF") && data.get(8..12) == Some(b"WAVE"),
+        _ => true
+    };
+
+    if matches {
+        Ok(())
+    } else {
+        Err(format!("data doesn't look like {} audio", extension))
+    }
+}
+
+// MPEG audio and ADTS frames both start with 11 set bits
+fn is_frame_sync(data: &[u8]) -> bool {
+    data.len() >= 2 && data[0] == 0xff && data[1] & 0xe0 == 0xe0
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn audio_that_passes() {
+        let mut m4a = vec![0, 0, 0, 0x20];
+        m4a.extend_from_slice(b"ftypM4A ");
+
+        let cases: [(&[u8], &str); 9] = [
+            (b"ID3\x04\0\0\0\0\0\0", "audio/mpeg"),
+            (&[0xff, 0xfb, 0x90, 0x64], "audio/mpeg"),
+            (&m4a, "audio/mp4; codecs=\"mp4a.40.2\""),
+            (&[0xff, 0xf1, 0x50, 0x80], "audio/aac"),
+            (b"OggS\0\x02", "audio/ogg; codecs=\"opus\""),
+            (b"OggS\0\x02", "audio/ogg"),
+            (&[0x1a, 0x45, 0xdf, 0xa3, 0x01], "audio/webm"),
+            (b"fLaC\0\0\0\x22", "audio/flac"),
+            (b"RIFF\x24\0\0\0WAVEfmt ", "audio/wav"),
+        ];
+        for (data, mime_type) in cases {
+            assert_eq!(verify_audio(data, Some(data.len() as u64), Some(mime_type)), Ok(()), "{}", mime_type);
+        }
+
+        // checks that can't be made are skipped
+        assert_eq!(verify_audio(b"anything", None, None), Ok(()));
+        assert_eq!(verify_audio(b"anything", None, Some("audio/x-unknown")), Ok(()));
+    }
+
+    #[test]
+    fn audio_that_fails() {
+        assert_eq!(
+            verify_audio(b"ID3\x04", Some(10), Some("audio/mpeg")),
+            Err("received 4 bytes but expected 10".to_string())
+        );
+        // a length that matches doesn't make up for the wrong contents
+        assert_eq!(
+            verify_audio(b"<html>", Some(6), Some("audio/mpeg")),
+            Err("data doesn't look like mp3 audio".to_string())
+        );
+
+        let cases: [(&[u8], &str); 8] = [
+            (b"", "audio/mpeg"),
+            (&[0xff], "audio/mpeg"),
+            (b"\0\0\0\x20moov", "audio/mp4"),
+            (&[0xff, 0x10], "audio/aac"),
+            (b"RIFF\x24\0\0\0WAVE", "audio/ogg"),
+            (b"OggS", "audio/webm"),
+            (b"ID3\x04", "audio/flac"),
+            (b"RIFF\x24\0\0\0AVI ", "audio/wav"),
+        ];
+        for (data, mime_type) in cases {
+            assert!(verify_audio(data, None, Some(mime_type)).is_err(), "{} passed", mime_type);
+        }
+    }
+}
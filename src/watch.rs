@@ -0,0 +1,97 @@
+//! Watching for new likes as they happen.
+
+use crate::{Error, Zester};
+use crate::api::ids::TrackId;
+use crate::api::likes::{LikesCollection, LikesRaw};
+use crate::events::WatchLikesEvent;
+use std::cell::Cell;
+use std::collections::HashSet;
+use std::thread;
+use std::time::Duration;
+
+/// The number of likes grabbed by each poll
+const POLL_PAGE_SIZE: u64 = 50;
+
+/// What a `Zester::watch_likes` callback wants to happen next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchControl {
+    /// Keep watching
+    Continue,
+    /// Stop watching once the current poll has been handled
+    Stop
+}
+
+impl Zester {
+    /// Poll the user's most recent likes every `interval`, calling the callback
+    /// with any that are new since the previous poll.
+    ///
+    /// The first poll only records which likes already exist, so the callback
+    /// only hears about likes made after watching started. If
+    /// `download_audio` is true the audio for new likes is downloaded as well,
+    /// and handed to the callback through `TrackAudio` events.
+    ///
+    /// Only the most recent page of likes is checked, so likes made faster than
+    /// that page fills up between polls may be missed. Errors while polling
+    /// are reported through the callback rather than ending the watch.
+    ///
+    /// Watching continues until the callback returns `WatchControl::Stop`.
+    pub fn watch_likes<F: Fn(WatchLikesEvent) -> WatchControl>(
+        &self,
+        interval: Duration,
+        download_audio: bool,
+        cb: F
+    ) -> Result<(), Error> {
+        use WatchLikesEvent::*;
+
+        let path = format!("users/{}/track_likes", self.my_id()?);
+        // the likes on the page at the last poll; only those are remembered,
+        // so that watching for a long time doesn't use more and more memory
+        let mut previous_ids = HashSet::new();
+        let mut first_poll = true;
+        let stop = Cell::new(false);
+        let cb = |e: WatchLikesEvent| if cb(e) == WatchControl::Stop {
+            stop.set(true);
+        };
+
+        loop {
+            match self.poll_likes(&path) {
+                Ok(page) => {
+                    let current_ids: HashSet<TrackId> = page.iter().filter_map(|like| like.track.id).collect();
+                    let new_likes: Vec<LikesCollection> = page
+                        .into_iter()
+                        .filter(|like| like.track.id.is_some_and(|id| !previous_ids.contains(&id)))
+                        .collect();
+                    previous_ids = current_ids;
+
+                    if !first_poll && !new_likes.is_empty() {
+                        cb(NewLikes { likes: &new_likes });
+
+                        if download_audio {
+                            let tracks = new_likes.iter().map(|l| &l.track);
+                            if let Err(err) = self.tracks_audio(tracks, |e| cb(TrackAudio(e))) {
+                                cb(PollError { err });
+                            }
+                        }
+                    }
+                    first_poll = false;
+                },
+                Err(err) => cb(PollError { err })
+            }
+
+            if stop.get() {
+                return Ok(());
+            }
+            thread::sleep(interval);
+        }
+    }
+
+    // Grab the most recent page of likes
+    fn poll_likes(&self, path: &str) -> Result<Vec<LikesCollection>, Error> {
+        let likes_raw: LikesRaw = serde_json::from_str(&self.api_req(
+            path,
+            &[("limit", &POLL_PAGE_SIZE.to_string()), ("offset", "0")]
+        )?)?;
+
+        Ok(likes_raw.collection.unwrap_or_default())
+    }
+}